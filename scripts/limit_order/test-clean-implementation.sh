@@ -26,7 +26,7 @@ dfx generate limit_order
 
 # Check what functions are available
 echo "📋 Available functions in limit_order canister:"
-dfx canister call limit_order greet '("test")' 2>/dev/null || echo "⚠️  Canister not deployed yet"
+dfx canister call limit_order health 2>/dev/null || echo "⚠️  Canister not deployed yet"
 
 echo ""
 echo "🎯 Core 1inch LOP Functions Implemented:"
@@ -79,6 +79,7 @@ RESULT=$(dfx canister call relayer fusion_plus_relayer_submit \
     makerTraits = \"0x\";
   },
   1 : nat64,
+  223 : nat64,
   \"$SIGNATURE\",
   \"$EXTENSION\",
   \"$QUOTE_ID\",
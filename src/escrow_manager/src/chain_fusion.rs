@@ -4,14 +4,35 @@ use ic_cdk::api::call::call_with_payment;
 use ic_cdk::api::management_canister::ecdsa::{
     sign_with_ecdsa, EcdsaCurve, EcdsaKeyId, SignWithEcdsaArgument,
 };
+use std::cell::RefCell;
 
-use crate::types::{EVMEscrowParams, Error, RpcService, ThresholdECDSAHealth, TransactionReceipt};
+use crate::memory;
+use crate::types::{
+    CostBreakdown, Create2Config, DeploymentAttempt, DeploymentStatus, EVMEscrowParams, Error,
+    EscrowVerificationReport, EvmEscrowImmutables, FieldMismatch, HTLCEscrow, PreparedTx,
+    RpcProviderStrategy, RpcService, ThresholdECDSAHealth, TransactionReceipt,
+};
 
 // Real EVM RPC canister (7hfb6-caaaa-aaaar-qadga-cai) from production
 const EVM_RPC_CANISTER: Principal =
     Principal::from_slice(b"\x00\x00\x00\x00\x02\x30\x00\xCC\x01\x01");
 pub const EVM_RPC_CYCLES_COST: u64 = 590_736_800;
 
+/// Function selector for `getImmutables()` on the reference escrow contract
+const GET_IMMUTABLES_SELECTOR: &str = "0xbcdb4dad";
+
+/// Number of 32-byte ABI words returned by `getImmutables()`
+const IMMUTABLES_WORD_COUNT: usize = 8;
+
+/// Factory function that deploys an escrow at the CREATE2 address of a salt
+const FACTORY_DEPLOY_SIGNATURE: &str = "deploy(bytes32,bytes)";
+
+/// Gas limit of an escrow deployment transaction
+const ESCROW_DEPLOYMENT_GAS: u64 = 100_000;
+
+/// EVM RPC calls of a fresh deployment: nonce lookup, broadcast and receipt
+const DEPLOYMENT_RPC_CALLS: u64 = 3;
+
 /// Chain Fusion Manager handles all EVM interactions via Chain Fusion and Threshold ECDSA
 pub struct ChainFusionManager {
     pub evm_rpc_canister: Principal,
@@ -19,6 +40,7 @@ pub struct ChainFusionManager {
     pub evm_chain_id: u64,
     pub max_retries: u32,
     pub base_gas_price: u64,
+    costs: RefCell<CostBreakdown>, // Accumulated by this manager's calls, see take_costs
 }
 
 impl Default for ChainFusionManager {
@@ -29,6 +51,7 @@ impl Default for ChainFusionManager {
             evm_chain_id: 84532, // Base Sepolia
             max_retries: 3,
             base_gas_price: 1_000_000_000, // 1 gwei
+            costs: RefCell::default(),
         }
     }
 }
@@ -46,9 +69,15 @@ impl ChainFusionManager {
             evm_chain_id,
             max_retries: 3,
             base_gas_price: 1_000_000_000,
+            costs: RefCell::default(),
         }
     }
 
+    /// Take the costs of the calls made so far, resetting the accumulator
+    pub fn take_costs(&self) -> CostBreakdown {
+        self.costs.take()
+    }
+
     /// Get RPC service based on network (production pattern from EvmManager)
     pub fn get_rpc_service(&self) -> RpcService {
         let network = std::env::var("VITE_NETWORK").unwrap_or_else(|_| "mainnet".to_string());
@@ -67,6 +96,10 @@ impl ChainFusionManager {
 
     /// Utility function for inter-canister calls with cycles (enhanced with retry logic)
     async fn call_evm_rpc_canister(&self, method: &str, args: String) -> Result<String, Error> {
+        if let Some(strategy) = memory::get_rpc_provider_strategy(self.evm_chain_id) {
+            return self.call_evm_rpc_providers(&strategy, method, &args).await;
+        }
+
         let max_retries = 3;
         let mut attempt = 0;
 
@@ -79,6 +112,11 @@ impl ChainFusionManager {
                 self.evm_rpc_canister
             );
 
+            self.costs.borrow_mut().add(&CostBreakdown {
+                evm_rpc_cycles: EVM_RPC_CYCLES_COST,
+                retries: u64::from(attempt > 1),
+                ..Default::default()
+            });
             let result = self._call_evm_rpc_canister(method, &args).await;
 
             match result {
@@ -89,7 +127,7 @@ impl ChainFusionManager {
                 Err(e) => {
                     ic_cdk::println!("EVM RPC call failed on attempt {}: {:?}", attempt, e);
                     if attempt == max_retries {
-                        return Err(e);
+                        return Err(Error::rpc_failed(method, e));
                     }
                     // Exponential backoff: wait 2^attempt seconds
                     let delay = 2u64.pow(attempt as u32);
@@ -99,11 +137,75 @@ impl ChainFusionManager {
             }
         }
 
-        Err(Error::ChainFusionRequestFailed)
+        Err(Error::rpc_failed(method, Error::ChainFusionRequestFailed))
+    }
+
+    /// Call every provider of a set, failing over to the next set when a whole set fails
+    ///
+    /// State reads need `k_of_n` identical responses within the set; other calls take the first.
+    async fn call_evm_rpc_providers(
+        &self,
+        strategy: &RpcProviderStrategy,
+        method: &str,
+        args: &str,
+    ) -> Result<String, Error> {
+        for providers in &strategy.provider_sets {
+            let mut responses = Vec::new();
+            for provider in providers {
+                self.costs.borrow_mut().add(&CostBreakdown {
+                    evm_rpc_cycles: EVM_RPC_CYCLES_COST,
+                    ..Default::default()
+                });
+                match self.call_evm_rpc_provider(provider, method, args).await {
+                    Ok(response) => responses.push((provider.as_str(), response)),
+                    Err(e) => {
+                        ic_cdk::println!(
+                            "EVM RPC provider {} failed {}: {:?}",
+                            provider,
+                            method,
+                            e
+                        );
+                        memory::update_rpc_provider_stats(self.evm_chain_id, provider, |stats| {
+                            stats.errors += 1
+                        });
+                    }
+                }
+            }
+
+            if responses.is_empty() {
+                continue;
+            }
+            let k_of_n = if requires_consensus(method) { strategy.k_of_n } else { 1 };
+            return settle_provider_responses(self.evm_chain_id, method, responses, k_of_n);
+        }
+
+        Err(Error::rpc_failed(method, Error::RpcError("every provider set failed".to_string())))
+    }
+
+    /// Call the EVM RPC canister through one provider
+    async fn call_evm_rpc_provider(
+        &self,
+        provider: &str,
+        method: &str,
+        args: &str,
+    ) -> Result<String, Error> {
+        #[cfg(test)]
+        if let Some(response) = tests::mock_provider_response(provider, method) {
+            return response;
+        }
+
+        // For MVP, every provider answers like the simulated default provider
+        ic_cdk::println!("EVM RPC call {} via provider {}", method, provider);
+        self._call_evm_rpc_canister(method, args).await
     }
 
     /// Internal EVM RPC call implementation
     async fn _call_evm_rpc_canister(&self, method: &str, args: &str) -> Result<String, Error> {
+        #[cfg(test)]
+        if let Some(response) = tests::mock_rpc_response(method, args) {
+            return response;
+        }
+
         // For MVP, simulate EVM RPC calls with enhanced error handling
         match method {
             "eth_sendTransaction" => {
@@ -127,6 +229,11 @@ impl ChainFusionManager {
                 }
                 Ok("0x0000000000000000000000000000000000000000000000000000000000000001".to_string())
             }
+            "eth_getTransactionByHash" => {
+                // Simulate a transaction that is still known to the network
+                Ok(format!("{{\"hash\":\"{}\"}}", args))
+            }
+            "eth_getTransactionCount" => Ok("0x0".to_string()),
             _ => Err(Error::InvalidData(format!("Unknown method: {}", method))),
         }
     }
@@ -137,7 +244,10 @@ impl ChainFusionManager {
         // In production, this would use threshold ECDSA to derive actual EVM address
 
         if order_hash.len() < 10 {
-            return Err(Error::InvalidData("Order hash too short".to_string()));
+            return Err(Error::EcdsaFailed {
+                stage: "address_derivation".to_string(),
+                detail: "Order hash too short".to_string(),
+            });
         }
 
         // Create deterministic address from order hash (last 40 chars for address)
@@ -248,6 +358,7 @@ impl ChainFusionManager {
             key_id,
         };
 
+        self.costs.borrow_mut().ecdsa_signatures += 1;
         match sign_with_ecdsa(sign_args).await {
             Ok((response,)) => Ok(response.signature),
             Err((_, err)) => {
@@ -322,21 +433,199 @@ impl ChainFusionManager {
             }
         }
 
-        // Step 2: Deploy contract
-        let contract_bytecode = self.get_escrow_contract_bytecode(&params)?;
-        let constructor_args = self.encode_constructor_args(&params)?;
-        let tx_hash =
-            self.deploy_contract_via_chain_fusion(contract_bytecode, constructor_args).await?;
-
-        // Step 3: Get receipt and extract contract address
-        let receipt = self.get_transaction_receipt(tx_hash).await?;
+        // Step 2: Deploy contract, resolving any earlier attempt for this order first
         let contract_address =
-            receipt.contract_address.ok_or_else(|| Error::EscrowCreationFailed)?;
+            self.deploy_escrow_idempotently(&params, ic_cdk::api::time()).await?;
 
         ic_cdk::println!("EVM escrow contract deployed at: {}", contract_address);
         Ok(contract_address)
     }
 
+    /// Deploy the escrow contract for an order at most once across retries
+    ///
+    /// An earlier attempt is resolved before anything new is broadcast: a confirmed attempt
+    /// returns its contract, a pending one fails without rebroadcasting, and only an attempt
+    /// whose transaction is provably dropped is replaced using the next nonce.
+    pub async fn deploy_escrow_idempotently(
+        &self,
+        params: &EVMEscrowParams,
+        now: u64,
+    ) -> Result<String, Error> {
+        let nonce = match memory::get_latest_deployment_attempt(&params.order_hash) {
+            Some(attempt) => match attempt.status {
+                DeploymentStatus::Confirmed => {
+                    return attempt.contract_address.ok_or(Error::EscrowCreationFailed);
+                }
+                DeploymentStatus::Broadcast => {
+                    if let Some(address) = self.resolve_deployment(&attempt, now).await? {
+                        return Ok(address);
+                    }
+                    attempt.nonce + 1
+                }
+                DeploymentStatus::Dropped => attempt.nonce + 1,
+            },
+            None => self.get_deployer_nonce(&params.evm_address).await?,
+        };
+
+        let prepared = self.prepare_deployment_tx(params, nonce)?;
+        let raw_tx_hash = prepared.tx_hash;
+
+        // Persist the attempt before broadcasting so a retry can find the transaction
+        memory::record_deployment_attempt(DeploymentAttempt {
+            order_hash: params.order_hash.clone(),
+            raw_tx_hash: raw_tx_hash.clone(),
+            nonce,
+            status: DeploymentStatus::Broadcast,
+            contract_address: None,
+            created_at: now,
+            updated_at: now,
+        });
+
+        self.deploy_contract_via_chain_fusion(prepared.raw_fields).await?;
+        self.confirm_deployment(&params.order_hash, raw_tx_hash, now).await
+    }
+
+    /// Build the deployment transaction the next `deploy_escrow_idempotently` call would send,
+    /// without signing, broadcasting or recording an attempt
+    pub async fn prepare_evm_escrow_tx(
+        &self,
+        params: &EVMEscrowParams,
+    ) -> Result<PreparedTx, Error> {
+        let nonce = match memory::get_latest_deployment_attempt(&params.order_hash) {
+            Some(attempt) => match attempt.status {
+                DeploymentStatus::Confirmed => {
+                    return Err(Error::InvalidData(format!(
+                        "Escrow for order {} is already deployed",
+                        params.order_hash
+                    )));
+                }
+                DeploymentStatus::Broadcast => {
+                    return Err(Error::InvalidData(format!(
+                        "Deployment {} is not yet resolved",
+                        attempt.raw_tx_hash
+                    )));
+                }
+                DeploymentStatus::Dropped => attempt.nonce + 1,
+            },
+            None => self.get_deployer_nonce(&params.evm_address).await?,
+        };
+
+        self.prepare_deployment_tx(params, nonce)
+    }
+
+    /// Validate, encode and price the deployment transaction of an escrow at a given nonce
+    fn prepare_deployment_tx(
+        &self,
+        params: &EVMEscrowParams,
+        nonce: u64,
+    ) -> Result<PreparedTx, Error> {
+        if params.amount == 0 || params.hash_lock.is_empty() {
+            return Err(Error::InvalidEscrowParameters);
+        }
+
+        let constructor_args = self.encode_constructor_args(params)?;
+        let config = memory::get_create2_config();
+        let raw_fields = match &config {
+            Some(config) => build_factory_deployment_tx(
+                config,
+                &params.order_hash,
+                constructor_args,
+                nonce,
+                self.base_gas_price,
+            ),
+            None => {
+                let contract_bytecode = self.get_escrow_contract_bytecode(params)?;
+                self.build_deployment_tx(contract_bytecode, constructor_args, nonce)
+            }
+        };
+        let predicted_address = match &config {
+            Some(config) => Some(predict_escrow_address(config, &params.order_hash)?),
+            None => None,
+        };
+
+        Ok(PreparedTx {
+            tx_hash: deployment_tx_hash(&raw_fields),
+            raw_fields,
+            nonce,
+            predicted_address,
+            estimated_gas: ESCROW_DEPLOYMENT_GAS,
+            estimated_cycles: DEPLOYMENT_RPC_CALLS * EVM_RPC_CYCLES_COST,
+        })
+    }
+
+    /// Resolve a broadcast attempt: its contract once confirmed, None once provably dropped
+    async fn resolve_deployment(
+        &self,
+        attempt: &DeploymentAttempt,
+        now: u64,
+    ) -> Result<Option<String>, Error> {
+        match self.confirm_deployment(&attempt.order_hash, attempt.raw_tx_hash.clone(), now).await {
+            Ok(address) => return Ok(Some(address)),
+            Err(e) => ic_cdk::println!(
+                "No receipt yet for deployment {} of order {}: {}",
+                attempt.raw_tx_hash,
+                attempt.order_hash,
+                e
+            ),
+        }
+
+        let transaction = self
+            .call_evm_rpc_canister("eth_getTransactionByHash", attempt.raw_tx_hash.clone())
+            .await?;
+        if transaction.trim() != "null" {
+            return Err(Error::RpcFailed {
+                method: "eth_getTransactionReceipt".to_string(),
+                detail: format!("Deployment {} is still pending", attempt.raw_tx_hash),
+            });
+        }
+
+        ic_cdk::println!("Deployment {} was dropped, rebroadcasting", attempt.raw_tx_hash);
+        memory::update_latest_deployment_attempt(
+            &attempt.order_hash,
+            DeploymentStatus::Dropped,
+            None,
+            now,
+        )
+        .map_err(|_| Error::SystemError)?;
+        Ok(None)
+    }
+
+    /// Look up a deployment receipt and mark the latest attempt confirmed
+    async fn confirm_deployment(
+        &self,
+        order_hash: &str,
+        tx_hash: String,
+        now: u64,
+    ) -> Result<String, Error> {
+        let receipt = self.get_transaction_receipt(tx_hash).await?;
+        let contract_address = receipt.contract_address.ok_or_else(|| Error::RpcFailed {
+            method: "eth_getTransactionReceipt".to_string(),
+            detail: "Receipt has no contract address".to_string(),
+        })?;
+
+        memory::update_latest_deployment_attempt(
+            order_hash,
+            DeploymentStatus::Confirmed,
+            Some(contract_address.clone()),
+            now,
+        )
+        .map_err(|_| Error::SystemError)?;
+
+        if let Some(config) = memory::get_create2_config() {
+            check_predicted_address(&config, order_hash, &contract_address, now)?;
+        }
+        Ok(contract_address)
+    }
+
+    /// Get the next nonce of the deploying account
+    async fn get_deployer_nonce(&self, address: &str) -> Result<u64, Error> {
+        let args = format!("[\"{}\",\"pending\"]", address);
+        let response = self.call_evm_rpc_canister("eth_getTransactionCount", args).await?;
+        u64::from_str_radix(response.trim_start_matches("0x"), 16).map_err(|e| {
+            Error::rpc_failed("eth_getTransactionCount", Error::DecodeError(e.to_string()))
+        })
+    }
+
     /// Get transaction receipt (production pattern from EvmManager)
     pub async fn get_transaction_receipt(
         &self,
@@ -345,36 +634,54 @@ impl ChainFusionManager {
         let service = self.get_rpc_service();
 
         // For MVP, simulate getting transaction receipt (in production this would be real EVM RPC call)
-        let _response = self
+        let response = self
             .call_evm_rpc_canister("eth_getTransactionReceipt", transaction_hash.clone())
             .await?;
 
-        // Create a mock transaction receipt for testing
+        // Create a mock transaction receipt for testing, keeping any contract address returned
+        let contract_address = json_string_field(&response, "contractAddress")
+            .unwrap_or_else(|| "0x1234567890123456789012345678901234567890".to_string());
         let mock_receipt = TransactionReceipt {
             transaction_hash: transaction_hash.clone(),
             status: Some(candid::Nat::from(1u32)),
-            contract_address: Some("0x1234567890123456789012345678901234567890".to_string()),
+            contract_address: Some(contract_address),
             logs: vec![],
             gas_used: Some(candid::Nat::from(100000u32)),
+            effective_gas_price: Some(candid::Nat::from(self.base_gas_price)),
         };
 
+        if let (Some(gas_used), Some(gas_price)) =
+            (&mock_receipt.gas_used, &mock_receipt.effective_gas_price)
+        {
+            let gas_wei =
+                u64::try_from(&(gas_used.0.clone() * gas_price.0.clone())).unwrap_or(u64::MAX);
+            self.costs
+                .borrow_mut()
+                .add(&CostBreakdown { estimated_evm_gas_wei: gas_wei, ..Default::default() });
+        }
+
         ic_cdk::println!("Transaction receipt retrieved successfully (simulated)");
         Ok(mock_receipt)
     }
 
-    async fn deploy_contract_via_chain_fusion(
+    /// Build the deployment transaction parameters for a given nonce
+    fn build_deployment_tx(
         &self,
         bytecode: String,
         constructor_args: String,
-    ) -> Result<String, Error> {
-        let service = self.get_rpc_service();
+        nonce: u64,
+    ) -> String {
         let full_data = format!("{}{}", bytecode, constructor_args);
 
         // For MVP, use simple transaction parameters (placeholder)
-        let tx_params = format!(
-            "{{\"data\":\"{}\",\"gas\":\"0x186A0\",\"gasPrice\":\"0x{:x}\",\"value\":\"0x0\"}}",
-            full_data, self.base_gas_price
-        );
+        format!(
+            "{{\"data\":\"{}\",\"gas\":\"0x{:X}\",\"gasPrice\":\"0x{:x}\",\"nonce\":\"0x{:x}\",\"value\":\"0x0\"}}",
+            full_data, ESCROW_DEPLOYMENT_GAS, self.base_gas_price, nonce
+        )
+    }
+
+    async fn deploy_contract_via_chain_fusion(&self, tx_params: String) -> Result<String, Error> {
+        let service = self.get_rpc_service();
 
         // For MVP, simulate the transaction (in production this would be real EVM RPC call)
         let response = self.call_evm_rpc_canister("eth_sendTransaction", tx_params).await?;
@@ -415,4 +722,998 @@ impl ChainFusionManager {
             }
         }
     }
+
+    /// Verify a deployed EVM escrow holds the amount, hashlock and timelocks recorded on the
+    /// HTLC escrow, rather than only checking that a call to it does not revert
+    pub async fn verify_evm_escrow_immutables(
+        &self,
+        escrow_address: String,
+        expected: &HTLCEscrow,
+        verified_at: u64,
+    ) -> Result<EscrowVerificationReport, Error> {
+        ic_cdk::println!("Verifying EVM escrow immutables for address: {}", escrow_address);
+
+        let call_params =
+            format!("{{\"to\":\"{}\",\"data\":\"{}\"}}", escrow_address, GET_IMMUTABLES_SELECTOR);
+        let response = self.call_evm_rpc_canister("eth_call", call_params).await?;
+        let immutables =
+            decode_escrow_immutables(&response).map_err(|e| Error::rpc_failed("eth_call", e))?;
+
+        let report = build_verification_report(escrow_address, expected, &immutables, verified_at);
+        if !report.passed {
+            ic_cdk::println!(
+                "EVM escrow verification failed for order {}: {} mismatched field(s)",
+                report.order_hash,
+                report.mismatches.len()
+            );
+        }
+
+        Ok(report)
+    }
+}
+
+/// Whether an RPC method reads chain state, so providers must agree on the response
+fn requires_consensus(method: &str) -> bool {
+    matches!(method, "eth_getTransactionReceipt" | "eth_call")
+}
+
+/// Pick the only response at least `k_of_n` providers returned, recording each provider's outcome
+///
+/// Providers outvoted by the agreed response count as disagreeing; without a single agreed
+/// response every provider does, and the call fails rather than picking one arbitrarily.
+fn settle_provider_responses(
+    chain_id: u64,
+    method: &str,
+    responses: Vec<(&str, String)>,
+    k_of_n: u32,
+) -> Result<String, Error> {
+    let mut votes: Vec<(&str, u32)> = Vec::new();
+    for (_, response) in &responses {
+        match votes.iter_mut().find(|(candidate, _)| candidate == response) {
+            Some((_, count)) => *count += 1,
+            None => votes.push((response, 1)),
+        }
+    }
+    let agreed: Vec<&str> =
+        votes.iter().filter(|(_, count)| *count >= k_of_n).map(|(response, _)| *response).collect();
+
+    let consensus = match agreed.as_slice() {
+        [response] => Some(response.to_string()),
+        _ => None,
+    };
+    for (provider, response) in &responses {
+        let agrees = consensus.as_ref() == Some(response);
+        memory::update_rpc_provider_stats(chain_id, provider, |stats| {
+            if agrees {
+                stats.successes += 1;
+            } else {
+                stats.disagreements += 1;
+            }
+        });
+    }
+
+    consensus.ok_or_else(|| Error::ProviderConsensusFailure {
+        method: method.to_string(),
+        detail: format!(
+            "{} distinct responses from {} providers, {} must agree",
+            votes.len(),
+            responses.len(),
+            k_of_n
+        ),
+    })
+}
+
+/// Reject strategies without providers or whose agreement no provider set can reach
+pub fn validate_rpc_provider_strategy(strategy: &RpcProviderStrategy) -> Result<(), Error> {
+    let smallest_set = strategy.provider_sets.iter().map(Vec::len).min().unwrap_or(0);
+    if smallest_set == 0 || strategy.provider_sets.iter().flatten().any(String::is_empty) {
+        return Err(Error::InvalidData("every provider set needs named providers".to_string()));
+    }
+    if strategy.k_of_n == 0 || strategy.k_of_n as usize > smallest_set {
+        return Err(Error::InvalidData(format!(
+            "k_of_n must be between 1 and {}, the smallest provider set",
+            smallest_set
+        )));
+    }
+    Ok(())
+}
+
+/// Hash identifying a deployment transaction before it is broadcast
+///
+/// Placeholder until deployments are signed: hashes the transaction parameters the way the EVM
+/// hashes the signed raw transaction, which is likewise known before broadcasting.
+fn deployment_tx_hash(tx_params: &str) -> String {
+    let digest = fusion_crypto::keccak256(tx_params.as_bytes());
+    format!("0x{}", digest.iter().map(|b| format!("{:02x}", b)).collect::<String>())
+}
+
+/// Read a string field from a flat JSON object
+fn json_string_field(json: &str, field: &str) -> Option<String> {
+    let start = json.find(&format!("\"{}\":\"", field))? + field.len() + 4;
+    let end = json[start..].find('"')?;
+    Some(json[start..start + end].to_string())
+}
+
+// ============================================================================
+// CREATE2 ADDRESS HELPERS
+// ============================================================================
+
+/// CREATE2 salt of an order's escrow: keccak256 of the order hash string
+pub fn escrow_salt(order_hash: &str) -> [u8; 32] {
+    fusion_crypto::keccak256(order_hash.as_bytes())
+}
+
+/// Compute a CREATE2 address: the last 20 bytes of
+/// keccak256(0xff ‖ deployer ‖ salt ‖ keccak256(init_code))
+pub fn compute_create2_address(
+    deployer: &str,
+    salt: &[u8; 32],
+    init_code_hash: &[u8; 32],
+) -> Result<String, Error> {
+    let deployer: [u8; 20] = decode_fixed_hex(deployer, "factory")?;
+
+    let mut preimage = Vec::with_capacity(85);
+    preimage.push(0xff);
+    preimage.extend_from_slice(&deployer);
+    preimage.extend_from_slice(salt);
+    preimage.extend_from_slice(init_code_hash);
+
+    let digest = fusion_crypto::keccak256(&preimage);
+    Ok(format!("0x{}", digest[12..].iter().map(|b| format!("{:02x}", b)).collect::<String>()))
+}
+
+/// Predict the address the factory deploys an order's escrow at
+pub fn predict_escrow_address(config: &Create2Config, order_hash: &str) -> Result<String, Error> {
+    let init_code_hash: [u8; 32] = decode_fixed_hex(&config.init_code_hash, "init_code_hash")?;
+    compute_create2_address(&config.factory, &escrow_salt(order_hash), &init_code_hash)
+}
+
+/// Build the factory call deploying an order's escrow under its CREATE2 salt
+///
+/// Placeholder encoding like `build_deployment_tx`: selector, salt and constructor words.
+fn build_factory_deployment_tx(
+    config: &Create2Config,
+    order_hash: &str,
+    constructor_args: String,
+    nonce: u64,
+    gas_price: u64,
+) -> String {
+    let selector = &fusion_crypto::keccak256(FACTORY_DEPLOY_SIGNATURE.as_bytes())[..4];
+    let data = format!(
+        "0x{}{}{}",
+        selector.iter().map(|b| format!("{:02x}", b)).collect::<String>(),
+        escrow_salt(order_hash).iter().map(|b| format!("{:02x}", b)).collect::<String>(),
+        constructor_args
+    );
+
+    format!(
+        "{{\"to\":\"{}\",\"data\":\"{}\",\"gas\":\"0x{:X}\",\"gasPrice\":\"0x{:x}\",\"nonce\":\"0x{:x}\",\"value\":\"0x0\"}}",
+        normalize_address(&config.factory),
+        data,
+        ESCROW_DEPLOYMENT_GAS,
+        gas_price,
+        nonce
+    )
+}
+
+/// Flag the escrow with a failing verification report when the deployed contract is not at
+/// its predicted CREATE2 address, which blocks the secret reveal
+fn check_predicted_address(
+    config: &Create2Config,
+    order_hash: &str,
+    contract_address: &str,
+    now: u64,
+) -> Result<(), Error> {
+    let predicted = predict_escrow_address(config, order_hash)?;
+    let actual = normalize_address(contract_address);
+    if predicted == actual {
+        return Ok(());
+    }
+
+    ic_cdk::println!(
+        "⚠️ Escrow for order {} deployed at {}, predicted {}",
+        order_hash,
+        actual,
+        predicted
+    );
+    memory::store_verification_report(EscrowVerificationReport {
+        order_hash: order_hash.to_string(),
+        escrow_address: actual.clone(),
+        passed: false,
+        mismatches: vec![FieldMismatch {
+            field: "address".to_string(),
+            expected: predicted,
+            actual,
+        }],
+        verified_at: now,
+    });
+    Ok(())
+}
+
+/// Decode 0x-prefixed hex of an exact byte length
+fn decode_fixed_hex<const N: usize>(value: &str, field: &str) -> Result<[u8; N], Error> {
+    let hex = value.strip_prefix("0x").unwrap_or(value);
+    if hex.len() != 2 * N || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(Error::InvalidData(format!("{} must be {} bytes of hex", field, N)));
+    }
+
+    let mut bytes = [0u8; N];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16)
+            .map_err(|e| Error::InvalidData(format!("{}: {}", field, e)))?;
+    }
+    Ok(bytes)
+}
+
+// ============================================================================
+// EVM ESCROW VERIFICATION HELPERS
+// ============================================================================
+
+/// Decode the ABI-encoded `getImmutables()` return data of the reference escrow contract
+///
+/// Layout: orderHash, hashlock, maker, taker, token, amount, safetyDeposit, timelocks
+pub fn decode_escrow_immutables(data: &str) -> Result<EvmEscrowImmutables, Error> {
+    let hex = data.strip_prefix("0x").unwrap_or(data);
+    if hex.len() != IMMUTABLES_WORD_COUNT * 64 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(Error::DecodeError(format!(
+            "getImmutables returned {} hex chars, expected {}",
+            hex.len(),
+            IMMUTABLES_WORD_COUNT * 64
+        )));
+    }
+
+    let hex = hex.to_ascii_lowercase();
+    let word = |index: usize| &hex[index * 64..(index + 1) * 64];
+
+    Ok(EvmEscrowImmutables {
+        order_hash: format!("0x{}", word(0)),
+        hashlock: format!("0x{}", word(1)),
+        maker: format!("0x{}", &word(2)[24..]),
+        taker: format!("0x{}", &word(3)[24..]),
+        token: format!("0x{}", &word(4)[24..]),
+        amount: decode_u64_word(word(5), "amount")?,
+        safety_deposit: decode_u64_word(word(6), "safetyDeposit")?,
+        timelocks: format!("0x{}", word(7)),
+    })
+}
+
+/// Compare decoded immutables field by field against the stored HTLC escrow
+pub fn build_verification_report(
+    escrow_address: String,
+    expected: &HTLCEscrow,
+    actual: &EvmEscrowImmutables,
+    verified_at: u64,
+) -> EscrowVerificationReport {
+    let checks = [
+        ("order_hash", normalize_word(&expected.order_hash), actual.order_hash.clone()),
+        ("hashlock", normalize_word(&expected.hashlock), actual.hashlock.clone()),
+        ("maker", normalize_address(&expected.maker), actual.maker.clone()),
+        ("taker", normalize_address(&expected.taker), actual.taker.clone()),
+        ("token", normalize_address(&expected.token), actual.token.clone()),
+        ("amount", expected.amount.to_string(), actual.amount.to_string()),
+        ("safety_deposit", expected.safety_deposit.to_string(), actual.safety_deposit.to_string()),
+        ("timelocks", format!("0x{:064x}", expected.timelock), actual.timelocks.clone()),
+    ];
+
+    let mismatches: Vec<FieldMismatch> = checks
+        .into_iter()
+        .filter(|(_, expected, actual)| expected != actual)
+        .map(|(field, expected, actual)| FieldMismatch {
+            field: field.to_string(),
+            expected,
+            actual,
+        })
+        .collect();
+
+    EscrowVerificationReport {
+        order_hash: expected.order_hash.clone(),
+        escrow_address,
+        passed: mismatches.is_empty(),
+        mismatches,
+        verified_at,
+    }
+}
+
+/// Decode a 32-byte ABI word that must fit into a u64
+fn decode_u64_word(word: &str, field: &str) -> Result<u64, Error> {
+    if word[..48].chars().any(|c| c != '0') {
+        return Err(Error::DecodeError(format!("{} does not fit into u64", field)));
+    }
+    u64::from_str_radix(&word[48..], 16)
+        .map_err(|e| Error::DecodeError(format!("Invalid {} word: {}", field, e)))
+}
+
+/// Normalize a hex value to a 0x-prefixed, lowercase, left-padded 32-byte word
+fn normalize_word(value: &str) -> String {
+    let hex = value.strip_prefix("0x").unwrap_or(value).to_ascii_lowercase();
+    format!("0x{:0>64}", hex)
+}
+
+/// Normalize an EVM address to 0x-prefixed lowercase hex
+fn normalize_address(value: &str) -> String {
+    let hex = value.strip_prefix("0x").unwrap_or(value).to_ascii_lowercase();
+    format!("0x{}", hex)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{EscrowError, EscrowStatus, EscrowType, TimelockConfig};
+    use std::cell::RefCell;
+    use std::collections::HashMap;
+
+    type MockResponse = (String, String, Result<String, Error>);
+
+    thread_local! {
+        static RPC_MOCKS: RefCell<Vec<MockResponse>> = const { RefCell::new(Vec::new()) };
+        static RPC_CALLS: RefCell<HashMap<String, u32>> = RefCell::new(HashMap::new());
+        static RPC_FAILURES: RefCell<HashMap<String, u32>> = RefCell::new(HashMap::new());
+        static PROVIDER_MOCKS: RefCell<HashMap<String, Result<String, Error>>> = RefCell::new(HashMap::new());
+        static PROVIDER_CALLS: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+    }
+
+    /// Log the provider call and return the provider's mocked response, if it has one
+    pub(super) fn mock_provider_response(
+        provider: &str,
+        _method: &str,
+    ) -> Option<Result<String, Error>> {
+        PROVIDER_CALLS.with(|calls| calls.borrow_mut().push(provider.to_string()));
+        PROVIDER_MOCKS.with(|mocks| mocks.borrow().get(provider).cloned())
+    }
+
+    fn mock_provider(provider: &str, response: Result<String, Error>) {
+        PROVIDER_MOCKS.with(|mocks| mocks.borrow_mut().insert(provider.to_string(), response));
+    }
+
+    fn provider_calls() -> Vec<String> {
+        PROVIDER_CALLS.with(|calls| calls.borrow().clone())
+    }
+
+    fn provider_stats(provider: &str) -> crate::types::RpcProviderStats {
+        memory::get_rpc_provider_stats()
+            .into_iter()
+            .find(|stats| stats.provider == provider)
+            .unwrap()
+    }
+
+    /// Query the default manager's chain through the given provider sets
+    fn use_providers(provider_sets: &[&[&str]], k_of_n: u32) {
+        let strategy = RpcProviderStrategy {
+            provider_sets: provider_sets
+                .iter()
+                .map(|set| set.iter().map(|provider| provider.to_string()).collect())
+                .collect(),
+            k_of_n,
+        };
+        validate_rpc_provider_strategy(&strategy).unwrap();
+        memory::set_rpc_provider_strategy(ChainFusionManager::default().evm_chain_id, strategy);
+    }
+
+    /// Count the RPC call and return a mocked response, if one matches the method and args
+    pub(super) fn mock_rpc_response(method: &str, args: &str) -> Option<Result<String, Error>> {
+        RPC_CALLS.with(|calls| *calls.borrow_mut().entry(method.to_string()).or_insert(0) += 1);
+        let fail = RPC_FAILURES.with(|failures| match failures.borrow_mut().get_mut(method) {
+            Some(remaining) if *remaining > 0 => {
+                *remaining -= 1;
+                true
+            }
+            _ => false,
+        });
+        if fail {
+            return Some(Err(Error::ChainFusionRequestFailed));
+        }
+        RPC_MOCKS.with(|mocks| {
+            mocks
+                .borrow()
+                .iter()
+                .find(|(m, pattern, _)| m == method && args.contains(pattern.as_str()))
+                .map(|(_, _, response)| response.clone())
+        })
+    }
+
+    fn mock_rpc(method: &str, args_containing: &str, response: Result<String, Error>) {
+        RPC_MOCKS.with(|mocks| {
+            mocks.borrow_mut().push((method.to_string(), args_containing.to_string(), response))
+        });
+    }
+
+    /// Fail the next `count` calls of a method, as a transient RPC outage would
+    fn fail_next_rpc(method: &str, count: u32) {
+        RPC_FAILURES.with(|failures| failures.borrow_mut().insert(method.to_string(), count));
+    }
+
+    fn rpc_calls(method: &str) -> u32 {
+        RPC_CALLS.with(|calls| calls.borrow().get(method).copied().unwrap_or(0))
+    }
+
+    fn reset_rpc_mocks() {
+        RPC_MOCKS.with(|mocks| mocks.borrow_mut().clear());
+        RPC_CALLS.with(|calls| calls.borrow_mut().clear());
+        RPC_FAILURES.with(|failures| failures.borrow_mut().clear());
+        PROVIDER_MOCKS.with(|mocks| mocks.borrow_mut().clear());
+        PROVIDER_CALLS.with(|calls| calls.borrow_mut().clear());
+        memory::clear_escrow_data();
+    }
+
+    fn deployment_params() -> EVMEscrowParams {
+        EVMEscrowParams {
+            order_hash: "0xorder".to_string(),
+            evm_address: MAKER.to_string(),
+            amount: 1_000_000,
+            timelock: 1_700_000_000,
+            safety_deposit: 10_000,
+            hash_lock: HASHLOCK.to_string(),
+            src_token: "ICP".to_string(),
+            dst_token: TOKEN.to_string(),
+            src_amount: 1_000_000,
+            dst_amount: 1_000_000,
+        }
+    }
+
+    const HASHLOCK: &str = "1111111111111111111111111111111111111111111111111111111111111111";
+    const MAKER: &str = "0x00000000000000000000000000000000000000aa";
+    const TAKER: &str = "0x00000000000000000000000000000000000000bb";
+    const TOKEN: &str = "0x00000000000000000000000000000000000000cc";
+
+    fn expected_escrow() -> HTLCEscrow {
+        HTLCEscrow {
+            order_hash: "0xabcdef0123456789".to_string(),
+            hashlock: HASHLOCK.to_string(),
+            maker: MAKER.to_string(),
+            taker: TAKER.to_string(),
+            token: TOKEN.to_string(),
+            amount: 1_000_000,
+            safety_deposit: 10_000,
+            timelock: 1_700_000_000,
+            src_chain_id: 1,
+            dst_chain_id: 84532,
+            src_token: "ICP".to_string(),
+            dst_token: TOKEN.to_string(),
+            src_amount: 1_000_000,
+            dst_amount: 1_000_000,
+            escrow_type: EscrowType::Destination,
+            status: EscrowStatus::Created,
+            address: String::new(),
+            timelock_config: TimelockConfig::default_config(),
+            threshold_ecdsa_key_id: None,
+            chain_health_status: None,
+            partial_fill_info: None,
+            events: vec![],
+            costs: None,
+            created_at: 0,
+            updated_at: 0,
+        }
+    }
+
+    fn encode_response(amount: u64, hashlock: &str) -> String {
+        let address_word = |address: &str| format!("{:0>64}", &address[2..]);
+        format!(
+            "0x{:0>64}{}{}{}{}{:064x}{:064x}{:064x}",
+            "abcdef0123456789",
+            hashlock,
+            address_word(MAKER),
+            address_word(TAKER),
+            address_word(TOKEN),
+            amount,
+            10_000u64,
+            1_700_000_000u64
+        )
+    }
+
+    #[test]
+    fn test_matching_immutables_pass() {
+        let immutables = decode_escrow_immutables(&encode_response(1_000_000, HASHLOCK)).unwrap();
+        let report =
+            build_verification_report("0xescrow".to_string(), &expected_escrow(), &immutables, 42);
+
+        assert!(report.passed);
+        assert!(report.mismatches.is_empty());
+        assert_eq!(report.verified_at, 42);
+    }
+
+    #[test]
+    fn test_short_amount_reported() {
+        let immutables = decode_escrow_immutables(&encode_response(1, HASHLOCK)).unwrap();
+        let report =
+            build_verification_report("0xescrow".to_string(), &expected_escrow(), &immutables, 0);
+
+        assert!(!report.passed);
+        assert_eq!(
+            report.mismatches,
+            vec![FieldMismatch {
+                field: "amount".to_string(),
+                expected: "1000000".to_string(),
+                actual: "1".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_hashlock_mismatch_reported() {
+        let other_hashlock = "22".repeat(32);
+        let immutables =
+            decode_escrow_immutables(&encode_response(1_000_000, &other_hashlock)).unwrap();
+        let report =
+            build_verification_report("0xescrow".to_string(), &expected_escrow(), &immutables, 0);
+
+        assert!(!report.passed);
+        assert_eq!(report.mismatches.len(), 1);
+        assert_eq!(report.mismatches[0].field, "hashlock");
+        assert_eq!(report.mismatches[0].actual, format!("0x{}", other_hashlock));
+    }
+
+    #[test]
+    fn test_malformed_response_rejected() {
+        // The bare success word returned by a lookalike contract is not a valid immutables payload
+        let result = decode_escrow_immutables(
+            "0x0000000000000000000000000000000000000000000000000000000000000001",
+        );
+        assert!(matches!(result, Err(Error::DecodeError(_))));
+    }
+
+    /// Drive a future that never waits on a real inter-canister call to completion
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        let mut future = std::pin::pin!(future);
+        let mut context = std::task::Context::from_waker(std::task::Waker::noop());
+        loop {
+            if let std::task::Poll::Ready(output) = future.as_mut().poll(&mut context) {
+                return output;
+            }
+        }
+    }
+
+    #[test]
+    fn test_failed_transaction_keeps_rpc_method() {
+        let manager = ChainFusionManager::default();
+        let error =
+            block_on(manager.deploy_contract_via_chain_fusion("invalid".to_string())).unwrap_err();
+
+        match EscrowError::from(error) {
+            EscrowError::ChainFusion { method, detail } => {
+                assert_eq!(method, "eth_sendTransaction");
+                assert_eq!(detail, "Chain Fusion request failed");
+            }
+            other => panic!("unexpected error: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_pending_receipt_is_distinguishable() {
+        let manager = ChainFusionManager::default();
+        let error = block_on(manager.get_transaction_receipt("0xpending".to_string())).unwrap_err();
+
+        match EscrowError::from(error) {
+            EscrowError::ChainFusion { method, detail } => {
+                assert_eq!(method, "eth_getTransactionReceipt");
+                assert_eq!(detail, "Invalid receipt");
+            }
+            other => panic!("unexpected error: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_reverted_and_malformed_calls_keep_context() {
+        let manager = ChainFusionManager::default();
+
+        let reverted = block_on(manager.verify_evm_escrow_immutables(
+            "0xrevert".to_string(),
+            &expected_escrow(),
+            0,
+        ))
+        .unwrap_err();
+        assert!(matches!(
+            EscrowError::from(reverted),
+            EscrowError::ChainFusion { ref method, ref detail }
+                if method == "eth_call" && detail == "Invalid data: Contract call reverted"
+        ));
+
+        // The simulated eth_call returns a single word, too short for getImmutables()
+        let malformed = block_on(manager.verify_evm_escrow_immutables(
+            "0xescrow".to_string(),
+            &expected_escrow(),
+            0,
+        ))
+        .unwrap_err();
+        assert!(matches!(
+            EscrowError::from(malformed),
+            EscrowError::ChainFusion { ref method, ref detail }
+                if method == "eth_call" && detail.starts_with("Decode error:")
+        ));
+    }
+
+    #[test]
+    fn test_ecdsa_errors_keep_stage() {
+        let manager = ChainFusionManager::default();
+        let error = manager.derive_deterministic_evm_address("0x12").unwrap_err();
+
+        match EscrowError::from(error) {
+            EscrowError::Ecdsa { stage, detail } => {
+                assert_eq!(stage, "address_derivation");
+                assert_eq!(detail, "Order hash too short");
+            }
+            other => panic!("unexpected error: {:?}", other),
+        }
+
+        assert!(matches!(
+            EscrowError::from(Error::ThresholdECDSASigningFailed),
+            EscrowError::Ecdsa { ref stage, .. } if stage == "signing"
+        ));
+    }
+
+    #[test]
+    fn test_deployment_retry_after_receipt_timeout_does_not_rebroadcast() {
+        reset_rpc_mocks();
+        let manager = ChainFusionManager::default();
+        let params = deployment_params();
+
+        // First try broadcasts but times out waiting for the receipt
+        mock_rpc("eth_getTransactionReceipt", "", Err(Error::InvalidReceipt));
+        assert!(block_on(manager.deploy_escrow_idempotently(&params, 1)).is_err());
+        assert_eq!(rpc_calls("eth_sendTransaction"), 1);
+
+        // Retry finds the receipt of the original transaction
+        RPC_MOCKS.with(|mocks| mocks.borrow_mut().clear());
+        let address = block_on(manager.deploy_escrow_idempotently(&params, 2)).unwrap();
+        assert_eq!(address, "0x1234567890123456789012345678901234567890");
+        assert_eq!(rpc_calls("eth_sendTransaction"), 1);
+
+        // Later retries return the confirmed contract without touching the chain
+        let again = block_on(manager.deploy_escrow_idempotently(&params, 3)).unwrap();
+        assert_eq!(again, address);
+        assert_eq!(rpc_calls("eth_sendTransaction"), 1);
+
+        let attempts = memory::get_deployment_attempts(&params.order_hash);
+        assert_eq!(attempts.len(), 1);
+        assert_eq!(attempts[0].status, DeploymentStatus::Confirmed);
+        assert_eq!(attempts[0].updated_at, 2);
+    }
+
+    #[test]
+    fn test_pending_deployment_is_not_rebroadcast() {
+        reset_rpc_mocks();
+        let manager = ChainFusionManager::default();
+        let params = deployment_params();
+
+        mock_rpc("eth_getTransactionReceipt", "", Err(Error::InvalidReceipt));
+        assert!(block_on(manager.deploy_escrow_idempotently(&params, 1)).is_err());
+
+        // Transaction is still known to the network, so the retry waits
+        let error = block_on(manager.deploy_escrow_idempotently(&params, 2)).unwrap_err();
+        assert!(matches!(error, Error::RpcFailed { ref detail, .. } if detail.contains("pending")));
+        assert_eq!(rpc_calls("eth_sendTransaction"), 1);
+        assert_eq!(memory::get_deployment_attempts(&params.order_hash).len(), 1);
+    }
+
+    #[test]
+    fn test_dropped_deployment_is_replaced_with_next_nonce() {
+        reset_rpc_mocks();
+        let manager = ChainFusionManager::default();
+        let params = deployment_params();
+
+        mock_rpc("eth_getTransactionCount", "", Ok("0x7".to_string()));
+        mock_rpc("eth_getTransactionReceipt", "", Err(Error::InvalidReceipt));
+        assert!(block_on(manager.deploy_escrow_idempotently(&params, 1)).is_err());
+        let dropped_hash =
+            memory::get_deployment_attempts(&params.order_hash)[0].raw_tx_hash.clone();
+
+        // Original transaction vanished from the mempool
+        RPC_MOCKS.with(|mocks| mocks.borrow_mut().clear());
+        mock_rpc("eth_getTransactionReceipt", &dropped_hash, Err(Error::InvalidReceipt));
+        mock_rpc("eth_getTransactionByHash", &dropped_hash, Ok("null".to_string()));
+        let address = block_on(manager.deploy_escrow_idempotently(&params, 2)).unwrap();
+        assert_eq!(address, "0x1234567890123456789012345678901234567890");
+        assert_eq!(rpc_calls("eth_sendTransaction"), 2);
+
+        let attempts = memory::get_deployment_attempts(&params.order_hash);
+        assert_eq!(attempts.len(), 2);
+        assert_eq!((attempts[0].nonce, &attempts[0].status), (7, &DeploymentStatus::Dropped));
+        assert_eq!((attempts[1].nonce, &attempts[1].status), (8, &DeploymentStatus::Confirmed));
+        assert_ne!(attempts[0].raw_tx_hash, attempts[1].raw_tx_hash);
+        assert_eq!(
+            attempts.iter().filter(|a| a.contract_address.is_some()).count(),
+            1,
+            "only one contract may ever be deployed per order"
+        );
+    }
+
+    #[test]
+    fn test_deployment_costs_include_retries_and_gas() {
+        reset_rpc_mocks();
+        let manager = ChainFusionManager::default();
+        let params = deployment_params();
+
+        // Two transient failures of the nonce lookup, then nonce, broadcast and receipt
+        fail_next_rpc("eth_getTransactionCount", 2);
+        block_on(manager.deploy_escrow_idempotently(&params, 1)).unwrap();
+
+        let costs = manager.take_costs();
+        assert_eq!(
+            costs,
+            CostBreakdown {
+                evm_rpc_cycles: 5 * EVM_RPC_CYCLES_COST,
+                ecdsa_signatures: 0,
+                estimated_evm_gas_wei: 100_000 * manager.base_gas_price,
+                retries: 2,
+            }
+        );
+        assert_eq!(manager.take_costs(), CostBreakdown::default());
+
+        // Costs accumulate on the order's escrow and into the summary of its creation window
+        let mut escrow = expected_escrow();
+        escrow.order_hash = params.order_hash.clone();
+        escrow.created_at = 10;
+        memory::store_htlc_escrow(escrow).unwrap();
+        memory::add_escrow_costs(&params.order_hash, &costs, 11).unwrap();
+        memory::add_escrow_costs(&params.order_hash, &costs, 12).unwrap();
+
+        let stored = memory::get_htlc_escrow(&params.order_hash).unwrap().costs.unwrap();
+        assert_eq!(stored.evm_rpc_cycles, 10 * EVM_RPC_CYCLES_COST);
+        assert_eq!(stored.retries, 4);
+
+        let summary = memory::get_cost_summary(10, 11);
+        assert_eq!((summary.escrow_count, summary.total), (1, stored));
+        assert_eq!(memory::get_cost_summary(11, 20).escrow_count, 0);
+        assert!(memory::add_escrow_costs("0xmissing", &costs, 13).is_err());
+    }
+
+    fn bytes32(hex: &str) -> [u8; 32] {
+        decode_fixed_hex(hex, "test").unwrap()
+    }
+
+    #[test]
+    fn test_create2_address_matches_reference_vectors() {
+        // EIP-1014 examples, as reproduced by foundry's `cast create2`
+        let zero = [0u8; 32];
+        let cases = [
+            ("0x0000000000000000000000000000000000000000", zero, "00", "0x4d1a2e2bb4f88f0250f26ffff098b0b30b26bf38"),
+            ("0xdeadbeef00000000000000000000000000000000", zero, "00", "0xb928f69bb1d91cd65274e3c79d8986362984fda3"),
+            (
+                "0xdeadbeef00000000000000000000000000000000",
+                bytes32("0x000000000000000000000000feed000000000000000000000000000000000000"),
+                "00",
+                "0xd04116cdd17bebe565eb2422f2497e06cc1c9833",
+            ),
+            ("0x0000000000000000000000000000000000000000", zero, "deadbeef", "0x70f2b2914a2a4b783faefb75f459a580616fcb5e"),
+            (
+                "0x00000000000000000000000000000000deadbeef",
+                bytes32("0x00000000000000000000000000000000000000000000000000000000cafebabe"),
+                "deadbeefdeadbeefdeadbeefdeadbeefdeadbeefdeadbeefdeadbeefdeadbeefdeadbeefdeadbeefdeadbeef",
+                "0x1d8bfdc5d46dc4f61d6b6115972536ebe6a8854c",
+            ),
+            ("0x0000000000000000000000000000000000000000", zero, "", "0xe33c0c7f7df4809055c3eba6c09cfe4baf1bd9e0"),
+        ];
+
+        for (deployer, salt, init_code, expected) in cases {
+            let init_code = (0..init_code.len())
+                .step_by(2)
+                .map(|i| u8::from_str_radix(&init_code[i..i + 2], 16).unwrap())
+                .collect::<Vec<u8>>();
+            let init_code_hash = fusion_crypto::keccak256(&init_code);
+            assert_eq!(
+                compute_create2_address(deployer, &salt, &init_code_hash).unwrap(),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_malformed_create2_config_rejected() {
+        let config = Create2Config {
+            factory: "0x1234".to_string(),
+            init_code_hash: format!("0x{}", "ab".repeat(32)),
+        };
+        assert!(matches!(predict_escrow_address(&config, "0xorder"), Err(Error::InvalidData(_))));
+
+        let config =
+            Create2Config { factory: MAKER.to_string(), init_code_hash: "0xzz".to_string() };
+        assert!(matches!(predict_escrow_address(&config, "0xorder"), Err(Error::InvalidData(_))));
+    }
+
+    #[test]
+    fn test_factory_deployment_matches_prediction() {
+        reset_rpc_mocks();
+        let manager = ChainFusionManager::default();
+        let params = deployment_params();
+        let config = Create2Config {
+            factory: MAKER.to_string(),
+            init_code_hash: format!("0x{}", "ab".repeat(32)),
+        };
+        memory::set_create2_config(config.clone());
+        let predicted = predict_escrow_address(&config, &params.order_hash).unwrap();
+
+        // Deployment calls the factory with the salt the prediction used
+        let salt: String =
+            escrow_salt(&params.order_hash).iter().map(|b| format!("{:02x}", b)).collect();
+        mock_rpc("eth_sendTransaction", &salt, Ok("0xfactorytx".to_string()));
+        mock_rpc(
+            "eth_getTransactionReceipt",
+            "",
+            Ok(format!(
+                "{{\"status\":\"0x1\",\"contractAddress\":\"{}\"}}",
+                predicted.to_uppercase().replace("0X", "0x")
+            )),
+        );
+
+        let address = block_on(manager.deploy_escrow_idempotently(&params, 1)).unwrap();
+        assert_eq!(rpc_calls("eth_sendTransaction"), 1);
+        assert_eq!(address.to_lowercase(), predicted);
+        assert!(memory::get_verification_report(&params.order_hash).is_none());
+    }
+
+    #[test]
+    fn test_deployment_away_from_prediction_flags_escrow() {
+        reset_rpc_mocks();
+        let manager = ChainFusionManager::default();
+        let params = deployment_params();
+        let config = Create2Config {
+            factory: MAKER.to_string(),
+            init_code_hash: format!("0x{}", "ab".repeat(32)),
+        };
+        memory::set_create2_config(config.clone());
+
+        // Default simulated receipt reports a fixed contract address
+        let address = block_on(manager.deploy_escrow_idempotently(&params, 1)).unwrap();
+        assert_eq!(address, "0x1234567890123456789012345678901234567890");
+
+        let report = memory::get_verification_report(&params.order_hash).unwrap();
+        assert!(!report.passed);
+        assert_eq!(
+            report.mismatches,
+            vec![FieldMismatch {
+                field: "address".to_string(),
+                expected: predict_escrow_address(&config, &params.order_hash).unwrap(),
+                actual: address,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_prepared_tx_matches_deployment() {
+        reset_rpc_mocks();
+        let params = deployment_params();
+        let config = Create2Config {
+            factory: MAKER.to_string(),
+            init_code_hash: format!("0x{}", "ab".repeat(32)),
+        };
+        memory::set_create2_config(config.clone());
+        mock_rpc("eth_getTransactionCount", "", Ok("0x7".to_string()));
+
+        let prepared =
+            block_on(ChainFusionManager::default().prepare_evm_escrow_tx(&params)).unwrap();
+        assert_eq!(prepared.nonce, 7);
+        assert_eq!(
+            prepared.predicted_address,
+            Some(predict_escrow_address(&config, &params.order_hash).unwrap())
+        );
+        assert!(prepared.raw_fields.contains("\"nonce\":\"0x7\""));
+
+        // Nothing is broadcast and the nonce is not reserved
+        assert_eq!(rpc_calls("eth_sendTransaction"), 0);
+        assert!(memory::get_deployment_attempts(&params.order_hash).is_empty());
+
+        // Real creation sends exactly the prepared transaction
+        mock_rpc("eth_sendTransaction", &prepared.raw_fields, Ok("0xfactorytx".to_string()));
+        mock_rpc(
+            "eth_getTransactionReceipt",
+            &prepared.tx_hash,
+            Ok(format!(
+                "{{\"status\":\"0x1\",\"contractAddress\":\"{}\"}}",
+                prepared.predicted_address.clone().unwrap()
+            )),
+        );
+        let manager = ChainFusionManager::default();
+        let address = block_on(manager.deploy_escrow_idempotently(&params, 1)).unwrap();
+        assert_eq!(Some(address), prepared.predicted_address);
+
+        let attempts = memory::get_deployment_attempts(&params.order_hash);
+        assert_eq!((attempts[0].nonce, &attempts[0].raw_tx_hash), (7, &prepared.tx_hash));
+
+        let costs = manager.take_costs();
+        assert_eq!(costs.evm_rpc_cycles, prepared.estimated_cycles);
+        assert_eq!(costs.estimated_evm_gas_wei, prepared.estimated_gas * manager.base_gas_price);
+
+        // A deployed escrow has nothing left to prepare
+        let error = block_on(manager.prepare_evm_escrow_tx(&params)).unwrap_err();
+        assert!(
+            matches!(error, Error::InvalidData(ref detail) if detail.contains("already deployed"))
+        );
+    }
+
+    #[test]
+    fn test_prepared_tx_follows_dropped_deployment() {
+        reset_rpc_mocks();
+        let manager = ChainFusionManager::default();
+        let params = deployment_params();
+
+        mock_rpc("eth_getTransactionCount", "", Ok("0x7".to_string()));
+        mock_rpc("eth_getTransactionReceipt", "", Err(Error::InvalidReceipt));
+        assert!(block_on(manager.deploy_escrow_idempotently(&params, 1)).is_err());
+
+        // An unresolved broadcast could still confirm, so no replacement is prepared
+        assert!(block_on(manager.prepare_evm_escrow_tx(&params)).is_err());
+
+        memory::update_latest_deployment_attempt(
+            &params.order_hash,
+            DeploymentStatus::Dropped,
+            None,
+            2,
+        )
+        .unwrap();
+        let prepared = block_on(manager.prepare_evm_escrow_tx(&params)).unwrap();
+        assert_eq!((prepared.nonce, prepared.predicted_address), (8, None));
+        assert_eq!(rpc_calls("eth_sendTransaction"), 1);
+
+        let mut invalid = params.clone();
+        invalid.amount = 0;
+        assert!(matches!(
+            block_on(manager.prepare_evm_escrow_tx(&invalid)),
+            Err(Error::InvalidEscrowParameters)
+        ));
+    }
+
+    #[test]
+    fn test_provider_majority_is_accepted() {
+        reset_rpc_mocks();
+        use_providers(&[&["a", "b", "c"]], 2);
+        mock_provider("a", Ok("0x01".to_string()));
+        mock_provider("b", Ok("0x01".to_string()));
+        mock_provider("c", Ok("0x02".to_string()));
+
+        let manager = ChainFusionManager::default();
+        let response = block_on(manager.call_evm_rpc_canister("eth_call", "0xescrow".to_string()));
+
+        assert_eq!(response.unwrap(), "0x01");
+        assert_eq!((provider_stats("a").successes, provider_stats("a").disagreements), (1, 0));
+        assert_eq!((provider_stats("c").successes, provider_stats("c").disagreements), (0, 1));
+        assert_eq!(manager.take_costs().evm_rpc_cycles, 3 * EVM_RPC_CYCLES_COST);
+    }
+
+    #[test]
+    fn test_provider_disagreement_fails_consensus() {
+        reset_rpc_mocks();
+        use_providers(&[&["a", "b", "c"]], 2);
+        mock_provider("a", Ok("0x01".to_string()));
+        mock_provider("b", Ok("0x02".to_string()));
+        mock_provider("c", Err(Error::ChainFusionRequestFailed));
+
+        let manager = ChainFusionManager::default();
+        let error = block_on(manager.get_transaction_receipt("0xtx".to_string())).unwrap_err();
+
+        match EscrowError::from(error) {
+            EscrowError::ProviderConsensusFailure { method, detail } => {
+                assert_eq!(method, "eth_getTransactionReceipt");
+                assert_eq!(detail, "2 distinct responses from 2 providers, 2 must agree");
+            }
+            other => panic!("unexpected error: {:?}", other),
+        }
+        assert_eq!(provider_stats("a").disagreements, 1);
+        assert_eq!(provider_stats("c").errors, 1);
+    }
+
+    #[test]
+    fn test_provider_sets_fail_over_in_order() {
+        reset_rpc_mocks();
+        use_providers(&[&["a", "b"], &["c"], &["d"]], 1);
+        mock_provider("a", Err(Error::ChainFusionRequestFailed));
+        mock_provider("b", Err(Error::ChainFusionRequestFailed));
+        mock_provider("c", Ok("0x03".to_string()));
+
+        let manager = ChainFusionManager::default();
+        let response = block_on(manager.call_evm_rpc_canister("eth_call", "0xescrow".to_string()));
+
+        assert_eq!(response.unwrap(), "0x03");
+        assert_eq!(provider_calls(), ["a", "b", "c"]);
+        assert_eq!((provider_stats("a").errors, provider_stats("c").successes), (1, 1));
+
+        // Once every set fails the call fails like any other RPC failure
+        mock_provider("c", Err(Error::ChainFusionRequestFailed));
+        mock_provider("d", Err(Error::ChainFusionRequestFailed));
+        let error = block_on(manager.call_evm_rpc_canister("eth_call", "0xescrow".to_string()));
+        assert!(matches!(error, Err(Error::RpcFailed { .. })));
+
+        // Agreement no provider set can reach is rejected up front
+        let unreachable =
+            RpcProviderStrategy { provider_sets: vec![vec!["a".to_string()]], k_of_n: 2 };
+        assert!(validate_rpc_provider_strategy(&unreachable).is_err());
+    }
 }
@@ -1,24 +1,44 @@
 mod chain_fusion; // Chain Fusion integration enabled (Task 5)
+mod locks;
 mod memory;
+mod reconcile;
+mod roles;
 mod timelock;
 mod types;
 
 use candid::Principal;
 use chain_fusion::ChainFusionManager; // Chain Fusion integration enabled (Task 5)
 use types::{
+    ArchivePolicy,
     ConservativeTimelocks,
     CoordinationState,
+    CostBreakdown,
+    CostSummary,
+    Create2Config,
     CrossChainEscrow,
+    CrossChainEscrowSummary,
+    DeploymentAttempt,
+    EscrowBatchParams,
     EscrowError,
     EscrowStatus,
+    EscrowSummary,
     EscrowType,
+    EscrowVerificationReport,
     HTLCEscrow,
+    HTLCEscrowStatus,
+    InconsistentEscrow,
+    PartSpec,
+    PreparedTx,
+    ReconciliationStats,
+    RoleAssignments,
+    RpcProviderStats,
+    RpcProviderStrategy,
     TimelockConfig,
     Token,
     // ThresholdECDSAHealth, // TODO: Enable in Task 5 for Chain Fusion
 };
 
-/// Create phased ICP escrow with conservative timelock calculation - Used by: Makers
+/// Create phased ICP escrow with conservative timelock calculation - Used by: Resolvers/Limit-order
 #[ic_cdk::update]
 async fn create_icp_escrow(
     order_hash: String,
@@ -36,8 +56,9 @@ async fn create_icp_escrow(
     src_amount: u64,
     dst_amount: u64,
 ) -> Result<String, EscrowError> {
-    let _caller = ic_cdk::caller();
+    roles::require_resolver()?;
     let current_time = ic_cdk::api::time();
+    let _lock = locks::OrderLock::acquire(&order_hash, current_time)?;
 
     // === PHASE 1: INPUT VALIDATION ===
     validate_escrow_inputs(
@@ -57,35 +78,24 @@ async fn create_icp_escrow(
         timelock::calculate_conservative_timelocks(timelock, current_time)?;
 
     // === PHASE 3: ICP ESCROW CREATION ===
-    let escrow = HTLCEscrow {
+    let base = EscrowBatchParams {
         order_hash: order_hash.clone(),
-        hashlock,
         maker,
         taker,
         token,
         amount,
         safety_deposit,
-        timelock: conservative_timelocks.icp_timelock,
+        timelock,
         src_chain_id,
         dst_chain_id,
         src_token,
         dst_token,
         src_amount,
         dst_amount,
-        escrow_type: EscrowType::Source, // ICP is source for the swap
-        status: EscrowStatus::Created,
-        address: format!("icp_htlc_{}", order_hash),
-        timelock_config: conservative_timelocks.config,
-        threshold_ecdsa_key_id: None,
-        chain_health_status: None,
-        partial_fill_info: None,
-        events: vec![types::CrossChainEscrowEvent::EscrowCreated {
-            escrow_id: order_hash.clone(),
-            chain: "ICP".to_string(),
-        }],
-        created_at: current_time,
-        updated_at: current_time,
     };
+    let part = PartSpec { amount, hashlock, part_index: 0 };
+    let escrow =
+        new_icp_escrow(&base, order_hash.clone(), part, &conservative_timelocks, current_time);
 
     // Store escrow
     memory::store_htlc_escrow(escrow)?;
@@ -100,10 +110,157 @@ async fn create_icp_escrow(
     Ok(order_hash)
 }
 
-/// Get HTLC escrow status - Used by: Frontend/Users
+/// Maximum number of parts created by one batch call
+const MAX_BATCH_PARTS: usize = 16;
+
+/// Create ICP escrows for every part of a partially filled order - Used by: Resolvers
+///
+/// Invalid batches are rejected as a whole. Once validated, each part is created independently:
+/// a failing part is reported in place and does not undo escrows created for its siblings.
+#[ic_cdk::update]
+fn create_icp_escrows_batch(
+    base: EscrowBatchParams,
+    parts: Vec<PartSpec>,
+) -> Result<Vec<Result<String, EscrowError>>, EscrowError> {
+    roles::require_resolver()?;
+    create_icp_escrows_batch_at(base, parts, ic_cdk::api::time())
+}
+
+fn create_icp_escrows_batch_at(
+    base: EscrowBatchParams,
+    parts: Vec<PartSpec>,
+    current_time: u64,
+) -> Result<Vec<Result<String, EscrowError>>, EscrowError> {
+    validate_batch_parts(&base, &parts)?;
+
+    // All parts share one timelock configuration
+    let conservative_timelocks =
+        timelock::calculate_conservative_timelocks(base.timelock, current_time)?;
+
+    let results: Vec<Result<String, EscrowError>> = parts
+        .into_iter()
+        .map(|part| {
+            let order_hash = part_order_hash(&base.order_hash, part.part_index);
+            validate_escrow_inputs(
+                &order_hash,
+                &part.hashlock,
+                &base.maker,
+                &base.taker,
+                &base.token,
+                part.amount,
+                base.safety_deposit,
+                base.timelock,
+                current_time,
+            )?;
+
+            let escrow = new_icp_escrow(
+                &base,
+                order_hash.clone(),
+                part,
+                &conservative_timelocks,
+                current_time,
+            );
+            memory::store_htlc_escrow(escrow)?;
+            Ok(order_hash)
+        })
+        .collect();
+
+    ic_cdk::println!(
+        "🔒 Created {}/{} ICP HTLC escrows for order {}",
+        results.iter().filter(|result| result.is_ok()).count(),
+        results.len(),
+        base.order_hash
+    );
+
+    Ok(results)
+}
+
+/// Validate a batch as a whole: bounded size, parts summing to the total, distinct parts
+fn validate_batch_parts(base: &EscrowBatchParams, parts: &[PartSpec]) -> Result<(), EscrowError> {
+    if parts.is_empty() || parts.len() > MAX_BATCH_PARTS {
+        return Err(EscrowError::InvalidPartialFill);
+    }
+
+    let total = parts
+        .iter()
+        .try_fold(0u64, |sum, part| sum.checked_add(part.amount))
+        .ok_or(EscrowError::InvalidAmount)?;
+    if total != base.amount {
+        return Err(EscrowError::InvalidPartialFill);
+    }
+
+    let mut hashlocks = std::collections::HashSet::new();
+    let mut indices = std::collections::HashSet::new();
+    for part in parts {
+        if !hashlocks.insert(part.hashlock.to_lowercase()) {
+            return Err(EscrowError::InvalidHashlock);
+        }
+        if !indices.insert(part.part_index) {
+            return Err(EscrowError::InvalidPartialFill);
+        }
+    }
+
+    Ok(())
+}
+
+/// Deterministic order hash of one part of a batch
+fn part_order_hash(order_hash: &str, part_index: u32) -> String {
+    format!("{}_part_{}", order_hash, part_index)
+}
+
+/// Build an ICP source escrow for one part, splitting source and destination amounts pro rata
+fn new_icp_escrow(
+    base: &EscrowBatchParams,
+    order_hash: String,
+    part: PartSpec,
+    conservative_timelocks: &ConservativeTimelocks,
+    current_time: u64,
+) -> HTLCEscrow {
+    let pro_rata = |total: u64| (total as u128 * part.amount as u128 / base.amount as u128) as u64;
+
+    HTLCEscrow {
+        order_hash: order_hash.clone(),
+        hashlock: part.hashlock,
+        maker: base.maker.clone(),
+        taker: base.taker.clone(),
+        token: base.token.clone(),
+        amount: part.amount,
+        safety_deposit: base.safety_deposit,
+        timelock: conservative_timelocks.icp_timelock,
+        src_chain_id: base.src_chain_id,
+        dst_chain_id: base.dst_chain_id,
+        src_token: base.src_token.clone(),
+        dst_token: base.dst_token.clone(),
+        src_amount: pro_rata(base.src_amount),
+        dst_amount: pro_rata(base.dst_amount),
+        escrow_type: EscrowType::Source, // ICP is source for the swap
+        status: EscrowStatus::Created,
+        address: format!("icp_htlc_{}", order_hash),
+        timelock_config: conservative_timelocks.config.clone(),
+        threshold_ecdsa_key_id: None,
+        chain_health_status: None,
+        partial_fill_info: None,
+        events: vec![types::CrossChainEscrowEvent::EscrowCreated {
+            escrow_id: order_hash,
+            chain: "ICP".to_string(),
+        }],
+        costs: None,
+        created_at: current_time,
+        updated_at: current_time,
+    }
+}
+
+/// Get HTLC escrow status, falling back to the summary of archived escrows - Used by: Frontend/Users
 #[ic_cdk::query]
-fn get_htlc_escrow_status(order_hash: String) -> Option<HTLCEscrow> {
-    memory::get_htlc_escrow(&order_hash).ok()
+fn get_htlc_escrow_status(order_hash: String) -> Option<HTLCEscrowStatus> {
+    if let Ok(escrow) = memory::get_htlc_escrow(&order_hash) {
+        return Some(HTLCEscrowStatus { archived: false, escrow: Some(escrow), summary: None });
+    }
+    memory::get_archived_escrow(&order_hash).map(|summary| HTLCEscrowStatus {
+        archived: true,
+        escrow: None,
+        summary: Some(summary),
+    })
 }
 
 /// List all HTLC escrows for debugging - Used by: Developers
@@ -120,6 +277,7 @@ async fn create_cross_chain_escrow(
     evm_escrow: HTLCEscrow,
 ) -> Result<String, EscrowError> {
     let current_time = ic_cdk::api::time();
+    let _lock = locks::OrderLock::acquire(&order_id, current_time)?;
 
     let cross_chain_escrow = CrossChainEscrow {
         order_id: order_id.clone(),
@@ -129,6 +287,7 @@ async fn create_cross_chain_escrow(
         icp_finality_lag: 0,
         evm_finality_lag: 0,
         failed_transactions: 0,
+        revealed_secret: None,
         events: Vec::new(),
         created_at: current_time,
         updated_at: current_time,
@@ -147,6 +306,44 @@ fn list_cross_chain_escrows() -> Vec<CrossChainEscrow> {
     memory::get_all_cross_chain_escrows()
 }
 
+/// Maximum number of summaries returned per page
+const MAX_PAGE_SIZE: u64 = 100;
+
+/// List HTLC escrows in a status, oldest first - Used by: Dashboards
+#[ic_cdk::query]
+fn list_htlc_escrows_by_status(
+    status: EscrowStatus,
+    offset: u64,
+    limit: u64,
+) -> Vec<EscrowSummary> {
+    let mut escrows = memory::get_htlc_escrows_by_status(status);
+    escrows.sort_by(|a, b| (a.created_at, &a.order_hash).cmp(&(b.created_at, &b.order_hash)));
+    page(&escrows, offset, limit).map(EscrowSummary::from).collect()
+}
+
+/// List cross-chain escrows in a coordination state, oldest first - Used by: Dashboards
+#[ic_cdk::query]
+fn list_cross_chain_escrows_by_state(
+    state: CoordinationState,
+    offset: u64,
+    limit: u64,
+) -> Vec<CrossChainEscrowSummary> {
+    let mut escrows = memory::get_cross_chain_escrows_by_state(state);
+    escrows.sort_by(|a, b| (a.created_at, &a.order_id).cmp(&(b.created_at, &b.order_id)));
+    page(&escrows, offset, limit).map(CrossChainEscrowSummary::from).collect()
+}
+
+/// Get escrow counts and estimated storage size per map - Used by: Dashboards
+#[ic_cdk::query]
+fn get_storage_stats() -> memory::MemoryStats {
+    memory::get_memory_stats()
+}
+
+/// Select one page of a listing, capping the page size
+fn page<T>(items: &[T], offset: u64, limit: u64) -> impl Iterator<Item = &T> {
+    items.iter().skip(offset as usize).take(limit.min(MAX_PAGE_SIZE) as usize)
+}
+
 /// Validate escrow creation inputs
 fn validate_escrow_inputs(
     order_hash: &str,
@@ -234,19 +431,69 @@ fn get_test_token_canister(token: &Token) -> Result<Principal, EscrowError> {
 #[ic_cdk::update]
 async fn check_threshold_ecdsa_health() -> Result<types::ThresholdECDSAHealth, EscrowError> {
     let chain_fusion_manager = ChainFusionManager::default();
-    chain_fusion_manager
-        .check_threshold_ecdsa_health()
-        .await
-        .map_err(|_| EscrowError::ThresholdECDSAUnavailable)
+    chain_fusion_manager.check_threshold_ecdsa_health().await.map_err(EscrowError::from)
 }
 
 /// Derive deterministic EVM address using threshold ECDSA
 #[ic_cdk::update]
 fn derive_deterministic_evm_address(order_hash: String) -> Result<String, EscrowError> {
     let chain_fusion_manager = ChainFusionManager::default();
-    chain_fusion_manager
-        .derive_deterministic_evm_address(&order_hash)
-        .map_err(|_| EscrowError::EVMAddressDerivationFailed)
+    chain_fusion_manager.derive_deterministic_evm_address(&order_hash).map_err(EscrowError::from)
+}
+
+/// Predict the CREATE2 address an order's EVM escrow will be deployed at - Used by: Resolvers
+#[ic_cdk::query]
+fn predict_evm_escrow_address(order_hash: String) -> Result<String, EscrowError> {
+    let config = memory::get_create2_config().ok_or(EscrowError::EVMAddressDerivationFailed)?;
+    chain_fusion::predict_escrow_address(&config, &order_hash).map_err(EscrowError::from)
+}
+
+/// Deploy EVM escrows through a CREATE2 factory - Used by: Controllers
+#[ic_cdk::update]
+fn set_create2_config(config: Create2Config) -> Result<(), EscrowError> {
+    roles::require_controller()?;
+    // Reject malformed addresses and hashes up front rather than at deployment
+    chain_fusion::predict_escrow_address(&config, "").map_err(|_| EscrowError::InvalidAddress)?;
+    memory::set_create2_config(config);
+    Ok(())
+}
+
+/// Get the CREATE2 factory configuration - Used by: Resolvers
+#[ic_cdk::query]
+fn get_create2_config() -> Option<Create2Config> {
+    memory::get_create2_config()
+}
+
+/// Query a chain's EVM RPC providers by strategy instead of the default provider - Used by: Controllers
+#[ic_cdk::update]
+fn set_rpc_provider_strategy(
+    chain_id: u64,
+    strategy: RpcProviderStrategy,
+) -> Result<(), EscrowError> {
+    roles::require_controller()?;
+    chain_fusion::validate_rpc_provider_strategy(&strategy)?;
+    memory::set_rpc_provider_strategy(chain_id, strategy);
+    Ok(())
+}
+
+/// Go back to the default EVM RPC provider of a chain - Used by: Controllers
+#[ic_cdk::update]
+fn remove_rpc_provider_strategy(chain_id: u64) -> Result<(), EscrowError> {
+    roles::require_controller()?;
+    memory::remove_rpc_provider_strategy(chain_id);
+    Ok(())
+}
+
+/// Get the EVM RPC provider strategy of a chain - Used by: Monitoring
+#[ic_cdk::query]
+fn get_rpc_provider_strategy(chain_id: u64) -> Option<RpcProviderStrategy> {
+    memory::get_rpc_provider_strategy(chain_id)
+}
+
+/// Get successes, errors and disagreements of every EVM RPC provider - Used by: Monitoring
+#[ic_cdk::query]
+fn get_rpc_provider_stats() -> Vec<RpcProviderStats> {
+    memory::get_rpc_provider_stats()
 }
 
 /// Get Chain Fusion configuration
@@ -274,6 +521,7 @@ async fn create_evm_escrow_via_chain_fusion(
     src_amount: u64,
     dst_amount: u64,
 ) -> Result<String, EscrowError> {
+    let _lock = locks::OrderLock::acquire(&order_hash, ic_cdk::api::time())?;
     let chain_fusion_manager = ChainFusionManager::default();
 
     // Create EVMEscrowParams from the input parameters (now using u64 directly)
@@ -290,20 +538,1338 @@ async fn create_evm_escrow_via_chain_fusion(
         dst_amount,
     };
 
-    chain_fusion_manager
-        .create_evm_escrow_via_chain_fusion(params)
-        .await
-        .map_err(|_| EscrowError::EVMEscrowCreationFailed)
+    let result = chain_fusion_manager.create_evm_escrow_via_chain_fusion(params).await;
+    record_chain_fusion_costs(&order_hash, &chain_fusion_manager, ic_cdk::api::time());
+    result.map_err(EscrowError::from)
+}
+
+/// Build the EVM deployment transaction of a stored escrow without sending it - Used by: Resolvers
+#[ic_cdk::update]
+async fn prepare_evm_escrow_tx(order_hash: String) -> Result<PreparedTx, EscrowError> {
+    let _lock = locks::OrderLock::acquire(&order_hash, ic_cdk::api::time())?;
+    let escrow = memory::get_htlc_escrow(&order_hash)?;
+
+    let chain_fusion_manager = ChainFusionManager::default();
+    let result = chain_fusion_manager.prepare_evm_escrow_tx(&evm_escrow_params(&escrow)).await;
+    record_chain_fusion_costs(&order_hash, &chain_fusion_manager, ic_cdk::api::time());
+    result.map_err(EscrowError::from)
+}
+
+/// Deployment parameters of the EVM counterpart of a stored HTLC escrow
+fn evm_escrow_params(escrow: &HTLCEscrow) -> types::EVMEscrowParams {
+    types::EVMEscrowParams {
+        order_hash: escrow.order_hash.clone(),
+        evm_address: String::new(), // Will be derived
+        amount: escrow.amount,
+        timelock: escrow.timelock,
+        safety_deposit: escrow.safety_deposit,
+        hash_lock: escrow.hashlock.clone(),
+        src_token: escrow.src_token.clone(),
+        dst_token: escrow.dst_token.clone(),
+        src_amount: escrow.src_amount,
+        dst_amount: escrow.dst_amount,
+    }
 }
 
 /// Verify EVM escrow state via Chain Fusion
 #[ic_cdk::update]
 async fn verify_evm_escrow_state(escrow_address: String) -> Result<bool, EscrowError> {
     let chain_fusion_manager = ChainFusionManager::default();
-    chain_fusion_manager
-        .verify_evm_escrow_state(escrow_address)
-        .await
-        .map_err(|_| EscrowError::SystemError)
+    chain_fusion_manager.verify_evm_escrow_state(escrow_address).await.map_err(EscrowError::from)
+}
+
+/// Verify an order's deployed EVM escrow against the stored HTLC escrow - Used by: Operators
+#[ic_cdk::update]
+async fn verify_evm_escrow_parameters(
+    order_hash: String,
+) -> Result<EscrowVerificationReport, EscrowError> {
+    roles::require_operator()?;
+    let _lock = locks::OrderLock::acquire(&order_hash, ic_cdk::api::time())?;
+    let escrow = memory::get_htlc_escrow(&order_hash)?;
+    let escrow_address = stored_evm_escrow_address(&order_hash)?;
+
+    let chain_fusion_manager = ChainFusionManager::default();
+    let result = chain_fusion_manager
+        .verify_evm_escrow_immutables(escrow_address, &escrow, ic_cdk::api::time())
+        .await;
+    record_chain_fusion_costs(&order_hash, &chain_fusion_manager, ic_cdk::api::time());
+    let report = result.map_err(EscrowError::from)?;
+
+    memory::store_verification_report(report.clone());
+
+    Ok(report)
+}
+
+/// Address of an order's EVM escrow: where its deployment landed, else its CREATE2 prediction
+fn stored_evm_escrow_address(order_hash: &str) -> Result<String, EscrowError> {
+    if let Some(address) = memory::get_latest_deployment_attempt(order_hash)
+        .and_then(|attempt| attempt.contract_address)
+    {
+        return Ok(address);
+    }
+    let config = memory::get_create2_config().ok_or(EscrowError::InvalidAddress)?;
+    chain_fusion::predict_escrow_address(&config, order_hash).map_err(EscrowError::from)
+}
+
+/// Get the Chain Fusion costs incurred settling an order - Used by: Operators
+#[ic_cdk::query]
+fn get_escrow_costs(order_hash: String) -> Result<CostBreakdown, EscrowError> {
+    Ok(memory::get_htlc_escrow(&order_hash)?.costs.unwrap_or_default())
+}
+
+/// Get the total costs of escrows created in [from_ns, to_ns) - Used by: Operators
+#[ic_cdk::query]
+fn get_cost_summary(from_ns: u64, to_ns: u64) -> CostSummary {
+    memory::get_cost_summary(from_ns, to_ns)
+}
+
+/// Attach the costs of a manager's calls to the order's HTLC escrow, if one is stored
+fn record_chain_fusion_costs(order_hash: &str, manager: &ChainFusionManager, current_time: u64) {
+    let costs = manager.take_costs();
+    if let Err(e) = memory::add_escrow_costs(order_hash, &costs, current_time) {
+        ic_cdk::println!("Untracked Chain Fusion costs for order {}: {:?}", order_hash, e);
+    }
+}
+
+// ============================================================================
+// SECRET REVEAL API
+// ============================================================================
+
+/// Claim an escrow by revealing the secret matching its hashlock - Used by: Takers
+#[ic_cdk::update]
+fn claim_icp_escrow(order_hash: String, preimage: Vec<u8>) -> Result<(), EscrowError> {
+    let caller = ic_cdk::caller().to_text();
+    claim_escrow_with_preimage(&order_hash, preimage, &caller, ic_cdk::api::time())
+}
+
+/// Get the secret revealed by a claim, None until claimed - Used by: Makers/Takers
+///
+/// Restricted to the parties of the escrow or of the cross-chain pair it belongs to, so the
+/// counterparty leg can be claimed without watching events off-chain.
+#[ic_cdk::query]
+fn get_revealed_preimage(order_hash: String) -> Result<Option<Vec<u8>>, EscrowError> {
+    revealed_preimage_for(&order_hash, &ic_cdk::caller().to_text())
+}
+
+/// Report a secret revealed on the EVM leg of a pair so its ICP leg is claimed with it - Used by: Resolvers
+///
+/// Anyone may report: the secret is public once revealed and is checked against the hashlock.
+#[ic_cdk::update]
+fn report_revealed_secret(order_id: String, preimage: Vec<u8>) -> Result<(), EscrowError> {
+    propagate_revealed_secret(&order_id, preimage, ic_cdk::api::time())
+}
+
+/// Claim the ICP leg of a pair for its taker with the pair's revealed secret - Used by: Anyone
+#[ic_cdk::update]
+fn auto_claim_with_revealed_secret(order_id: String) -> Result<(), EscrowError> {
+    auto_claim_icp_leg(&order_id, ic_cdk::api::time())
+}
+
+/// Claim an escrow as its taker with a secret whose keccak256 matches the hashlock
+///
+/// Claiming the ICP leg of a pair reveals the secret, so it waits for a passing verification
+/// report of the pair's EVM escrow.
+fn claim_escrow_with_preimage(
+    order_hash: &str,
+    preimage: Vec<u8>,
+    caller: &str,
+    current_time: u64,
+) -> Result<(), EscrowError> {
+    let _lock = locks::OrderLock::acquire(order_hash, current_time)?;
+    if memory::get_htlc_escrow(order_hash)?.taker != caller {
+        return Err(EscrowError::Unauthorized);
+    }
+    let unverified_evm_leg = memory::get_all_cross_chain_escrows().iter().any(|pair| {
+        pair.icp_escrow.order_hash == order_hash
+            && !secret_reveal_ready(&pair.evm_escrow.order_hash)
+    });
+    if unverified_evm_leg {
+        return Err(EscrowError::CrossChainCoordinationFailed);
+    }
+
+    complete_escrow_with_preimage(order_hash, preimage, current_time)
+}
+
+/// Complete an escrow with a secret whose keccak256 matches the hashlock
+fn complete_escrow_with_preimage(
+    order_hash: &str,
+    preimage: Vec<u8>,
+    current_time: u64,
+) -> Result<(), EscrowError> {
+    let mut escrow = memory::get_htlc_escrow(order_hash)?;
+
+    if !escrow.status.can_transition_to(&EscrowStatus::Completed) {
+        return Err(EscrowError::StateTransitionInvalid);
+    }
+
+    if current_time >= escrow.timelock {
+        return Err(EscrowError::TimelockExpired);
+    }
+
+    let secret_hash = verify_preimage(&preimage, &escrow.hashlock)?;
+
+    escrow.status = EscrowStatus::Completed;
+    escrow.updated_at = current_time;
+    escrow.events.push(types::CrossChainEscrowEvent::SecretRevealed {
+        escrow_id: order_hash.to_string(),
+        secret_hash,
+    });
+    memory::update_htlc_escrow(order_hash, escrow)?;
+    memory::store_revealed_preimage(order_hash, preimage);
+
+    ic_cdk::println!("🔓 Escrow {} claimed, secret revealed", order_hash);
+    notify_limit_order(order_hash);
+    Ok(())
+}
+
+/// Tell the limit-order canister an escrow was claimed, so the Fusion fill it settles completes
+///
+/// One-way: the limit-order canister ignores escrows that do not settle one of its fills.
+fn notify_limit_order(order_hash: &str) {
+    let Some(limit_order) = memory::get_limit_order_canister() else {
+        return;
+    };
+    if let Err(code) = ic_cdk::api::call::notify(
+        limit_order,
+        "notify_fusion_fill_completed",
+        (order_hash.to_string(),),
+    ) {
+        ic_cdk::println!("Limit-order canister not notified of {}: {:?}", order_hash, code);
+    }
+}
+
+/// Hex keccak256 of a preimage, if it matches the hashlock
+fn verify_preimage(preimage: &[u8], hashlock: &str) -> Result<String, EscrowError> {
+    let secret_hash: String =
+        fusion_crypto::keccak256(preimage).iter().map(|b| format!("{:02x}", b)).collect();
+    if secret_hash.eq_ignore_ascii_case(hashlock.trim_start_matches("0x")) {
+        Ok(secret_hash)
+    } else {
+        Err(EscrowError::SecretVerificationFailed)
+    }
+}
+
+/// Record a verified secret revealed on the EVM leg of a pair, then try to claim its ICP leg
+///
+/// A leg that is not claimable yet keeps the secret for a later `auto_claim_with_revealed_secret`.
+fn propagate_revealed_secret(
+    order_id: &str,
+    preimage: Vec<u8>,
+    current_time: u64,
+) -> Result<(), EscrowError> {
+    let mut pair = memory::get_cross_chain_escrow(order_id)?;
+    let secret_hash = verify_preimage(&preimage, &pair.evm_escrow.hashlock)?;
+
+    if pair.revealed_secret.is_none() {
+        pair.revealed_secret = Some(preimage);
+        pair.coordination_state = CoordinationState::SecretRevealed;
+        pair.events.push(types::CrossChainEscrowEvent::SecretRevealed {
+            escrow_id: pair.evm_escrow.order_hash.clone(),
+            secret_hash,
+        });
+        pair.updated_at = current_time;
+        memory::update_cross_chain_escrow(order_id, pair)?;
+    }
+
+    if let Err(e) = auto_claim_icp_leg(order_id, current_time) {
+        ic_cdk::println!("ICP leg of {} not claimed with revealed secret: {:?}", order_id, e);
+    }
+    Ok(())
+}
+
+/// Claim the ICP leg of a pair with its revealed secret; a completed leg is left as is
+fn auto_claim_icp_leg(order_id: &str, current_time: u64) -> Result<(), EscrowError> {
+    let mut pair = memory::get_cross_chain_escrow(order_id)?;
+    let preimage = pair.revealed_secret.clone().ok_or(EscrowError::InvalidState)?;
+    let icp_order_hash = pair.icp_escrow.order_hash.clone();
+    if memory::get_htlc_escrow(&icp_order_hash)?.status == EscrowStatus::Completed {
+        return Ok(());
+    }
+
+    let lock = locks::OrderLock::acquire(&icp_order_hash, current_time)?;
+    complete_escrow_with_preimage(&icp_order_hash, preimage, current_time)?;
+    drop(lock);
+
+    pair.icp_escrow = memory::get_htlc_escrow(&icp_order_hash)?;
+    pair.coordination_state = CoordinationState::Completed;
+    pair.events.push(types::CrossChainEscrowEvent::SecretPropagated {
+        escrow_id: icp_order_hash,
+        from_chain: "EVM".to_string(),
+        to_chain: "ICP".to_string(),
+    });
+    pair.updated_at = current_time;
+    memory::update_cross_chain_escrow(order_id, pair)
+}
+
+/// Get the revealed secret for a party of the escrow or of its linked cross-chain pair
+fn revealed_preimage_for(order_hash: &str, caller: &str) -> Result<Option<Vec<u8>>, EscrowError> {
+    let escrow = memory::get_htlc_escrow(order_hash)?;
+    let is_party = |escrow: &HTLCEscrow| escrow.maker == caller || escrow.taker == caller;
+
+    let authorized = is_party(&escrow)
+        || memory::get_all_cross_chain_escrows().iter().any(|pair| {
+            (pair.icp_escrow.order_hash == order_hash || pair.evm_escrow.order_hash == order_hash)
+                && (is_party(&pair.icp_escrow) || is_party(&pair.evm_escrow))
+        });
+    if !authorized {
+        return Err(EscrowError::Unauthorized);
+    }
+
+    Ok(memory::get_revealed_preimage(order_hash))
+}
+
+/// Get the latest EVM escrow verification report - Used by: Frontend/Relayer
+#[ic_cdk::query]
+fn get_escrow_verification_report(order_hash: String) -> Option<EscrowVerificationReport> {
+    memory::get_verification_report(&order_hash)
+}
+
+/// Check whether an order may proceed to secret reveal - Used by: Relayer
+///
+/// Requires a passing verification report for the deployed EVM escrow.
+#[ic_cdk::query]
+fn is_ready_for_secret_reveal(order_hash: String) -> bool {
+    secret_reveal_ready(&order_hash)
+}
+
+/// Whether the latest verification report of an EVM escrow passed
+fn secret_reveal_ready(order_hash: &str) -> bool {
+    memory::get_verification_report(order_hash).is_some_and(|report| report.passed)
+}
+
+/// Get all EVM escrow deployment attempts for an order, oldest first - Used by: Frontend/Relayer
+#[ic_cdk::query]
+fn get_deployment_attempts(order_hash: String) -> Vec<DeploymentAttempt> {
+    memory::get_deployment_attempts(&order_hash)
+}
+
+// ============================================================================
+// STATUS ADMINISTRATION
+// ============================================================================
+
+/// Set an escrow status outside the lifecycle rules, recording an audit event - Used by: Controllers
+#[ic_cdk::update]
+fn force_set_status(
+    order_hash: String,
+    status: EscrowStatus,
+    reason: String,
+) -> Result<(), EscrowError> {
+    roles::require_controller()?;
+    force_escrow_status(
+        &order_hash,
+        status,
+        &ic_cdk::caller().to_text(),
+        reason,
+        ic_cdk::api::time(),
+    )
+}
+
+/// Overwrite an escrow status and append a StatusForced event naming the controller
+fn force_escrow_status(
+    order_hash: &str,
+    status: EscrowStatus,
+    controller: &str,
+    reason: String,
+    current_time: u64,
+) -> Result<(), EscrowError> {
+    let _lock = locks::OrderLock::acquire(order_hash, current_time)?;
+    let mut escrow = memory::get_htlc_escrow(order_hash)?;
+
+    escrow.events.push(types::CrossChainEscrowEvent::StatusForced {
+        from: escrow.status.clone(),
+        to: status.clone(),
+        controller: controller.to_string(),
+        reason,
+    });
+    escrow.status = status;
+    escrow.updated_at = current_time;
+    memory::update_htlc_escrow(order_hash, escrow)?;
+
+    ic_cdk::println!("⚠️ Escrow {} status forced by {}", order_hash, controller);
+    Ok(())
+}
+
+/// List order hashes locked by an in-flight operation and when each was locked - Used by: Operators
+#[ic_cdk::query]
+fn get_locked_orders() -> Vec<(String, u64)> {
+    memory::get_locked_orders()
+}
+
+// ============================================================================
+// COORDINATION RECONCILIATION
+// ============================================================================
+
+/// How often every pair's coordination state is checked against its legs
+const RECONCILE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(300);
+
+/// Reconcile one pair's coordination state with its legs now, returning the resulting state - Used by: Operators
+#[ic_cdk::update]
+fn reconcile_coordination(order_id: String) -> Result<CoordinationState, EscrowError> {
+    roles::require_operator()?;
+    reconcile::reconcile_pair(&order_id, ic_cdk::api::time())
+}
+
+/// List pairs whose legs cannot be reconciled and need manual review - Used by: Operators, Dashboards
+#[ic_cdk::query]
+fn list_inconsistent_escrows() -> Vec<InconsistentEscrow> {
+    memory::get_inconsistent_escrows()
+}
+
+/// Get drift correction counts and when reconciliation last ran - Used by: Dashboards
+#[ic_cdk::query]
+fn get_reconciliation_stats() -> ReconciliationStats {
+    memory::get_reconciliation_stats()
+}
+
+/// Start the periodic reconciliation timer
+fn start_reconcile_timer() {
+    ic_cdk_timers::set_timer_interval(RECONCILE_INTERVAL, || {
+        let corrected = reconcile::reconcile_all(ic_cdk::api::time());
+        if corrected > 0 {
+            ic_cdk::println!("🩺 Corrected coordination drift on {} pairs", corrected);
+        }
+    });
+}
+
+// ============================================================================
+// ESCROW ARCHIVAL
+// ============================================================================
+
+/// How often terminal escrows past their retention are archived
+const ARCHIVE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(3600);
+
+/// Set how long terminal escrows keep their full record and how many summaries are kept - Used by: Controllers
+#[ic_cdk::update]
+fn set_archive_policy(policy: ArchivePolicy) -> Result<(), EscrowError> {
+    roles::require_controller()?;
+    memory::set_archive_policy(policy);
+    Ok(())
+}
+
+/// Get the archive retention rules - Used by: Dashboards
+#[ic_cdk::query]
+fn get_archive_policy() -> ArchivePolicy {
+    memory::get_archive_policy()
+}
+
+/// Archive terminal escrows past their retention now instead of waiting for the timer - Used by: Operators
+#[ic_cdk::update]
+fn run_archive_sweep() -> Result<u64, EscrowError> {
+    roles::require_operator()?;
+    Ok(memory::archive_terminal_escrows(ic_cdk::api::time()) as u64)
+}
+
+/// Init hook: Start the archival and reconciliation timers
+#[ic_cdk::init]
+fn init() {
+    start_archive_timer();
+    start_reconcile_timer();
+}
+
+/// Pre-upgrade hook: Save role assignments to stable memory
+#[ic_cdk::pre_upgrade]
+fn pre_upgrade() {
+    let roles =
+        (memory::get_operators(), memory::get_resolvers(), memory::get_limit_order_canister());
+    ic_cdk::storage::stable_save(roles).expect("Failed to save roles");
+}
+
+/// Post-upgrade hook: Restore role assignments and restart the timers, since they do not survive upgrades
+#[ic_cdk::post_upgrade]
+fn post_upgrade() {
+    // Versions before roles saved nothing, and versions before resolvers saved operators only
+    let (operators, resolvers, limit_order_canister) =
+        ic_cdk::storage::stable_restore::<(Vec<Principal>, Vec<Principal>, Option<Principal>)>()
+            .or_else(|_| {
+                ic_cdk::storage::stable_restore::<(Vec<Principal>,)>()
+                    .map(|(operators,)| (operators, Vec::new(), None))
+            })
+            .unwrap_or_default();
+    memory::set_operators(operators);
+    memory::set_resolvers(resolvers);
+    memory::set_limit_order_canister(limit_order_canister);
+    start_archive_timer();
+    start_reconcile_timer();
+}
+
+/// Start the periodic archival timer
+fn start_archive_timer() {
+    ic_cdk_timers::set_timer_interval(ARCHIVE_INTERVAL, || {
+        let archived = memory::archive_terminal_escrows(ic_cdk::api::time());
+        if archived > 0 {
+            ic_cdk::println!("🗄️ Archived {} terminal escrows", archived);
+        }
+    });
+}
+
+// ============================================================================
+// ROLE ADMINISTRATION
+// ============================================================================
+
+/// Grant the operator role for day-to-day actions - Used by: Controllers
+#[ic_cdk::update]
+fn add_operator(principal: Principal) -> Result<(), EscrowError> {
+    roles::require_controller()?;
+    memory::add_operator(principal);
+    ic_cdk::println!("🔑 Operator {} added", principal);
+    Ok(())
+}
+
+/// Revoke the operator role - Used by: Controllers
+#[ic_cdk::update]
+fn remove_operator(principal: Principal) -> Result<(), EscrowError> {
+    roles::require_controller()?;
+    if memory::remove_operator(&principal) {
+        ic_cdk::println!("🔑 Operator {} removed", principal);
+    }
+    Ok(())
+}
+
+/// Grant the resolver role for escrow creation - Used by: Controllers
+#[ic_cdk::update]
+fn add_resolver(principal: Principal) -> Result<(), EscrowError> {
+    roles::require_controller()?;
+    memory::add_resolver(principal);
+    ic_cdk::println!("🔑 Resolver {} added", principal);
+    Ok(())
+}
+
+/// Revoke the resolver role - Used by: Controllers
+#[ic_cdk::update]
+fn remove_resolver(principal: Principal) -> Result<(), EscrowError> {
+    roles::require_controller()?;
+    if memory::remove_resolver(&principal) {
+        ic_cdk::println!("🔑 Resolver {} removed", principal);
+    }
+    Ok(())
+}
+
+/// Set the limit-order canister whose Fusion fills this canister settles - Used by: Controllers
+///
+/// It holds the resolver role and is notified when the escrow of one of its fills is claimed.
+#[ic_cdk::update]
+fn set_limit_order_canister(canister_id: Option<Principal>) -> Result<(), EscrowError> {
+    roles::require_controller()?;
+    memory::set_limit_order_canister(canister_id);
+    Ok(())
+}
+
+/// Get the current role assignments - Used by: Dashboards
+#[ic_cdk::query]
+fn get_roles() -> RoleAssignments {
+    RoleAssignments {
+        operators: memory::get_operators(),
+        resolvers: memory::get_resolvers(),
+        limit_order_canister: memory::get_limit_order_canister(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::DeploymentStatus;
+
+    const NOW: u64 = 1_700_000_000_000_000_000;
+    const HOUR_NS: u64 = 3600 * 1_000_000_000;
+
+    fn batch_base(amount: u64) -> EscrowBatchParams {
+        EscrowBatchParams {
+            order_hash: "0xbatchorder".to_string(),
+            maker: "maker".to_string(),
+            taker: "resolver".to_string(),
+            token: "ICP".to_string(),
+            amount,
+            safety_deposit: 10_000,
+            timelock: NOW + 2 * HOUR_NS,
+            src_chain_id: 0,
+            dst_chain_id: 84532,
+            src_token: "ICP".to_string(),
+            dst_token: "ETH".to_string(),
+            src_amount: amount,
+            dst_amount: 2 * amount,
+        }
+    }
+
+    fn part(part_index: u32, amount: u64) -> PartSpec {
+        PartSpec { amount, hashlock: format!("{:064x}", part_index + 1), part_index }
+    }
+
+    #[test]
+    fn test_batch_creates_all_parts() {
+        memory::clear_escrow_data();
+        let parts = (0..4).map(|i| part(i, 250)).collect();
+
+        let results = create_icp_escrows_batch_at(batch_base(1_000), parts, NOW).unwrap();
+
+        assert_eq!(results.len(), 4);
+        for (i, result) in results.iter().enumerate() {
+            let order_hash = result.as_ref().unwrap();
+            assert_eq!(order_hash, &format!("0xbatchorder_part_{}", i));
+
+            let escrow = memory::get_htlc_escrow(order_hash).unwrap();
+            assert_eq!((escrow.amount, escrow.src_amount, escrow.dst_amount), (250, 250, 500));
+            assert_eq!(escrow.timelock_config.deployed_at, NOW);
+        }
+    }
+
+    #[test]
+    fn test_batch_rejects_sum_mismatch() {
+        memory::clear_escrow_data();
+        let parts = vec![part(0, 500), part(1, 400)];
+
+        let result = create_icp_escrows_batch_at(batch_base(1_000), parts, NOW);
+
+        assert!(matches!(result, Err(EscrowError::InvalidPartialFill)));
+        assert!(memory::get_all_htlc_escrows().is_empty());
+    }
+
+    #[test]
+    fn test_batch_rejects_duplicate_hashlock_and_oversized_batch() {
+        memory::clear_escrow_data();
+        let mut duplicate = part(1, 500);
+        duplicate.hashlock = part(0, 0).hashlock.to_uppercase();
+
+        let result =
+            create_icp_escrows_batch_at(batch_base(1_000), vec![part(0, 500), duplicate], NOW);
+        assert!(matches!(result, Err(EscrowError::InvalidHashlock)));
+
+        let parts: Vec<PartSpec> = (0..17).map(|i| part(i, 1)).collect();
+        let result = create_icp_escrows_batch_at(batch_base(17), parts, NOW);
+        assert!(matches!(result, Err(EscrowError::InvalidPartialFill)));
+        assert!(memory::get_all_htlc_escrows().is_empty());
+    }
+
+    #[test]
+    fn test_batch_part_failure_keeps_siblings() {
+        memory::clear_escrow_data();
+        // Part 1 was already escrowed by an earlier call
+        let earlier = create_icp_escrows_batch_at(batch_base(100), vec![part(1, 100)], NOW);
+        assert!(earlier.unwrap()[0].is_ok());
+
+        let parts = vec![part(0, 250), part(1, 250), part(2, 500)];
+        let results = create_icp_escrows_batch_at(batch_base(1_000), parts, NOW).unwrap();
+
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].as_deref().ok(), Some("0xbatchorder_part_0"));
+        assert!(matches!(results[1], Err(EscrowError::EscrowAlreadyExists)));
+        assert_eq!(results[2].as_deref().ok(), Some("0xbatchorder_part_2"));
+        assert_eq!(memory::get_all_htlc_escrows().len(), 3);
+    }
+
+    fn store_claimable_escrow(secret: &[u8]) -> String {
+        let mut part = part(0, 1_000);
+        part.hashlock =
+            fusion_crypto::keccak256(secret).iter().map(|b| format!("{:02x}", b)).collect();
+        let base = batch_base(1_000);
+        let timelocks = timelock::calculate_conservative_timelocks(base.timelock, NOW).unwrap();
+        let escrow = new_icp_escrow(&base, base.order_hash.clone(), part, &timelocks, NOW);
+        memory::store_htlc_escrow(escrow).unwrap();
+        for status in [EscrowStatus::Funded, EscrowStatus::Active] {
+            memory::update_htlc_escrow_status(&base.order_hash, status, NOW).unwrap();
+        }
+        base.order_hash
+    }
+
+    #[test]
+    fn test_preimage_unavailable_before_claim() {
+        memory::clear_escrow_data();
+        let order_hash = store_claimable_escrow(b"secret");
+
+        assert!(matches!(revealed_preimage_for(&order_hash, "maker"), Ok(None)));
+        assert!(matches!(revealed_preimage_for(&order_hash, "resolver"), Ok(None)));
+    }
+
+    #[test]
+    fn test_preimage_available_to_parties_after_claim() {
+        memory::clear_escrow_data();
+        let order_hash = store_claimable_escrow(b"secret");
+
+        // Wrong secrets and non-takers cannot claim
+        assert!(matches!(
+            claim_escrow_with_preimage(&order_hash, b"wrong".to_vec(), "resolver", NOW),
+            Err(EscrowError::SecretVerificationFailed)
+        ));
+        assert!(matches!(
+            claim_escrow_with_preimage(&order_hash, b"secret".to_vec(), "maker", NOW),
+            Err(EscrowError::Unauthorized)
+        ));
+
+        claim_escrow_with_preimage(&order_hash, b"secret".to_vec(), "resolver", NOW).unwrap();
+        assert_eq!(memory::get_htlc_escrow(&order_hash).unwrap().status, EscrowStatus::Completed);
+        for party in ["maker", "resolver"] {
+            let preimage = revealed_preimage_for(&order_hash, party).unwrap();
+            assert_eq!(preimage, Some(b"secret".to_vec()));
+        }
+    }
+
+    #[test]
+    fn test_preimage_denied_to_strangers() {
+        memory::clear_escrow_data();
+        let order_hash = store_claimable_escrow(b"secret");
+        claim_escrow_with_preimage(&order_hash, b"secret".to_vec(), "resolver", NOW).unwrap();
+
+        assert!(matches!(
+            revealed_preimage_for(&order_hash, "stranger"),
+            Err(EscrowError::Unauthorized)
+        ));
+    }
+
+    /// Store a pair whose legs share one claimable escrow locked to `secret`
+    fn store_claimable_pair(secret: &[u8]) -> String {
+        let order_hash = store_claimable_escrow(secret);
+        let escrow = memory::get_htlc_escrow(&order_hash).unwrap();
+        memory::store_cross_chain_escrow(CrossChainEscrow {
+            order_id: "pair".to_string(),
+            icp_escrow: escrow.clone(),
+            evm_escrow: escrow,
+            coordination_state: CoordinationState::EscrowsCreated,
+            events: Vec::new(),
+            icp_finality_lag: 0,
+            evm_finality_lag: 0,
+            failed_transactions: 0,
+            revealed_secret: None,
+            created_at: NOW,
+            updated_at: NOW,
+        })
+        .unwrap();
+        "pair".to_string()
+    }
+
+    #[test]
+    fn test_revealed_secret_claims_icp_leg() {
+        memory::clear_escrow_data();
+        let order_id = store_claimable_pair(b"secret");
+
+        propagate_revealed_secret(&order_id, b"secret".to_vec(), NOW).unwrap();
+
+        let pair = memory::get_cross_chain_escrow(&order_id).unwrap();
+        assert_eq!(pair.revealed_secret, Some(b"secret".to_vec()));
+        assert_eq!(pair.coordination_state, CoordinationState::Completed);
+        assert_eq!(pair.icp_escrow.status, EscrowStatus::Completed);
+        assert!(matches!(
+            pair.events.last(),
+            Some(types::CrossChainEscrowEvent::SecretPropagated { .. })
+        ));
+        let icp_order_hash = pair.icp_escrow.order_hash;
+        assert_eq!(
+            memory::get_htlc_escrow(&icp_order_hash).unwrap().status,
+            EscrowStatus::Completed
+        );
+    }
+
+    #[test]
+    fn test_reported_secret_must_match_hashlock() {
+        memory::clear_escrow_data();
+        let order_id = store_claimable_pair(b"secret");
+
+        assert!(matches!(
+            propagate_revealed_secret(&order_id, b"wrong".to_vec(), NOW),
+            Err(EscrowError::SecretVerificationFailed)
+        ));
+        assert!(matches!(auto_claim_icp_leg(&order_id, NOW), Err(EscrowError::InvalidState)));
+
+        let pair = memory::get_cross_chain_escrow(&order_id).unwrap();
+        assert_eq!(pair.revealed_secret, None);
+        assert_eq!(pair.icp_escrow.status, EscrowStatus::Active);
+    }
+
+    #[test]
+    fn test_auto_claim_is_idempotent() {
+        memory::clear_escrow_data();
+        let order_id = store_claimable_pair(b"secret");
+        propagate_revealed_secret(&order_id, b"secret".to_vec(), NOW).unwrap();
+        let events = memory::get_cross_chain_escrow(&order_id).unwrap().events.len();
+
+        auto_claim_icp_leg(&order_id, NOW + 1).unwrap();
+        propagate_revealed_secret(&order_id, b"secret".to_vec(), NOW + 1).unwrap();
+
+        assert_eq!(memory::get_cross_chain_escrow(&order_id).unwrap().events.len(), events);
+    }
+
+    #[test]
+    fn test_icp_leg_claim_waits_for_evm_verification() {
+        memory::clear_escrow_data();
+        let order_id = store_claimable_pair(b"secret");
+        let order_hash = memory::get_cross_chain_escrow(&order_id).unwrap().evm_escrow.order_hash;
+        let report = |passed| EscrowVerificationReport {
+            order_hash: order_hash.clone(),
+            escrow_address: format!("0x{}", "e".repeat(40)),
+            passed,
+            mismatches: Vec::new(),
+            verified_at: NOW,
+        };
+
+        for verification in [None, Some(report(false))] {
+            if let Some(report) = verification {
+                memory::store_verification_report(report);
+            }
+            assert!(matches!(
+                claim_escrow_with_preimage(&order_hash, b"secret".to_vec(), "resolver", NOW),
+                Err(EscrowError::CrossChainCoordinationFailed)
+            ));
+            assert_eq!(memory::get_revealed_preimage(&order_hash), None);
+        }
+
+        memory::store_verification_report(report(true));
+        claim_escrow_with_preimage(&order_hash, b"secret".to_vec(), "resolver", NOW).unwrap();
+        assert_eq!(memory::get_htlc_escrow(&order_hash).unwrap().status, EscrowStatus::Completed);
+    }
+
+    #[test]
+    fn test_verified_address_comes_from_deployment() {
+        memory::clear_escrow_data();
+        let order_hash = store_claimable_escrow(b"secret");
+        assert!(matches!(stored_evm_escrow_address(&order_hash), Err(EscrowError::InvalidAddress)));
+
+        let deployed = format!("0x{}", "d".repeat(40));
+        memory::record_deployment_attempt(DeploymentAttempt {
+            order_hash: order_hash.clone(),
+            raw_tx_hash: "0xtx".to_string(),
+            nonce: 0,
+            status: DeploymentStatus::Broadcast,
+            contract_address: None,
+            created_at: NOW,
+            updated_at: NOW,
+        });
+        memory::update_latest_deployment_attempt(
+            &order_hash,
+            DeploymentStatus::Confirmed,
+            Some(deployed.clone()),
+            NOW,
+        )
+        .unwrap();
+        assert_eq!(stored_evm_escrow_address(&order_hash).unwrap(), deployed);
+    }
+
+    const ALL_STATUSES: [EscrowStatus; 6] = [
+        EscrowStatus::Created,
+        EscrowStatus::Funded,
+        EscrowStatus::Active,
+        EscrowStatus::Completed,
+        EscrowStatus::Cancelled,
+        EscrowStatus::Expired,
+    ];
+
+    #[test]
+    fn test_status_transition_table() {
+        use EscrowStatus::*;
+        let allowed = [
+            (Created, Funded),
+            (Created, Cancelled),
+            (Funded, Active),
+            (Funded, Cancelled),
+            (Funded, Expired),
+            (Active, Completed),
+            (Active, Expired),
+            (Expired, Cancelled),
+        ];
+
+        for from in &ALL_STATUSES {
+            for to in &ALL_STATUSES {
+                let expected = allowed.contains(&(from.clone(), to.clone()));
+                assert_eq!(from.can_transition_to(to), expected, "{:?} -> {:?}", from, to);
+            }
+        }
+    }
+
+    #[test]
+    fn test_status_update_rejects_skips() {
+        memory::clear_escrow_data();
+        let order_hash = create_icp_escrows_batch_at(batch_base(100), vec![part(0, 100)], NOW)
+            .unwrap()
+            .remove(0)
+            .unwrap();
+
+        // Created cannot jump to Completed, by status update or by claim
+        assert!(matches!(
+            memory::update_htlc_escrow_status(&order_hash, EscrowStatus::Completed, NOW),
+            Err(EscrowError::StateTransitionInvalid)
+        ));
+        assert!(matches!(
+            claim_escrow_with_preimage(&order_hash, b"secret".to_vec(), "resolver", NOW),
+            Err(EscrowError::StateTransitionInvalid)
+        ));
+        assert_eq!(memory::get_htlc_escrow(&order_hash).unwrap().status, EscrowStatus::Created);
+
+        for status in [EscrowStatus::Funded, EscrowStatus::Active, EscrowStatus::Completed] {
+            memory::update_htlc_escrow_status(&order_hash, status, NOW + 1).unwrap();
+        }
+        let escrow = memory::get_htlc_escrow(&order_hash).unwrap();
+        assert_eq!((escrow.status, escrow.updated_at), (EscrowStatus::Completed, NOW + 1));
+        assert!(matches!(
+            memory::update_htlc_escrow_status("missing", EscrowStatus::Funded, NOW),
+            Err(EscrowError::EscrowNotFound)
+        ));
+    }
+
+    #[test]
+    fn test_forced_status_records_audit_event() {
+        memory::clear_escrow_data();
+        let order_hash = store_claimable_escrow(b"secret");
+        memory::update_htlc_escrow_status(&order_hash, EscrowStatus::Completed, NOW).unwrap();
+
+        force_escrow_status(
+            &order_hash,
+            EscrowStatus::Active,
+            "controller",
+            "claim replayed by mistake".to_string(),
+            NOW + 5,
+        )
+        .unwrap();
+
+        let escrow = memory::get_htlc_escrow(&order_hash).unwrap();
+        assert_eq!((escrow.status, escrow.updated_at), (EscrowStatus::Active, NOW + 5));
+        match escrow.events.last() {
+            Some(types::CrossChainEscrowEvent::StatusForced { from, to, controller, reason }) => {
+                assert_eq!((from, to), (&EscrowStatus::Completed, &EscrowStatus::Active));
+                assert_eq!(controller, "controller");
+                assert_eq!(reason, "claim replayed by mistake");
+            }
+            other => panic!("expected StatusForced event, got {:?}", other),
+        }
+    }
+
+    /// Create five ICP escrows, two of them funded, and three cross-chain pairs
+    fn store_mixed_escrows() {
+        memory::clear_escrow_data();
+        let parts = (0..5).map(|i| part(i, 200)).collect();
+        let hashes = create_icp_escrows_batch_at(batch_base(1_000), parts, NOW).unwrap();
+        for order_hash in hashes.iter().take(2) {
+            let order_hash = order_hash.as_ref().unwrap();
+            memory::update_htlc_escrow_status(order_hash, EscrowStatus::Funded, NOW).unwrap();
+        }
+
+        let escrow = memory::get_htlc_escrow("0xbatchorder_part_0").unwrap();
+        let states = [
+            CoordinationState::EscrowsCreated,
+            CoordinationState::Completed,
+            CoordinationState::EscrowsCreated,
+        ];
+        for (i, state) in states.into_iter().enumerate() {
+            memory::store_cross_chain_escrow(CrossChainEscrow {
+                order_id: format!("pair_{}", i),
+                icp_escrow: escrow.clone(),
+                evm_escrow: escrow.clone(),
+                coordination_state: state,
+                events: Vec::new(),
+                icp_finality_lag: 0,
+                evm_finality_lag: 0,
+                failed_transactions: 0,
+                revealed_secret: None,
+                created_at: NOW - i as u64,
+                updated_at: NOW,
+            })
+            .unwrap();
+        }
+    }
+
+    #[test]
+    fn test_status_listing_pagination() {
+        store_mixed_escrows();
+
+        let page_hashes = |status: EscrowStatus, offset, limit| -> Vec<String> {
+            list_htlc_escrows_by_status(status, offset, limit)
+                .into_iter()
+                .map(|summary| summary.order_hash)
+                .collect()
+        };
+        assert_eq!(
+            page_hashes(EscrowStatus::Created, 0, 2),
+            vec!["0xbatchorder_part_2", "0xbatchorder_part_3"]
+        );
+        assert_eq!(page_hashes(EscrowStatus::Created, 2, 2), vec!["0xbatchorder_part_4"]);
+        assert!(page_hashes(EscrowStatus::Created, 3, 2).is_empty());
+        assert_eq!(page_hashes(EscrowStatus::Funded, 0, 1_000).len(), 2);
+        assert!(page_hashes(EscrowStatus::Funded, 0, 0).is_empty());
+
+        let summary = &list_htlc_escrows_by_status(EscrowStatus::Funded, 0, 1)[0];
+        assert_eq!((summary.amount, summary.status.clone()), (200, EscrowStatus::Funded));
+
+        // Cross-chain listings are ordered by creation time
+        let pairs = list_cross_chain_escrows_by_state(CoordinationState::EscrowsCreated, 0, 10);
+        let ids: Vec<&str> = pairs.iter().map(|pair| pair.order_id.as_str()).collect();
+        assert_eq!(ids, vec!["pair_2", "pair_0"]);
+        assert_eq!(
+            list_cross_chain_escrows_by_state(CoordinationState::EscrowsCreated, 1, 10).len(),
+            1
+        );
+        assert!(list_cross_chain_escrows_by_state(CoordinationState::Failed, 0, 10).is_empty());
+    }
+
+    #[test]
+    fn test_storage_stats_after_mixed_escrows() {
+        memory::clear_escrow_data();
+        let empty = get_storage_stats();
+        assert_eq!((empty.total_escrows, empty.total_bytes), (0, 0));
+
+        store_mixed_escrows();
+        let stats = get_storage_stats();
+        assert_eq!(
+            (stats.htlc_escrows_count, stats.cross_chain_escrows_count, stats.total_escrows),
+            (5, 3, 8)
+        );
+        assert!(stats.htlc_escrows_bytes > 0);
+        assert!(stats.cross_chain_escrows_bytes > 0);
+        assert_eq!(stats.total_bytes, stats.htlc_escrows_bytes + stats.cross_chain_escrows_bytes);
+    }
+
+    /// Create five Created escrows and settle the first `settled` of them at `settled_at`
+    fn store_settled_escrows(settled: &[EscrowStatus], settled_at: u64) -> Vec<String> {
+        memory::clear_escrow_data();
+        memory::set_archive_policy(ArchivePolicy { ttl_ns: HOUR_NS, max_archived: 10 });
+        let parts = (0..5).map(|i| part(i, 200)).collect();
+        let hashes: Vec<String> = create_icp_escrows_batch_at(batch_base(1_000), parts, NOW)
+            .unwrap()
+            .into_iter()
+            .map(Result::unwrap)
+            .collect();
+
+        for (order_hash, status) in hashes.iter().zip(settled) {
+            force_escrow_status(order_hash, status.clone(), "ops", "settled".into(), settled_at)
+                .unwrap();
+        }
+        hashes
+    }
+
+    #[test]
+    fn test_terminal_escrows_archived_after_ttl() {
+        let settled = [EscrowStatus::Completed, EscrowStatus::Cancelled, EscrowStatus::Expired];
+        let hashes = store_settled_escrows(&settled, NOW);
+
+        assert_eq!(memory::archive_terminal_escrows(NOW + HOUR_NS - 1), 0);
+        assert_eq!(memory::get_all_htlc_escrows().len(), 5);
+
+        assert_eq!(memory::archive_terminal_escrows(NOW + HOUR_NS), 3);
+        for order_hash in &hashes[..3] {
+            assert!(memory::get_htlc_escrow(order_hash).is_err());
+            assert!(memory::get_archived_escrow(order_hash).is_some());
+        }
+        assert_eq!(memory::get_all_htlc_escrows().len(), 2);
+        assert_eq!(memory::archive_terminal_escrows(NOW + 2 * HOUR_NS), 0);
+    }
+
+    #[test]
+    fn test_archived_status_fallback_shape() {
+        let hashes = store_settled_escrows(&[EscrowStatus::Completed], NOW);
+
+        // Seven earlier events plus the StatusForced event are trimmed to the last five
+        let mut escrow = memory::get_htlc_escrow(&hashes[0]).unwrap();
+        escrow.events = (0..7)
+            .map(|i| types::CrossChainEscrowEvent::EscrowFunded {
+                escrow_id: format!("funding_{}", i),
+                chain: "ICP".to_string(),
+            })
+            .collect();
+        memory::update_htlc_escrow(&hashes[0], escrow).unwrap();
+        force_escrow_status(&hashes[0], EscrowStatus::Completed, "ops", "done".into(), NOW)
+            .unwrap();
+
+        let live = get_htlc_escrow_status(hashes[0].clone()).unwrap();
+        assert!(!live.archived && live.escrow.is_some() && live.summary.is_none());
+
+        memory::archive_terminal_escrows(NOW + HOUR_NS);
+        let archived = get_htlc_escrow_status(hashes[0].clone()).unwrap();
+        assert!(archived.archived);
+        assert!(archived.escrow.is_none());
+
+        let summary = archived.summary.unwrap();
+        assert_eq!(summary.order_hash, hashes[0]);
+        assert_eq!(summary.status, EscrowStatus::Completed);
+        assert_eq!((summary.amount, summary.completed_at), (200, NOW));
+        assert_eq!(summary.final_event_count, 8);
+        assert_eq!(summary.events.len(), types::ARCHIVED_EVENT_LIMIT);
+        assert!(matches!(
+            summary.events.last(),
+            Some(types::CrossChainEscrowEvent::StatusForced { .. })
+        ));
+
+        assert!(get_htlc_escrow_status("0xunknown".to_string()).is_none());
+    }
+
+    #[test]
+    fn test_non_terminal_escrows_never_archived() {
+        let active = [EscrowStatus::Funded, EscrowStatus::Active];
+        let hashes = store_settled_escrows(&active, NOW);
+        memory::set_archive_policy(ArchivePolicy { ttl_ns: 0, max_archived: 10 });
+
+        assert_eq!(memory::archive_terminal_escrows(NOW + 365 * 24 * HOUR_NS), 0);
+        for order_hash in &hashes {
+            assert!(!get_htlc_escrow_status(order_hash.clone()).unwrap().archived);
+        }
+    }
+
+    #[test]
+    fn test_archive_cap_evicts_oldest_summaries() {
+        memory::clear_escrow_data();
+        let mut hashes = Vec::new();
+        for (i, settled_at) in [NOW + 2, NOW, NOW + 1].into_iter().enumerate() {
+            let mut base = batch_base(200);
+            base.order_hash = format!("0xorder{}", i);
+            let hash = create_icp_escrows_batch_at(base, vec![part(0, 200)], NOW).unwrap();
+            let hash = hash[0].clone().unwrap();
+            force_escrow_status(&hash, EscrowStatus::Completed, "ops", "done".into(), settled_at)
+                .unwrap();
+            hashes.push(hash);
+        }
+        memory::set_archive_policy(ArchivePolicy { ttl_ns: HOUR_NS, max_archived: 2 });
+
+        assert_eq!(memory::archive_terminal_escrows(NOW + 2 * HOUR_NS), 3);
+
+        // The escrow completed first is evicted entirely
+        assert!(get_htlc_escrow_status(hashes[1].clone()).is_none());
+        assert!(get_htlc_escrow_status(hashes[0].clone()).unwrap().archived);
+        assert!(get_htlc_escrow_status(hashes[2].clone()).unwrap().archived);
+    }
+
+    #[test]
+    fn test_operator_allowed_only_on_operator_endpoints() {
+        memory::clear_escrow_data();
+        let operator = Principal::from_slice(&[1]);
+        let stranger = Principal::from_slice(&[2]);
+        memory::add_operator(operator);
+
+        assert!(roles::has_role(&operator, false, types::Role::Operator));
+        assert!(!roles::has_role(&operator, false, types::Role::Controller));
+        assert!(!roles::has_role(&stranger, false, types::Role::Operator));
+
+        // Controllers hold every role without being listed
+        assert!(roles::has_role(&stranger, true, types::Role::Operator));
+        assert!(roles::has_role(&stranger, true, types::Role::Controller));
+
+        // Revoked operators lose access; adding twice does not duplicate
+        memory::add_operator(operator);
+        assert_eq!(get_roles().operators, vec![operator]);
+        assert!(memory::remove_operator(&operator));
+        assert!(!memory::remove_operator(&operator));
+        assert!(!roles::has_role(&operator, false, types::Role::Operator));
+    }
+
+    #[test]
+    fn test_roles_persist_across_upgrade() {
+        memory::clear_escrow_data();
+        let operators = vec![Principal::from_slice(&[1]), Principal::from_slice(&[2])];
+        for operator in &operators {
+            memory::add_operator(*operator);
+        }
+
+        // Same round trip as pre_upgrade / post_upgrade
+        let saved = candid::encode_one(memory::get_operators()).unwrap();
+        memory::clear_escrow_data();
+        assert!(get_roles().operators.is_empty());
+        memory::set_operators(candid::decode_one(&saved).unwrap());
+        assert_eq!(get_roles().operators, operators);
+
+        // Backups carry the role list too
+        memory::import_escrow_data(memory::EscrowBackup {
+            htlc_escrows: vec![],
+            cross_chain_escrows: vec![],
+            archived_escrows: vec![],
+            deployment_attempts: vec![],
+            revealed_preimages: vec![],
+            operators: vec![operators[1]],
+            resolvers: vec![operators[0]],
+            limit_order_canister: None,
+            exported_at: NOW,
+        })
+        .unwrap();
+        assert!(!memory::is_operator(&operators[0]));
+        assert!(memory::is_operator(&operators[1]));
+        assert_eq!(get_roles().resolvers, vec![operators[0]]);
+    }
+
+    #[test]
+    fn test_resolver_role_covers_escrow_creators() {
+        memory::clear_escrow_data();
+        let resolver = Principal::from_slice(&[1]);
+        let limit_order = Principal::from_slice(&[2]);
+        let operator = Principal::from_slice(&[3]);
+        let stranger = Principal::from_slice(&[4]);
+        memory::add_resolver(resolver);
+        memory::add_operator(operator);
+        memory::set_limit_order_canister(Some(limit_order));
+
+        for principal in [resolver, limit_order, operator] {
+            assert!(roles::has_role(&principal, false, types::Role::Resolver));
+        }
+        assert!(!roles::has_role(&stranger, false, types::Role::Resolver));
+        assert!(roles::has_role(&stranger, true, types::Role::Resolver));
+
+        // Resolvers create escrows but hold no operator privileges
+        assert!(!roles::has_role(&resolver, false, types::Role::Operator));
+        assert!(memory::remove_resolver(&resolver));
+        memory::set_limit_order_canister(None);
+        assert!(!roles::has_role(&resolver, false, types::Role::Resolver));
+        assert!(!roles::has_role(&limit_order, false, types::Role::Resolver));
+    }
+
+    #[test]
+    fn test_locked_order_rejects_concurrent_mutations() {
+        memory::clear_escrow_data();
+        let order_hash = store_claimable_escrow(b"secret");
+
+        // A claim in flight holds the lock, so a concurrent refund is turned away
+        let claim = locks::OrderLock::acquire(&order_hash, NOW).unwrap();
+        assert_eq!(memory::get_locked_orders(), vec![(order_hash.clone(), NOW)]);
+        let refund = force_escrow_status(
+            &order_hash,
+            EscrowStatus::Cancelled,
+            "controller",
+            "refund".to_string(),
+            NOW,
+        );
+        assert!(matches!(refund, Err(EscrowError::OperationInProgress)));
+        assert!(matches!(
+            claim_escrow_with_preimage(&order_hash, b"secret".to_vec(), "resolver", NOW),
+            Err(EscrowError::OperationInProgress)
+        ));
+        assert_eq!(memory::get_htlc_escrow(&order_hash).unwrap().status, EscrowStatus::Active);
+
+        // Released locks let the next operation proceed, and are released on errors too
+        drop(claim);
+        assert!(memory::get_locked_orders().is_empty());
+        claim_escrow_with_preimage(&order_hash, b"secret".to_vec(), "resolver", NOW).unwrap();
+        assert!(matches!(
+            claim_escrow_with_preimage(&order_hash, b"secret".to_vec(), "resolver", NOW),
+            Err(EscrowError::StateTransitionInvalid)
+        ));
+        assert!(memory::get_locked_orders().is_empty());
+        assert_eq!(memory::get_htlc_escrow(&order_hash).unwrap().status, EscrowStatus::Completed);
+    }
+
+    #[test]
+    fn test_stale_order_lock_reclaimed() {
+        memory::clear_escrow_data();
+        let leaked = locks::OrderLock::acquire("0xorder", NOW).unwrap();
+
+        assert!(matches!(
+            locks::OrderLock::acquire("0xorder", NOW + locks::STALE_LOCK_NS),
+            Err(EscrowError::OperationInProgress)
+        ));
+        let reclaimed =
+            locks::OrderLock::acquire("0xorder", NOW + locks::STALE_LOCK_NS + 1).unwrap();
+
+        // The original holder finishing late does not release the reclaimed lock
+        drop(leaked);
+        assert_eq!(
+            memory::get_locked_orders(),
+            vec![("0xorder".to_string(), NOW + locks::STALE_LOCK_NS + 1)]
+        );
+        drop(reclaimed);
+        assert!(memory::get_locked_orders().is_empty());
+    }
+
+    /// Store a pair with separate active ICP and EVM legs, both locked to `secret`, whose EVM
+    /// escrow passed verification
+    fn store_drifting_pair(secret: &[u8], coordination_state: CoordinationState) -> String {
+        let icp_escrow = memory::get_htlc_escrow(&store_claimable_escrow(secret)).unwrap();
+        let evm_escrow =
+            HTLCEscrow { order_hash: "0xbatchorder_evm".to_string(), ..icp_escrow.clone() };
+        memory::store_htlc_escrow(evm_escrow.clone()).unwrap();
+        memory::store_verification_report(EscrowVerificationReport {
+            order_hash: evm_escrow.order_hash.clone(),
+            escrow_address: format!("0x{}", "e".repeat(40)),
+            passed: true,
+            mismatches: Vec::new(),
+            verified_at: NOW,
+        });
+        memory::store_cross_chain_escrow(CrossChainEscrow {
+            order_id: "pair".to_string(),
+            icp_escrow,
+            evm_escrow,
+            coordination_state,
+            events: Vec::new(),
+            icp_finality_lag: 0,
+            evm_finality_lag: 0,
+            failed_transactions: 0,
+            revealed_secret: None,
+            created_at: NOW,
+            updated_at: NOW,
+        })
+        .unwrap();
+        "pair".to_string()
+    }
+
+    /// Overwrite a stored leg's status without going through the lifecycle checks
+    fn set_leg_status(order_hash: &str, status: EscrowStatus) {
+        let mut escrow = memory::get_htlc_escrow(order_hash).unwrap();
+        escrow.status = status;
+        memory::update_htlc_escrow(order_hash, escrow).unwrap();
+    }
+
+    #[test]
+    fn test_coordination_drift_corrected() {
+        use CoordinationState::*;
+        use EscrowStatus::{Active as Live, Cancelled, Completed as Done, Funded};
+
+        let expected = [
+            (Funded, Live, false, EscrowsCreated),
+            (Live, Live, false, Active),
+            (Done, Live, false, SecretRevealed),
+            (Live, Live, true, SecretRevealed),
+            (Done, Live, true, Completed),
+            (Live, Cancelled, false, Expired),
+        ];
+        for (icp, evm, revealed, state) in expected {
+            let derived = reconcile::derive_coordination_state(&icp, &evm, revealed);
+            assert_eq!(derived, Ok(state), "{:?}/{:?} revealed={}", icp, evm, revealed);
+        }
+
+        // The ICP leg completes while coordination still says Active
+        memory::clear_escrow_data();
+        let order_id = store_drifting_pair(b"secret", Active);
+        claim_escrow_with_preimage("0xbatchorder", b"secret".to_vec(), "resolver", NOW).unwrap();
+
+        assert!(matches!(reconcile::reconcile_pair(&order_id, NOW + 1), Ok(SecretRevealed)));
+        let pair = memory::get_cross_chain_escrow(&order_id).unwrap();
+        assert_eq!(pair.icp_escrow.status, Done);
+        assert_eq!(pair.updated_at, NOW + 1);
+        assert!(matches!(
+            pair.events.last(),
+            Some(types::CrossChainEscrowEvent::DriftCorrected { from: Active, to: SecretRevealed })
+        ));
+
+        // Then the EVM leg, after which a further pass changes nothing
+        set_leg_status("0xbatchorder_evm", Done);
+        assert_eq!(reconcile::reconcile_all(NOW + 2), 1);
+        assert_eq!(reconcile::reconcile_all(NOW + 3), 0);
+        let pair = memory::get_cross_chain_escrow(&order_id).unwrap();
+        assert_eq!(pair.coordination_state, Completed);
+        assert_eq!(pair.events.len(), 2);
+        assert_eq!(pair.updated_at, NOW + 2);
+
+        let stats = memory::get_reconciliation_stats();
+        assert_eq!(stats.drift_corrections, 2);
+        assert_eq!(stats.inconsistent_escrows, 0);
+        assert_eq!(stats.last_run_at, Some(NOW + 3));
+    }
+
+    #[test]
+    fn test_irreconcilable_legs_flagged() {
+        memory::clear_escrow_data();
+        let order_id = store_drifting_pair(b"secret", CoordinationState::Active);
+        claim_escrow_with_preimage("0xbatchorder", b"secret".to_vec(), "resolver", NOW).unwrap();
+        set_leg_status("0xbatchorder_evm", EscrowStatus::Cancelled);
+
+        assert_eq!(reconcile::reconcile_all(NOW + 1), 1);
+        reconcile::reconcile_all(NOW + 2);
+
+        let pair = memory::get_cross_chain_escrow(&order_id).unwrap();
+        assert_eq!(pair.coordination_state, CoordinationState::Failed);
+        let flagged = memory::get_inconsistent_escrows();
+        assert_eq!(flagged.len(), 1);
+        assert_eq!(flagged[0].order_id, order_id);
+        assert_eq!(flagged[0].icp_status, EscrowStatus::Completed);
+        assert_eq!(flagged[0].evm_status, EscrowStatus::Cancelled);
+        assert_eq!(flagged[0].flagged_at, NOW + 1);
+        assert_eq!(memory::get_reconciliation_stats().inconsistent_escrows, 1);
+
+        // A locked pair is skipped rather than reconciled under an operation in flight
+        let _lock = locks::OrderLock::acquire(&order_id, NOW + 3).unwrap();
+        assert!(matches!(
+            reconcile::reconcile_pair(&order_id, NOW + 3),
+            Err(EscrowError::OperationInProgress)
+        ));
+    }
 }
 
 ic_cdk::export_candid!();
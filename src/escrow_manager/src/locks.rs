@@ -0,0 +1,38 @@
+use crate::memory;
+use crate::types::EscrowError;
+
+// ============================================================================
+// ORDER LOCKS
+// ============================================================================
+
+/// Age after which a lock is presumed left behind by a call that never released it
+pub const STALE_LOCK_NS: u64 = 10 * 60 * 1_000_000_000;
+
+/// Exclusive right to mutate the escrows of one order hash, released when dropped
+///
+/// Held across await points, so a concurrent call on the same order is rejected instead of
+/// interleaving with it. A trap drops the guard during call cleanup; locks that still leak are
+/// reclaimed once older than `STALE_LOCK_NS`.
+#[must_use]
+pub struct OrderLock {
+    order_hash: String,
+    locked_at: u64,
+}
+
+impl OrderLock {
+    /// Take the lock of an order hash, failing with OperationInProgress while another call holds it
+    pub fn acquire(order_hash: &str, current_time: u64) -> Result<Self, EscrowError> {
+        memory::try_lock_order(
+            order_hash,
+            current_time,
+            current_time.saturating_sub(STALE_LOCK_NS),
+        )?;
+        Ok(Self { order_hash: order_hash.to_string(), locked_at: current_time })
+    }
+}
+
+impl Drop for OrderLock {
+    fn drop(&mut self) {
+        memory::unlock_order(&self.order_hash, self.locked_at);
+    }
+}
@@ -1,14 +1,33 @@
 use crate::types::{
-    CoordinationState, CrossChainEscrow, CrossChainEscrowEvent, EscrowError, EscrowStatus,
-    HTLCEscrow,
+    ArchivePolicy, ArchivedEscrow, CoordinationState, CostBreakdown, CostSummary, Create2Config,
+    CrossChainEscrow, CrossChainEscrowEvent, DeploymentAttempt, DeploymentStatus, EscrowError,
+    EscrowStatus, EscrowVerificationReport, HTLCEscrow, InconsistentEscrow, ReconciliationStats,
+    RpcProviderStats, RpcProviderStrategy,
 };
+use candid::{CandidType, Deserialize, Principal};
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 // Global state using thread_local! for safety
 thread_local! {
     static HTLC_ESCROWS: RefCell<HashMap<String, HTLCEscrow>> = RefCell::new(HashMap::new());
     static CROSS_CHAIN_ESCROWS: RefCell<HashMap<String, CrossChainEscrow>> = RefCell::new(HashMap::new());
+    static VERIFICATION_REPORTS: RefCell<HashMap<String, EscrowVerificationReport>> = RefCell::new(HashMap::new());
+    static DEPLOYMENT_ATTEMPTS: RefCell<HashMap<String, Vec<DeploymentAttempt>>> = RefCell::new(HashMap::new());
+    static REVEALED_PREIMAGES: RefCell<HashMap<String, Vec<u8>>> = RefCell::new(HashMap::new());
+    static ARCHIVED_ESCROWS: RefCell<HashMap<String, ArchivedEscrow>> = RefCell::new(HashMap::new());
+    static ARCHIVE_POLICY: RefCell<ArchivePolicy> = RefCell::new(ArchivePolicy::default());
+    static OPERATORS: RefCell<Vec<Principal>> = const { RefCell::new(Vec::new()) };
+    static RESOLVERS: RefCell<Vec<Principal>> = const { RefCell::new(Vec::new()) };
+    static LIMIT_ORDER_CANISTER: RefCell<Option<Principal>> = const { RefCell::new(None) };
+    static CREATE2_CONFIG: RefCell<Option<Create2Config>> = const { RefCell::new(None) };
+    static RPC_PROVIDER_STRATEGIES: RefCell<HashMap<u64, RpcProviderStrategy>> = RefCell::new(HashMap::new());
+    static RPC_PROVIDER_STATS: RefCell<HashMap<(u64, String), RpcProviderStats>> = RefCell::new(HashMap::new());
+    // Order hashes with a mutating operation in flight, and when it took the lock
+    static ORDER_LOCKS: RefCell<BTreeMap<String, u64>> = const { RefCell::new(BTreeMap::new()) };
+    // Cross-chain escrows whose legs disagree beyond repair, and reconciliation counters
+    static INCONSISTENT_ESCROWS: RefCell<BTreeMap<String, InconsistentEscrow>> = const { RefCell::new(BTreeMap::new()) };
+    static RECONCILIATION_STATS: RefCell<ReconciliationStats> = RefCell::new(ReconciliationStats::default());
 }
 
 /// Store an HTLC escrow
@@ -32,16 +51,20 @@ pub fn update_htlc_escrow(order_hash: &str, updated_escrow: HTLCEscrow) -> Resul
     })
 }
 
-/// Update HTLC escrow status
+/// Update HTLC escrow status, rejecting moves the lifecycle does not allow
 pub fn update_htlc_escrow_status(
     order_hash: &str,
     new_status: EscrowStatus,
+    current_time: u64,
 ) -> Result<(), EscrowError> {
     HTLC_ESCROWS.with(|escrows| {
         let mut escrows_map = escrows.borrow_mut();
         if let Some(escrow) = escrows_map.get_mut(order_hash) {
+            if !escrow.status.can_transition_to(&new_status) {
+                return Err(EscrowError::StateTransitionInvalid);
+            }
             escrow.status = new_status;
-            escrow.updated_at = ic_cdk::api::time();
+            escrow.updated_at = current_time;
             Ok(())
         } else {
             Err(EscrowError::EscrowNotFound)
@@ -85,11 +108,101 @@ pub fn get_htlc_escrows_by_status(status: EscrowStatus) -> Vec<HTLCEscrow> {
     })
 }
 
+/// Add Chain Fusion costs incurred for an order to its HTLC escrow
+pub fn add_escrow_costs(
+    order_hash: &str,
+    costs: &CostBreakdown,
+    current_time: u64,
+) -> Result<(), EscrowError> {
+    HTLC_ESCROWS.with(|escrows| {
+        let mut escrows_map = escrows.borrow_mut();
+        let escrow = escrows_map.get_mut(order_hash).ok_or(EscrowError::EscrowNotFound)?;
+        escrow.costs.get_or_insert_with(CostBreakdown::default).add(costs);
+        escrow.updated_at = current_time;
+        Ok(())
+    })
+}
+
+/// Sum the costs of HTLC escrows created in [from_ns, to_ns)
+pub fn get_cost_summary(from_ns: u64, to_ns: u64) -> CostSummary {
+    HTLC_ESCROWS.with(|escrows| {
+        let mut summary =
+            CostSummary { from_ns, to_ns, escrow_count: 0, total: CostBreakdown::default() };
+        for escrow in escrows.borrow().values() {
+            if let (true, Some(costs)) =
+                ((from_ns..to_ns).contains(&escrow.created_at), &escrow.costs)
+            {
+                summary.escrow_count += 1;
+                summary.total.add(costs);
+            }
+        }
+        summary
+    })
+}
+
 /// Check if HTLC escrow exists
 pub fn htlc_escrow_exists(order_hash: &str) -> bool {
     HTLC_ESCROWS.with(|escrows| escrows.borrow().contains_key(order_hash))
 }
 
+/// Get the archived summary of an HTLC escrow
+pub fn get_archived_escrow(order_hash: &str) -> Option<ArchivedEscrow> {
+    ARCHIVED_ESCROWS.with(|archive| archive.borrow().get(order_hash).cloned())
+}
+
+/// Replace the archive retention rules
+pub fn set_archive_policy(policy: ArchivePolicy) {
+    ARCHIVE_POLICY.with(|current| *current.borrow_mut() = policy);
+}
+
+/// Get the archive retention rules
+pub fn get_archive_policy() -> ArchivePolicy {
+    ARCHIVE_POLICY.with(|policy| policy.borrow().clone())
+}
+
+/// Archive terminal HTLC escrows whose last update is older than the retention TTL
+///
+/// Full records are replaced by summaries; non-terminal escrows are never touched. Once the
+/// archive exceeds its cap, the summaries completed earliest are evicted. Returns the number of
+/// escrows archived.
+pub fn archive_terminal_escrows(current_time: u64) -> usize {
+    let policy = get_archive_policy();
+
+    let archived: Vec<HTLCEscrow> = HTLC_ESCROWS.with(|escrows| {
+        let mut escrows = escrows.borrow_mut();
+        let due: Vec<String> = escrows
+            .values()
+            .filter(|escrow| {
+                escrow.status.is_terminal()
+                    && current_time.saturating_sub(escrow.updated_at) >= policy.ttl_ns
+            })
+            .map(|escrow| escrow.order_hash.clone())
+            .collect();
+        due.iter().filter_map(|order_hash| escrows.remove(order_hash)).collect()
+    });
+
+    ARCHIVED_ESCROWS.with(|archive| {
+        let mut archive = archive.borrow_mut();
+        for escrow in &archived {
+            archive.insert(escrow.order_hash.clone(), ArchivedEscrow::from(escrow));
+        }
+
+        let excess = archive.len().saturating_sub(policy.max_archived as usize);
+        if excess > 0 {
+            let mut oldest: Vec<(u64, String)> = archive
+                .values()
+                .map(|summary| (summary.completed_at, summary.order_hash.clone()))
+                .collect();
+            oldest.sort();
+            for (_, order_hash) in oldest.into_iter().take(excess) {
+                archive.remove(&order_hash);
+            }
+        }
+    });
+
+    archived.len()
+}
+
 /// Store a cross-chain escrow
 pub fn store_cross_chain_escrow(escrow: CrossChainEscrow) -> Result<(), EscrowError> {
     CROSS_CHAIN_ESCROWS.with(|escrows| {
@@ -176,36 +289,280 @@ pub fn cross_chain_escrow_exists(order_id: &str) -> bool {
     CROSS_CHAIN_ESCROWS.with(|escrows| escrows.borrow().contains_key(order_id))
 }
 
+/// Store the latest EVM escrow verification report for an order
+pub fn store_verification_report(report: EscrowVerificationReport) {
+    VERIFICATION_REPORTS.with(|reports| {
+        reports.borrow_mut().insert(report.order_hash.clone(), report);
+    });
+}
+
+/// Get the latest EVM escrow verification report for an order
+pub fn get_verification_report(order_hash: &str) -> Option<EscrowVerificationReport> {
+    VERIFICATION_REPORTS.with(|reports| reports.borrow().get(order_hash).cloned())
+}
+
+/// Record a deployment attempt before its transaction is broadcast
+pub fn record_deployment_attempt(attempt: DeploymentAttempt) {
+    DEPLOYMENT_ATTEMPTS.with(|attempts| {
+        attempts.borrow_mut().entry(attempt.order_hash.clone()).or_default().push(attempt);
+    });
+}
+
+/// Update the outcome of the latest deployment attempt for an order
+pub fn update_latest_deployment_attempt(
+    order_hash: &str,
+    status: DeploymentStatus,
+    contract_address: Option<String>,
+    updated_at: u64,
+) -> Result<(), EscrowError> {
+    DEPLOYMENT_ATTEMPTS.with(|attempts| {
+        let mut attempts = attempts.borrow_mut();
+        let attempt = attempts
+            .get_mut(order_hash)
+            .and_then(|history| history.last_mut())
+            .ok_or(EscrowError::EscrowNotFound)?;
+        attempt.status = status;
+        attempt.contract_address = contract_address;
+        attempt.updated_at = updated_at;
+        Ok(())
+    })
+}
+
+/// Get the latest deployment attempt for an order
+pub fn get_latest_deployment_attempt(order_hash: &str) -> Option<DeploymentAttempt> {
+    DEPLOYMENT_ATTEMPTS
+        .with(|attempts| attempts.borrow().get(order_hash).and_then(|h| h.last().cloned()))
+}
+
+/// Get all deployment attempts for an order, oldest first
+pub fn get_deployment_attempts(order_hash: &str) -> Vec<DeploymentAttempt> {
+    DEPLOYMENT_ATTEMPTS
+        .with(|attempts| attempts.borrow().get(order_hash).cloned().unwrap_or_default())
+}
+
+/// Store the secret revealed when an escrow was claimed
+pub fn store_revealed_preimage(order_hash: &str, preimage: Vec<u8>) {
+    REVEALED_PREIMAGES.with(|preimages| {
+        preimages.borrow_mut().insert(order_hash.to_string(), preimage);
+    });
+}
+
+/// Get the secret revealed for an escrow, if it was claimed
+pub fn get_revealed_preimage(order_hash: &str) -> Option<Vec<u8>> {
+    REVEALED_PREIMAGES.with(|preimages| preimages.borrow().get(order_hash).cloned())
+}
+
 /// Get memory statistics for monitoring
 pub fn get_memory_stats() -> MemoryStats {
-    let htlc_count = HTLC_ESCROWS.with(|escrows| escrows.borrow().len());
-    let cross_chain_count = CROSS_CHAIN_ESCROWS.with(|escrows| escrows.borrow().len());
+    let htlc_count = HTLC_ESCROWS.with(|escrows| escrows.borrow().len()) as u64;
+    let cross_chain_count = CROSS_CHAIN_ESCROWS.with(|escrows| escrows.borrow().len()) as u64;
+
+    let htlc_escrows_bytes = HTLC_ESCROWS.with(|escrows| encoded_size(escrows.borrow().values()));
+    let cross_chain_escrows_bytes =
+        CROSS_CHAIN_ESCROWS.with(|escrows| encoded_size(escrows.borrow().values()));
+    let verification_reports_bytes =
+        VERIFICATION_REPORTS.with(|reports| encoded_size(reports.borrow().values()));
+    let deployment_attempts_bytes =
+        DEPLOYMENT_ATTEMPTS.with(|attempts| encoded_size(attempts.borrow().values().flatten()));
+    let revealed_preimages_bytes = REVEALED_PREIMAGES
+        .with(|preimages| preimages.borrow().values().map(|p| p.len() as u64).sum::<u64>());
 
     MemoryStats {
         htlc_escrows_count: htlc_count,
         cross_chain_escrows_count: cross_chain_count,
         total_escrows: htlc_count + cross_chain_count,
+        htlc_escrows_bytes,
+        cross_chain_escrows_bytes,
+        verification_reports_bytes,
+        deployment_attempts_bytes,
+        revealed_preimages_bytes,
+        total_bytes: htlc_escrows_bytes
+            + cross_chain_escrows_bytes
+            + verification_reports_bytes
+            + deployment_attempts_bytes
+            + revealed_preimages_bytes,
     }
 }
 
+/// Estimate the stored size of values by their Candid encoding
+fn encoded_size<'a, T: CandidType + 'a>(values: impl Iterator<Item = &'a T>) -> u64 {
+    values.map(|value| candid::encode_one(value).map_or(0, |bytes| bytes.len() as u64)).sum()
+}
+
 /// Memory statistics structure
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, CandidType, Deserialize, PartialEq)]
 pub struct MemoryStats {
-    pub htlc_escrows_count: usize,
-    pub cross_chain_escrows_count: usize,
-    pub total_escrows: usize,
+    pub htlc_escrows_count: u64,
+    pub cross_chain_escrows_count: u64,
+    pub total_escrows: u64,
+
+    // Estimated bytes per map, by Candid encoding of the stored values
+    pub htlc_escrows_bytes: u64,
+    pub cross_chain_escrows_bytes: u64,
+    pub verification_reports_bytes: u64,
+    pub deployment_attempts_bytes: u64,
+    pub revealed_preimages_bytes: u64,
+    pub total_bytes: u64,
+}
+
+/// Set the factory used for CREATE2 escrow deployments
+pub fn set_create2_config(config: Create2Config) {
+    CREATE2_CONFIG.with(|current| *current.borrow_mut() = Some(config));
+}
+
+/// Get the CREATE2 factory configuration, None while deployments use plain CREATE
+pub fn get_create2_config() -> Option<Create2Config> {
+    CREATE2_CONFIG.with(|config| config.borrow().clone())
+}
+
+/// Query a chain's EVM RPC providers by strategy
+pub fn set_rpc_provider_strategy(chain_id: u64, strategy: RpcProviderStrategy) {
+    RPC_PROVIDER_STRATEGIES.with(|strategies| strategies.borrow_mut().insert(chain_id, strategy));
+}
+
+/// Go back to the default provider of a chain, returning whether it had a strategy
+pub fn remove_rpc_provider_strategy(chain_id: u64) -> bool {
+    RPC_PROVIDER_STRATEGIES.with(|strategies| strategies.borrow_mut().remove(&chain_id).is_some())
+}
+
+/// Get the EVM RPC provider strategy of a chain, None while it uses the default provider
+pub fn get_rpc_provider_strategy(chain_id: u64) -> Option<RpcProviderStrategy> {
+    RPC_PROVIDER_STRATEGIES.with(|strategies| strategies.borrow().get(&chain_id).cloned())
+}
+
+/// Update the call statistics of a chain's EVM RPC provider
+pub fn update_rpc_provider_stats(
+    chain_id: u64,
+    provider: &str,
+    update: impl FnOnce(&mut RpcProviderStats),
+) {
+    RPC_PROVIDER_STATS.with(|stats| {
+        let mut stats = stats.borrow_mut();
+        let entry = stats.entry((chain_id, provider.to_string())).or_insert_with(|| {
+            RpcProviderStats { chain_id, provider: provider.to_string(), ..Default::default() }
+        });
+        update(entry);
+    });
+}
+
+/// Get the call statistics of every EVM RPC provider, sorted by chain and provider
+pub fn get_rpc_provider_stats() -> Vec<RpcProviderStats> {
+    let mut all: Vec<RpcProviderStats> =
+        RPC_PROVIDER_STATS.with(|stats| stats.borrow().values().cloned().collect());
+    all.sort_by(|a, b| (a.chain_id, &a.provider).cmp(&(b.chain_id, &b.provider)));
+    all
+}
+
+/// Grant the operator role, ignoring principals that already hold it
+pub fn add_operator(principal: Principal) {
+    OPERATORS.with(|operators| {
+        let mut operators = operators.borrow_mut();
+        if !operators.contains(&principal) {
+            operators.push(principal);
+        }
+    });
+}
+
+/// Revoke the operator role, returning whether the principal held it
+pub fn remove_operator(principal: &Principal) -> bool {
+    OPERATORS.with(|operators| {
+        let mut operators = operators.borrow_mut();
+        let before = operators.len();
+        operators.retain(|operator| operator != principal);
+        operators.len() != before
+    })
+}
+
+/// Get all operators in the order they were added
+pub fn get_operators() -> Vec<Principal> {
+    OPERATORS.with(|operators| operators.borrow().clone())
+}
+
+/// Replace the operator list, used when restoring state after an upgrade
+pub fn set_operators(principals: Vec<Principal>) {
+    OPERATORS.with(|operators| *operators.borrow_mut() = principals);
+}
+
+/// Check if a principal holds the operator role
+pub fn is_operator(principal: &Principal) -> bool {
+    OPERATORS.with(|operators| operators.borrow().contains(principal))
+}
+
+/// Grant the resolver role, ignoring principals that already hold it
+pub fn add_resolver(principal: Principal) {
+    RESOLVERS.with(|resolvers| {
+        let mut resolvers = resolvers.borrow_mut();
+        if !resolvers.contains(&principal) {
+            resolvers.push(principal);
+        }
+    });
+}
+
+/// Revoke the resolver role, returning whether the principal held it
+pub fn remove_resolver(principal: &Principal) -> bool {
+    RESOLVERS.with(|resolvers| {
+        let mut resolvers = resolvers.borrow_mut();
+        let before = resolvers.len();
+        resolvers.retain(|resolver| resolver != principal);
+        resolvers.len() != before
+    })
+}
+
+/// Get all resolvers in the order they were added
+pub fn get_resolvers() -> Vec<Principal> {
+    RESOLVERS.with(|resolvers| resolvers.borrow().clone())
+}
+
+/// Replace the resolver list, used when restoring state after an upgrade
+pub fn set_resolvers(principals: Vec<Principal>) {
+    RESOLVERS.with(|resolvers| *resolvers.borrow_mut() = principals);
+}
+
+/// Check if a principal holds the resolver role
+pub fn is_resolver(principal: &Principal) -> bool {
+    RESOLVERS.with(|resolvers| resolvers.borrow().contains(principal))
+}
+
+/// Set the limit-order canister whose Fusion fills this canister settles, or clear it
+pub fn set_limit_order_canister(canister_id: Option<Principal>) {
+    LIMIT_ORDER_CANISTER.with(|current| *current.borrow_mut() = canister_id);
+}
+
+/// Get the limit-order canister whose Fusion fills this canister settles
+pub fn get_limit_order_canister() -> Option<Principal> {
+    LIMIT_ORDER_CANISTER.with(|canister| *canister.borrow())
 }
 
 /// Canister upgrade support - export data for backup
 pub fn export_escrow_data() -> EscrowBackup {
     let htlc_escrows = get_all_htlc_escrows();
     let cross_chain_escrows = get_all_cross_chain_escrows();
+    let deployment_attempts = DEPLOYMENT_ATTEMPTS
+        .with(|attempts| attempts.borrow().values().flatten().cloned().collect());
+    let revealed_preimages = REVEALED_PREIMAGES.with(|preimages| {
+        preimages.borrow().iter().map(|(hash, preimage)| (hash.clone(), preimage.clone())).collect()
+    });
+
+    let archived_escrows =
+        ARCHIVED_ESCROWS.with(|archive| archive.borrow().values().cloned().collect());
 
-    EscrowBackup { htlc_escrows, cross_chain_escrows, exported_at: ic_cdk::api::time() }
+    EscrowBackup {
+        htlc_escrows,
+        cross_chain_escrows,
+        archived_escrows,
+        deployment_attempts,
+        revealed_preimages,
+        operators: get_operators(),
+        resolvers: get_resolvers(),
+        limit_order_canister: get_limit_order_canister(),
+        exported_at: ic_cdk::api::time(),
+    }
 }
 
 /// Canister upgrade support - import data from backup
 pub fn import_escrow_data(backup: EscrowBackup) -> Result<(), EscrowError> {
+    ic_cdk::println!("📦 Importing escrow backup exported at {}", backup.exported_at);
+
     // Clear existing data
     clear_escrow_data();
 
@@ -225,14 +582,119 @@ pub fn import_escrow_data(backup: EscrowBackup) -> Result<(), EscrowError> {
         }
     });
 
+    // Import archived summaries
+    ARCHIVED_ESCROWS.with(|archive| {
+        let mut archive = archive.borrow_mut();
+        for summary in backup.archived_escrows {
+            archive.insert(summary.order_hash.clone(), summary);
+        }
+    });
+
+    // Import deployment attempts (kept in order so the latest attempt stays last)
+    for attempt in backup.deployment_attempts {
+        record_deployment_attempt(attempt);
+    }
+
+    // Import revealed secrets
+    for (order_hash, preimage) in backup.revealed_preimages {
+        store_revealed_preimage(&order_hash, preimage);
+    }
+
+    // Import role assignments
+    set_operators(backup.operators);
+    set_resolvers(backup.resolvers);
+    set_limit_order_canister(backup.limit_order_canister);
+
     Ok(())
 }
 
+/// Lock an order hash for a mutating operation, reclaiming locks taken before `stale_before`
+pub fn try_lock_order(
+    order_hash: &str,
+    current_time: u64,
+    stale_before: u64,
+) -> Result<(), EscrowError> {
+    ORDER_LOCKS.with(|locks| {
+        let mut locks = locks.borrow_mut();
+        match locks.get(order_hash) {
+            Some(&locked_at) if locked_at >= stale_before => Err(EscrowError::OperationInProgress),
+            _ => {
+                locks.insert(order_hash.to_string(), current_time);
+                Ok(())
+            }
+        }
+    })
+}
+
+/// Release an order hash lock, unless it was reclaimed and taken again since `locked_at`
+pub fn unlock_order(order_hash: &str, locked_at: u64) {
+    ORDER_LOCKS.with(|locks| {
+        let mut locks = locks.borrow_mut();
+        if locks.get(order_hash) == Some(&locked_at) {
+            locks.remove(order_hash);
+        }
+    });
+}
+
+/// Locked order hashes and when each lock was taken
+pub fn get_locked_orders() -> Vec<(String, u64)> {
+    ORDER_LOCKS.with(|locks| {
+        locks
+            .borrow()
+            .iter()
+            .map(|(order_hash, locked_at)| (order_hash.clone(), *locked_at))
+            .collect()
+    })
+}
+
+/// Flag a cross-chain escrow as inconsistent, keeping the time it was first flagged
+pub fn flag_inconsistent_escrow(escrow: InconsistentEscrow) {
+    INCONSISTENT_ESCROWS.with(|flagged| {
+        let mut flagged = flagged.borrow_mut();
+        let flagged_at =
+            flagged.get(&escrow.order_id).map_or(escrow.flagged_at, |previous| previous.flagged_at);
+        flagged.insert(escrow.order_id.clone(), InconsistentEscrow { flagged_at, ..escrow });
+    });
+}
+
+/// Clear the inconsistency flag of a cross-chain escrow whose legs agree again
+pub fn clear_inconsistent_escrow(order_id: &str) {
+    INCONSISTENT_ESCROWS.with(|flagged| flagged.borrow_mut().remove(order_id));
+}
+
+/// Cross-chain escrows flagged as inconsistent, by order ID
+pub fn get_inconsistent_escrows() -> Vec<InconsistentEscrow> {
+    INCONSISTENT_ESCROWS.with(|flagged| flagged.borrow().values().cloned().collect())
+}
+
+/// Count a coordination state rewritten by reconciliation
+pub fn record_drift_correction() {
+    RECONCILIATION_STATS.with(|stats| stats.borrow_mut().drift_corrections += 1);
+}
+
+/// Record the end of a reconciliation pass over all cross-chain escrows
+pub fn record_reconciliation_run(current_time: u64) {
+    RECONCILIATION_STATS.with(|stats| stats.borrow_mut().last_run_at = Some(current_time));
+}
+
+/// Reconciliation counters, with the current number of flagged escrows
+pub fn get_reconciliation_stats() -> ReconciliationStats {
+    let inconsistent_escrows = INCONSISTENT_ESCROWS.with(|flagged| flagged.borrow().len()) as u64;
+    RECONCILIATION_STATS
+        .with(|stats| ReconciliationStats { inconsistent_escrows, ..stats.borrow().clone() })
+}
+
 /// Backup structure for canister upgrades
 #[derive(Clone, Debug)]
 pub struct EscrowBackup {
     pub htlc_escrows: Vec<HTLCEscrow>,
     pub cross_chain_escrows: Vec<CrossChainEscrow>,
+    pub archived_escrows: Vec<ArchivedEscrow>,
+    pub deployment_attempts: Vec<DeploymentAttempt>,
+    pub revealed_preimages: Vec<(String, Vec<u8>)>,
+    pub operators: Vec<Principal>,
+    pub resolvers: Vec<Principal>,
+    pub limit_order_canister: Option<Principal>,
     pub exported_at: u64,
 }
 
@@ -241,6 +703,20 @@ pub struct EscrowBackup {
 pub fn clear_escrow_data() {
     HTLC_ESCROWS.with(|escrows| escrows.borrow_mut().clear());
     CROSS_CHAIN_ESCROWS.with(|escrows| escrows.borrow_mut().clear());
+    VERIFICATION_REPORTS.with(|reports| reports.borrow_mut().clear());
+    DEPLOYMENT_ATTEMPTS.with(|attempts| attempts.borrow_mut().clear());
+    REVEALED_PREIMAGES.with(|preimages| preimages.borrow_mut().clear());
+    ARCHIVED_ESCROWS.with(|archive| archive.borrow_mut().clear());
+    set_archive_policy(ArchivePolicy::default());
+    OPERATORS.with(|operators| operators.borrow_mut().clear());
+    RESOLVERS.with(|resolvers| resolvers.borrow_mut().clear());
+    LIMIT_ORDER_CANISTER.with(|canister| *canister.borrow_mut() = None);
+    CREATE2_CONFIG.with(|config| *config.borrow_mut() = None);
+    RPC_PROVIDER_STRATEGIES.with(|strategies| strategies.borrow_mut().clear());
+    RPC_PROVIDER_STATS.with(|stats| stats.borrow_mut().clear());
+    ORDER_LOCKS.with(|locks| locks.borrow_mut().clear());
+    INCONSISTENT_ESCROWS.with(|flagged| flagged.borrow_mut().clear());
+    RECONCILIATION_STATS.with(|stats| *stats.borrow_mut() = ReconciliationStats::default());
 }
 
 /// Clear all escrow data (for production use during upgrades)
@@ -248,4 +724,8 @@ pub fn clear_escrow_data() {
 pub fn clear_escrow_data() {
     HTLC_ESCROWS.with(|escrows| escrows.borrow_mut().clear());
     CROSS_CHAIN_ESCROWS.with(|escrows| escrows.borrow_mut().clear());
+    VERIFICATION_REPORTS.with(|reports| reports.borrow_mut().clear());
+    DEPLOYMENT_ATTEMPTS.with(|attempts| attempts.borrow_mut().clear());
+    REVEALED_PREIMAGES.with(|preimages| preimages.borrow_mut().clear());
+    ARCHIVED_ESCROWS.with(|archive| archive.borrow_mut().clear());
 }
@@ -0,0 +1,139 @@
+use crate::locks;
+use crate::memory;
+use crate::types::{
+    CoordinationState, CrossChainEscrow, CrossChainEscrowEvent, EscrowError, EscrowStatus,
+    HTLCEscrow, InconsistentEscrow,
+};
+
+// ============================================================================
+// COORDINATION RECONCILIATION
+// ============================================================================
+
+/// Coordination state implied by the statuses of both legs of a pair
+///
+/// A secret reported from the EVM leg means that leg was withdrawn even while its stored status
+/// lags. Returns the reason when one leg settled to the counterparty and the other was unwound,
+/// a combination no coordination state describes.
+pub fn derive_coordination_state(
+    icp_status: &EscrowStatus,
+    evm_status: &EscrowStatus,
+    secret_revealed_on_evm: bool,
+) -> Result<CoordinationState, &'static str> {
+    let unwound =
+        |status: &EscrowStatus| matches!(status, EscrowStatus::Cancelled | EscrowStatus::Expired);
+    let icp_completed = *icp_status == EscrowStatus::Completed;
+    let evm_completed = *evm_status == EscrowStatus::Completed || secret_revealed_on_evm;
+
+    if icp_completed && evm_completed {
+        Ok(CoordinationState::Completed)
+    } else if (icp_completed && unwound(evm_status)) || (evm_completed && unwound(icp_status)) {
+        Err("one leg completed while the other was cancelled or expired")
+    } else if icp_completed || evm_completed {
+        Ok(CoordinationState::SecretRevealed)
+    } else if unwound(icp_status) || unwound(evm_status) {
+        Ok(CoordinationState::Expired)
+    } else if *icp_status == EscrowStatus::Active && *evm_status == EscrowStatus::Active {
+        Ok(CoordinationState::Active)
+    } else {
+        Ok(CoordinationState::EscrowsCreated)
+    }
+}
+
+/// Bring a pair's coordination state in line with its legs, returning the resulting state
+///
+/// Embedded leg copies are refreshed from the stored HTLC escrows (or their archived summaries)
+/// first. A mismatch is corrected with a DriftCorrected event; irreconcilable legs, or an EVM
+/// escrow whose latest verification failed, move the pair to Failed and flag it.
+pub fn reconcile_pair(order_id: &str, current_time: u64) -> Result<CoordinationState, EscrowError> {
+    let _lock = locks::OrderLock::acquire(order_id, current_time)?;
+    let mut pair = memory::get_cross_chain_escrow(order_id)?;
+    let mut changed = refresh_leg(&mut pair.icp_escrow) | refresh_leg(&mut pair.evm_escrow);
+
+    let evm_verification_failed = memory::get_verification_report(&pair.evm_escrow.order_hash)
+        .is_some_and(|report| !report.passed);
+    let derived = derive_coordination_state(
+        &pair.icp_escrow.status,
+        &pair.evm_escrow.status,
+        pair.revealed_secret.is_some(),
+    )
+    .and_then(|state| match state {
+        CoordinationState::Completed => Ok(state),
+        _ if evm_verification_failed => Err("EVM escrow does not match its expected parameters"),
+        _ => Ok(state),
+    });
+
+    let state = match derived {
+        Ok(state) => {
+            memory::clear_inconsistent_escrow(order_id);
+            state
+        }
+        Err(reason) => {
+            memory::flag_inconsistent_escrow(InconsistentEscrow {
+                order_id: order_id.to_string(),
+                icp_status: pair.icp_escrow.status.clone(),
+                evm_status: pair.evm_escrow.status.clone(),
+                reason: reason.to_string(),
+                flagged_at: current_time,
+            });
+            CoordinationState::Failed
+        }
+    };
+
+    if pair.coordination_state != state {
+        pair.events.push(CrossChainEscrowEvent::DriftCorrected {
+            from: pair.coordination_state.clone(),
+            to: state.clone(),
+        });
+        ic_cdk::println!(
+            "🩺 Coordination of {} corrected from {:?} to {:?}",
+            order_id,
+            pair.coordination_state,
+            state
+        );
+        pair.coordination_state = state.clone();
+        memory::record_drift_correction();
+        changed = true;
+    }
+
+    if changed {
+        pair.updated_at = current_time;
+        memory::update_cross_chain_escrow(order_id, pair)?;
+    }
+    Ok(state)
+}
+
+/// Reconcile every cross-chain escrow, skipping those locked by an operation in flight
+///
+/// Returns the number of pairs whose coordination state was corrected.
+pub fn reconcile_all(current_time: u64) -> u64 {
+    let pairs: Vec<(String, CoordinationState)> = memory::get_all_cross_chain_escrows()
+        .into_iter()
+        .map(|pair: CrossChainEscrow| (pair.order_id, pair.coordination_state))
+        .collect();
+
+    let mut corrected = 0;
+    for (order_id, before) in pairs {
+        match reconcile_pair(&order_id, current_time) {
+            Ok(after) if after != before => corrected += 1,
+            Ok(_) => {}
+            Err(e) => ic_cdk::println!("Coordination of {} not reconciled: {:?}", order_id, e),
+        }
+    }
+    memory::record_reconciliation_run(current_time);
+    corrected
+}
+
+/// Replace an embedded leg copy with the stored escrow, or its status once archived
+fn refresh_leg(leg: &mut HTLCEscrow) -> bool {
+    if let Ok(stored) = memory::get_htlc_escrow(&leg.order_hash) {
+        let changed = stored.status != leg.status || stored.updated_at != leg.updated_at;
+        *leg = stored;
+        changed
+    } else if let Some(archived) = memory::get_archived_escrow(&leg.order_hash) {
+        let changed = archived.status != leg.status;
+        leg.status = archived.status;
+        changed
+    } else {
+        false
+    }
+}
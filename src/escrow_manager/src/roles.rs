@@ -0,0 +1,45 @@
+use crate::memory;
+use crate::types::{EscrowError, Role};
+use candid::Principal;
+
+// ============================================================================
+// ROLE CHECKS
+// ============================================================================
+
+/// Reject callers that are not controllers of this canister
+pub fn require_controller() -> Result<(), EscrowError> {
+    require_role(Role::Controller)
+}
+
+/// Reject callers that are neither operators nor controllers
+pub fn require_operator() -> Result<(), EscrowError> {
+    require_role(Role::Operator)
+}
+
+/// Reject callers that hold neither the resolver role nor a role above it
+pub fn require_resolver() -> Result<(), EscrowError> {
+    require_role(Role::Resolver)
+}
+
+/// Check the calling principal against a required role
+fn require_role(required: Role) -> Result<(), EscrowError> {
+    let caller = ic_cdk::caller();
+    if has_role(&caller, ic_cdk::api::is_controller(&caller), required) {
+        Ok(())
+    } else {
+        Err(EscrowError::Unauthorized)
+    }
+}
+
+/// Whether a principal holds a role; controllers implicitly hold every role
+pub fn has_role(principal: &Principal, is_controller: bool, required: Role) -> bool {
+    match required {
+        Role::Controller => is_controller,
+        Role::Operator => is_controller || memory::is_operator(principal),
+        Role::Resolver => {
+            has_role(principal, is_controller, Role::Operator)
+                || memory::is_resolver(principal)
+                || memory::get_limit_order_canister() == Some(*principal)
+        }
+    }
+}
@@ -1,5 +1,5 @@
 /// Data types for escrow manager canister
-use candid::{CandidType, Deserialize};
+use candid::{CandidType, Deserialize, Principal};
 use serde::Serialize;
 
 /// Transaction Receipt structure for EVM transactions
@@ -10,6 +10,7 @@ pub struct TransactionReceipt {
     pub contract_address: Option<String>,
     pub logs: Vec<LogEntry>,
     pub gas_used: Option<candid::Nat>,
+    pub effective_gas_price: Option<candid::Nat>,
 }
 
 /// Log Entry structure for parsing transaction logs
@@ -27,6 +28,32 @@ pub enum RpcService {
     BaseMainnet,
 }
 
+/// Parameters shared by every part of a batch of ICP escrows
+#[derive(Clone, Debug, CandidType, Deserialize)]
+pub struct EscrowBatchParams {
+    pub order_hash: String,
+    pub maker: String,
+    pub taker: String,
+    pub token: String,
+    pub amount: u64,         // Sum of all part amounts
+    pub safety_deposit: u64, // Per part
+    pub timelock: u64,
+    pub src_chain_id: u64,
+    pub dst_chain_id: u64,
+    pub src_token: String,
+    pub dst_token: String,
+    pub src_amount: u64, // Split across parts pro rata
+    pub dst_amount: u64, // Split across parts pro rata
+}
+
+/// One part of a partially filled order, escrowed under its own hashlock
+#[derive(Clone, Debug, CandidType, Deserialize)]
+pub struct PartSpec {
+    pub amount: u64,
+    pub hashlock: String,
+    pub part_index: u32,
+}
+
 /// EVM Escrow Parameters for contract creation
 #[derive(Clone, Debug, CandidType, Deserialize)]
 pub struct EVMEscrowParams {
@@ -42,6 +69,92 @@ pub struct EVMEscrowParams {
     pub dst_amount: u64,
 }
 
+/// Lifecycle of a broadcast EVM escrow deployment transaction
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize, PartialEq)]
+pub enum DeploymentStatus {
+    Broadcast, // Recorded before broadcasting, outcome not yet known
+    Confirmed, // Receipt found, contract deployed
+    Dropped,   // Transaction provably not on chain, safe to replace
+}
+
+/// A single EVM escrow deployment attempt, keyed by order hash for idempotent retries
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize, PartialEq)]
+pub struct DeploymentAttempt {
+    pub order_hash: String,
+    pub raw_tx_hash: String,
+    pub nonce: u64,
+    pub status: DeploymentStatus,
+    pub contract_address: Option<String>,
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+/// Immutables decoded from a deployed EVM escrow via `getImmutables()`
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize, PartialEq)]
+pub struct EvmEscrowImmutables {
+    pub order_hash: String,
+    pub hashlock: String,
+    pub maker: String,
+    pub taker: String,
+    pub token: String,
+    pub amount: u64,
+    pub safety_deposit: u64,
+    pub timelocks: String,
+}
+
+/// Single field that differs between the stored escrow and the deployed contract
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize, PartialEq)]
+pub struct FieldMismatch {
+    pub field: String,
+    pub expected: String,
+    pub actual: String,
+}
+
+/// Factory used to deploy EVM escrows at CREATE2 addresses known before deployment
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize, PartialEq)]
+pub struct Create2Config {
+    pub factory: String,        // 0x-prefixed factory contract address
+    pub init_code_hash: String, // 0x-prefixed keccak256 of the escrow init code
+}
+
+/// EVM RPC providers of a chain, in failover order, and the agreement state reads need
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize, PartialEq)]
+pub struct RpcProviderStrategy {
+    pub provider_sets: Vec<Vec<String>>, // The next set is queried when every provider of a set fails
+    pub k_of_n: u32,                     // Identical responses required for receipts and eth_call
+}
+
+/// Outcomes of the calls made to one EVM RPC provider of a chain
+#[derive(Clone, Debug, Default, CandidType, Deserialize, Serialize, PartialEq)]
+pub struct RpcProviderStats {
+    pub chain_id: u64,
+    pub provider: String,
+    pub successes: u64,
+    pub errors: u64,
+    pub disagreements: u64, // Responses outvoted by the other providers of the set
+}
+
+/// Escrow deployment transaction built exactly as it would be broadcast, without signing it
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize, PartialEq)]
+pub struct PreparedTx {
+    pub raw_fields: String, // Transaction parameters as passed to eth_sendTransaction
+    pub tx_hash: String,
+    pub nonce: u64,
+    pub predicted_address: Option<String>, // None without a CREATE2 factory
+    pub estimated_gas: u64,
+    pub estimated_cycles: u64, // EVM RPC cycles of the deployment, assuming no retries
+}
+
+/// Result of comparing a deployed EVM escrow against the stored HTLC escrow
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize)]
+pub struct EscrowVerificationReport {
+    pub order_hash: String,
+    pub escrow_address: String,
+    pub passed: bool,
+    pub mismatches: Vec<FieldMismatch>,
+    pub verified_at: u64,
+}
+
 /// General error type for the escrow manager
 #[derive(Clone, Debug, CandidType, Deserialize)]
 pub enum Error {
@@ -72,6 +185,53 @@ pub enum Error {
     // Chain Fusion specific errors
     ChainFusionRequestFailed,
     InvalidReceipt,
+
+    // Errors carrying the failing RPC method or ECDSA stage
+    RpcFailed { method: String, detail: String },
+    EcdsaFailed { stage: String, detail: String },
+    ProviderConsensusFailure { method: String, detail: String },
+}
+
+impl Error {
+    /// Attach the RPC method to an error, keeping the innermost method if already attached
+    pub fn rpc_failed(method: &str, error: Error) -> Self {
+        match error {
+            Error::RpcFailed { .. } | Error::ProviderConsensusFailure { .. } => error,
+            other => Error::RpcFailed { method: method.to_string(), detail: other.to_string() },
+        }
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Error::RpcError(msg) => write!(f, "RPC error: {}", msg),
+            Error::Inconsistent(msg) => write!(f, "Inconsistent RPC responses: {}", msg),
+            Error::DecodeError(msg) => write!(f, "Decode error: {}", msg),
+            Error::Rejected(msg) => write!(f, "Request rejected: {}", msg),
+            Error::NotFound(msg) => write!(f, "Not found: {}", msg),
+            Error::InvalidData(msg) => write!(f, "Invalid data: {}", msg),
+            Error::ThresholdECDSAUnavailable => write!(f, "Threshold ECDSA is unavailable"),
+            Error::ThresholdECDSASigningFailed => write!(f, "Threshold ECDSA signing failed"),
+            Error::ThresholdECDSAKeyNotFound => write!(f, "Threshold ECDSA key not found"),
+            Error::InvalidEscrowParameters => write!(f, "Invalid escrow parameters"),
+            Error::EscrowCreationFailed => write!(f, "Escrow creation failed"),
+            Error::EscrowVerificationFailed => write!(f, "Escrow verification failed"),
+            Error::NetworkError => write!(f, "Network error"),
+            Error::SystemError => write!(f, "System error"),
+            Error::EncodeError => write!(f, "Encode error"),
+            Error::General(msg) => write!(f, "{}", msg),
+            Error::ChainFusionRequestFailed => write!(f, "Chain Fusion request failed"),
+            Error::InvalidReceipt => write!(f, "Invalid receipt"),
+            Error::RpcFailed { method, detail } => write!(f, "{} failed: {}", method, detail),
+            Error::EcdsaFailed { stage, detail } => {
+                write!(f, "Threshold ECDSA {} failed: {}", stage, detail)
+            }
+            Error::ProviderConsensusFailure { method, detail } => {
+                write!(f, "{} providers disagree: {}", method, detail)
+            }
+        }
+    }
 }
 
 /// Token types supported by the escrow manager
@@ -92,6 +252,32 @@ pub enum EscrowStatus {
     Expired,
 }
 
+impl EscrowStatus {
+    /// Whether the lifecycle allows moving from this status to `next`
+    ///
+    /// Created → Funded → Active → Completed is the happy path. Unfunded or funded escrows can
+    /// be cancelled, escrows past their timelock expire, and expired escrows are refunded.
+    pub fn can_transition_to(&self, next: &EscrowStatus) -> bool {
+        use EscrowStatus::*;
+        matches!(
+            (self, next),
+            (Created, Funded)
+                | (Created, Cancelled)
+                | (Funded, Active)
+                | (Funded, Cancelled)
+                | (Funded, Expired)
+                | (Active, Completed)
+                | (Active, Expired)
+                | (Expired, Cancelled)
+        )
+    }
+
+    /// Whether the escrow has settled and may be archived once past retention
+    pub fn is_terminal(&self) -> bool {
+        matches!(self, EscrowStatus::Completed | EscrowStatus::Cancelled | EscrowStatus::Expired)
+    }
+}
+
 /// HTLC coordination state for cross-chain escrow management
 #[derive(Clone, Debug, CandidType, Deserialize, Serialize, PartialEq)]
 pub enum CoordinationState {
@@ -145,6 +331,9 @@ pub enum CrossChainEscrowEvent {
     EscrowCancelled { escrow_id: String, chain: String },
     NetworkPartitionDetected { chain: String, lag: u64 },
     HealthCheckFailed { chain: String, error: String },
+    StatusForced { from: EscrowStatus, to: EscrowStatus, controller: String, reason: String },
+    SecretPropagated { escrow_id: String, from_chain: String, to_chain: String },
+    DriftCorrected { from: CoordinationState, to: CoordinationState },
 }
 
 /// Enhanced HTLC escrow structure with cross-chain compatibility
@@ -181,10 +370,40 @@ pub struct HTLCEscrow {
     pub chain_health_status: Option<ChainHealthStatus>,
     pub partial_fill_info: Option<PartialFillInfo>,
     pub events: Vec<CrossChainEscrowEvent>,
+    pub costs: Option<CostBreakdown>, // None until a Chain Fusion call is made for the order
     pub created_at: u64,
     pub updated_at: u64,
 }
 
+/// Costs incurred settling an escrow through Chain Fusion
+#[derive(Clone, Debug, Default, CandidType, Deserialize, Serialize, PartialEq)]
+pub struct CostBreakdown {
+    pub evm_rpc_cycles: u64,
+    pub ecdsa_signatures: u64,
+    pub estimated_evm_gas_wei: u64, // Receipt gas_used times effective gas price
+    pub retries: u64,
+}
+
+impl CostBreakdown {
+    /// Add another breakdown to this one, saturating each counter
+    pub fn add(&mut self, other: &CostBreakdown) {
+        self.evm_rpc_cycles = self.evm_rpc_cycles.saturating_add(other.evm_rpc_cycles);
+        self.ecdsa_signatures = self.ecdsa_signatures.saturating_add(other.ecdsa_signatures);
+        self.estimated_evm_gas_wei =
+            self.estimated_evm_gas_wei.saturating_add(other.estimated_evm_gas_wei);
+        self.retries = self.retries.saturating_add(other.retries);
+    }
+}
+
+/// Aggregate costs of the escrows created in a time window
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize, PartialEq)]
+pub struct CostSummary {
+    pub from_ns: u64,
+    pub to_ns: u64,
+    pub escrow_count: u64, // Escrows in the window with any recorded cost
+    pub total: CostBreakdown,
+}
+
 /// Timelock configuration for conservative coordination
 #[derive(Clone, Debug, CandidType, Deserialize, Serialize)]
 pub struct TimelockConfig {
@@ -258,10 +477,134 @@ pub struct CrossChainEscrow {
     pub icp_finality_lag: u64,
     pub evm_finality_lag: u64,
     pub failed_transactions: u32,
+    pub revealed_secret: Option<Vec<u8>>, // Verified preimage once revealed on either leg
     pub created_at: u64,
     pub updated_at: u64,
 }
 
+/// Cross-chain escrow whose legs cannot both settle, flagged Failed for manual handling
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize, PartialEq)]
+pub struct InconsistentEscrow {
+    pub order_id: String,
+    pub icp_status: EscrowStatus,
+    pub evm_status: EscrowStatus,
+    pub reason: String,
+    pub flagged_at: u64,
+}
+
+/// Outcome counters of coordination reconciliation
+#[derive(Clone, Debug, Default, CandidType, Deserialize, Serialize, PartialEq)]
+pub struct ReconciliationStats {
+    pub drift_corrections: u64, // Coordination states rewritten since install or upgrade
+    pub inconsistent_escrows: u64, // Escrows currently flagged
+    pub last_run_at: Option<u64>, // Last timer or manual pass over all escrows
+}
+
+/// Lightweight HTLC escrow view for dashboard listings
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize, PartialEq)]
+pub struct EscrowSummary {
+    pub order_hash: String,
+    pub status: EscrowStatus,
+    pub amount: u64,
+    pub timelock: u64,
+}
+
+impl From<&HTLCEscrow> for EscrowSummary {
+    fn from(escrow: &HTLCEscrow) -> Self {
+        Self {
+            order_hash: escrow.order_hash.clone(),
+            status: escrow.status.clone(),
+            amount: escrow.amount,
+            timelock: escrow.timelock,
+        }
+    }
+}
+
+/// Lightweight cross-chain escrow view for dashboard listings
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize, PartialEq)]
+pub struct CrossChainEscrowSummary {
+    pub order_id: String,
+    pub coordination_state: CoordinationState,
+    pub icp_escrow: EscrowSummary,
+    pub evm_escrow: EscrowSummary,
+}
+
+impl From<&CrossChainEscrow> for CrossChainEscrowSummary {
+    fn from(escrow: &CrossChainEscrow) -> Self {
+        Self {
+            order_id: escrow.order_id.clone(),
+            coordination_state: escrow.coordination_state.clone(),
+            icp_escrow: EscrowSummary::from(&escrow.icp_escrow),
+            evm_escrow: EscrowSummary::from(&escrow.evm_escrow),
+        }
+    }
+}
+
+/// Number of trailing events kept for an archived escrow
+pub const ARCHIVED_EVENT_LIMIT: usize = 5;
+
+/// Compact record kept for a terminal HTLC escrow after its full record is removed
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize)]
+pub struct ArchivedEscrow {
+    pub order_hash: String,
+    pub status: EscrowStatus,
+    pub amount: u64,
+    pub completed_at: u64, // Last update of the escrow, when it reached its final status
+    pub final_event_count: u64, // Events recorded before trimming
+    pub events: Vec<CrossChainEscrowEvent>, // Last ARCHIVED_EVENT_LIMIT events
+}
+
+impl From<&HTLCEscrow> for ArchivedEscrow {
+    fn from(escrow: &HTLCEscrow) -> Self {
+        let kept_from = escrow.events.len().saturating_sub(ARCHIVED_EVENT_LIMIT);
+        Self {
+            order_hash: escrow.order_hash.clone(),
+            status: escrow.status.clone(),
+            amount: escrow.amount,
+            completed_at: escrow.updated_at,
+            final_event_count: escrow.events.len() as u64,
+            events: escrow.events[kept_from..].to_vec(),
+        }
+    }
+}
+
+/// Retention rules for archiving terminal HTLC escrows
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize, PartialEq)]
+pub struct ArchivePolicy {
+    pub ttl_ns: u64,       // How long a terminal escrow keeps its full record
+    pub max_archived: u64, // Oldest summaries are evicted beyond this count
+}
+
+impl Default for ArchivePolicy {
+    fn default() -> Self {
+        Self { ttl_ns: 30 * 24 * 3600 * 1_000_000_000, max_archived: 10_000 }
+    }
+}
+
+/// Privilege tier required by administrative endpoints
+#[derive(Clone, Copy, Debug, CandidType, Deserialize, Serialize, PartialEq)]
+pub enum Role {
+    Controller, // Configuration changes and status overrides
+    Operator,   // Day-to-day actions such as manual sweeps
+    Resolver,   // Escrow creation, also held by operators and the limit-order canister
+}
+
+/// Role assignments managed by the canister; controllers are set in canister settings
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize, PartialEq)]
+pub struct RoleAssignments {
+    pub operators: Vec<Principal>,
+    pub resolvers: Vec<Principal>,
+    pub limit_order_canister: Option<Principal>, // Creates the escrows of Fusion fills
+}
+
+/// HTLC escrow status lookup - the full record while retained, its summary once archived
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize)]
+pub struct HTLCEscrowStatus {
+    pub archived: bool,
+    pub escrow: Option<HTLCEscrow>,
+    pub summary: Option<ArchivedEscrow>,
+}
+
 /// Enhanced escrow-specific error types with Chain Fusion and ECDSA support
 #[derive(Clone, Debug, CandidType, Deserialize, Serialize)]
 pub enum EscrowError {
@@ -311,6 +654,14 @@ pub enum EscrowError {
     // Partial fill errors
     InvalidPartialFill,
     PartialFillValidationFailed,
+
+    // Detailed Chain Fusion and threshold ECDSA errors
+    ChainFusion { method: String, detail: String },
+    Ecdsa { stage: String, detail: String },
+    ProviderConsensusFailure { method: String, detail: String },
+
+    // Concurrency errors
+    OperationInProgress,
 }
 
 impl EscrowError {
@@ -372,6 +723,48 @@ impl EscrowError {
             EscrowError::PartialFillValidationFailed => {
                 "Partial fill validation failed".to_string()
             }
+
+            // Detailed Chain Fusion and threshold ECDSA error messages
+            EscrowError::ChainFusion { method, detail } => {
+                format!("Chain Fusion {} failed: {}", method, detail)
+            }
+            EscrowError::Ecdsa { stage, detail } => {
+                format!("Threshold ECDSA {} failed: {}", stage, detail)
+            }
+            EscrowError::ProviderConsensusFailure { method, detail } => {
+                format!("EVM RPC providers disagree on {}: {}", method, detail)
+            }
+
+            // Concurrency error messages
+            EscrowError::OperationInProgress => {
+                "Another operation on this order is in progress".to_string()
+            }
+        }
+    }
+}
+
+/// Convert Chain Fusion errors at the canister boundary without losing their context
+impl From<Error> for EscrowError {
+    fn from(error: Error) -> Self {
+        let detail = error.to_string();
+        match error {
+            Error::RpcFailed { method, detail } => EscrowError::ChainFusion { method, detail },
+            Error::EcdsaFailed { stage, detail } => EscrowError::Ecdsa { stage, detail },
+            Error::ProviderConsensusFailure { method, detail } => {
+                EscrowError::ProviderConsensusFailure { method, detail }
+            }
+            Error::ThresholdECDSAUnavailable => {
+                EscrowError::Ecdsa { stage: "availability".to_string(), detail }
+            }
+            Error::ThresholdECDSASigningFailed => {
+                EscrowError::Ecdsa { stage: "signing".to_string(), detail }
+            }
+            Error::ThresholdECDSAKeyNotFound => {
+                EscrowError::Ecdsa { stage: "key_lookup".to_string(), detail }
+            }
+            Error::InvalidReceipt => EscrowError::InvalidReceipt,
+            Error::EscrowCreationFailed => EscrowError::EVMEscrowCreationFailed,
+            _ => EscrowError::ChainFusion { method: "unspecified".to_string(), detail },
         }
     }
 }
@@ -0,0 +1,240 @@
+//! Ethereum-compatible hashing shared by the canisters
+//!
+//! One implementation of keccak256, EIP-55 checksums and EIP-712 digests, so order hashing,
+//! address handling and signature verification agree across canisters.
+
+use std::fmt;
+use tiny_keccak::{Hasher, Keccak};
+
+/// EIP-712 domain type, hashed into every domain separator
+const EIP712_DOMAIN_TYPE: &[u8] =
+    b"EIP712Domain(string name,string version,uint256 chainId,address verifyingContract)";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CryptoError {
+    InvalidAddress(String),
+}
+
+impl fmt::Display for CryptoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CryptoError::InvalidAddress(address) => write!(f, "Invalid EVM address: {}", address),
+        }
+    }
+}
+
+impl std::error::Error for CryptoError {}
+
+// ============================================================================
+// HASHING
+// ============================================================================
+
+/// Keccak-256 as used by the EVM (not NIST SHA3-256)
+pub fn keccak256(data: &[u8]) -> [u8; 32] {
+    let mut hasher = Keccak::v256();
+    hasher.update(data);
+    let mut output = [0u8; 32];
+    hasher.finalize(&mut output);
+    output
+}
+
+// ============================================================================
+// ADDRESSES
+// ============================================================================
+
+/// Parse a 0x-prefixed hex address of any letter case
+pub fn parse_address(address: &str) -> Result<[u8; 20], CryptoError> {
+    let invalid = || CryptoError::InvalidAddress(address.to_string());
+    let hex_part = address.strip_prefix("0x").ok_or_else(invalid)?;
+    if hex_part.len() != 40 {
+        return Err(invalid());
+    }
+
+    let mut bytes = [0u8; 20];
+    hex::decode_to_slice(hex_part, &mut bytes).map_err(|_| invalid())?;
+    Ok(bytes)
+}
+
+/// EIP-55 mixed-case checksum encoding of an address
+pub fn eip55_checksum(address: &str) -> Result<String, CryptoError> {
+    let lower = hex::encode(parse_address(address)?);
+    let hash = keccak256(lower.as_bytes());
+
+    let checksummed: String = lower
+        .chars()
+        .enumerate()
+        .map(|(i, c)| {
+            let nibble = (hash[i / 2] >> if i % 2 == 0 { 4 } else { 0 }) & 0x0f;
+            if nibble >= 8 {
+                c.to_ascii_uppercase()
+            } else {
+                c
+            }
+        })
+        .collect();
+
+    Ok(format!("0x{}", checksummed))
+}
+
+/// Check an address is either single-case or carries a valid EIP-55 checksum
+pub fn is_valid_eip55(address: &str) -> bool {
+    let Ok(checksummed) = eip55_checksum(address) else {
+        return false;
+    };
+    let hex_part = &address[2..];
+    let single_case =
+        hex_part == hex_part.to_ascii_lowercase() || hex_part == hex_part.to_ascii_uppercase();
+
+    single_case || checksummed == address
+}
+
+// ============================================================================
+// EIP-712 TYPED DATA
+// ============================================================================
+
+/// EIP-712 signing domain
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Eip712Domain {
+    pub name: String,
+    pub version: String,
+    pub chain_id: u64,
+    pub verifying_contract: [u8; 20],
+}
+
+impl Eip712Domain {
+    /// Domain separator binding signatures to this contract and chain
+    pub fn separator(&self) -> [u8; 32] {
+        let mut encoded = Vec::with_capacity(5 * 32);
+        encoded.extend_from_slice(&keccak256(EIP712_DOMAIN_TYPE));
+        encoded.extend_from_slice(&keccak256(self.name.as_bytes()));
+        encoded.extend_from_slice(&keccak256(self.version.as_bytes()));
+        encoded.extend_from_slice(&abi_word_u64(self.chain_id));
+        encoded.extend_from_slice(&abi_word_address(&self.verifying_contract));
+        keccak256(&encoded)
+    }
+}
+
+/// Digest signed for a typed struct: keccak256(0x1901 ‖ domainSeparator ‖ hashStruct)
+///
+/// `encoded_struct` is the struct's encodeData, i.e. its members as 32-byte ABI words.
+pub fn eip712_digest(
+    domain: &Eip712Domain,
+    typehash: &[u8; 32],
+    encoded_struct: &[u8],
+) -> [u8; 32] {
+    let mut struct_data = Vec::with_capacity(32 + encoded_struct.len());
+    struct_data.extend_from_slice(typehash);
+    struct_data.extend_from_slice(encoded_struct);
+
+    let mut message = Vec::with_capacity(2 + 64);
+    message.extend_from_slice(&[0x19, 0x01]);
+    message.extend_from_slice(&domain.separator());
+    message.extend_from_slice(&keccak256(&struct_data));
+    keccak256(&message)
+}
+
+/// Left-pad an unsigned integer to a 32-byte ABI word
+pub fn abi_word_u64(value: u64) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    word[24..].copy_from_slice(&value.to_be_bytes());
+    word
+}
+
+/// Left-pad an address to a 32-byte ABI word
+pub fn abi_word_address(address: &[u8; 20]) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    word[12..].copy_from_slice(address);
+    word
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex32(value: &str) -> [u8; 32] {
+        let mut bytes = [0u8; 32];
+        hex::decode_to_slice(value, &mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn test_keccak256_vectors() {
+        assert_eq!(
+            keccak256(b""),
+            hex32("c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470")
+        );
+        assert_eq!(
+            keccak256(b"abc"),
+            hex32("4e03657aea45a94fc7d47ba826c8d667c0d1e6e33a64a036ec44f58fa12d6c45")
+        );
+        // Function selector of transfer(address,uint256)
+        assert_eq!(keccak256(b"transfer(address,uint256)")[..4], [0xa9, 0x05, 0x9c, 0xbb]);
+    }
+
+    #[test]
+    fn test_eip55_vectors() {
+        // Vectors from EIP-55
+        let vectors = [
+            "0x52908400098527886E0F7030069857D2E4169EE7",
+            "0x8617E340B3D01FA5F11F306F4090FD50E238070D",
+            "0xde709f2102306220921060314715629080e2fb77",
+            "0x27b1fdb04752bbc536007a920d24acb045561c26",
+            "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed",
+            "0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359",
+            "0xdbF03B407c01E7cD3CBea99509d93f8DDDC8C6FB",
+            "0xD1220A0cf47c7B9Be7A2E6BA89F429762e7b9aDb",
+        ];
+        for expected in vectors {
+            let lower = expected.to_ascii_lowercase();
+            let is_mixed_case =
+                lower != expected && expected[2..].to_ascii_uppercase() != expected[2..];
+            if is_mixed_case {
+                assert_eq!(eip55_checksum(&lower).unwrap(), expected);
+            }
+            assert!(is_valid_eip55(expected), "rejected {}", expected);
+        }
+
+        // A single flipped letter breaks the checksum
+        assert!(!is_valid_eip55("0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAeD"));
+        assert!(eip55_checksum("0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeA").is_err());
+        assert!(eip55_checksum("5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed").is_err());
+        assert!(eip55_checksum("0xzzAeb6053F3E94C9b9A09f33669435E7Ef1BeAed").is_err());
+    }
+
+    #[test]
+    fn test_eip712_mail_example() {
+        // Example from EIP-712
+        let domain = Eip712Domain {
+            name: "Ether Mail".to_string(),
+            version: "1".to_string(),
+            chain_id: 1,
+            verifying_contract: parse_address("0xCcCCccccCCCCcCCCCCCcCcCccCcCCCcCcccccccC")
+                .unwrap(),
+        };
+        assert_eq!(
+            domain.separator(),
+            hex32("f2cee375fa42b42143804025fc449deafd50cc031ca257e0b194a650a912090f")
+        );
+
+        let person_typehash = keccak256(b"Person(string name,address wallet)");
+        let hash_person = |name: &str, wallet: &str| {
+            let mut encoded = person_typehash.to_vec();
+            encoded.extend_from_slice(&keccak256(name.as_bytes()));
+            encoded.extend_from_slice(&abi_word_address(&parse_address(wallet).unwrap()));
+            keccak256(&encoded)
+        };
+
+        let mail_typehash = keccak256(
+            b"Mail(Person from,Person to,string contents)Person(string name,address wallet)",
+        );
+        let mut mail = Vec::new();
+        mail.extend_from_slice(&hash_person("Cow", "0xCD2a3d9F938E13CD947Ec05AbC7FE734Df8DD826"));
+        mail.extend_from_slice(&hash_person("Bob", "0xbBbBBBBbbBBBbbbBbbBbbbbBBbBbbbbBbBbbBBbB"));
+        mail.extend_from_slice(&keccak256(b"Hello, Bob!"));
+
+        assert_eq!(
+            eip712_digest(&domain, &mail_typehash, &mail),
+            hex32("be609aee343fb3c4b28e1df9e632fca64fcfaede20f02e86244efddf30957bd2")
+        );
+    }
+}
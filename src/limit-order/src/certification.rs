@@ -0,0 +1,399 @@
+use crate::memory::{with_cancelled_orders_read, with_filled_orders_read, with_orders_read};
+use crate::types::{Order, OrderId};
+use sha2::{Digest, Sha256};
+
+// ============================================================================
+// CERTIFIED ACTIVE ORDERS - Trust-minimized order book snapshots
+// ============================================================================
+//
+// The certified data is the root of an IC hash tree over pages of active orders:
+//
+//   labeled("active_orders", fork(... labeled(page_index_be32, leaf(page_hash)) ...))
+//
+// A page hash is sha256 over the entry hashes of its orders in id order, and an entry hash is
+// sha256(order_id_be64 ‖ order_hash ‖ "active"). Only one leaf per page keeps the tree small
+// while a witness for any page stays logarithmic in the number of pages.
+//
+// Active here means stored and neither filled nor cancelled. Expiry is not a state change,
+// so expired orders stay certified until cancelled; the expiration is bound by the order hash
+// and frontends compare it against the certificate time.
+
+/// Number of orders hashed into one leaf of the certified tree
+pub const CERTIFIED_PAGE_SIZE: usize = 100;
+
+/// Label of the active order subtree in the certified tree
+pub const ACTIVE_ORDERS_LABEL: &[u8] = b"active_orders";
+
+/// State recorded for every certified order
+const ACTIVE_STATE: &[u8] = b"active";
+
+/// CBOR self-describe tag prefixed to serialized hash trees
+const CBOR_SELF_DESCRIBE_TAG: [u8; 3] = [0xd9, 0xd9, 0xf7];
+
+/// IC hash tree, as verified by agents against a certificate's certified data
+#[derive(Clone, Debug, PartialEq)]
+pub enum HashTree {
+    Empty,
+    Fork(Box<HashTree>, Box<HashTree>),
+    Labeled(Vec<u8>, Box<HashTree>),
+    Leaf(Vec<u8>),
+    Pruned([u8; 32]),
+}
+
+impl HashTree {
+    /// Root hash of the tree, with the domain separators of the IC interface specification
+    pub fn reconstruct(&self) -> [u8; 32] {
+        match self {
+            HashTree::Empty => domain_hash("ic-hashtree-empty", &[]),
+            HashTree::Fork(left, right) => {
+                domain_hash("ic-hashtree-fork", &[&left.reconstruct(), &right.reconstruct()])
+            }
+            HashTree::Labeled(label, subtree) => {
+                domain_hash("ic-hashtree-labeled", &[label, &subtree.reconstruct()])
+            }
+            HashTree::Leaf(value) => domain_hash("ic-hashtree-leaf", &[value]),
+            HashTree::Pruned(hash) => *hash,
+        }
+    }
+
+    /// Serialize to the CBOR encoding agents expect for witnesses
+    pub fn to_cbor(&self) -> Vec<u8> {
+        let mut out = CBOR_SELF_DESCRIBE_TAG.to_vec();
+        self.write_cbor(&mut out);
+        out
+    }
+
+    fn write_cbor(&self, out: &mut Vec<u8>) {
+        match self {
+            HashTree::Empty => {
+                write_cbor_header(out, 4, 1);
+                write_cbor_header(out, 0, 0);
+            }
+            HashTree::Fork(left, right) => {
+                write_cbor_header(out, 4, 3);
+                write_cbor_header(out, 0, 1);
+                left.write_cbor(out);
+                right.write_cbor(out);
+            }
+            HashTree::Labeled(label, subtree) => {
+                write_cbor_header(out, 4, 3);
+                write_cbor_header(out, 0, 2);
+                write_cbor_bytes(out, label);
+                subtree.write_cbor(out);
+            }
+            HashTree::Leaf(value) => {
+                write_cbor_header(out, 4, 2);
+                write_cbor_header(out, 0, 3);
+                write_cbor_bytes(out, value);
+            }
+            HashTree::Pruned(hash) => {
+                write_cbor_header(out, 4, 2);
+                write_cbor_header(out, 0, 4);
+                write_cbor_bytes(out, hash);
+            }
+        }
+    }
+
+    /// Whether the tree contains a labeled node with the given label
+    fn contains_label(&self, target: &[u8]) -> bool {
+        match self {
+            HashTree::Fork(left, right) => {
+                left.contains_label(target) || right.contains_label(target)
+            }
+            HashTree::Labeled(label, _) => label == target,
+            _ => false,
+        }
+    }
+}
+
+/// Hash of a tree node: sha256(len(sep) ‖ sep ‖ parts...)
+fn domain_hash(separator: &str, parts: &[&[u8]]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update([separator.len() as u8]);
+    hasher.update(separator.as_bytes());
+    for part in parts {
+        hasher.update(part);
+    }
+    hasher.finalize().into()
+}
+
+fn write_cbor_header(out: &mut Vec<u8>, major: u8, value: u64) {
+    let major = major << 5;
+    match value {
+        0..=23 => out.push(major | value as u8),
+        24..=0xff => out.extend_from_slice(&[major | 24, value as u8]),
+        0x100..=0xffff => {
+            out.push(major | 25);
+            out.extend_from_slice(&(value as u16).to_be_bytes());
+        }
+        0x1_0000..=0xffff_ffff => {
+            out.push(major | 26);
+            out.extend_from_slice(&(value as u32).to_be_bytes());
+        }
+        _ => {
+            out.push(major | 27);
+            out.extend_from_slice(&value.to_be_bytes());
+        }
+    }
+}
+
+fn write_cbor_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    write_cbor_header(out, 2, bytes.len() as u64);
+    out.extend_from_slice(bytes);
+}
+
+/// Hash of one certified order entry
+pub fn order_entry_hash(order_id: OrderId, order_hash: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(order_id.to_be_bytes());
+    hasher.update(order_hash);
+    hasher.update(ACTIVE_STATE);
+    hasher.finalize().into()
+}
+
+/// Hash of a page of orders, in the order given
+pub fn page_hash(orders: &[Order]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    for order in orders {
+        hasher.update(order_entry_hash(order.id, &crate::limit_orders::compute_order_hash(order)));
+    }
+    hasher.finalize().into()
+}
+
+/// Label of a page in the certified tree; big-endian so labels sort like page indices
+pub fn page_label(page: usize) -> Vec<u8> {
+    (page as u32).to_be_bytes().to_vec()
+}
+
+/// Active orders sorted by id, the order pages are cut in
+pub fn get_certified_orders() -> Vec<Order> {
+    let mut orders: Vec<Order> = with_orders_read(|orders| {
+        orders
+            .values()
+            .filter(|order| {
+                !with_filled_orders_read(|filled| filled.contains(&order.id))
+                    && !with_cancelled_orders_read(|cancelled| cancelled.contains(&order.id))
+            })
+            .cloned()
+            .collect()
+    });
+    orders.sort_by_key(|order| order.id);
+    orders
+}
+
+/// Build the full certified tree over the given active orders
+pub fn build_tree(orders: &[Order]) -> HashTree {
+    let pages: Vec<HashTree> = orders
+        .chunks(CERTIFIED_PAGE_SIZE)
+        .enumerate()
+        .map(|(page, chunk)| {
+            HashTree::Labeled(page_label(page), Box::new(HashTree::Leaf(page_hash(chunk).to_vec())))
+        })
+        .collect();
+    HashTree::Labeled(ACTIVE_ORDERS_LABEL.to_vec(), Box::new(balanced_fork(pages)))
+}
+
+/// Combine labeled pages into a balanced tree, keeping them in label order
+fn balanced_fork(mut nodes: Vec<HashTree>) -> HashTree {
+    match nodes.len() {
+        0 => HashTree::Empty,
+        1 => nodes.pop().unwrap_or(HashTree::Empty),
+        len => {
+            let right = nodes.split_off(len / 2);
+            HashTree::Fork(Box::new(balanced_fork(nodes)), Box::new(balanced_fork(right)))
+        }
+    }
+}
+
+/// Reduce a tree to a witness revealing only the given page, pruning every other subtree
+pub fn witness(tree: &HashTree, label: &[u8]) -> HashTree {
+    match tree {
+        HashTree::Labeled(root, subtree) if root.as_slice() == ACTIVE_ORDERS_LABEL => {
+            HashTree::Labeled(root.clone(), Box::new(witness(subtree, label)))
+        }
+        HashTree::Fork(left, right) => {
+            let prune_or_descend = |node: &HashTree| {
+                if node.contains_label(label) {
+                    witness(node, label)
+                } else {
+                    HashTree::Pruned(node.reconstruct())
+                }
+            };
+            HashTree::Fork(Box::new(prune_or_descend(left)), Box::new(prune_or_descend(right)))
+        }
+        HashTree::Labeled(page, _) if page.as_slice() == label => tree.clone(),
+        HashTree::Empty => HashTree::Empty,
+        other => HashTree::Pruned(other.reconstruct()),
+    }
+}
+
+/// Recompute the certified tree and publish its root as the canister's certified data
+///
+/// Called after every change to the set of active orders.
+pub fn certify_active_orders() {
+    let root = build_tree(&get_certified_orders()).reconstruct();
+    #[cfg(not(test))]
+    ic_cdk::api::set_certified_data(&root);
+    #[cfg(test)]
+    CERTIFIED_ROOT.with(|certified| *certified.borrow_mut() = root);
+}
+
+#[cfg(test)]
+thread_local! {
+    static CERTIFIED_ROOT: std::cell::RefCell<[u8; 32]> = const { std::cell::RefCell::new([0; 32]) };
+}
+
+/// Root last passed to set_certified_data, as seen by tests
+#[cfg(test)]
+pub fn certified_root() -> [u8; 32] {
+    CERTIFIED_ROOT.with(|certified| *certified.borrow())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::limit_orders::tests::setup_test;
+    use crate::test_utils::OrderTestFixtures;
+    use crate::memory::{mark_order_cancelled, with_orders};
+
+    /// Verify a page the way a frontend would: the witness must hash to the certified root and
+    /// reveal the recomputed hash of the received orders under the page's label
+    fn verify_page(witness: &HashTree, root: [u8; 32], page: usize, orders: &[Order]) -> bool {
+        fn find_leaf<'a>(tree: &'a HashTree, label: &[u8]) -> Option<&'a [u8]> {
+            match tree {
+                HashTree::Fork(left, right) => {
+                    find_leaf(left, label).or_else(|| find_leaf(right, label))
+                }
+                HashTree::Labeled(found, subtree) if found.as_slice() == label => {
+                    match &**subtree {
+                        HashTree::Leaf(value) => Some(value),
+                        _ => None,
+                    }
+                }
+                _ => None,
+            }
+        }
+
+        let HashTree::Labeled(root_label, pages) = witness else {
+            return false;
+        };
+        witness.reconstruct() == root
+            && root_label.as_slice() == ACTIVE_ORDERS_LABEL
+            && find_leaf(pages, &page_label(page)) == Some(&page_hash(orders)[..])
+    }
+
+    fn store_orders(count: u64) {
+        with_orders(|orders| {
+            for id in 1..=count {
+                let mut order = OrderTestFixtures::create_basic_order();
+                order.id = id;
+                order.salt = id;
+                orders.insert(id, order);
+            }
+        });
+        certify_active_orders();
+    }
+
+    fn page_orders(page: usize) -> Vec<Order> {
+        get_certified_orders().chunks(CERTIFIED_PAGE_SIZE).nth(page).unwrap_or_default().to_vec()
+    }
+
+    #[test]
+    fn test_witness_verifies_included_orders() {
+        setup_test();
+        store_orders(250);
+        let tree = build_tree(&get_certified_orders());
+        assert_eq!(tree.reconstruct(), certified_root());
+
+        for page in 0..3 {
+            let witness = witness(&tree, &page_label(page));
+            assert!(verify_page(&witness, certified_root(), page, &page_orders(page)));
+        }
+        assert_eq!(page_orders(2).len(), 50);
+    }
+
+    #[test]
+    fn test_witness_rejects_tampered_orders() {
+        setup_test();
+        store_orders(150);
+        let tree = build_tree(&get_certified_orders());
+        let witness = witness(&tree, &page_label(1));
+
+        let mut tampered = page_orders(1);
+        tampered[0].taking_amount += 1;
+        assert!(!verify_page(&witness, certified_root(), 1, &tampered));
+
+        // Dropping an order or presenting the page under another index also fails
+        assert!(!verify_page(&witness, certified_root(), 1, &page_orders(1)[1..]));
+        assert!(!verify_page(&witness, certified_root(), 0, &page_orders(1)));
+
+        // A tampered witness no longer hashes to the certified root
+        let HashTree::Labeled(label, _) = &witness else { panic!("witness lost its label") };
+        let forged = HashTree::Labeled(label.clone(), Box::new(HashTree::Empty));
+        assert!(!verify_page(&forged, certified_root(), 1, &page_orders(1)));
+    }
+
+    #[test]
+    fn test_state_change_updates_certified_root() {
+        setup_test();
+        store_orders(3);
+        let before = certified_root();
+        let stale_witness = witness(&build_tree(&get_certified_orders()), &page_label(0));
+        let stale_page = page_orders(0);
+
+        mark_order_cancelled(2);
+        certify_active_orders();
+
+        assert_ne!(certified_root(), before);
+        assert!(!verify_page(&stale_witness, certified_root(), 0, &stale_page));
+        assert_eq!(page_orders(0).iter().map(|order| order.id).collect::<Vec<_>>(), vec![1, 3]);
+
+        let fresh = witness(&build_tree(&get_certified_orders()), &page_label(0));
+        assert!(verify_page(&fresh, certified_root(), 0, &page_orders(0)));
+    }
+
+    #[test]
+    fn test_reconstruct_matches_interface_spec_example() {
+        let labeled = |label: &str, tree: HashTree| HashTree::Labeled(label.into(), Box::new(tree));
+        let leaf = |value: &str| HashTree::Leaf(value.into());
+        let fork =
+            |left: HashTree, right: HashTree| HashTree::Fork(Box::new(left), Box::new(right));
+
+        let tree = fork(
+            fork(
+                labeled(
+                    "a",
+                    fork(
+                        fork(labeled("x", leaf("hello")), HashTree::Empty),
+                        labeled("y", leaf("world")),
+                    ),
+                ),
+                labeled("b", leaf("good")),
+            ),
+            fork(labeled("c", HashTree::Empty), labeled("d", leaf("morning"))),
+        );
+        let root: String = tree.reconstruct().iter().map(|b| format!("{:02x}", b)).collect();
+        assert_eq!(root, "eb5c5b2195e62d996b84c9bcc8259d19a83786a2f59e0878cec84c811f669aa0");
+    }
+
+    #[test]
+    fn test_witness_cbor_encoding() {
+        let tree = HashTree::Labeled(
+            b"a".to_vec(),
+            Box::new(HashTree::Fork(
+                Box::new(HashTree::Leaf(vec![0x01])),
+                Box::new(HashTree::Empty),
+            )),
+        );
+        assert_eq!(
+            tree.to_cbor(),
+            vec![
+                0xd9, 0xd9, 0xf7, // self-describe tag
+                0x83, 0x02, 0x41, b'a', // [2, "a", ...
+                0x83, 0x01, // [1, ...
+                0x82, 0x03, 0x41, 0x01, // [3, h'01']
+                0x81, 0x00, // [0]
+            ]
+        );
+    }
+}
@@ -0,0 +1,445 @@
+use crate::memory::{
+    count_orders_by_state, with_cancelled_orders_read, with_filled_orders_read,
+    with_order_counter_read, with_orders_read, with_system_stats_read,
+};
+use crate::types::{
+    DiagnosticsDump, ErrorAlarm, ErrorEvent, HealthReport, HealthStatus, MemoryStatistics,
+};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+// ============================================================================
+// ERROR ALARMS & DIAGNOSTICS - Incident Triage
+// ============================================================================
+
+/// Sliding window over which error counts are compared against alarm thresholds
+pub const ALARM_WINDOW_NS: u64 = 3600 * 1_000_000_000;
+
+/// How often the alarm timer re-evaluates the error window
+pub const ALARM_CHECK_INTERVAL_SECS: u64 = 300;
+
+/// Maximum number of error events kept for diagnostics and health reporting
+const MAX_ERROR_EVENTS: usize = 1_000;
+
+/// Width of the per-type error counters alarms are evaluated against
+const ERROR_BUCKET_NS: u64 = 60 * 1_000_000_000;
+
+/// Maximum number of fired alarms kept in the history
+const MAX_ALARM_HISTORY: usize = 100;
+
+/// Number of recent errors included in a diagnostics dump
+const DUMP_RECENT_ERRORS: usize = 50;
+
+/// Errors that degrade health while they are inside the alarm window
+const CRITICAL_ERRORS: [&str; 1] = ["rollback_failed_critical"];
+
+thread_local! {
+    static ERROR_EVENTS: RefCell<VecDeque<ErrorEvent>> = const { RefCell::new(VecDeque::new()) };
+    // Per error type, (bucket start, count) for each minute of the alarm window
+    static ERROR_BUCKETS: RefCell<BTreeMap<String, VecDeque<(u64, u64)>>> =
+        const { RefCell::new(BTreeMap::new()) };
+    static ALARM_THRESHOLDS: RefCell<HashMap<String, u64>> = RefCell::new(HashMap::new());
+    static ACTIVE_ALARMS: RefCell<HashMap<String, ErrorAlarm>> = RefCell::new(HashMap::new());
+    static ALARM_HISTORY: RefCell<VecDeque<ErrorAlarm>> = const { RefCell::new(VecDeque::new()) };
+    static TIMER_REGISTERED: RefCell<bool> = const { RefCell::new(false) };
+}
+
+/// Record a timestamped error occurrence for the sliding alarm window
+///
+/// Alarms count errors per type in minute buckets, so bursts larger than the event buffer
+/// still reach their thresholds.
+pub fn record_error_event(error_type: &str, timestamp: u64) {
+    ERROR_EVENTS.with(|events| {
+        let mut events = events.borrow_mut();
+        events.push_back(ErrorEvent { error_type: error_type.to_string(), timestamp });
+        while events.len() > MAX_ERROR_EVENTS {
+            events.pop_front();
+        }
+    });
+
+    let bucket_start = timestamp - timestamp % ERROR_BUCKET_NS;
+    ERROR_BUCKETS.with(|buckets| {
+        let mut buckets = buckets.borrow_mut();
+        let buckets = buckets.entry(error_type.to_string()).or_default();
+        match buckets.back_mut() {
+            Some((start, count)) if *start >= bucket_start => *count += 1,
+            _ => buckets.push_back((bucket_start, 1)),
+        }
+    });
+}
+
+/// Count errors per type in the window, dropping buckets that left it
+///
+/// Buckets straddling the window start still count, so counts are exact to the minute.
+fn count_errors_in_window(window_start: u64) -> HashMap<String, u64> {
+    ERROR_BUCKETS.with(|buckets| {
+        let mut buckets = buckets.borrow_mut();
+        buckets.retain(|_, type_buckets| {
+            while type_buckets
+                .front()
+                .is_some_and(|(start, _)| start + ERROR_BUCKET_NS <= window_start)
+            {
+                type_buckets.pop_front();
+            }
+            !type_buckets.is_empty()
+        });
+        buckets
+            .iter()
+            .map(|(error_type, type_buckets)| {
+                (error_type.clone(), type_buckets.iter().map(|(_, count)| count).sum())
+            })
+            .collect()
+    })
+}
+
+/// Configure the maximum number of errors of a type tolerated per hour
+pub fn set_alarm_threshold(error_type: String, max_per_hour: u64) {
+    ALARM_THRESHOLDS.with(|thresholds| {
+        thresholds.borrow_mut().insert(error_type, max_per_hour);
+    });
+}
+
+/// Remove an alarm threshold, clearing any alarm currently firing for it
+pub fn remove_alarm_threshold(error_type: &str, current_time: u64) {
+    ALARM_THRESHOLDS.with(|thresholds| {
+        thresholds.borrow_mut().remove(error_type);
+    });
+    clear_alarm(error_type, current_time);
+}
+
+/// Get all configured alarm thresholds
+pub fn get_alarm_thresholds() -> Vec<(String, u64)> {
+    ALARM_THRESHOLDS.with(|thresholds| {
+        let mut thresholds: Vec<(String, u64)> =
+            thresholds.borrow().iter().map(|(k, v)| (k.clone(), *v)).collect();
+        thresholds.sort();
+        thresholds
+    })
+}
+
+/// Restore alarm thresholds after canister upgrade
+pub fn restore_alarm_thresholds(thresholds: Vec<(String, u64)>) {
+    ALARM_THRESHOLDS.with(|map| {
+        let mut map = map.borrow_mut();
+        map.clear();
+        map.extend(thresholds);
+    });
+}
+
+/// Evaluate error counts in the sliding window, firing and clearing alarms
+pub fn evaluate_alarms(current_time: u64) {
+    let window_start = current_time.saturating_sub(ALARM_WINDOW_NS);
+
+    let counts = count_errors_in_window(window_start);
+
+    for (error_type, max_per_hour) in get_alarm_thresholds() {
+        let count = counts.get(&error_type).copied().unwrap_or(0);
+
+        if count > max_per_hour {
+            fire_alarm(&error_type, max_per_hour, count, current_time);
+        } else {
+            clear_alarm(&error_type, current_time);
+        }
+    }
+}
+
+/// Fire an alarm, or refresh the count of one that is already firing
+fn fire_alarm(error_type: &str, max_per_hour: u64, count: u64, current_time: u64) {
+    let newly_fired = ACTIVE_ALARMS.with(|alarms| {
+        let mut alarms = alarms.borrow_mut();
+        match alarms.get_mut(error_type) {
+            Some(alarm) => {
+                alarm.count_in_window = count;
+                None
+            }
+            None => {
+                let alarm = ErrorAlarm {
+                    error_type: error_type.to_string(),
+                    max_per_hour,
+                    count_in_window: count,
+                    fired_at: current_time,
+                    cleared_at: None,
+                };
+                alarms.insert(error_type.to_string(), alarm.clone());
+                Some(alarm)
+            }
+        }
+    });
+
+    if let Some(alarm) = newly_fired {
+        ic_cdk::println!(
+            "🚨 Alarm fired: {} occurred {} times in the last hour (max {})",
+            error_type,
+            count,
+            max_per_hour
+        );
+        ALARM_HISTORY.with(|history| {
+            let mut history = history.borrow_mut();
+            history.push_back(alarm);
+            while history.len() > MAX_ALARM_HISTORY {
+                history.pop_front();
+            }
+        });
+    }
+}
+
+/// Clear a firing alarm and record when it cleared in the history
+fn clear_alarm(error_type: &str, current_time: u64) {
+    let cleared = ACTIVE_ALARMS.with(|alarms| alarms.borrow_mut().remove(error_type));
+
+    if let Some(alarm) = cleared {
+        ALARM_HISTORY.with(|history| {
+            if let Some(entry) = history.borrow_mut().iter_mut().rev().find(|entry| {
+                entry.error_type == alarm.error_type && entry.fired_at == alarm.fired_at
+            }) {
+                entry.cleared_at = Some(current_time);
+            }
+        });
+    }
+}
+
+/// Get all alarms that are currently firing
+pub fn get_active_alarms() -> Vec<ErrorAlarm> {
+    ACTIVE_ALARMS.with(|alarms| {
+        let mut alarms: Vec<ErrorAlarm> = alarms.borrow().values().cloned().collect();
+        alarms.sort_by(|a, b| a.error_type.cmp(&b.error_type));
+        alarms
+    })
+}
+
+/// Build a structured diagnostics dump for incident triage
+pub fn build_diagnostics_dump(current_time: u64) -> DiagnosticsDump {
+    let (recent_errors, error_events_buffered) = ERROR_EVENTS.with(|events| {
+        let events = events.borrow();
+        let skip = events.len().saturating_sub(DUMP_RECENT_ERRORS);
+        (events.iter().skip(skip).cloned().collect(), events.len() as u64)
+    });
+
+    DiagnosticsDump {
+        generated_at: current_time,
+        recent_errors,
+        error_counts: with_system_stats_read(|stats| stats.error_counts.clone()),
+        alarm_thresholds: get_alarm_thresholds(),
+        active_alarms: get_active_alarms(),
+        alarm_history: ALARM_HISTORY.with(|history| history.borrow().iter().cloned().collect()),
+        order_counts: count_orders_by_state(current_time),
+        memory: MemoryStatistics {
+            orders_stored: with_orders_read(|orders| orders.len() as u64),
+            filled_tracked: with_filled_orders_read(|filled| filled.len() as u64),
+            cancelled_tracked: with_cancelled_orders_read(|cancelled| cancelled.len() as u64),
+            error_events_buffered,
+            stable_memory_pages: 0, // Filled in by the canister endpoint
+        },
+    }
+}
+
+/// Record that the periodic alarm timer is running
+pub fn mark_timer_registered() {
+    TIMER_REGISTERED.with(|registered| *registered.borrow_mut() = true);
+}
+
+/// Cheap consistency check of order storage, returning the first violation found
+pub fn check_storage_invariants() -> Result<(), String> {
+    let counter = with_order_counter_read(|counter| *counter);
+    let max_id = with_orders_read(|orders| orders.keys().max().copied()).unwrap_or(0);
+    if counter < max_id {
+        return Err(format!("order counter {} below stored order id {}", counter, max_id));
+    }
+
+    let both = with_filled_orders_read(|filled| {
+        with_cancelled_orders_read(|cancelled| filled.intersection(cancelled).min().copied())
+    });
+    if let Some(order_id) = both {
+        return Err(format!("order {} is both filled and cancelled", order_id));
+    }
+
+    Ok(())
+}
+
+/// Build the health report used by deploy scripts and monitoring
+///
+/// `last_error` carries the invariant violation when unhealthy, otherwise the latest tracked error.
+pub fn build_health_report(current_time: u64, stable_memory_ok: bool) -> HealthReport {
+    let window_start = current_time.saturating_sub(ALARM_WINDOW_NS);
+    let (last_error, recent_critical) = ERROR_EVENTS.with(|events| {
+        let events = events.borrow();
+        let recent_critical = events.iter().any(|event| {
+            event.timestamp > window_start && CRITICAL_ERRORS.contains(&event.error_type.as_str())
+        });
+        (events.back().map(|event| event.error_type.clone()), recent_critical)
+    });
+    let timer_registered = TIMER_REGISTERED.with(|registered| *registered.borrow());
+
+    let invariants = check_storage_invariants();
+    let status = if invariants.is_err() {
+        HealthStatus::Unhealthy
+    } else if recent_critical || !timer_registered || !stable_memory_ok {
+        HealthStatus::Degraded
+    } else {
+        HealthStatus::Healthy
+    };
+
+    HealthReport {
+        status,
+        order_count: with_orders_read(|orders| orders.len() as u64),
+        active_order_count: count_orders_by_state(current_time).active,
+        last_error: invariants.err().or(last_error),
+        stable_memory_ok,
+        timer_registered,
+    }
+}
+
+/// Clear all diagnostics data (for testing)
+#[cfg(test)]
+pub fn clear_diagnostics_data() {
+    ERROR_EVENTS.with(|events| events.borrow_mut().clear());
+    ERROR_BUCKETS.with(|buckets| buckets.borrow_mut().clear());
+    ALARM_THRESHOLDS.with(|thresholds| thresholds.borrow_mut().clear());
+    ACTIVE_ALARMS.with(|alarms| alarms.borrow_mut().clear());
+    ALARM_HISTORY.with(|history| history.borrow_mut().clear());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::{clear_limit_order_data, set_test_time, track_error};
+
+    const MINUTE_NS: u64 = 60 * 1_000_000_000;
+
+    #[test]
+    fn test_error_burst_fires_and_clears_alarm() {
+        clear_limit_order_data();
+        let start = 10 * ALARM_WINDOW_NS;
+        set_alarm_threshold("taker_transfer_failed".to_string(), 3);
+
+        // Burst of four errors within ten minutes exceeds the threshold
+        for i in 0..4 {
+            set_test_time(start + i * MINUTE_NS);
+            track_error("taker_transfer_failed");
+        }
+        evaluate_alarms(start + 10 * MINUTE_NS);
+
+        let active = get_active_alarms();
+        assert_eq!(active.len(), 1);
+        assert_eq!(active[0].error_type, "taker_transfer_failed");
+        assert_eq!(active[0].count_in_window, 4);
+        assert_eq!(active[0].fired_at, start + 10 * MINUTE_NS);
+
+        // An hour later the burst has left the window and the alarm clears
+        let later = start + 70 * MINUTE_NS;
+        evaluate_alarms(later);
+        assert!(get_active_alarms().is_empty());
+
+        let dump = build_diagnostics_dump(later);
+        assert_eq!(dump.alarm_history.len(), 1);
+        assert_eq!(dump.alarm_history[0].cleared_at, Some(later));
+        assert_eq!(dump.error_counts.get("taker_transfer_failed"), Some(&4));
+    }
+
+    #[test]
+    fn test_errors_under_threshold_do_not_fire() {
+        clear_limit_order_data();
+        set_alarm_threshold("order_not_found".to_string(), 5);
+
+        for _ in 0..5 {
+            record_error_event("order_not_found", ALARM_WINDOW_NS);
+        }
+        evaluate_alarms(ALARM_WINDOW_NS);
+
+        assert!(get_active_alarms().is_empty());
+        assert_eq!(build_diagnostics_dump(ALARM_WINDOW_NS).recent_errors.len(), 5);
+    }
+
+    #[test]
+    fn test_alarm_fires_for_bursts_beyond_event_buffer() {
+        clear_limit_order_data();
+        let start = 10 * ALARM_WINDOW_NS;
+        set_alarm_threshold("order_not_found".to_string(), 1_500);
+
+        for i in 0..1_600 {
+            record_error_event("order_not_found", start + i * 1_000_000_000);
+        }
+        let now = start + 1_600 * 1_000_000_000;
+        evaluate_alarms(now);
+
+        let active = get_active_alarms();
+        assert_eq!(active.len(), 1);
+        assert_eq!(active[0].count_in_window, 1_600);
+        assert_eq!(build_diagnostics_dump(now).memory.error_events_buffered, 1_000);
+
+        // Other error types flooding the buffer do not hide the counts either
+        clear_alarm("order_not_found", now);
+        for _ in 0..MAX_ERROR_EVENTS {
+            record_error_event("taker_transfer_failed", now);
+        }
+        evaluate_alarms(now);
+        assert_eq!(get_active_alarms()[0].count_in_window, 1_600);
+    }
+
+    fn store_orders(ids: &[u64]) {
+        for &id in ids {
+            let mut order = crate::test_utils::OrderTestFixtures::create_basic_order();
+            order.id = id;
+            crate::memory::with_orders(|orders| {
+                orders.insert(id, order);
+            });
+        }
+        crate::memory::with_order_counter(|counter| *counter = ids.len() as u64);
+    }
+
+    #[test]
+    fn test_normal_state_reports_healthy() {
+        clear_limit_order_data();
+        mark_timer_registered();
+        store_orders(&[1, 2]);
+        crate::memory::mark_order_filled(1);
+
+        let report = build_health_report(crate::memory::current_time(), true);
+        assert_eq!(report.status, HealthStatus::Healthy);
+        assert_eq!((report.order_count, report.active_order_count), (2, 1));
+        assert_eq!(report.last_error, None);
+        assert!(report.timer_registered && report.stable_memory_ok);
+    }
+
+    #[test]
+    fn test_critical_error_or_missing_timer_degrades() {
+        clear_limit_order_data();
+        let now = 10 * ALARM_WINDOW_NS;
+        set_test_time(now);
+        assert_eq!(build_health_report(now, true).status, HealthStatus::Degraded);
+
+        mark_timer_registered();
+        track_error("rollback_failed_critical");
+        track_error("order_not_found");
+        let report = build_health_report(now + MINUTE_NS, true);
+        assert_eq!(report.status, HealthStatus::Degraded);
+        assert_eq!(report.last_error.as_deref(), Some("order_not_found"));
+
+        // The critical error stops counting once it leaves the window
+        assert_eq!(build_health_report(now + ALARM_WINDOW_NS, true).status, HealthStatus::Healthy);
+        assert_eq!(
+            build_health_report(now + ALARM_WINDOW_NS, false).status,
+            HealthStatus::Degraded
+        );
+    }
+
+    #[test]
+    fn test_invariant_violations_report_unhealthy() {
+        clear_limit_order_data();
+        mark_timer_registered();
+        store_orders(&[1, 2]);
+        crate::memory::mark_order_filled(2);
+        crate::memory::mark_order_cancelled(2);
+
+        let report = build_health_report(crate::memory::current_time(), true);
+        assert_eq!(report.status, HealthStatus::Unhealthy);
+        assert_eq!(report.last_error.as_deref(), Some("order 2 is both filled and cancelled"));
+
+        clear_limit_order_data();
+        store_orders(&[1, 7]);
+        assert_eq!(
+            check_storage_invariants(),
+            Err("order counter 2 below stored order id 7".to_string())
+        );
+    }
+}
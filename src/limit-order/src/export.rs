@@ -0,0 +1,431 @@
+use crate::memory::{
+    get_fills_for_maker, with_cancelled_orders_read, with_filled_orders_read, with_orders_read,
+};
+use crate::types::{FillRecord, HttpRequest, HttpResponse, Order};
+use candid::Principal;
+use sha2::{Digest, Sha256};
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+// ============================================================================
+// CSV EXPORT - Order and fill history for accounting
+// ============================================================================
+
+/// Most rows in one export response; later rows are fetched with the continuation token
+pub const EXPORT_ROW_CAP: usize = 1_000;
+
+/// Response header holding the `continuation` query value of the next page, absent on the last page
+pub const CONTINUATION_HEADER: &str = "X-Continuation-Token";
+
+const ORDER_COLUMNS: [&str; 10] = [
+    "order_id",
+    "maker",
+    "receiver",
+    "maker_asset",
+    "taker_asset",
+    "making_amount",
+    "taking_amount",
+    "created_at",
+    "expiration",
+    "status",
+];
+
+const FILL_COLUMNS: [&str; 9] = [
+    "order_id",
+    "taker",
+    "making_amount",
+    "taking_amount",
+    "taker_transfer_block",
+    "maker_transfer_block",
+    "integrator_fee_recipient",
+    "integrator_fee_amount",
+    "timestamp",
+];
+
+thread_local! {
+    // Maker of each export token, keyed by the token's SHA-256 so stored state holds no usable token
+    static EXPORT_TOKENS: RefCell<HashMap<String, Principal>> = RefCell::new(HashMap::new());
+}
+
+/// Issue a maker's export token from random bytes, revoking any token issued to them before
+pub fn issue_export_token(maker: Principal, random_bytes: &[u8]) -> String {
+    let token = to_hex(random_bytes);
+    EXPORT_TOKENS.with(|tokens| {
+        let mut tokens = tokens.borrow_mut();
+        tokens.retain(|_, owner| *owner != maker);
+        tokens.insert(token_digest(&token), maker);
+    });
+    token
+}
+
+/// Maker an export token was issued to
+fn token_owner(token: &str) -> Option<Principal> {
+    EXPORT_TOKENS.with(|tokens| tokens.borrow().get(&token_digest(token)).copied())
+}
+
+fn token_digest(token: &str) -> String {
+    to_hex(&Sha256::digest(token.as_bytes()))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Serve `GET /export/orders.csv` and `GET /export/fills.csv` for the maker owning the `token`
+///
+/// Query parameters: `token`, optional `maker` (must be the token's owner), `from`/`to` (ns,
+/// half-open range on creation or fill time), `limit` (1 up to the row cap) and `continuation`.
+pub fn handle_http_request(request: &HttpRequest, current_time: u64) -> HttpResponse {
+    let (path, query) = request.url.split_once('?').unwrap_or((request.url.as_str(), ""));
+    let export = match path {
+        "/export/orders.csv" => Export::Orders,
+        "/export/fills.csv" => Export::Fills,
+        _ => return text_response(404, "Not found"),
+    };
+    if !request.method.eq_ignore_ascii_case("GET") {
+        return text_response(405, "Method not allowed");
+    }
+
+    let params: HashMap<&str, &str> =
+        query.split('&').filter_map(|pair| pair.split_once('=')).collect();
+    let Some(owner) = params.get("token").and_then(|token| token_owner(token)) else {
+        return text_response(401, "Missing or unknown export token");
+    };
+    let maker = match params.get("maker").map(Principal::from_text) {
+        None => owner,
+        Some(Ok(maker)) => maker,
+        Some(Err(_)) => return text_response(400, "Invalid maker principal"),
+    };
+    if maker != owner {
+        return text_response(403, "Export token was issued to another maker");
+    }
+
+    let (Ok(from), Ok(to), Ok(offset), Ok(limit)) = (
+        number_param(&params, "from", 0),
+        number_param(&params, "to", u64::MAX),
+        number_param(&params, "continuation", 0),
+        number_param(&params, "limit", EXPORT_ROW_CAP as u64),
+    ) else {
+        return text_response(400, "from, to, continuation and limit must be unsigned integers");
+    };
+    // An empty page would hand out the same continuation forever
+    if limit == 0 {
+        return text_response(400, "limit must be at least 1");
+    }
+    let (offset, limit) = (offset as usize, (limit as usize).min(EXPORT_ROW_CAP));
+
+    let (rows, total) = match export {
+        Export::Orders => export_page(maker_orders(maker, from, to), offset, limit, |order| {
+            order_row(order, current_time)
+        }),
+        Export::Fills => export_page(maker_fills(maker, from, to), offset, limit, fill_row),
+    };
+
+    let mut body = csv_line(export.columns().iter().map(|column| column.to_string()));
+    for row in rows {
+        body.push_str(&csv_line(row.into_iter()));
+    }
+
+    let mut headers = vec![
+        ("Content-Type".to_string(), "text/csv; charset=utf-8".to_string()),
+        (
+            "Content-Disposition".to_string(),
+            format!("attachment; filename=\"{}\"", export.filename()),
+        ),
+    ];
+    let next = offset + limit;
+    if next < total {
+        headers.push((CONTINUATION_HEADER.to_string(), next.to_string()));
+    }
+    HttpResponse { status_code: 200, headers, body: body.into_bytes() }
+}
+
+/// The files that can be exported
+#[derive(Clone, Copy)]
+enum Export {
+    Orders,
+    Fills,
+}
+
+impl Export {
+    fn filename(self) -> &'static str {
+        match self {
+            Export::Orders => "orders.csv",
+            Export::Fills => "fills.csv",
+        }
+    }
+
+    fn columns(self) -> &'static [&'static str] {
+        match self {
+            Export::Orders => &ORDER_COLUMNS,
+            Export::Fills => &FILL_COLUMNS,
+        }
+    }
+}
+
+/// Rows of one page of records and the number of records across all pages
+fn export_page<T>(
+    records: Vec<T>,
+    offset: usize,
+    limit: usize,
+    row: impl Fn(&T) -> Vec<String>,
+) -> (Vec<Vec<String>>, usize) {
+    let total = records.len();
+    (records.iter().skip(offset).take(limit).map(row).collect(), total)
+}
+
+/// A maker's orders created in [from, to), oldest first
+fn maker_orders(maker: Principal, from: u64, to: u64) -> Vec<Order> {
+    let mut orders: Vec<Order> = with_orders_read(|orders| {
+        orders
+            .values()
+            .filter(|order| {
+                order.maker == maker && order.created_at >= from && order.created_at < to
+            })
+            .cloned()
+            .collect()
+    });
+    orders.sort_by_key(|order| (order.created_at, order.id));
+    orders
+}
+
+/// Fills of a maker's orders made in [from, to), oldest first
+fn maker_fills(maker: Principal, from: u64, to: u64) -> Vec<FillRecord> {
+    get_fills_for_maker(maker, 0, usize::MAX)
+        .into_iter()
+        .filter(|fill| fill.timestamp >= from && fill.timestamp < to)
+        .collect()
+}
+
+fn order_row(order: &Order, current_time: u64) -> Vec<String> {
+    let status = if with_filled_orders_read(|filled| filled.contains(&order.id)) {
+        "filled"
+    } else if with_cancelled_orders_read(|cancelled| cancelled.contains(&order.id)) {
+        "cancelled"
+    } else if order.expiration <= current_time {
+        "expired"
+    } else {
+        "active"
+    };
+    vec![
+        order.id.to_string(),
+        order.maker.to_text(),
+        order.receiver.to_text(),
+        order.maker_asset.to_text(),
+        order.taker_asset.to_text(),
+        order.making_amount.to_string(),
+        order.taking_amount.to_string(),
+        order.created_at.to_string(),
+        order.expiration.to_string(),
+        status.to_string(),
+    ]
+}
+
+fn fill_row(fill: &FillRecord) -> Vec<String> {
+    let (fee_recipient, fee_amount) = match &fill.integrator_fee {
+        Some(fee) => (fee.recipient.to_text(), fee.amount.to_string()),
+        None => (String::new(), String::new()),
+    };
+    vec![
+        fill.order_id.to_string(),
+        fill.taker.to_text(),
+        fill.making_amount.to_string(),
+        fill.taking_amount.to_string(),
+        fill.block_indices.0.to_string(),
+        fill.block_indices.1.to_string(),
+        fee_recipient,
+        fee_amount,
+        fill.timestamp.to_string(),
+    ]
+}
+
+/// Parse an optional unsigned query parameter
+fn number_param(params: &HashMap<&str, &str>, name: &str, default: u64) -> Result<u64, ()> {
+    params.get(name).map_or(Ok(default), |value| value.parse().map_err(|_| ()))
+}
+
+/// One CSV record terminated by CRLF (RFC 4180)
+fn csv_line(fields: impl Iterator<Item = String>) -> String {
+    let mut line = fields.map(|field| csv_field(&field)).collect::<Vec<_>>().join(",");
+    line.push_str("\r\n");
+    line
+}
+
+/// Quote a field containing a separator, quote or line break, doubling embedded quotes
+pub fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn text_response(status_code: u16, message: &str) -> HttpResponse {
+    HttpResponse {
+        status_code,
+        headers: vec![("Content-Type".to_string(), "text/plain".to_string())],
+        body: message.as_bytes().to_vec(),
+    }
+}
+
+/// Export tokens by digest, for saving across upgrades
+pub fn get_export_tokens() -> Vec<(String, Principal)> {
+    EXPORT_TOKENS.with(|tokens| {
+        tokens.borrow().iter().map(|(digest, maker)| (digest.clone(), *maker)).collect()
+    })
+}
+
+/// Restore export tokens after an upgrade
+pub fn restore_export_tokens(tokens: Vec<(String, Principal)>) {
+    EXPORT_TOKENS.with(|stored| *stored.borrow_mut() = tokens.into_iter().collect());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::{clear_limit_order_data, record_fill, with_orders};
+    use crate::test_utils::OrderTestFixtures;
+    use crate::types::IntegratorFeePayment;
+
+    const NOW: u64 = 1_000_000_000_000;
+
+    fn store_orders(count: u64) -> Principal {
+        let order = OrderTestFixtures::create_basic_order();
+        for id in 1..=count {
+            let order = Order { id, created_at: NOW + id, ..order.clone() };
+            with_orders(|orders| {
+                orders.insert(id, order);
+            });
+        }
+        order.maker
+    }
+
+    fn get(url: &str) -> HttpResponse {
+        let request = HttpRequest {
+            method: "GET".to_string(),
+            url: url.to_string(),
+            headers: vec![],
+            body: vec![],
+        };
+        handle_http_request(&request, NOW)
+    }
+
+    fn header<'a>(response: &'a HttpResponse, name: &str) -> Option<&'a str> {
+        response.headers.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str())
+    }
+
+    fn body_lines(response: &HttpResponse) -> Vec<String> {
+        String::from_utf8(response.body.clone()).unwrap().lines().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn test_csv_escaping_of_principals_and_amounts() {
+        clear_limit_order_data();
+        let maker = store_orders(1);
+        let (_, taker) = OrderTestFixtures::test_principals();
+        record_fill(
+            maker,
+            FillRecord {
+                order_id: 1,
+                taker,
+                making_amount: 1_000_000,
+                taking_amount: 18_446_744_073_709_551_615,
+                block_indices: (7, 8),
+                timestamp: NOW + 5,
+                integrator_fee: Some(IntegratorFeePayment {
+                    recipient: taker,
+                    amount: 20,
+                    block_index: 9,
+                }),
+            },
+        );
+        let token = issue_export_token(maker, &[0xab; 32]);
+
+        // Principals and amounts carry no separators, so they are written unquoted
+        let response = get(&format!("/export/fills.csv?token={}", token));
+        assert_eq!(response.status_code, 200);
+        assert_eq!(header(&response, "Content-Type"), Some("text/csv; charset=utf-8"));
+        assert_eq!(
+            header(&response, "Content-Disposition"),
+            Some("attachment; filename=\"fills.csv\"")
+        );
+        assert_eq!(body_lines(&response)[0], FILL_COLUMNS.join(","));
+        assert_eq!(
+            body_lines(&response)[1],
+            format!("1,{taker},1000000,18446744073709551615,7,8,{taker},20,{}", NOW + 5)
+        );
+
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+        assert_eq!(csv_field(&maker.to_text()), maker.to_text());
+    }
+
+    #[test]
+    fn test_row_cap_and_continuation() {
+        clear_limit_order_data();
+        let maker = store_orders(5);
+        let token = issue_export_token(maker, &[1; 32]);
+        let ids = |response: &HttpResponse| -> Vec<String> {
+            body_lines(response)[1..]
+                .iter()
+                .map(|line| line.split(',').next().unwrap().to_string())
+                .collect()
+        };
+
+        let first = get(&format!("/export/orders.csv?token={}&limit=2", token));
+        assert_eq!(ids(&first), vec!["1", "2"]);
+        assert_eq!(header(&first, CONTINUATION_HEADER), Some("2"));
+
+        let last = get(&format!("/export/orders.csv?token={}&limit=2&continuation=4", token));
+        assert_eq!(ids(&last), vec!["5"]);
+        assert_eq!(header(&last, CONTINUATION_HEADER), None);
+
+        // The range filters on creation time, and limits above the cap are capped
+        let ranged = get(&format!(
+            "/export/orders.csv?token={}&from={}&to={}&limit=5000",
+            token,
+            NOW + 2,
+            NOW + 4
+        ));
+        assert_eq!(ids(&ranged), vec!["2", "3"]);
+        assert_eq!(header(&ranged, CONTINUATION_HEADER), None);
+        assert!(body_lines(&ranged)[1].ends_with(",active"));
+
+        for limit in ["x", "0"] {
+            let response = get(&format!("/export/orders.csv?token={}&limit={}", token, limit));
+            assert_eq!(response.status_code, 400);
+            assert_eq!(header(&response, CONTINUATION_HEADER), None);
+        }
+    }
+
+    #[test]
+    fn test_export_token_validation() {
+        clear_limit_order_data();
+        let maker = store_orders(1);
+        let (_, other) = OrderTestFixtures::test_principals();
+        let token = issue_export_token(maker, &[2; 32]);
+        let other_token = issue_export_token(other, &[3; 32]);
+
+        assert_eq!(get("/export/orders.csv").status_code, 401);
+        assert_eq!(get(&format!("/export/orders.csv?token={}", "00".repeat(32))).status_code, 401);
+        assert_eq!(
+            get(&format!("/export/orders.csv?token={}&maker={}", other_token, maker)).status_code,
+            403
+        );
+        assert_eq!(
+            get(&format!("/export/orders.csv?token={}&maker={}", token, maker)).status_code,
+            200
+        );
+        assert_eq!(get(&format!("/export/trades.csv?token={}", token)).status_code, 404);
+
+        // Reissuing revokes the previous token, and tokens survive upgrades
+        let reissued = issue_export_token(maker, &[4; 32]);
+        assert_eq!(get(&format!("/export/orders.csv?token={}", token)).status_code, 401);
+        let saved = get_export_tokens();
+        restore_export_tokens(vec![]);
+        assert_eq!(get(&format!("/export/orders.csv?token={}", reissued)).status_code, 401);
+        restore_export_tokens(saved);
+        assert_eq!(get(&format!("/export/orders.csv?token={}", reissued)).status_code, 200);
+    }
+}
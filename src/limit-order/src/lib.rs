@@ -1,3 +1,6 @@
+mod certification;
+mod diagnostics;
+mod export;
 mod hashlock_timelock;
 mod limit_orders;
 mod memory;
@@ -7,7 +10,11 @@ mod mock_icrc1_token;
 mod test_utils;
 mod types;
 
-use types::{Order, OrderError, OrderId, SystemStats, MakerTraits, TakerTraits};
+use types::{
+    AgentAction, AgentAuthorization, AgentScope, CancellationMethod, CancellationRecord, Candle, CandleInterval, CertifiedOrders, CreateOrderOptions, CreateOrderParams, DeadReason, DiagnosticsDump, ErrorAlarm, FillRecord,
+    FillSimulation, FusionFill, HealthReport, HttpRequest, HttpResponse, IncompleteFill, IncompleteFillResolution, InitArgs, MakerTraits, Order, OrderError, OrderId, OrderReference,
+    PausedAsset, PriceInfo, PriceSanity, RuntimeLimits, StpGroup, SystemStats, TakerTraits,
+};
 
 // Keep the hello world function for testing
 #[ic_cdk::query]
@@ -15,11 +22,31 @@ fn greet(name: String) -> String {
     format!("Hello, {}!", name)
 }
 
+/// Stable memory size above which upgrades risk running out of heap on restore (4 GiB)
+const STABLE_MEMORY_HEALTHY_PAGES: u64 = 65_536;
+
+/// Structured liveness and state health check - Used by: Deploy scripts/Monitoring
+#[ic_cdk::query]
+fn health() -> HealthReport {
+    let stable_memory_ok = ic_cdk::api::stable::stable_size() < STABLE_MEMORY_HEALTHY_PAGES;
+    diagnostics::build_health_report(ic_cdk::api::time(), stable_memory_ok)
+}
+
 // ============================================================================
 // CORE LOP FUNCTIONS - Order Management and Token Swaps
 // ============================================================================
 
-// Note: 1inch LOP does not have create_order() - orders are created off-chain and signed!
+/// Create an order owned by this canister, or a Fusion order when escrow terms are given - Used by: Makers/Agents
+///
+/// 1inch LOP orders are signed off-chain; stored orders let the canister validate fills against
+/// the maker's own terms rather than the taker's copy of the order.
+#[ic_cdk::update]
+async fn create_order(
+    params: CreateOrderParams,
+    options: CreateOrderOptions,
+) -> Result<OrderId, OrderError> {
+    limit_orders::create_order(params, options, ic_cdk::caller()).await
+}
 
 // Note: Old fill_order/cancel_order functions removed - replaced with 1inch LOP compliant versions
 
@@ -29,6 +56,28 @@ fn get_active_orders() -> Vec<Order> {
     limit_orders::get_active_orders_list()
 }
 
+/// Get a page of active orders with a certificate and witness - Used by: Frontend
+///
+/// Lets frontends served through untrusted boundary nodes verify the order list.
+#[ic_cdk::query]
+fn get_active_orders_certified(page: u64) -> CertifiedOrders {
+    let orders = certification::get_certified_orders();
+    let tree = certification::build_tree(&orders);
+    let witness = certification::witness(&tree, &certification::page_label(page as usize));
+
+    CertifiedOrders {
+        orders: orders
+            .chunks(certification::CERTIFIED_PAGE_SIZE)
+            .nth(page as usize)
+            .map(|chunk| chunk.to_vec())
+            .unwrap_or_default(),
+        page,
+        page_count: orders.len().div_ceil(certification::CERTIFIED_PAGE_SIZE) as u64,
+        certificate: ic_cdk::api::data_certificate().unwrap_or_default(),
+        witness: witness.to_cbor(),
+    }
+}
+
 /// Get a specific order by ID - Used by: Frontend/Users
 #[ic_cdk::query]
 fn get_order_by_id(order_id: OrderId) -> Option<Order> {
@@ -50,91 +99,375 @@ fn get_orders_by_asset_pair(
     limit_orders::get_orders_by_asset_pair(maker_asset, taker_asset)
 }
 
+/// Preview the transfer legs of a fill without executing it - Used by: Frontend/Takers
+#[ic_cdk::query(composite = true)]
+async fn simulate_fill(
+    order: OrderReference,
+    amount: u64,
+    taker: candid::Principal,
+) -> FillSimulation {
+    limit_orders::simulate_fill(order, amount, taker).await
+}
+
+/// Get an order's price adjusted for its assets' decimals - Used by: Frontend/Traders
+#[ic_cdk::query]
+fn get_normalized_price(order_id: OrderId) -> Option<PriceInfo> {
+    limit_orders::get_normalized_price(order_id)
+}
+
+/// Get an asset pair's price candles starting within [from_ns, to_ns) - Used by: Frontend/Traders
+#[ic_cdk::query]
+fn get_candles(
+    maker_asset: candid::Principal,
+    taker_asset: candid::Principal,
+    interval: CandleInterval,
+    from_ns: u64,
+    to_ns: u64,
+) -> Vec<Candle> {
+    limit_orders::get_candles(maker_asset, taker_asset, interval, from_ns, to_ns)
+}
+
+/// Compare a prospective order's price with the pair's recent fills before creating it - Used by: Frontend/Makers
+#[ic_cdk::query]
+fn get_price_sanity(
+    maker_asset: candid::Principal,
+    taker_asset: candid::Principal,
+    making_amount: u64,
+    taking_amount: u64,
+) -> PriceSanity {
+    limit_orders::get_price_sanity(maker_asset, taker_asset, making_amount, taking_amount)
+}
+
+/// Register as negotiating an order to keep fill rights during its grace window - Used by: Takers
+#[ic_cdk::update]
+fn express_intent(order_id: OrderId) -> Result<(), OrderError> {
+    limit_orders::express_intent(order_id)
+}
+
 /// Get system statistics - Used by: Frontend/Monitoring
 #[ic_cdk::query]
 fn get_system_stats() -> SystemStats {
     limit_orders::get_system_statistics()
 }
 
+/// Maximum number of fill records returned per page
+const MAX_FILLS_PAGE_SIZE: u64 = 100;
+
+/// Get the fill of an order with taker identity, at most one record - Used by: Frontend/Makers
+#[ic_cdk::query]
+fn get_fills_for_order(order_id: OrderId) -> Vec<FillRecord> {
+    limit_orders::get_fills_for_order(order_id)
+}
+
+/// Get a page of a maker's fills across all their orders - Used by: Frontend/Makers
+#[ic_cdk::query]
+fn get_fills_for_maker(maker: candid::Principal, offset: u64, limit: u64) -> Vec<FillRecord> {
+    limit_orders::get_fills_for_maker(
+        maker,
+        offset as usize,
+        limit.min(MAX_FILLS_PAGE_SIZE) as usize,
+    )
+}
+
 // ============================================================================
-// HELPER FUNCTIONS FOR 1INCH LOP IMPLEMENTATION  
+// DIAGNOSTICS API - Error Alarms and Incident Triage
 // ============================================================================
 
-// Note: Cross-chain functionality handled through fill_order_args() extension data
-// All cross-chain coordination functions have been removed - only 1inch LOP compliant API remains
+/// Configure an error alarm threshold (errors per hour) - Used by: Controllers
+#[ic_cdk::update]
+fn set_error_alarm_threshold(error_type: String, max_per_hour: u64) -> Result<(), OrderError> {
+    require_controller()?;
+    diagnostics::set_alarm_threshold(error_type, max_per_hour);
+    Ok(())
+}
 
-/// Validate order signature (ICP adaptation)
-fn validate_order_signature(order: &Order, signature: &[u8], taker: candid::Principal) -> Result<(), OrderError> {
-    // ICP adaptation: Use principal-based validation instead of EIP-712
-    // For now, we'll accept any signature as valid since we use principal authentication
+/// Remove an error alarm threshold - Used by: Controllers
+#[ic_cdk::update]
+fn remove_error_alarm_threshold(error_type: String) -> Result<(), OrderError> {
+    require_controller()?;
+    diagnostics::remove_alarm_threshold(&error_type, ic_cdk::api::time());
     Ok(())
 }
 
-/// Validate order is fillable
-fn validate_order_fillable(order: &Order) -> Result<(), OrderError> {
-    // Check if order is expired
-    if order.expiration <= ic_cdk::api::time() {
-        return Err(OrderError::OrderExpired);
+/// Get alarms that are currently firing - Used by: Monitoring
+#[ic_cdk::query]
+fn get_active_alarms() -> Vec<ErrorAlarm> {
+    diagnostics::get_active_alarms()
+}
+
+/// Get a structured diagnostics dump for incident triage - Used by: Controllers
+#[ic_cdk::query]
+fn get_diagnostics_dump() -> Result<DiagnosticsDump, OrderError> {
+    require_controller()?;
+    let mut dump = diagnostics::build_diagnostics_dump(ic_cdk::api::time());
+    dump.memory.stable_memory_pages = ic_cdk::api::stable::stable_size();
+    Ok(dump)
+}
+
+// ============================================================================
+// GOVERNANCE API - Runtime System Limits
+// ============================================================================
+
+/// Replace the system limits for new operations - Used by: Controllers
+#[ic_cdk::update]
+fn set_runtime_limits(limits: RuntimeLimits) -> Result<(), OrderError> {
+    require_controller()?;
+    limits.validate()?;
+    memory::set_runtime_limits(limits);
+    Ok(())
+}
+
+/// Get the system limits currently in force - Used by: Frontend/Monitoring
+#[ic_cdk::query]
+fn get_runtime_limits() -> RuntimeLimits {
+    memory::get_runtime_limits()
+}
+
+/// Halt creation and fills of orders involving a token - Used by: Controllers
+#[ic_cdk::update]
+fn pause_asset(token: candid::Principal, reason: String) -> Result<(), OrderError> {
+    require_controller()?;
+    memory::pause_asset(token, reason, ic_cdk::api::time());
+    Ok(())
+}
+
+/// Resume trading of a paused token - Used by: Controllers
+#[ic_cdk::update]
+fn unpause_asset(token: candid::Principal) -> Result<(), OrderError> {
+    require_controller()?;
+    memory::unpause_asset(token);
+    Ok(())
+}
+
+/// Re-fetch and cache an asset's decimals from its ledger - Used by: Controllers
+#[ic_cdk::update]
+async fn refresh_asset_decimals(token: candid::Principal) -> Result<u8, OrderError> {
+    require_controller()?;
+    limit_orders::refresh_asset_decimals(token).await
+}
+
+/// Get paused tokens with reasons and pause times - Used by: Frontend/Monitoring
+#[ic_cdk::query]
+fn get_paused_assets() -> Vec<PausedAsset> {
+    memory::get_paused_assets()
+}
+
+/// Enable or disable skipping of ledger balance checks and transfers - Used by: Controllers
+#[ic_cdk::update]
+fn set_test_mode(enabled: bool) -> Result<(), OrderError> {
+    limit_orders::configure_test_mode(enabled, ic_cdk::api::is_controller(&ic_cdk::caller()))
+}
+
+/// Check whether ledger calls are being skipped - Used by: Deploy scripts/Monitoring
+#[ic_cdk::query]
+fn is_test_mode() -> bool {
+    memory::is_test_mode()
+}
+
+/// Reject callers that are not controllers of this canister
+fn require_controller() -> Result<(), OrderError> {
+    if ic_cdk::api::is_controller(&ic_cdk::caller()) {
+        Ok(())
+    } else {
+        Err(OrderError::Unauthorized)
     }
-    
-    // Check if order has sufficient amounts
-    if order.making_amount == 0 || order.taking_amount == 0 {
-        return Err(OrderError::InvalidAmount);
+}
+
+/// Start the periodic alarm evaluation timer
+fn start_alarm_timer() {
+    ic_cdk_timers::set_timer_interval(
+        std::time::Duration::from_secs(diagnostics::ALARM_CHECK_INTERVAL_SECS),
+        || diagnostics::evaluate_alarms(ic_cdk::api::time()),
+    );
+    diagnostics::mark_timer_registered();
+}
+
+// ============================================================================
+// SELF-TRADE PREVENTION - Groups of principals that may not fill each other
+// ============================================================================
+
+/// Longest accepted self-trade prevention group name
+const MAX_STP_GROUP_NAME_LEN: usize = 64;
+
+/// Create a self-trade prevention group principals can join - Used by: Controllers
+#[ic_cdk::update]
+fn create_stp_group(name: String) -> Result<(), OrderError> {
+    require_controller()?;
+    if name.is_empty() || name.len() > MAX_STP_GROUP_NAME_LEN {
+        return Err(OrderError::InvalidConfiguration(format!(
+            "Group name must be 1 to {} bytes",
+            MAX_STP_GROUP_NAME_LEN
+        )));
     }
-    
+    memory::create_stp_group(name, ic_cdk::api::time());
     Ok(())
 }
 
-/// Validate fill amount
-fn validate_fill_amount(order: &Order, amount: u64) -> Result<(), OrderError> {
-    if amount == 0 {
-        return Err(OrderError::InvalidAmount);
+/// Join a self-trade prevention group, leaving any previous one - Used by: Makers/Takers
+#[ic_cdk::update]
+fn join_stp_group(name: String) -> Result<(), OrderError> {
+    let caller = ic_cdk::caller();
+    limit_orders::validate_principal(caller, "stp_member")?;
+    if memory::join_stp_group(caller, &name) {
+        Ok(())
+    } else {
+        Err(OrderError::StpGroupNotFound(name))
     }
-    
-    if amount > order.taking_amount {
-        return Err(OrderError::InsufficientAmount);
+}
+
+/// Leave the caller's self-trade prevention group - Used by: Makers/Takers
+#[ic_cdk::update]
+fn leave_stp_group() -> Result<(), OrderError> {
+    memory::leave_stp_group(ic_cdk::caller());
+    Ok(())
+}
+
+/// Get the self-trade prevention group a principal belongs to - Used by: Frontend/Takers
+#[ic_cdk::query]
+fn get_stp_group_of(principal: candid::Principal) -> Option<String> {
+    memory::get_stp_group_of(principal)
+}
+
+/// Get a self-trade prevention group and its members - Used by: Frontend/Monitoring
+#[ic_cdk::query]
+fn get_stp_group(name: String) -> Option<StpGroup> {
+    memory::get_stp_group(&name)
+}
+
+// ============================================================================
+// AGENT AUTHORIZATIONS - Session keys acting for a maker
+// ============================================================================
+
+/// Let an agent create and cancel orders for the caller within a scope - Used by: Makers
+#[ic_cdk::update]
+fn authorize_agent(agent: candid::Principal, scope: AgentScope) -> Result<(), OrderError> {
+    let maker = ic_cdk::caller();
+    limit_orders::validate_principal(maker, "maker")?;
+    limit_orders::validate_principal(agent, "agent")?;
+    if agent == maker {
+        return Err(OrderError::InvalidPrincipal);
     }
-    
+    if scope.expires_at <= ic_cdk::api::time() {
+        return Err(OrderError::InvalidExpiration);
+    }
+
+    let authorized_at = ic_cdk::api::time();
+    memory::authorize_agent(maker, AgentAuthorization { agent, scope, authorized_at });
     Ok(())
 }
 
-/// Execute atomic token fill
-async fn execute_atomic_fill(order: &Order, amount: u64, taker: candid::Principal) -> Result<(u64, u64), OrderError> {
-    // Calculate proportional amounts
-    let making_amount = (order.making_amount * amount) / order.taking_amount;
-    let taking_amount = amount;
-    
-    // This would integrate with existing limit_orders::fill_order logic
-    // For now, return the calculated amounts
-    Ok((making_amount, taking_amount))
+/// Revoke an agent of the caller with immediate effect - Used by: Makers
+#[ic_cdk::update]
+fn revoke_agent(agent: candid::Principal) -> Result<(), OrderError> {
+    memory::revoke_agent(ic_cdk::caller(), agent);
+    Ok(())
 }
 
-/// Compute order hash (ICP adaptation)
-fn compute_order_hash(order: &Order) -> Vec<u8> {
-    // ICP adaptation: Use structured hashing instead of EIP-712
-    use sha2::{Digest, Sha256};
-    let mut hasher = Sha256::new();
-    
-    // Hash order fields
-    hasher.update(order.salt.to_be_bytes());
-    hasher.update(order.maker.as_slice());
-    hasher.update(order.receiver.as_slice());
-    hasher.update(order.maker_asset.as_slice());
-    hasher.update(order.taker_asset.as_slice());
-    hasher.update(order.making_amount.to_be_bytes());
-    hasher.update(order.taking_amount.to_be_bytes());
-    hasher.update(order.expiration.to_be_bytes());
-    
-    hasher.finalize().to_vec()
+/// List the agents the caller authorized, including expired ones - Used by: Makers
+#[ic_cdk::query]
+fn list_agents() -> Vec<AgentAuthorization> {
+    memory::get_agents(ic_cdk::caller())
 }
 
-/// Update order state after fill
-fn update_order_state(order_hash: &[u8], making_amount: u64) -> Result<(), OrderError> {
-    // This would integrate with existing order state management
-    // For now, just return success
+// ============================================================================
+// FUSION SETTLEMENT - Fills of escrow-coordinated orders
+// ============================================================================
+
+/// Set the escrow_manager that settles Fusion fills, or clear it - Used by: Controllers
+#[ic_cdk::update]
+fn set_escrow_manager(canister_id: Option<candid::Principal>) -> Result<(), OrderError> {
+    require_controller()?;
+    memory::set_escrow_manager(canister_id);
     Ok(())
 }
 
+/// Get the escrow_manager that settles Fusion fills - Used by: Frontend/Monitoring
+#[ic_cdk::query]
+fn get_escrow_manager() -> Option<candid::Principal> {
+    memory::get_escrow_manager()
+}
+
+/// Report that the escrow of a Fusion fill was claimed, completing the fill - Used by: escrow_manager
+#[ic_cdk::update]
+fn notify_fusion_fill_completed(escrow_id: String) -> Result<(), OrderError> {
+    limit_orders::complete_fusion_fill(&escrow_id, ic_cdk::caller())
+}
+
+/// Get the escrow-settled fill of an order while it is pending or failed - Used by: Frontend/Takers
+#[ic_cdk::query]
+fn get_fusion_fill(order_id: OrderId) -> Option<FusionFill> {
+    memory::get_fusion_fill(order_id)
+}
+
+// ============================================================================
+// INCOMPLETE FILLS - Direct fills stuck after one transfer
+// ============================================================================
+
+/// List fills whose second transfer and rollback both failed - Used by: Controllers/Monitoring
+#[ic_cdk::query]
+fn list_incomplete_fills() -> Vec<IncompleteFill> {
+    memory::list_incomplete_fills()
+}
+
+/// Settle an incomplete fill and clear its record - Used by: Controllers
+#[ic_cdk::update]
+async fn resolve_incomplete_fill(
+    order_id: OrderId,
+    resolution: IncompleteFillResolution,
+) -> Result<(), OrderError> {
+    require_controller()?;
+    limit_orders::resolve_incomplete_fill(order_id, resolution).await
+}
+
+// ============================================================================
+// CSV EXPORT - Order and fill history for accounting
+// ============================================================================
+
+/// Issue the caller an export token for the CSV endpoints, revoking their previous one - Used by: Makers
+#[ic_cdk::update]
+async fn create_export_token() -> Result<String, OrderError> {
+    let maker = ic_cdk::caller();
+    limit_orders::validate_principal(maker, "maker")?;
+    let (random_bytes,) = ic_cdk::api::management_canister::main::raw_rand().await.map_err(
+        |(code, message)| OrderError::SystemError(format!("raw_rand failed: {:?} {}", code, message)),
+    )?;
+    Ok(export::issue_export_token(maker, &random_bytes))
+}
+
+/// Serve orders.csv and fills.csv exports under /export - Used by: Accounting tools/Browsers
+///
+/// Responses are not certified, so they must be fetched through the raw gateway domain.
+#[ic_cdk::query]
+fn http_request(request: HttpRequest) -> HttpResponse {
+    export::handle_http_request(&request, memory::current_time())
+}
+
+// ============================================================================
+// HELPER FUNCTIONS FOR 1INCH LOP IMPLEMENTATION  
+// ============================================================================
+
+// Note: Cross-chain functionality handled through fill_order_args() extension data
+// All cross-chain coordination functions have been removed - only 1inch LOP compliant API remains
+
+/// Validate order signature (ICP adaptation)
+fn validate_order_signature(order: &Order, signature: &[u8], taker: candid::Principal) -> Result<(), OrderError> {
+    // ICP adaptation: Use principal-based validation instead of EIP-712
+    // For now, we'll accept any signature as valid since we use principal authentication
+    Ok(())
+}
+
+/// Reject orders whose hash was filled, cancelled or invalidated
+fn validate_order_alive(order_hash: &[u8]) -> Result<(), OrderError> {
+    match limit_orders::is_order_dead(order_hash, ic_cdk::api::time()) {
+        DeadReason::Alive => Ok(()),
+        DeadReason::Filled => Err(OrderError::OrderAlreadyFilled),
+        DeadReason::Expired => Err(OrderError::OrderExpired),
+        DeadReason::CancelledByHash | DeadReason::CancelledByBitSlot(..) => {
+            Err(OrderError::OrderCancelled)
+        }
+    }
+}
+
 /// Parse extension arguments for cross-chain data
 fn parse_extension_args(args: &[u8]) -> Result<ExtensionData, OrderError> {
     // Parse extension data from args bytes
@@ -157,25 +490,14 @@ async fn fill_cross_chain_order_internal(
     fill_order(order, signature, amount, taker_traits).await
 }
 
-/// Validate order ownership for cancellation
-fn validate_order_ownership(order_hash: &[u8], maker: candid::Principal) -> Result<(), OrderError> {
-    // This would check if the maker owns the order
-    // For now, just return success
-    Ok(())
-}
-
-/// Invalidate order by bit invalidator
+/// Invalidate order by bit invalidator (also validates the maker owns the order)
 fn invalidate_order_by_bit(maker: candid::Principal, order_hash: &[u8]) -> Result<(), OrderError> {
-    // This would use bit invalidator pattern like 1inch LOP
-    // For now, just return success
-    Ok(())
+    limit_orders::cancel_order_by_hash(order_hash, maker, CancellationMethod::Bit)
 }
 
-/// Invalidate order by hash
+/// Invalidate order by hash (also validates the maker owns the order)
 fn invalidate_order_by_hash(maker: candid::Principal, order_hash: &[u8]) -> Result<(), OrderError> {
-    // This would mark the specific order hash as cancelled
-    // For now, just return success
-    Ok(())
+    limit_orders::cancel_order_by_hash(order_hash, maker, CancellationMethod::Hash)
 }
 
 /// Get remaining amount for order
@@ -187,9 +509,7 @@ fn get_remaining_amount(maker: candid::Principal, order_hash: &[u8]) -> u64 {
 
 /// Get invalidation bits for slot
 fn get_invalidation_bits(maker: candid::Principal, slot: u64) -> u64 {
-    // This would return the bit invalidator status for the slot
-    // For now, return 0
-    0
+    memory::get_invalidation_bits(maker, slot)
 }
 
 // Helper types for extension data
@@ -218,16 +538,12 @@ async fn fill_order(
     // 1. Validate order signature (ICP: use principal-based validation)
     validate_order_signature(&order, &signature, taker)?;
     
-    // 2. Validate order state and amounts
-    validate_order_fillable(&order)?;
-    validate_fill_amount(&order, amount)?;
+    // 2. Validate the order is still alive
+    let order_hash = limit_orders::compute_order_hash(&order);
+    validate_order_alive(&order_hash)?;
     
-    // 3. Execute atomic token transfers
-    let (making_amount, taking_amount) = execute_atomic_fill(&order, amount, taker).await?;
-    
-    // 4. Update order state and get hash
-    let order_hash = compute_order_hash(&order);
-    update_order_state(&order_hash, making_amount)?;
+    // 3. Fill the stored order with this hash, validated against the maker's terms
+    let (making_amount, taking_amount) = limit_orders::fill_order(&order_hash, amount, taker).await?;
     
     Ok((making_amount, taking_amount, order_hash))
 }
@@ -254,12 +570,16 @@ async fn fill_order_args(
 }
 
 /// Cancel single order - Core 1inch LOP function
+///
+/// Agents pass the maker they act for as `on_behalf_of`.
 #[ic_cdk::update]
-fn cancel_order(maker_traits: MakerTraits, order_hash: Vec<u8>) -> Result<(), OrderError> {
-    let maker = ic_cdk::caller();
-    
-    // Validate maker owns the order
-    validate_order_ownership(&order_hash, maker)?;
+fn cancel_order(
+    maker_traits: MakerTraits,
+    order_hash: Vec<u8>,
+    on_behalf_of: Option<candid::Principal>,
+) -> Result<(), OrderError> {
+    let (maker, _) =
+        limit_orders::resolve_acting_maker(ic_cdk::caller(), on_behalf_of, AgentAction::Cancel)?;
     
     // Cancel order using appropriate invalidation method
     if maker_traits == MakerTraits::HasExtension {
@@ -276,13 +596,14 @@ fn cancel_order(maker_traits: MakerTraits, order_hash: Vec<u8>) -> Result<(), Or
 fn cancel_orders(
     maker_traits: Vec<MakerTraits>,
     order_hashes: Vec<Vec<u8>>,
+    on_behalf_of: Option<candid::Principal>,
 ) -> Result<(), OrderError> {
     if maker_traits.len() != order_hashes.len() {
         return Err(OrderError::MismatchArraysLengths);
     }
     
     for (traits, hash) in maker_traits.iter().zip(order_hashes.iter()) {
-        cancel_order(traits.clone(), hash.clone())?;
+        cancel_order(traits.clone(), hash.clone(), on_behalf_of)?;
     }
     
     Ok(())
@@ -292,7 +613,7 @@ fn cancel_orders(
 #[ic_cdk::query]
 fn hash_order(order: Order) -> Vec<u8> {
     // ICP adaptation: Use structured hashing instead of EIP-712
-    compute_order_hash(&order)
+    limit_orders::compute_order_hash(&order)
 }
 
 /// Check remaining amount for order - Core 1inch LOP function
@@ -313,16 +634,40 @@ fn bit_invalidator_for_order(
     get_invalidation_bits(maker, slot)
 }
 
+/// Get the cancellation receipt of an order hash - Used by: Off-chain order caches
+#[ic_cdk::query]
+fn get_cancellation_proof(order_hash: Vec<u8>) -> Option<CancellationRecord> {
+    limit_orders::get_cancellation_proof(&order_hash)
+}
+
+/// Check whether an order hash can still be filled, and why not - Used by: Off-chain order caches
+#[ic_cdk::query]
+fn is_order_dead(order_hash: Vec<u8>) -> DeadReason {
+    limit_orders::is_order_dead(&order_hash, ic_cdk::api::time())
+}
+
 // ============================================================================
-// CANISTER UPGRADE HOOKS
+// CANISTER LIFECYCLE & UPGRADE HOOKS
 // ============================================================================
 
+/// Init hook: Start background timers
+#[ic_cdk::init]
+fn init(args: Option<InitArgs>) {
+    // The installer is a controller, so only the mainnet feature can reject the flag
+    let args = args.unwrap_or_default();
+    if let Err(error) = limit_orders::configure_test_mode(args.test_mode, true) {
+        ic_cdk::trap(&error.to_string());
+    }
+    start_alarm_timer();
+}
+
 /// Pre-upgrade hook: Save state to stable memory
 #[ic_cdk::pre_upgrade]
 fn pre_upgrade() {
     let state = memory::serialize_limit_order_state();
+    let extended_state = memory::serialize_extended_state();
     // Save state to stable memory, but don't panic if it fails
-    if let Err(e) = ic_cdk::storage::stable_save((state,)) {
+    if let Err(e) = ic_cdk::storage::stable_save((state, extended_state)) {
         // Log the error but don't panic - this allows the upgrade to proceed
         ic_cdk::print(format!("Warning: Failed to save state during upgrade: {:?}", e));
     }
@@ -332,10 +677,13 @@ fn pre_upgrade() {
 #[ic_cdk::post_upgrade]
 fn post_upgrade() {
     // Try to restore state, but handle the case where no state exists (fresh deployment)
-    match ic_cdk::storage::stable_restore() {
-        Ok((state,)) => {
+    let restored: Result<(_, Option<memory::ExtendedState>), _> =
+        ic_cdk::storage::stable_restore();
+    match restored {
+        Ok((state, extended_state)) => {
             let (orders, filled, cancelled, counter, stats) = state;
             memory::deserialize_limit_order_state(orders, filled, cancelled, counter, stats);
+            memory::deserialize_extended_state(extended_state.unwrap_or_default());
         }
         Err(_) => {
             // No existing state found - this is a fresh deployment
@@ -349,6 +697,10 @@ fn post_upgrade() {
             );
         }
     }
+
+    // Certified data does not survive upgrades
+    certification::certify_active_orders();
+    start_alarm_timer();
 }
 
 ic_cdk::export_candid!();
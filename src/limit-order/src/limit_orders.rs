@@ -1,15 +1,27 @@
 use candid::Principal;
-use ic_cdk::api::time;
 use ic_cdk::caller;
+use std::collections::VecDeque;
 
 use crate::memory::{
-    generate_order_id, get_active_orders, get_order, is_order_active, mark_order_cancelled,
-    mark_order_filled, track_error, track_order_cancelled, track_order_created, track_order_filled,
-    with_cancelled_orders_read, with_filled_orders_read, with_orders,
+    begin_incomplete_fill_resolution, current_time, end_incomplete_fill_resolution,
+    find_fusion_fill_by_escrow, generate_order_id, get_active_orders, get_agent_authorization,
+    get_asset_decimals, get_cancellation_record, get_escrow_manager, get_fusion_fill,
+    get_incomplete_fill, get_invalidation_bits, get_order, get_paused_asset, get_runtime_limits,
+    get_stored_candles, get_stp_group_of, has_fusion_fill_in_progress, has_incomplete_fill,
+    has_order_intent, invalidate_bits, is_order_active, is_test_mode, mark_order_cancelled,
+    mark_order_filled, record_cancellation, record_fill, record_order_intent, remove_fusion_fill,
+    remove_incomplete_fill, set_asset_decimals, set_test_mode, store_fusion_fill,
+    store_incomplete_fill, track_error, track_order_cancelled, track_order_created,
+    track_order_filled, update_candles, with_cancelled_orders_read, with_filled_orders_read,
+    with_orders, with_orders_read,
 };
 use crate::types::{
-    MakerTraits, Order, OrderError, OrderId, OrderResult, OrderType, ProcessingStrategy,
-    SystemStats, TakerTraits, TokenInterface, MAX_ACTIVE_ORDERS, MAX_EXPIRATION_DAYS,
+    AgentAction, CancellationMethod, CancellationRecord, Candle, CandleInterval,
+    CreateOrderOptions, CreateOrderParams, DeadReason, EscrowManagerError, FillLeg, FillRecord,
+    FillSimulation, FusionFill, IncompleteFill, IncompleteFillResolution, IntegratorFee,
+    IntegratorFeePayment, MakerTraits, Order, OrderError, OrderId, OrderMetadata, OrderReference,
+    OrderResult, OrderState, OrderType, PriceInfo, PriceSanity, ProcessingStrategy, SystemStats,
+    TakerTraits, TokenInterface, MAX_INTEGRATOR_FEE_BPS,
 };
 // ============================================================================
 // VALIDATION FUNCTIONS
@@ -80,6 +92,11 @@ pub fn check_taker_fee_balance(_taker: Principal) -> OrderResult<()> {
     Ok(())
 }
 
+/// Protocol fee charged to the taker for a fill (MVP - no fee is charged yet)
+pub fn compute_protocol_fee(_taking_amount: u64) -> u64 {
+    0
+}
+
 /// Validate Principal is not anonymous and properly formatted
 pub fn validate_principal(principal: Principal, field_name: &str) -> OrderResult<()> {
     if principal == Principal::anonymous() {
@@ -93,7 +110,9 @@ pub fn validate_principal(principal: Principal, field_name: &str) -> OrderResult
 
 /// Validate token amounts are within reasonable bounds
 pub fn validate_token_amounts(making_amount: u64, taking_amount: u64) -> OrderResult<()> {
-    // Check for zero amounts
+    let limits = get_runtime_limits();
+
+    // Check for zero and dust amounts
     if making_amount == 0 {
         track_error("invalid_making_amount_zero");
         return Err(OrderError::InvalidAmount);
@@ -104,15 +123,18 @@ pub fn validate_token_amounts(making_amount: u64, taking_amount: u64) -> OrderRe
         return Err(OrderError::InvalidAmount);
     }
 
-    // Check for reasonable maximum amounts (prevent overflow)
-    const MAX_TOKEN_AMOUNT: u64 = u64::MAX / 1000; // Leave room for calculations
+    if making_amount < limits.min_order_amount || taking_amount < limits.min_order_amount {
+        track_error("invalid_amount_below_minimum");
+        return Err(OrderError::InvalidAmount);
+    }
 
-    if making_amount > MAX_TOKEN_AMOUNT {
+    // Check for reasonable maximum amounts (prevent overflow)
+    if making_amount > limits.max_token_amount {
         track_error("invalid_making_amount_too_large");
         return Err(OrderError::InvalidAmount);
     }
 
-    if taking_amount > MAX_TOKEN_AMOUNT {
+    if taking_amount > limits.max_token_amount {
         track_error("invalid_taking_amount_too_large");
         return Err(OrderError::InvalidAmount);
     }
@@ -122,7 +144,8 @@ pub fn validate_token_amounts(making_amount: u64, taking_amount: u64) -> OrderRe
 
 /// Validate expiration timestamp
 pub fn validate_expiration_timestamp(expiration: u64) -> OrderResult<()> {
-    let current_time = time();
+    let current_time = current_time();
+    let limits = get_runtime_limits();
 
     // Check if expiration is in the past
     if expiration <= current_time {
@@ -130,15 +153,15 @@ pub fn validate_expiration_timestamp(expiration: u64) -> OrderResult<()> {
         return Err(OrderError::InvalidExpiration);
     }
 
-    // Check if expiration is too far in the future (30 days max)
-    let max_expiration = current_time + (MAX_EXPIRATION_DAYS * 24 * 3600 * 1_000_000_000);
+    // Check if expiration is too far in the future (30 days max by default)
+    let max_expiration = current_time + (limits.max_expiration_days * 24 * 3600 * 1_000_000_000);
     if expiration > max_expiration {
         track_error("invalid_expiration_too_far");
         return Err(OrderError::InvalidExpiration);
     }
 
-    // Check if expiration is too soon (minimum 1 minute)
-    let min_expiration = current_time + (60 * 1_000_000_000); // 1 minute
+    // Check if expiration is too soon (1 minute minimum by default)
+    let min_expiration = current_time + (limits.min_expiration_secs * 1_000_000_000);
     if expiration < min_expiration {
         track_error("invalid_expiration_too_soon");
         return Err(OrderError::InvalidExpiration);
@@ -147,6 +170,25 @@ pub fn validate_expiration_timestamp(expiration: u64) -> OrderResult<()> {
     Ok(())
 }
 
+/// Validate the optional soft expiry that opens the grace window before hard expiration
+pub fn validate_soft_expiry(soft_expiry_ns: Option<u64>, expiration: u64) -> OrderResult<()> {
+    let Some(soft_expiry_ns) = soft_expiry_ns else {
+        return Ok(());
+    };
+
+    if soft_expiry_ns <= current_time() {
+        track_error("invalid_soft_expiry_past");
+        return Err(OrderError::InvalidExpiration);
+    }
+
+    if soft_expiry_ns >= expiration {
+        track_error("invalid_soft_expiry_after_expiration");
+        return Err(OrderError::InvalidExpiration);
+    }
+
+    Ok(())
+}
+
 /// Validate asset pair for trading
 pub fn validate_asset_pair(maker_asset: Principal, taker_asset: Principal) -> OrderResult<()> {
     // Assets cannot be the same
@@ -159,23 +201,48 @@ pub fn validate_asset_pair(maker_asset: Principal, taker_asset: Principal) -> Or
     validate_principal(maker_asset, "maker_asset")?;
     validate_principal(taker_asset, "taker_asset")?;
 
+    // The management canister is not a ledger
+    if maker_asset == Principal::management_canister()
+        || taker_asset == Principal::management_canister()
+    {
+        track_error("invalid_asset_management_canister");
+        return Err(OrderError::TokenNotSupported(
+            "management canister is not a token".to_string(),
+        ));
+    }
+
     // Additional validation could be added here for supported tokens
     Ok(())
 }
 
+/// Reject trading of an asset pair while either token is paused
+pub fn validate_assets_not_paused(
+    maker_asset: Principal,
+    taker_asset: Principal,
+) -> OrderResult<()> {
+    for asset in [maker_asset, taker_asset] {
+        if let Some(paused) = get_paused_asset(asset) {
+            track_error("asset_paused");
+            return Err(OrderError::AssetPaused(paused.reason));
+        }
+    }
+    Ok(())
+}
+
 /// Check system limits and DoS protection
 pub fn validate_system_limits(caller: Principal) -> OrderResult<()> {
+    let limits = get_runtime_limits();
+
     // Check total number of active orders
-    let active_order_count = get_active_orders().len();
-    if active_order_count >= MAX_ACTIVE_ORDERS {
+    let active_order_count = get_active_orders().len() as u64;
+    if active_order_count >= limits.max_active_orders {
         track_error("system_max_orders_reached");
         return Err(OrderError::TooManyOrders);
     }
 
     // Check orders per maker (prevent spam)
     let maker_orders = get_orders_by_maker(caller);
-    const MAX_ORDERS_PER_MAKER: usize = 100;
-    if maker_orders.len() >= MAX_ORDERS_PER_MAKER {
+    if maker_orders.len() as u64 >= limits.max_orders_per_maker {
         track_error("maker_max_orders_reached");
         return Err(OrderError::TooManyOrders);
     }
@@ -205,6 +272,64 @@ pub fn validate_taker_whitelist(taker: Principal) -> OrderResult<()> {
     }
 }
 
+/// Reject fills between members of the same self-trade prevention group
+///
+/// Makers who set `allow_self_trade` on an order accept fills from their own group.
+pub fn validate_self_trade(order: &Order, taker: Principal) -> OrderResult<()> {
+    if order.allow_self_trade == Some(true) {
+        return Ok(());
+    }
+
+    match (get_stp_group_of(order.maker), get_stp_group_of(taker)) {
+        (Some(maker_group), Some(taker_group)) if maker_group == taker_group => {
+            track_error("fill_self_trade_blocked");
+            Err(OrderError::SelfTradeBlocked(maker_group))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Resolve the maker an action is taken for and the agent taking it, if any
+///
+/// Without `on_behalf_of` the caller acts as maker. Otherwise the caller must hold an unexpired
+/// authorization from that maker whose scope covers the action.
+pub fn resolve_acting_maker(
+    caller: Principal,
+    on_behalf_of: Option<Principal>,
+    action: AgentAction,
+) -> OrderResult<(Principal, Option<Principal>)> {
+    let maker = match on_behalf_of {
+        Some(maker) if maker != caller => maker,
+        _ => return Ok((caller, None)),
+    };
+
+    let Some(authorization) = get_agent_authorization(maker, caller) else {
+        track_error("agent_not_authorized");
+        return Err(OrderError::AgentNotAuthorized);
+    };
+    if authorization.scope.expires_at <= current_time() {
+        track_error("agent_expired");
+        return Err(OrderError::AgentNotAuthorized);
+    }
+
+    let scope = &authorization.scope;
+    match action {
+        AgentAction::Create { .. } if !scope.can_create => {
+            track_error("agent_scope_create");
+            Err(OrderError::AgentScopeExceeded("can_create".to_string()))
+        }
+        AgentAction::Create { making_amount } if making_amount > scope.max_order_size => {
+            track_error("agent_scope_order_size");
+            Err(OrderError::AgentScopeExceeded("max_order_size".to_string()))
+        }
+        AgentAction::Cancel if !scope.can_cancel => {
+            track_error("agent_scope_cancel");
+            Err(OrderError::AgentScopeExceeded("can_cancel".to_string()))
+        }
+        _ => Ok((maker, Some(caller))),
+    }
+}
+
 /// Validate order creation parameters
 pub fn validate_create_order(
     caller: Principal,
@@ -223,6 +348,7 @@ pub fn validate_create_order(
 
     // Validate asset pair
     validate_asset_pair(maker_asset, taker_asset)?;
+    validate_assets_not_paused(maker_asset, taker_asset)?;
 
     // Validate amounts
     validate_token_amounts(making_amount, taking_amount)?;
@@ -240,20 +366,36 @@ pub fn validate_create_order(
 // ORDER MANAGEMENT FUNCTIONS
 // ============================================================================
 
-/// Create a new limit order
+/// Create a new limit order, optionally as an agent on behalf of a maker
+///
+/// Orders with escrow terms are Fusion orders whose fills settle through the escrow_manager.
 pub async fn create_order(
-    receiver: Principal,
-    maker_asset: Principal,
-    taker_asset: Principal,
-    making_amount: u64,
-    taking_amount: u64,
-    expiration: u64,
+    params: CreateOrderParams,
+    options: CreateOrderOptions,
+    caller: Principal,
 ) -> OrderResult<OrderId> {
-    let caller = caller();
+    let CreateOrderParams {
+        receiver,
+        maker_asset,
+        taker_asset,
+        making_amount,
+        taking_amount,
+        expiration,
+    } = params;
+    let CreateOrderOptions {
+        soft_expiry_ns,
+        allow_self_trade,
+        on_behalf_of,
+        integrator_fee,
+        accept_price_warning,
+        escrow_terms,
+    } = options;
+    let (maker, agent) =
+        resolve_acting_maker(caller, on_behalf_of, AgentAction::Create { making_amount })?;
 
     // Validate order creation
     validate_create_order(
-        caller,
+        maker,
         receiver,
         maker_asset,
         taker_asset,
@@ -261,13 +403,33 @@ pub async fn create_order(
         taking_amount,
         expiration,
     )?;
+    validate_soft_expiry(soft_expiry_ns, expiration)?;
+    validate_integrator_fee(integrator_fee.as_ref())?;
+    let processing_strategy = match &escrow_terms {
+        Some(terms) => {
+            validate_escrow_terms(terms, expiration)?;
+            ProcessingStrategy::EscrowCoordination
+        }
+        None => ProcessingStrategy::DirectTransfer,
+    };
 
-    // Check maker has sufficient balance (skip in test mode with mock tokens)
-    if maker_asset != Principal::management_canister()
-        && taker_asset != Principal::management_canister()
-    {
-        check_maker_balance(maker_asset, caller, making_amount).await?;
+    // Check maker has sufficient balance and learn the asset decimals (skipped in test mode)
+    if !is_test_mode() {
+        check_maker_balance(maker_asset, maker, making_amount).await?;
+        for asset in [maker_asset, taker_asset] {
+            if get_asset_decimals(asset).is_none() {
+                // Unknown decimals only leave the order's price unadjusted
+                let _ = refresh_asset_decimals(asset).await;
+            }
+        }
     }
+    validate_price_sanity(
+        maker_asset,
+        taker_asset,
+        making_amount,
+        taking_amount,
+        accept_price_warning.unwrap_or(false),
+    )?;
 
     // Generate unique order ID
     let order_id = generate_order_id();
@@ -275,26 +437,31 @@ pub async fn create_order(
     // Create order
     let order = Order {
         id: order_id,
-        maker: caller,
+        maker,
         receiver,
         maker_asset,
         taker_asset,
         making_amount,
         taking_amount,
         expiration,
-        created_at: time(),
+        soft_expiry_ns,
+        allow_self_trade,
+        agent,
+        integrator_fee,
+        created_at: current_time(),
         order_type: OrderType::Normal, // Default to normal order for MVP
-        processing_strategy: ProcessingStrategy::DirectTransfer, // Default to direct transfer
-        salt: order_id,                // Use order_id as salt for uniqueness
+        processing_strategy,
+        salt: order_id,                  // Use order_id as salt for uniqueness
         maker_traits: MakerTraits::None, // Default to no special traits
         taker_traits: TakerTraits::None, // Default to no special traits
-        metadata: None,                // MVP doesn't use metadata, reserved for ChainFusion+
+        metadata: escrow_terms,
     };
 
     // Store order
     with_orders(|orders| {
         orders.insert(order_id, order);
     });
+    crate::certification::certify_active_orders();
 
     // Track statistics
     track_order_created();
@@ -308,9 +475,33 @@ pub async fn create_order(
 /// - Thorough validation and authorization checks
 /// - Detailed error reporting for different failure scenarios
 /// - Proper state management and statistics tracking
-pub fn cancel_order(order_id: OrderId) -> OrderResult<()> {
-    let caller = caller();
+///
+/// Cancellation stays available while an asset is paused so makers can exit.
+pub fn cancel_order(order_id: OrderId, caller: Principal) -> OrderResult<()> {
+    cancel_order_with_method(order_id, caller, CancellationMethod::Id)
+}
+
+/// Cancel the stored order with the given hash, as 1inch cancel_order does
+///
+/// With `Bit`, the bit of the order's salt is also set in the maker's invalidator slot.
+pub fn cancel_order_by_hash(
+    order_hash: &[u8],
+    caller: Principal,
+    method: CancellationMethod,
+) -> OrderResult<()> {
+    let order = find_order_by_hash(order_hash).ok_or_else(|| {
+        track_error("cancel_order_hash_not_found");
+        OrderError::OrderNotFound
+    })?;
+    cancel_order_with_method(order.id, caller, method)
+}
 
+/// Cancel an order and record a cancellation receipt for its hash
+fn cancel_order_with_method(
+    order_id: OrderId,
+    caller: Principal,
+    method: CancellationMethod,
+) -> OrderResult<()> {
     // Phase 1: Order retrieval and basic validation
     let order = get_order(order_id).ok_or_else(|| {
         track_error("cancel_order_not_found");
@@ -323,7 +514,7 @@ pub fn cancel_order(order_id: OrderId) -> OrderResult<()> {
     // Phase 3: Order state validation with detailed error reporting
     if !is_order_active(order_id) {
         // Check specific reason for inactivity
-        if order.expiration <= time() {
+        if order.expiration <= current_time() {
             track_error("cancel_expired_order");
             return Err(OrderError::OrderExpired);
         }
@@ -349,10 +540,55 @@ pub fn cancel_order(order_id: OrderId) -> OrderResult<()> {
 
     // Phase 4: Execute cancellation
     update_order_cancelled_state(order_id);
+    if method == CancellationMethod::Bit {
+        let (slot, bit) = bit_invalidator_position(order.salt);
+        invalidate_bits(order.maker, slot, bit);
+    }
+
+    // Phase 5: Record the receipt
+    record_cancellation(CancellationRecord {
+        order_hash: compute_order_hash(&order),
+        maker: caller,
+        cancelled_at: current_time(),
+        method,
+    });
 
     Ok(())
 }
 
+/// Compute order hash (ICP adaptation)
+pub fn compute_order_hash(order: &Order) -> Vec<u8> {
+    // ICP adaptation: Use structured hashing instead of EIP-712
+    use sha2::{Digest, Sha256};
+    let mut hasher = Sha256::new();
+
+    // Hash order fields
+    hasher.update(order.salt.to_be_bytes());
+    hasher.update(order.maker.as_slice());
+    hasher.update(order.receiver.as_slice());
+    hasher.update(order.maker_asset.as_slice());
+    hasher.update(order.taker_asset.as_slice());
+    hasher.update(order.making_amount.to_be_bytes());
+    hasher.update(order.taking_amount.to_be_bytes());
+    hasher.update(order.expiration.to_be_bytes());
+
+    hasher.finalize().to_vec()
+}
+
+/// Find the stored order with the given hash
+///
+/// Scans all orders; orders are never removed, so every recorded hash stays resolvable.
+pub fn find_order_by_hash(order_hash: &[u8]) -> Option<Order> {
+    with_orders_read(|orders| {
+        orders.values().find(|order| compute_order_hash(order) == order_hash).cloned()
+    })
+}
+
+/// Bit invalidator slot and bit mask of an order salt (64 bits per slot, as in 1inch LOP)
+pub fn bit_invalidator_position(salt: u64) -> (u64, u64) {
+    (salt >> 6, 1 << (salt & 63))
+}
+
 /// Helper function: Execute atomic token transfers for order filling
 ///
 /// This is a helper function used by fill_order() to perform the actual token swaps.
@@ -361,15 +597,234 @@ pub fn cancel_order(order_id: OrderId) -> OrderResult<()> {
 /// 2. Execute both transfers
 /// 3. If any transfer fails, attempt rollback (best effort)
 
-/// Fill an existing order atomically
+/// Fill the stored order with the given hash, returning the (making, taking) amounts
 ///
 /// This function implements a comprehensive order filling process with:
-/// - Thorough validation before execution
-/// - Atomic token transfers with rollback capability
+/// - Thorough validation of the stored order, never the caller's copy of it
+/// - Atomic token transfers with rollback capability, or settlement through an escrow
 /// - Proper state management and statistics tracking
-pub async fn fill_order(order_id: OrderId) -> OrderResult<()> {
-    let taker = caller();
+///
+/// Orders are filled whole, so `amount` must be the order's full taking amount.
+pub async fn fill_order(
+    order_hash: &[u8],
+    amount: u64,
+    taker: Principal,
+) -> OrderResult<(u64, u64)> {
+    let order = find_order_by_hash(order_hash).ok_or_else(|| {
+        track_error("fill_order_not_found");
+        OrderError::OrderNotFound
+    })?;
+
+    execute_fill(order.id, amount, taker).await
+}
+
+/// Fill an order on behalf of a taker, returning the (making, taking) amounts
+async fn execute_fill(order_id: OrderId, amount: u64, taker: Principal) -> OrderResult<(u64, u64)> {
+    // Phases 1-4b: Whitelist, trading halt, authorization, state and grace window validation
+    let order = validate_fill(order_id, taker)?;
+    let (making_amount, taking_amount) = whole_fill_amounts(&order, amount)?;
+
+    // Phase 5: Balance validation (skipped in test mode)
+    let order = if is_test_mode() {
+        order
+    } else {
+        check_taker_balance(order.taker_asset, taker, taking_amount).await?;
+        // Another fill may have taken the order while the balance check awaited
+        validate_fill(order_id, taker)?
+    };
+
+    // Phase 5b: Fusion orders settle through an escrow instead of direct transfers
+    if is_escrow_fill(&order) {
+        start_escrow_fill(&order, taker, making_amount, taking_amount).await?;
+        return Ok((making_amount, taking_amount));
+    }
+
+    // Phase 6: Execute atomic transfers
+    let transfer_result =
+        execute_order_transfers(&order, taker, making_amount, taking_amount).await;
+
+    // Phase 7: Update state, statistics and fill history (only after successful transfers)
+    complete_order_fill(&order, taker, transfer_result)?;
+    Ok((making_amount, taking_amount))
+}
+
+/// Whether fills of the order settle through an escrow rather than direct transfers
+fn is_escrow_fill(order: &Order) -> bool {
+    matches!(order.processing_strategy, ProcessingStrategy::EscrowCoordination)
+}
+
+/// Hand the fill of an escrow-coordinated order to the escrow_manager
+///
+/// The order is Pending while the escrow is created and EscrowCreated until the escrow_manager
+/// reports completion through `complete_fusion_fill`. A failed creation reopens the order.
+async fn start_escrow_fill(
+    order: &Order,
+    taker: Principal,
+    making_amount: u64,
+    taking_amount: u64,
+) -> OrderResult<()> {
+    let escrow_manager = get_escrow_manager().ok_or_else(|| {
+        track_error("fusion_fill_without_escrow_manager");
+        OrderError::EscrowManagerUnavailable
+    })?;
+    let (hashlock, timelock) = fusion_escrow_terms(order)?;
+
+    store_fusion_fill(FusionFill {
+        order_id: order.id,
+        taker,
+        making_amount,
+        taking_amount,
+        escrow_id: None,
+        state: OrderState::Pending,
+        started_at: current_time(),
+    });
+    crate::certification::certify_active_orders();
 
+    let escrow =
+        create_fill_escrow(escrow_manager, order, taker, hashlock, timelock, making_amount).await;
+    finish_escrow_creation(order.id, escrow)
+}
+
+/// Shortest escrow timelock the escrow_manager accepts, counted from escrow creation
+pub const ESCROW_MIN_TIMELOCK_NS: u64 = 10 * 60 * 1_000_000_000;
+
+/// Validate the escrow terms of a new Fusion order expiring at `expiration`
+///
+/// Mirrors the escrow_manager's checks, so an accepted order can actually be filled: a positive
+/// safety deposit and a timelock more than `ESCROW_MIN_TIMELOCK_NS` away.
+fn validate_escrow_terms(terms: &OrderMetadata, expiration: u64) -> OrderResult<()> {
+    if terms.hashlock.as_ref().is_none_or(|hashlock| hashlock.len() != 32) {
+        track_error("invalid_escrow_hashlock");
+        return Err(OrderError::InvalidHashlock);
+    }
+
+    if terms.safety_deposit.unwrap_or(0) == 0 {
+        track_error("invalid_escrow_safety_deposit");
+        return Err(OrderError::InvalidConfiguration(
+            "Escrow safety deposit must be positive".to_string(),
+        ));
+    }
+
+    let min_timelock = current_time().saturating_add(ESCROW_MIN_TIMELOCK_NS);
+    match terms.timelock {
+        Some(timelock) if timelock > min_timelock && timelock <= expiration => Ok(()),
+        _ => {
+            track_error("invalid_escrow_timelock");
+            Err(OrderError::InvalidConfiguration(
+                "Escrow timelock must fall between 10 minutes from now and the order expiration"
+                    .to_string(),
+            ))
+        }
+    }
+}
+
+/// Hex hashlock and timelock of a Fusion order, from its metadata
+fn fusion_escrow_terms(order: &Order) -> OrderResult<(String, u64)> {
+    let metadata = order.metadata.as_ref();
+    let hashlock = metadata.and_then(|metadata| metadata.hashlock.as_ref()).ok_or_else(|| {
+        track_error("fusion_fill_without_hashlock");
+        OrderError::HashlockNotFound
+    })?;
+    let timelock = metadata.and_then(|metadata| metadata.timelock).ok_or_else(|| {
+        track_error("fusion_fill_without_timelock");
+        OrderError::InvalidConfiguration("Fusion order has no timelock".to_string())
+    })?;
+
+    Ok((hashlock.iter().map(|b| format!("{:02x}", b)).collect(), timelock))
+}
+
+/// Create the ICP escrow of a fill on the escrow_manager, returning its id
+async fn create_fill_escrow(
+    escrow_manager: Principal,
+    order: &Order,
+    taker: Principal,
+    hashlock: String,
+    timelock: u64,
+    making_amount: u64,
+) -> OrderResult<String> {
+    let order_hash: String =
+        compute_order_hash(order).iter().map(|b| format!("{:02x}", b)).collect();
+
+    // Test mode: the escrow_manager names escrows by order hash, so simulate that
+    if is_test_mode() {
+        return Ok(order_hash);
+    }
+
+    let metadata = order.metadata.as_ref();
+    let result: Result<(Result<String, EscrowManagerError>,), _> = ic_cdk::call(
+        escrow_manager,
+        "create_icp_escrow",
+        (
+            order_hash,
+            hashlock,
+            order.maker.to_text(),
+            taker.to_text(),
+            order.maker_asset.to_text(),
+            making_amount,
+            metadata.and_then(|metadata| metadata.safety_deposit).unwrap_or(0),
+            timelock,
+            0u64, // ICP
+            metadata.and_then(|metadata| metadata.chain_id).unwrap_or(0) as u64,
+            order.maker_asset.to_text(),
+            order.taker_asset.to_text(),
+            order.making_amount,
+            order.taking_amount,
+        ),
+    )
+    .await;
+
+    match result {
+        Ok((Ok(escrow_id),)) => Ok(escrow_id),
+        Ok((Err(e),)) => Err(OrderError::EscrowCreationFailed(format!("{:?}", e))),
+        Err((code, message)) => {
+            Err(OrderError::CrossCanisterCallFailed(format!("{:?}: {}", code, message)))
+        }
+    }
+}
+
+/// Record the outcome of an escrow creation on the order's Fusion fill
+fn finish_escrow_creation(order_id: OrderId, escrow: OrderResult<String>) -> OrderResult<()> {
+    let mut fill = get_fusion_fill(order_id).ok_or(OrderError::EscrowNotFound)?;
+    let result = match escrow {
+        Ok(escrow_id) => {
+            fill.escrow_id = Some(escrow_id);
+            fill.state = OrderState::EscrowCreated;
+            Ok(())
+        }
+        Err(e) => {
+            track_error("fusion_escrow_creation_failed");
+            fill.state = OrderState::Failed;
+            crate::certification::certify_active_orders();
+            Err(e)
+        }
+    };
+    store_fusion_fill(fill);
+    result
+}
+
+/// Finish the Fusion fill settled by an escrow once it was claimed - callable by the escrow_manager only
+pub fn complete_fusion_fill(escrow_id: &str, caller: Principal) -> OrderResult<()> {
+    if get_escrow_manager() != Some(caller) {
+        track_error("unauthorized_fusion_completion");
+        return Err(OrderError::Unauthorized);
+    }
+
+    let fill = find_fusion_fill_by_escrow(escrow_id).ok_or(OrderError::EscrowNotFound)?;
+    if fill.state != OrderState::EscrowCreated {
+        track_error("fusion_completion_without_escrow");
+        return Err(OrderError::OrderInactive);
+    }
+    let order = get_order(fill.order_id).ok_or(OrderError::OrderNotFound)?;
+
+    remove_fusion_fill(fill.order_id);
+    // The escrow moved the tokens, so this canister holds no ledger block indices for them
+    complete_order_fill(&order, fill.taker, Ok(((0, 0), None)))
+}
+
+/// Validate that a taker may fill an order right now, returning the order
+///
+/// Shared by fill execution and simulation so both reject fills for the same reasons.
+pub fn validate_fill(order_id: OrderId, taker: Principal) -> OrderResult<Order> {
     // Phase 1: Taker whitelist validation
     validate_taker_whitelist(taker)?;
 
@@ -379,16 +834,20 @@ pub async fn fill_order(order_id: OrderId) -> OrderResult<()> {
         OrderError::OrderNotFound
     })?;
 
+    // Phase 2b: Trading halt validation
+    validate_assets_not_paused(order.maker_asset, order.taker_asset)?;
+
     // Phase 3: Authorization validation
     if taker == order.maker {
         track_error("fill_own_order");
         return Err(OrderError::Unauthorized);
     }
+    validate_self_trade(&order, taker)?;
 
     // Phase 4: Order state validation
     if !is_order_active(order_id) {
         // Determine specific reason for better error reporting
-        if order.expiration <= time() {
+        if order.expiration <= current_time() {
             track_error("fill_expired_order");
             return Err(OrderError::OrderExpired);
         } else {
@@ -409,158 +868,919 @@ pub async fn fill_order(order_id: OrderId) -> OrderResult<()> {
                 Ok(())
             })?;
 
+            if has_fusion_fill_in_progress(order_id) {
+                track_error("fill_fusion_order_in_progress");
+                return Err(OrderError::OrderInactive);
+            }
+
+            if has_incomplete_fill(order_id) {
+                track_error("fill_order_with_incomplete_fill");
+                return Err(OrderError::OrderInactive);
+            }
+
             // If we reach here, something unexpected happened
             track_error("fill_inactive_order_unknown");
             return Err(OrderError::OrderAlreadyFilled);
         }
     }
 
-    // Phase 5: Balance validation (skip in test mode with mock tokens)
-    if order.taker_asset != Principal::management_canister() {
-        check_taker_balance(order.taker_asset, taker, order.taking_amount).await?;
-    }
-
-    // Phase 6: Execute atomic transfers
-    execute_order_transfers(&order, taker).await?;
-
-    // Phase 7: Update state and statistics (only after successful transfers)
-    update_order_filled_state(order_id, &order);
+    // Phase 4b: Grace window validation (only negotiating takers past the soft expiry)
+    validate_fill_window(&order, taker)?;
 
-    Ok(())
+    Ok(order)
 }
 
-/// Helper function: Execute atomic token transfers for order filling
+/// Compute the (making, taking) amounts of a fill, which must pay the whole taking amount
 ///
-/// This is a helper function used by fill_order() to perform the actual token swaps.
-/// It implements a two-phase commit pattern for better atomicity:
-/// 1. Pre-validate both transfers can succeed
-/// 2. Execute both transfers
-/// 3. If any transfer fails, attempt rollback (best effort)
-async fn execute_order_transfers(order: &Order, taker: Principal) -> OrderResult<()> {
-    // Skip actual transfers in test mode with mock tokens (management canister)
-    if order.maker_asset == Principal::management_canister()
-        || order.taker_asset == Principal::management_canister()
-    {
-        // Test mode: simulate successful transfers without actual ICRC calls
-        return Ok(());
+/// Shared by fill execution and simulation. Orders are filled whole, so a fill of any other
+/// amount is rejected with InvalidAmount.
+pub fn whole_fill_amounts(order: &Order, amount: u64) -> OrderResult<(u64, u64)> {
+    if amount != order.taking_amount {
+        track_error("fill_partial_amount");
+        return Err(OrderError::InvalidAmount);
     }
+    compute_fill_amounts(order, amount)
+}
 
-    let maker_token = TokenInterface::new(order.maker_asset);
-    let taker_token = TokenInterface::new(order.taker_asset);
-
-    // Phase 1: Pre-validation - Check balances again to minimize failure risk
-    let taker_balance = taker_token.balance_of(taker).await?;
-    if taker_balance < order.taking_amount {
-        track_error("taker_insufficient_balance_at_fill");
-        return Err(OrderError::InsufficientBalance);
+/// Compute the (making, taking) amounts of a fill paying `amount` of the taker asset
+///
+/// The maker amount is proportional to the order's rate and rounded down in the maker's favour.
+pub fn compute_fill_amounts(order: &Order, amount: u64) -> OrderResult<(u64, u64)> {
+    if amount == 0 || order.taking_amount == 0 {
+        return Err(OrderError::InvalidAmount);
     }
 
-    let maker_balance = maker_token.balance_of(order.maker).await?;
-    if maker_balance < order.making_amount {
-        track_error("maker_insufficient_balance_at_fill");
-        return Err(OrderError::InsufficientBalance);
+    if amount > order.taking_amount {
+        return Err(OrderError::InsufficientAmount);
     }
 
-    // Phase 2: Execute transfers with rollback capability
-    // Transfer 1: Taker asset from taker to receiver
-    let taker_transfer_result =
-        taker_token.transfer(taker, order.receiver, order.taking_amount).await;
+    let making_amount =
+        (order.making_amount as u128 * amount as u128 / order.taking_amount as u128) as u64;
+    Ok((making_amount, amount))
+}
 
-    let taker_block_index = match taker_transfer_result {
-        Ok(block_index) => block_index,
-        Err(e) => {
-            track_error("taker_transfer_failed");
-            return Err(e);
-        }
-    };
+/// Check both parties hold the amounts a fill transfers
+pub async fn check_fill_balances(
+    order: &Order,
+    taker: Principal,
+    making_amount: u64,
+    taking_amount: u64,
+) -> OrderResult<()> {
+    check_taker_balance(order.taker_asset, taker, taking_amount).await?;
+    check_maker_balance(order.maker_asset, order.maker, making_amount).await
+}
 
-    // Transfer 2: Maker asset from maker to taker
-    let maker_transfer_result = maker_token.transfer(order.maker, taker, order.making_amount).await;
+/// Register the caller as negotiating an order so they may still fill it during the grace window
+pub fn express_intent(order_id: OrderId) -> OrderResult<()> {
+    register_order_intent(order_id, caller())
+}
 
-    match maker_transfer_result {
-        Ok(_maker_block_index) => {
-            // Both transfers successful
-            Ok(())
-        }
-        Err(e) => {
-            track_error("maker_transfer_failed");
-
-            // Attempt rollback of taker transfer (best effort)
-            // Note: This is not guaranteed to succeed due to ICRC-1 limitations
-            // In production, consider using ICRC-2 with allowances for better atomicity
-            let rollback_result =
-                taker_token.transfer(order.receiver, taker, order.taking_amount).await;
-
-            match rollback_result {
-                Ok(_) => {
-                    track_error("transfer_rolled_back_successfully");
-                    // Return original error
-                    Err(e)
-                }
-                Err(rollback_error) => {
-                    track_error("rollback_failed_critical");
-                    // Critical: Rollback failed, system may be in inconsistent state
-                    // Log both errors for manual intervention
-                    Err(OrderError::SystemError(format!(
-                        "Transfer failed and rollback failed. Original: {:?}, Rollback: {:?}, TakerBlockIndex: {}",
-                        e, rollback_error, taker_block_index
-                    )))
-                }
-            }
-        }
+/// Register a taker's intent while the order is still open to new takers
+fn register_order_intent(order_id: OrderId, taker: Principal) -> OrderResult<()> {
+    validate_principal(taker, "taker")?;
+
+    let order = get_order(order_id).ok_or_else(|| {
+        track_error("intent_order_not_found");
+        OrderError::OrderNotFound
+    })?;
+
+    if taker == order.maker {
+        track_error("intent_own_order");
+        return Err(OrderError::Unauthorized);
     }
-}
 
-/// Update order state and statistics after successful fill
-/// This function ensures atomic state updates
-fn update_order_filled_state(order_id: OrderId, order: &Order) {
-    // Mark order as filled
-    mark_order_filled(order_id);
+    if !is_order_active(order_id) {
+        track_error("intent_inactive_order");
+        return Err(OrderError::OrderInactive);
+    }
 
-    // Track statistics for both assets
-    track_order_filled(order.maker_asset, order.making_amount);
-    track_order_filled(order.taker_asset, order.taking_amount);
-}
+    if is_in_grace_period(&order, current_time()) {
+        track_error("intent_during_grace_period");
+        return Err(OrderError::OrderInGracePeriod);
+    }
 
-/// Update order state and statistics after successful cancellation
-/// This function ensures atomic state updates
-fn update_order_cancelled_state(order_id: OrderId) {
-    // Mark order as cancelled
-    mark_order_cancelled(order_id);
+    record_order_intent(order_id, taker);
+    Ok(())
+}
 
-    // Track statistics
-    track_order_cancelled();
+/// Check whether an order is past its soft expiry but not yet hard expired
+pub fn is_in_grace_period(order: &Order, now: u64) -> bool {
+    order.soft_expiry_ns.is_some_and(|soft_expiry| soft_expiry <= now) && now < order.expiration
 }
 
-/// Batch cancel multiple orders by the same maker
+/// Validate a taker may fill the order at the current time
+///
+/// Anyone may fill before the soft expiry, only takers who expressed intent beforehand may fill
+/// during the grace window, and nobody may fill after hard expiration.
+pub fn validate_fill_window(order: &Order, taker: Principal) -> OrderResult<()> {
+    let now = current_time();
+
+    if order.expiration <= now {
+        track_error("fill_expired_order");
+        return Err(OrderError::OrderExpired);
+    }
 
-// ============================================================================
-// QUERY FUNCTIONS
-// ============================================================================
+    if is_in_grace_period(order, now) && !has_order_intent(order.id, taker) {
+        track_error("fill_grace_period_without_intent");
+        return Err(OrderError::OrderInGracePeriod);
+    }
 
-/// Get all active orders
-pub fn get_active_orders_list() -> Vec<Order> {
-    get_active_orders()
+    Ok(())
 }
 
-/// Get a specific order by ID
-pub fn get_order_by_id(order_id: OrderId) -> Option<Order> {
-    get_order(order_id)
-}
+/// Ledger blocks of a settled fill: (taker asset, maker asset) transfers plus the fee leg
+pub type SettledTransfers = ((u64, u64), Option<IntegratorFeePayment>);
 
-/// Get orders by maker
+/// Helper function: Finalize a fill once the token transfers have completed
+///
+/// Failed or rolled back transfers leave the order state and fill history untouched.
+fn complete_order_fill(
+    order: &Order,
+    taker: Principal,
+    transfer_result: OrderResult<SettledTransfers>,
+) -> OrderResult<()> {
+    let (block_indices, integrator_fee) = transfer_result?;
+
+    update_order_filled_state(order.id, order);
+    record_fill(
+        order.maker,
+        FillRecord {
+            order_id: order.id,
+            taker,
+            making_amount: order.making_amount,
+            taking_amount: order.taking_amount,
+            block_indices,
+            timestamp: current_time(),
+            integrator_fee,
+        },
+    );
+
+    Ok(())
+}
+
+/// Validate the integrator fee attached to a new order
+pub fn validate_integrator_fee(integrator_fee: Option<&IntegratorFee>) -> OrderResult<()> {
+    let Some(fee) = integrator_fee else {
+        return Ok(());
+    };
+
+    validate_principal(fee.recipient, "integrator_fee_recipient")?;
+    if fee.bps > MAX_INTEGRATOR_FEE_BPS {
+        track_error("integrator_fee_too_high");
+        return Err(OrderError::IntegratorFeeTooHigh(MAX_INTEGRATOR_FEE_BPS));
+    }
+
+    Ok(())
+}
+
+/// Reject an order priced far from the pair's recent fills, unless the maker accepts the warning
+pub fn validate_price_sanity(
+    maker_asset: Principal,
+    taker_asset: Principal,
+    making_amount: u64,
+    taking_amount: u64,
+    accept_price_warning: bool,
+) -> OrderResult<()> {
+    if accept_price_warning {
+        return Ok(());
+    }
+
+    let sanity = get_price_sanity(maker_asset, taker_asset, making_amount, taking_amount);
+    match sanity.reference_price {
+        Some(reference_price) if sanity.exceeds_limit => {
+            track_error("price_deviation_warning");
+            Err(OrderError::PriceDeviationWarning(
+                sanity.order_price.display,
+                reference_price.display,
+            ))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Part of a fill's taking amount paid to the order's integrator, rounded down
+pub fn compute_integrator_fee(order: &Order, taking_amount: u64) -> u64 {
+    order
+        .integrator_fee
+        .as_ref()
+        .map_or(0, |fee| (taking_amount as u128 * fee.bps as u128 / 10_000) as u64)
+}
+
+/// Parties and amounts of one direct fill, shared by its transfer legs
+struct FillTransfer<'a> {
+    order: &'a Order,
+    taker: Principal,
+    making_amount: u64,
+    taking_amount: u64,
+}
+
+impl FillTransfer<'_> {
+    /// Legs in execution order; the fee leg only exists when the fee is not zero
+    fn legs(&self) -> Vec<FillLeg> {
+        let mut legs = vec![FillLeg::TakerToReceiver];
+        if self.integrator_fee() > 0 {
+            legs.push(FillLeg::TakerToIntegrator);
+        }
+        legs.push(FillLeg::MakerToTaker);
+        legs
+    }
+
+    fn integrator_fee(&self) -> u64 {
+        compute_integrator_fee(self.order, self.taking_amount)
+    }
+
+    /// Execute one leg, or reverse it, returning the ledger block index
+    async fn execute(&self, leg: FillLeg, reverse: bool) -> OrderResult<u64> {
+        // Test mode: simulate the transfer without an actual ICRC call
+        if is_test_mode() {
+            return simulated_leg_transfer(leg, reverse);
+        }
+
+        let (token, from, to, amount) = match leg {
+            FillLeg::TakerToReceiver => (
+                self.order.taker_asset,
+                self.taker,
+                self.order.receiver,
+                self.taking_amount - self.integrator_fee(),
+            ),
+            FillLeg::TakerToIntegrator => {
+                let recipient = self.order.integrator_fee.as_ref().map(|fee| fee.recipient);
+                let recipient = recipient.ok_or_else(|| {
+                    OrderError::InvalidConfiguration("Order has no integrator fee".to_string())
+                })?;
+                (self.order.taker_asset, self.taker, recipient, self.integrator_fee())
+            }
+            FillLeg::MakerToTaker => {
+                (self.order.maker_asset, self.order.maker, self.taker, self.making_amount)
+            }
+        };
+        let (from, to) = if reverse { (to, from) } else { (from, to) };
+        TokenInterface::new(token).transfer(from, to, amount).await
+    }
+
+    /// Block indices of the settled legs, zero for legs settled without a known block
+    fn settlement(&self, settled: &[(FillLeg, u64)]) -> SettledTransfers {
+        let block_of = |wanted: FillLeg| {
+            settled.iter().find(|(leg, _)| *leg == wanted).map(|(_, block_index)| *block_index)
+        };
+        let integrator_fee = block_of(FillLeg::TakerToIntegrator).and_then(|block_index| {
+            self.order.integrator_fee.as_ref().map(|fee| IntegratorFeePayment {
+                recipient: fee.recipient,
+                amount: self.integrator_fee(),
+                block_index,
+            })
+        });
+        let block_indices = (
+            block_of(FillLeg::TakerToReceiver).unwrap_or(0),
+            block_of(FillLeg::MakerToTaker).unwrap_or(0),
+        );
+        (block_indices, integrator_fee)
+    }
+
+    /// Record of this fill stuck with `completed_leg` and the legs before it settled
+    fn incomplete(&self, completed: (FillLeg, u64), pending_leg: FillLeg) -> IncompleteFill {
+        IncompleteFill {
+            order_id: self.order.id,
+            taker: self.taker,
+            completed_leg: completed.0,
+            pending_leg,
+            block_index: completed.1,
+            making_amount: self.making_amount,
+            taking_amount: self.taking_amount,
+            timestamp: current_time(),
+        }
+    }
+}
+
+/// Test mode transfer: succeeds, except where a unit test asked it to fail
+#[cfg(not(test))]
+fn simulated_leg_transfer(_leg: FillLeg, _reverse: bool) -> OrderResult<u64> {
+    Ok(0)
+}
+
+#[cfg(test)]
+fn simulated_leg_transfer(leg: FillLeg, reverse: bool) -> OrderResult<u64> {
+    tests::mock_leg_transfer(leg, reverse)
+}
+
+/// Helper function: Execute atomic token transfers for order filling
+///
+/// This is a helper function used by fill_order() to perform the actual token swaps.
+/// It implements a two-phase commit pattern for better atomicity:
+/// 1. Pre-validate both parties hold the amounts
+/// 2. Execute the legs in order: receiver, integrator fee (if any), maker
+/// 3. If any transfer fails, roll back the settled legs newest first (best effort)
+async fn execute_order_transfers(
+    order: &Order,
+    taker: Principal,
+    making_amount: u64,
+    taking_amount: u64,
+) -> OrderResult<SettledTransfers> {
+    let transfer = FillTransfer { order, taker, making_amount, taking_amount };
+
+    // Phase 1: Pre-validation - Check balances again to minimize failure risk
+    if !is_test_mode() {
+        check_fill_balances(order, taker, making_amount, taking_amount).await?;
+    }
+
+    // Phase 2: Execute transfers with rollback capability
+    let mut settled = Vec::new();
+    for leg in transfer.legs() {
+        match transfer.execute(leg, false).await {
+            Ok(block_index) => settled.push((leg, block_index)),
+            Err(e) => {
+                track_error(match leg {
+                    FillLeg::TakerToReceiver => "taker_transfer_failed",
+                    FillLeg::TakerToIntegrator => "integrator_fee_transfer_failed",
+                    FillLeg::MakerToTaker => "maker_transfer_failed",
+                });
+                return Err(roll_back_fill_legs(&transfer, settled, leg, e).await);
+            }
+        }
+    }
+
+    Ok(transfer.settlement(&settled))
+}
+
+/// Reverse the settled legs of a fill newest first after `failed_leg` failed
+///
+/// Note: This is not guaranteed to succeed due to ICRC-1 limitations. A reversal that fails
+/// leaves its leg and the ones before it settled, recorded as an incomplete fill.
+async fn roll_back_fill_legs(
+    transfer: &FillTransfer<'_>,
+    mut settled: Vec<(FillLeg, u64)>,
+    failed_leg: FillLeg,
+    error: OrderError,
+) -> OrderError {
+    let rolled_back = !settled.is_empty();
+    while let Some((leg, block_index)) = settled.pop() {
+        if let Err(rollback_error) = transfer.execute(leg, true).await {
+            track_error("rollback_failed_critical");
+            // Critical: Rollback failed, system may be in inconsistent state
+            // Record it so operators can resolve it manually
+            let fill = transfer.incomplete((leg, block_index), failed_leg);
+            return record_incomplete_fill(fill, error, rollback_error);
+        }
+    }
+
+    if rolled_back {
+        track_error("transfer_rolled_back_successfully");
+    }
+    error
+}
+
+/// Record a fill stuck after a failed rollback and build the error reporting it
+fn record_incomplete_fill(
+    fill: IncompleteFill,
+    error: OrderError,
+    rollback_error: OrderError,
+) -> OrderError {
+    let message = format!(
+        "Transfer failed and rollback failed, recorded as incomplete fill {}. Original: {:?}, Rollback: {:?}, SettledBlockIndex: {}",
+        fill.order_id, error, rollback_error, fill.block_index
+    );
+    store_incomplete_fill(fill);
+    crate::certification::certify_active_orders();
+    OrderError::SystemError(message)
+}
+
+/// Settle an incomplete fill the chosen way and clear its record
+///
+/// Retrying or manually settling finishes the fill; refunding reverses the settled legs and
+/// reopens the order. A failed transfer keeps the record, updated to the legs now settled.
+pub async fn resolve_incomplete_fill(
+    order_id: OrderId,
+    resolution: IncompleteFillResolution,
+) -> OrderResult<()> {
+    let fill = get_incomplete_fill(order_id).ok_or(OrderError::OrderNotFound)?;
+    let order = get_order(order_id).ok_or(OrderError::OrderNotFound)?;
+
+    // Ledger calls may interleave with another resolution of the same fill
+    if !begin_incomplete_fill_resolution(order_id) {
+        return Err(OrderError::ConcurrencyError(
+            "Incomplete fill is already being resolved".to_string(),
+        ));
+    }
+    let result = execute_incomplete_fill_resolution(&order, &fill, resolution).await;
+    end_incomplete_fill_resolution(order_id);
+    if result.is_err() {
+        track_error("incomplete_fill_resolution_failed");
+    }
+    result
+}
+
+/// Carry out a resolution while the fill is marked as being resolved
+async fn execute_incomplete_fill_resolution(
+    order: &Order,
+    fill: &IncompleteFill,
+    resolution: IncompleteFillResolution,
+) -> OrderResult<()> {
+    let transfer = FillTransfer {
+        order,
+        taker: fill.taker,
+        making_amount: fill.making_amount,
+        taking_amount: fill.taking_amount,
+    };
+    let legs = transfer.legs();
+    let completed = legs.iter().position(|leg| *leg == fill.completed_leg).unwrap_or(0);
+
+    // Legs up to the completed one are settled; only the completed leg's block is known
+    let mut settled: Vec<(FillLeg, u64)> = legs[..=completed]
+        .iter()
+        .map(|leg| (*leg, if *leg == fill.completed_leg { fill.block_index } else { 0 }))
+        .collect();
+
+    match resolution {
+        IncompleteFillResolution::RetrySecondLeg => {
+            for leg in &legs[completed + 1..] {
+                match transfer.execute(*leg, false).await {
+                    Ok(block_index) => settled.push((*leg, block_index)),
+                    Err(e) => {
+                        let last_settled = settled[settled.len() - 1];
+                        store_incomplete_fill(transfer.incomplete(last_settled, *leg));
+                        return Err(e);
+                    }
+                }
+            }
+        }
+        // Settled outside this canister
+        IncompleteFillResolution::ManualSettled => {
+            settled.extend(legs[completed + 1..].iter().map(|leg| (*leg, 0)));
+        }
+        IncompleteFillResolution::RefundFirstLeg => {
+            while let Some((leg, block_index)) = settled.pop() {
+                if let Err(e) = transfer.execute(leg, true).await {
+                    store_incomplete_fill(
+                        transfer.incomplete((leg, block_index), fill.pending_leg),
+                    );
+                    return Err(e);
+                }
+            }
+            remove_incomplete_fill(order.id);
+            crate::certification::certify_active_orders();
+            return Ok(());
+        }
+    }
+
+    remove_incomplete_fill(order.id);
+    complete_order_fill(order, fill.taker, Ok(transfer.settlement(&settled)))
+}
+
+/// Update order state and statistics after successful fill
+/// This function ensures atomic state updates
+fn update_order_filled_state(order_id: OrderId, order: &Order) {
+    // Mark order as filled
+    mark_order_filled(order_id);
+    crate::certification::certify_active_orders();
+
+    // Track statistics for both assets
+    track_order_filled(order.maker_asset, order.making_amount);
+    track_order_filled(order.taker_asset, order.taking_amount);
+
+    record_fill_candles(order, current_time());
+}
+
+/// Chart a fill in the 5-minute and hourly candles of its asset pair
+fn record_fill_candles(order: &Order, now: u64) {
+    let price = compute_price(
+        order.making_amount,
+        order.taking_amount,
+        get_asset_decimals(order.maker_asset),
+        get_asset_decimals(order.taker_asset),
+    );
+    for interval in [CandleInterval::FiveMinutes, CandleInterval::OneHour] {
+        update_candles(order.maker_asset, order.taker_asset, interval, |candles| {
+            add_fill_to_candles(candles, interval, &price, order, now)
+        });
+    }
+}
+
+/// Merge a fill into the candle of its interval, then drop candles past the retention
+///
+/// Fills arrive in time order, so only the newest candle can still receive fills.
+fn add_fill_to_candles(
+    candles: &mut VecDeque<Candle>,
+    interval: CandleInterval,
+    price: &PriceInfo,
+    order: &Order,
+    now: u64,
+) {
+    let start_ns = now - now % interval.duration_ns();
+    match candles.back_mut() {
+        Some(candle) if candle.start_ns >= start_ns => {
+            if compare_prices(price, &candle.high).is_gt() {
+                candle.high = price.clone();
+            }
+            if compare_prices(price, &candle.low).is_lt() {
+                candle.low = price.clone();
+            }
+            candle.close = price.clone();
+            candle.making_volume = candle.making_volume.saturating_add(order.making_amount);
+            candle.taking_volume = candle.taking_volume.saturating_add(order.taking_amount);
+            candle.fill_count += 1;
+        }
+        _ => candles.push_back(Candle {
+            start_ns,
+            open: price.clone(),
+            high: price.clone(),
+            low: price.clone(),
+            close: price.clone(),
+            making_volume: order.making_amount,
+            taking_volume: order.taking_amount,
+            fill_count: 1,
+        }),
+    }
+
+    while candles
+        .front()
+        .is_some_and(|oldest| oldest.start_ns + interval.retention_ns() <= start_ns)
+    {
+        candles.pop_front();
+    }
+}
+
+/// Update order state and statistics after successful cancellation
+/// This function ensures atomic state updates
+fn update_order_cancelled_state(order_id: OrderId) {
+    // Mark order as cancelled
+    mark_order_cancelled(order_id);
+    crate::certification::certify_active_orders();
+
+    // Track statistics
+    track_order_cancelled();
+}
+
+/// Batch cancel multiple orders by the same maker
+
+// ============================================================================
+// QUERY FUNCTIONS
+// ============================================================================
+
+/// Get the cancellation receipt of an order hash, if it was cancelled
+pub fn get_cancellation_proof(order_hash: &[u8]) -> Option<CancellationRecord> {
+    get_cancellation_record(order_hash)
+}
+
+/// Digits after the decimal point in price display strings
+pub const PRICE_DISPLAY_DECIMALS: usize = 8;
+
+/// Get an order's price normalized by its assets' decimals
+pub fn get_normalized_price(order_id: OrderId) -> Option<PriceInfo> {
+    get_order(order_id).map(|order| {
+        compute_price(
+            order.making_amount,
+            order.taking_amount,
+            get_asset_decimals(order.maker_asset),
+            get_asset_decimals(order.taker_asset),
+        )
+    })
+}
+
+/// Price of one whole maker asset in whole taker assets
+///
+/// The amounts are scaled by 10^decimals of the other asset so both sides are in whole tokens.
+/// When either asset's decimals are unknown (or scaling would overflow) the raw amount ratio is
+/// used and the price is marked as not decimals-adjusted.
+pub fn compute_price(
+    making_amount: u64,
+    taking_amount: u64,
+    maker_decimals: Option<u8>,
+    taker_decimals: Option<u8>,
+) -> PriceInfo {
+    let scale = |amount: u64, decimals: Option<u8>| {
+        10u128.checked_pow(decimals? as u32)?.checked_mul(amount as u128)
+    };
+    let (numerator, denominator, decimals_adjusted) =
+        match (scale(taking_amount, maker_decimals), scale(making_amount, taker_decimals)) {
+            (Some(numerator), Some(denominator)) => (numerator, denominator, true),
+            _ => (taking_amount as u128, making_amount as u128, false),
+        };
+
+    let divisor = gcd(numerator, denominator).max(1);
+    let (numerator, denominator) = (numerator / divisor, denominator / divisor);
+    PriceInfo {
+        numerator,
+        denominator,
+        display: format_fixed_point(numerator, denominator, PRICE_DISPLAY_DECIMALS),
+        decimals_adjusted,
+    }
+}
+
+/// How far back fills count towards a pair's reference price
+pub const REFERENCE_PRICE_WINDOW_NS: u64 = 24 * 60 * 60 * 1_000_000_000;
+
+/// Volume-weighted price of a pair's fills within the reference window, if it had any
+pub fn get_reference_price(maker_asset: Principal, taker_asset: Principal) -> Option<PriceInfo> {
+    let since = current_time().saturating_sub(REFERENCE_PRICE_WINDOW_NS);
+    let (making_volume, taking_volume) =
+        get_stored_candles(maker_asset, taker_asset, CandleInterval::FiveMinutes)
+            .iter()
+            .filter(|candle| candle.start_ns + CandleInterval::FiveMinutes.duration_ns() > since)
+            .fold((0u64, 0u64), |(making, taking), candle| {
+                (
+                    making.saturating_add(candle.making_volume),
+                    taking.saturating_add(candle.taking_volume),
+                )
+            });
+    if making_volume == 0 || taking_volume == 0 {
+        return None;
+    }
+
+    Some(compute_price(
+        making_volume,
+        taking_volume,
+        get_asset_decimals(maker_asset),
+        get_asset_decimals(taker_asset),
+    ))
+}
+
+/// Compare the price of a prospective order with the pair's recent fills
+///
+/// Pairs without fills in the reference window never exceed the limit.
+pub fn get_price_sanity(
+    maker_asset: Principal,
+    taker_asset: Principal,
+    making_amount: u64,
+    taking_amount: u64,
+) -> PriceSanity {
+    let order_price = compute_price(
+        making_amount,
+        taking_amount,
+        get_asset_decimals(maker_asset),
+        get_asset_decimals(taker_asset),
+    );
+    let reference_price = get_reference_price(maker_asset, taker_asset);
+    let deviation_bps =
+        reference_price.as_ref().map(|reference| price_deviation_bps(&order_price, reference));
+    let max_deviation_bps = get_runtime_limits().max_price_deviation_bps;
+
+    PriceSanity {
+        exceeds_limit: matches!(
+            (deviation_bps, max_deviation_bps),
+            (Some(deviation), Some(max)) if deviation > max
+        ),
+        order_price,
+        reference_price,
+        deviation_bps,
+        max_deviation_bps,
+    }
+}
+
+/// Distance of a price from a reference, in basis points of the reference
+fn price_deviation_bps(price: &PriceInfo, reference: &PriceInfo) -> u64 {
+    let exact = price
+        .numerator
+        .checked_mul(reference.denominator)
+        .zip(reference.numerator.checked_mul(price.denominator))
+        .and_then(|(left, right)| left.abs_diff(right).checked_mul(10_000)?.checked_div(right));
+    match exact {
+        Some(bps) => bps.min(u64::MAX as u128) as u64,
+        None => {
+            let price = price.numerator as f64 / price.denominator as f64;
+            let reference = reference.numerator as f64 / reference.denominator as f64;
+            ((price - reference).abs() / reference * 10_000.0) as u64
+        }
+    }
+}
+
+/// Order two prices by value, comparing as floats only when cross-multiplying would overflow
+fn compare_prices(a: &PriceInfo, b: &PriceInfo) -> std::cmp::Ordering {
+    match (a.numerator.checked_mul(b.denominator), b.numerator.checked_mul(a.denominator)) {
+        (Some(left), Some(right)) => left.cmp(&right),
+        _ => (a.numerator as f64 / a.denominator as f64)
+            .total_cmp(&(b.numerator as f64 / b.denominator as f64)),
+    }
+}
+
+/// Get an asset pair's candles starting within `[from_ns, to_ns)`, oldest first
+///
+/// Intervals without fills between two charted ones are returned as flat candles at the
+/// previous close, so charts have no holes.
+pub fn get_candles(
+    maker_asset: Principal,
+    taker_asset: Principal,
+    interval: CandleInterval,
+    from_ns: u64,
+    to_ns: u64,
+) -> Vec<Candle> {
+    let mut candles: Vec<Candle> = Vec::new();
+    for candle in get_stored_candles(maker_asset, taker_asset, interval) {
+        if let Some(previous) = candles.last().cloned() {
+            let mut start_ns = previous.start_ns + interval.duration_ns();
+            while start_ns < candle.start_ns {
+                candles.push(Candle {
+                    start_ns,
+                    open: previous.close.clone(),
+                    high: previous.close.clone(),
+                    low: previous.close.clone(),
+                    close: previous.close.clone(),
+                    making_volume: 0,
+                    taking_volume: 0,
+                    fill_count: 0,
+                });
+                start_ns += interval.duration_ns();
+            }
+        }
+        candles.push(candle);
+    }
+
+    candles.retain(|candle| from_ns <= candle.start_ns && candle.start_ns < to_ns);
+    candles
+}
+
+/// Format a fraction as a decimal string, truncated to `digits` fractional digits
+fn format_fixed_point(numerator: u128, denominator: u128, digits: usize) -> String {
+    if denominator == 0 {
+        return "0".to_string();
+    }
+
+    // Long division keeps the remainder below the denominator, which for u64 amounts scaled by
+    // up to 10^18 is far from overflowing when multiplied by 10
+    let mut display = format!("{}.", numerator / denominator);
+    let mut remainder = numerator % denominator;
+    for _ in 0..digits {
+        let shifted = remainder.saturating_mul(10);
+        display.push(char::from(b'0' + (shifted / denominator) as u8));
+        remainder = shifted % denominator;
+    }
+    display
+}
+
+fn gcd(mut a: u128, mut b: u128) -> u128 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+/// Fetch an asset's decimals from its ledger and cache them
+pub async fn refresh_asset_decimals(token: Principal) -> OrderResult<u8> {
+    let decimals = TokenInterface::new(token).decimals().await.inspect_err(|_| {
+        track_error("decimals_fetch_failed");
+    })?;
+    set_asset_decimals(token, decimals);
+    Ok(decimals)
+}
+
+/// Predict the outcome of a fill without transferring tokens or changing state
+///
+/// Runs the same validation, amount and balance functions as fill_order, on the same branch:
+/// Fusion fills list no transfer legs and check the taker balance only. Ledger fees and
+/// balances are queried from the ledgers, except in test mode where fills skip the ledgers too.
+pub async fn simulate_fill(
+    reference: OrderReference,
+    amount: u64,
+    taker: Principal,
+) -> FillSimulation {
+    let mut simulation = FillSimulation {
+        making_amount: 0,
+        taking_amount: 0,
+        protocol_fee: 0,
+        integrator_fee: 0,
+        ledger_fees: vec![],
+        would_succeed: false,
+        failure_reason: None,
+    };
+
+    match predict_fill(reference, amount, taker, &mut simulation).await {
+        Ok(()) => simulation.would_succeed = true,
+        Err(error) => simulation.failure_reason = Some(error),
+    }
+    simulation
+}
+
+/// Fill the simulation step by step, stopping at the first check a fill would fail
+async fn predict_fill(
+    reference: OrderReference,
+    amount: u64,
+    taker: Principal,
+    simulation: &mut FillSimulation,
+) -> OrderResult<()> {
+    let order_id = match reference {
+        OrderReference::Id(order_id) => order_id,
+        OrderReference::Hash(order_hash) => {
+            find_order_by_hash(&order_hash).ok_or(OrderError::OrderNotFound)?.id
+        }
+    };
+
+    let order = validate_fill(order_id, taker)?;
+    let (making_amount, taking_amount) = whole_fill_amounts(&order, amount)?;
+    simulation.making_amount = making_amount;
+    simulation.taking_amount = taking_amount;
+    simulation.protocol_fee = compute_protocol_fee(taking_amount);
+    let transfer = FillTransfer { order: &order, taker, making_amount, taking_amount };
+    simulation.integrator_fee = transfer.integrator_fee();
+
+    if is_test_mode() {
+        return Ok(());
+    }
+
+    // Fusion fills move no tokens here: the escrow does, and only the taker balance is checked
+    if is_escrow_fill(&order) {
+        return check_taker_balance(order.taker_asset, taker, taking_amount).await;
+    }
+
+    // Each leg is a single transfer on its ledger, charged that ledger's fee
+    for leg in transfer.legs() {
+        let ledger = match leg {
+            FillLeg::TakerToReceiver | FillLeg::TakerToIntegrator => order.taker_asset,
+            FillLeg::MakerToTaker => order.maker_asset,
+        };
+        let fee = TokenInterface::new(ledger).fee().await?;
+        simulation.ledger_fees.push((ledger, fee));
+    }
+
+    check_fill_balances(&order, taker, making_amount, taking_amount).await
+}
+
+/// Explain why an order hash can no longer be filled at the given time
+pub fn is_order_dead(order_hash: &[u8], now: u64) -> DeadReason {
+    if let Some(record) = get_cancellation_record(order_hash) {
+        if record.method != CancellationMethod::Bit {
+            return DeadReason::CancelledByHash;
+        }
+    }
+
+    let Some(order) = find_order_by_hash(order_hash) else {
+        return DeadReason::Alive;
+    };
+
+    if with_filled_orders_read(|filled| filled.contains(&order.id)) {
+        return DeadReason::Filled;
+    }
+
+    let (slot, bit) = bit_invalidator_position(order.salt);
+    let bits = get_invalidation_bits(order.maker, slot);
+    if bits & bit != 0 {
+        return DeadReason::CancelledByBitSlot(slot, bits);
+    }
+
+    if with_cancelled_orders_read(|cancelled| cancelled.contains(&order.id)) {
+        return DeadReason::CancelledByHash;
+    }
+
+    if order.expiration <= now {
+        return DeadReason::Expired;
+    }
+
+    DeadReason::Alive
+}
+
+/// Get all active orders open to new takers (excludes orders in their grace window)
+pub fn get_active_orders_list() -> Vec<Order> {
+    let now = current_time();
+    get_active_orders().into_iter().filter(|order| !is_in_grace_period(order, now)).collect()
+}
+
+/// Get a specific order by ID
+pub fn get_order_by_id(order_id: OrderId) -> Option<Order> {
+    get_order(order_id)
+}
+
+/// Get orders by maker
 pub fn get_orders_by_maker(maker: Principal) -> Vec<Order> {
     with_orders(|orders| orders.values().filter(|order| order.maker == maker).cloned().collect())
 }
 
-/// Get orders by asset pair
+/// Get orders by asset pair open to new takers
 pub fn get_orders_by_asset_pair(maker_asset: Principal, taker_asset: Principal) -> Vec<Order> {
-    get_active_orders()
+    get_active_orders_list()
         .into_iter()
         .filter(|order| order.maker_asset == maker_asset && order.taker_asset == taker_asset)
         .collect()
 }
 
+/// Get the fill history of an order
+///
+/// Partial fills are not supported, so the history holds at most the one fill that completed
+/// the order.
+pub fn get_fills_for_order(order_id: OrderId) -> Vec<FillRecord> {
+    crate::memory::get_fills_for_order(order_id)
+}
+
+/// Get a page of fills across all orders of a maker
+pub fn get_fills_for_maker(maker: Principal, offset: usize, limit: usize) -> Vec<FillRecord> {
+    crate::memory::get_fills_for_maker(maker, offset, limit)
+}
+
+/// Enable or disable test mode; only controllers may change it
+///
+/// Test mode cannot be enabled in builds with the `mainnet` feature.
+pub fn configure_test_mode(enabled: bool, is_controller: bool) -> OrderResult<()> {
+    if !is_controller {
+        track_error("unauthorized_test_mode_change");
+        return Err(OrderError::Unauthorized);
+    }
+    if enabled && cfg!(feature = "mainnet") {
+        track_error("test_mode_on_mainnet");
+        return Err(OrderError::SystemError(
+            "test mode is not available in mainnet builds".to_string(),
+        ));
+    }
+    set_test_mode(enabled);
+    Ok(())
+}
+
 /// Get system statistics
 pub fn get_system_statistics() -> SystemStats {
     crate::memory::with_system_stats_read(|stats| stats.clone())
@@ -574,6 +1794,7 @@ pub fn get_system_statistics() -> SystemStats {
 pub mod tests {
     use super::*;
     use crate::memory::clear_limit_order_data;
+    use crate::types::RuntimeLimits;
 
     /// Create a test order for unit testing
     pub fn create_test_order() -> Order {
@@ -585,8 +1806,12 @@ pub mod tests {
             taker_asset: Principal::from_slice(&[5, 6, 7, 8]),
             making_amount: 1000,
             taking_amount: 2000,
-            expiration: time() + 3600_000_000_000, // 1 hour
-            created_at: time(),
+            expiration: current_time() + 3600_000_000_000, // 1 hour
+            soft_expiry_ns: None,
+            allow_self_trade: None,
+            agent: None,
+            integrator_fee: None,
+            created_at: current_time(),
 
             order_type: OrderType::Normal,
             processing_strategy: ProcessingStrategy::DirectTransfer,
@@ -600,5 +1825,1595 @@ pub mod tests {
     /// Setup function for tests
     pub fn setup_test() {
         clear_limit_order_data();
+        FAILING_TRANSFERS.with(|failing| failing.borrow_mut().clear());
+    }
+
+    // Transfers the test mode ledger fails, as (leg, is reversal)
+    thread_local! {
+        static FAILING_TRANSFERS: std::cell::RefCell<Vec<(FillLeg, bool)>> =
+            const { std::cell::RefCell::new(Vec::new()) };
+    }
+
+    /// Make the test mode ledger fail a leg, or the reversal of a leg
+    fn fail_transfer(leg: FillLeg, reverse: bool) {
+        FAILING_TRANSFERS.with(|failing| failing.borrow_mut().push((leg, reverse)));
+    }
+
+    /// Outcome of a test mode transfer
+    pub fn mock_leg_transfer(leg: FillLeg, reverse: bool) -> OrderResult<u64> {
+        if FAILING_TRANSFERS.with(|failing| failing.borrow().contains(&(leg, reverse))) {
+            return Err(OrderError::TransferFailed("mock ledger failure".to_string()));
+        }
+        Ok(0)
+    }
+
+    /// Store a basic fixture order so fills can be recorded against it
+    fn store_fixture_order(order_id: OrderId) -> Order {
+        let mut order = crate::test_utils::OrderTestFixtures::create_basic_order();
+        order.id = order_id;
+        with_orders(|orders| {
+            orders.insert(order_id, order.clone());
+        });
+        order
+    }
+
+    fn test_taker() -> Principal {
+        Principal::from_slice(&[9; 10])
+    }
+
+    #[test]
+    fn test_lowering_maker_cap_blocks_new_orders_only() {
+        setup_test();
+        let order = store_fixture_order(1);
+        store_fixture_order(2);
+        store_fixture_order(3);
+        assert!(validate_system_limits(order.maker).is_ok());
+
+        crate::memory::set_runtime_limits(RuntimeLimits {
+            max_orders_per_maker: 3,
+            ..RuntimeLimits::default()
+        });
+
+        assert!(matches!(validate_system_limits(order.maker), Err(OrderError::TooManyOrders)));
+        assert_eq!(get_orders_by_maker(order.maker).len(), 3);
+        assert!(is_order_active(1) && is_order_active(2) && is_order_active(3));
+        assert!(validate_system_limits(test_taker()).is_ok());
+    }
+
+    #[test]
+    fn test_runtime_limits_apply_to_amounts_and_expiration() {
+        setup_test();
+        crate::memory::set_runtime_limits(RuntimeLimits {
+            min_order_amount: 1_000,
+            max_expiration_days: 1,
+            ..RuntimeLimits::default()
+        });
+
+        assert!(validate_token_amounts(999, 5_000).is_err());
+        assert!(validate_token_amounts(1_000, 5_000).is_ok());
+
+        let day_ns = 24 * 3600 * 1_000_000_000;
+        assert!(validate_expiration_timestamp(current_time() + day_ns).is_ok());
+        assert!(validate_expiration_timestamp(current_time() + 2 * day_ns).is_err());
+    }
+
+    #[test]
+    fn test_invalid_runtime_limits_rejected() {
+        let defaults = RuntimeLimits::default();
+        assert!(defaults.validate().is_ok());
+
+        let invalid = [
+            RuntimeLimits { max_active_orders: 99, ..defaults.clone() },
+            RuntimeLimits { max_active_orders: 1_000_001, ..defaults.clone() },
+            RuntimeLimits { max_orders_per_maker: 0, ..defaults.clone() },
+            RuntimeLimits { max_orders_per_maker: 20_000, ..defaults.clone() },
+            RuntimeLimits { max_expiration_days: 0, ..defaults.clone() },
+            RuntimeLimits { max_expiration_days: 366, ..defaults.clone() },
+            RuntimeLimits { min_expiration_secs: 30 * 24 * 3600, ..defaults.clone() },
+            RuntimeLimits { min_order_amount: 0, ..defaults.clone() },
+            RuntimeLimits { max_token_amount: u64::MAX, ..defaults.clone() },
+        ];
+        for limits in invalid {
+            assert!(
+                matches!(limits.validate(), Err(OrderError::InvalidConfiguration(_))),
+                "accepted {:?}",
+                limits
+            );
+        }
+    }
+
+    #[test]
+    fn test_runtime_limits_survive_upgrade() {
+        setup_test();
+        let limits = RuntimeLimits { max_active_orders: 500, ..RuntimeLimits::default() };
+        crate::memory::set_runtime_limits(limits.clone());
+
+        let extended = crate::memory::serialize_extended_state();
+        clear_limit_order_data();
+        assert_eq!(crate::memory::get_runtime_limits(), RuntimeLimits::default());
+
+        crate::memory::deserialize_extended_state(extended);
+        assert_eq!(crate::memory::get_runtime_limits(), limits);
+
+        // Snapshots taken before runtime limits existed restore the defaults
+        crate::memory::deserialize_extended_state(crate::memory::ExtendedState::default());
+        assert_eq!(crate::memory::get_runtime_limits(), RuntimeLimits::default());
+    }
+
+    #[test]
+    fn test_full_fill_records_block_indices() {
+        setup_test();
+        let order = store_fixture_order(1);
+
+        complete_order_fill(&order, test_taker(), Ok(((7, 9), None))).unwrap();
+
+        let fills = get_fills_for_order(1);
+        assert_eq!(fills.len(), 1);
+        assert_eq!(fills[0].taker, test_taker());
+        assert_eq!(fills[0].block_indices, (7, 9));
+        assert_eq!(fills[0].making_amount, order.making_amount);
+        assert_eq!(fills[0].taking_amount, order.taking_amount);
+        assert_eq!(fills[0].timestamp, current_time());
+        assert_eq!(get_fills_for_maker(order.maker, 0, 10), fills);
+        assert!(with_filled_orders_read(|filled| filled.contains(&1)));
+    }
+
+    #[test]
+    fn test_fill_by_hash_records_fill() {
+        setup_test();
+        crate::memory::set_test_mode(true);
+        let order_id =
+            run_ready(create_order(order_params(), CreateOrderOptions::default(), test_maker()))
+                .unwrap();
+        let order = get_order(order_id).unwrap();
+
+        run_ready(fill_order(&compute_order_hash(&order), order.taking_amount, test_taker()))
+            .unwrap();
+
+        let fills = get_fills_for_order(order_id);
+        assert_eq!(fills.len(), 1);
+        assert_eq!(fills[0].taker, test_taker());
+        assert_eq!(fills[0].making_amount, order.making_amount);
+        assert_eq!(fills[0].taking_amount, order.taking_amount);
+        assert_eq!(get_fills_for_maker(test_maker(), 0, 10), fills);
+    }
+
+    #[test]
+    fn test_each_order_records_at_most_one_fill() {
+        setup_test();
+        crate::memory::set_test_mode(true);
+        let order = store_fixture_order(1);
+        store_fixture_order(2);
+
+        // Orders are filled whole, so an order never gains a second fill record
+        assert!(matches!(
+            run_ready(execute_fill(1, order.taking_amount / 2, test_taker())),
+            Err(OrderError::InvalidAmount)
+        ));
+        fill_whole(1, test_taker()).unwrap();
+        assert!(fill_whole(1, test_taker()).is_err());
+        assert_eq!(get_fills_for_order(1).len(), 1);
+        assert!(get_fills_for_order(2).is_empty());
+
+        // Maker history spans orders in fill order and supports paging
+        fill_whole(2, test_taker()).unwrap();
+        assert_eq!(get_fills_for_maker(order.maker, 0, 10).len(), 2);
+        let page = get_fills_for_maker(order.maker, 1, 1);
+        assert_eq!(page.len(), 1);
+        assert_eq!(page[0].order_id, 2);
+    }
+
+    #[test]
+    fn test_failed_transfers_record_no_fill() {
+        setup_test();
+        let order = store_fixture_order(1);
+
+        let result = complete_order_fill(
+            &order,
+            test_taker(),
+            Err(OrderError::TransferFailed("maker transfer failed".to_string())),
+        );
+
+        assert!(result.is_err());
+        assert!(get_fills_for_order(1).is_empty());
+        assert!(get_fills_for_maker(order.maker, 0, 10).is_empty());
+        assert!(!with_filled_orders_read(|filled| filled.contains(&1)));
+    }
+
+    #[test]
+    fn test_fill_history_survives_upgrade() {
+        setup_test();
+        let order = store_fixture_order(1);
+        complete_order_fill(&order, test_taker(), Ok(((3, 4), None))).unwrap();
+
+        let (orders, filled, cancelled, counter, stats) =
+            crate::memory::serialize_limit_order_state();
+        let extended = crate::memory::serialize_extended_state();
+        clear_limit_order_data();
+
+        crate::memory::deserialize_limit_order_state(orders, filled, cancelled, counter, stats);
+        crate::memory::deserialize_extended_state(extended);
+
+        assert_eq!(get_fills_for_order(1).len(), 1);
+        let fills = get_fills_for_maker(order.maker, 0, 10);
+        assert_eq!(fills.len(), 1);
+        assert_eq!(fills[0].block_indices, (3, 4));
+    }
+
+    /// Store a fixture order with a grace window from 10 to 20 minutes after now
+    fn store_grace_order(order_id: OrderId) -> Order {
+        let minute = 60 * 1_000_000_000;
+        let mut order = store_fixture_order(order_id);
+        order.soft_expiry_ns = Some(current_time() + 10 * minute);
+        order.expiration = current_time() + 20 * minute;
+        with_orders(|orders| {
+            orders.insert(order_id, order.clone());
+        });
+        order
+    }
+
+    #[test]
+    fn test_intent_holder_can_fill_during_grace_period() {
+        setup_test();
+        let order = store_grace_order(1);
+
+        register_order_intent(1, test_taker()).unwrap();
+        crate::memory::set_test_time(order.soft_expiry_ns.unwrap() + 1);
+
+        assert!(validate_fill_window(&order, test_taker()).is_ok());
+        assert!(get_active_orders_list().is_empty());
+        assert!(is_order_active(1));
+
+        // Intent can no longer be registered once the grace window opened
+        let late = Principal::from_slice(&[8; 10]);
+        assert!(matches!(register_order_intent(1, late), Err(OrderError::OrderInGracePeriod)));
+    }
+
+    #[test]
+    fn test_new_taker_rejected_during_grace_period() {
+        setup_test();
+        let order = store_grace_order(1);
+        assert!(validate_fill_window(&order, test_taker()).is_ok());
+        assert_eq!(get_active_orders_list().len(), 1);
+
+        crate::memory::set_test_time(order.soft_expiry_ns.unwrap());
+        assert!(matches!(
+            validate_fill_window(&order, test_taker()),
+            Err(OrderError::OrderInGracePeriod)
+        ));
+        assert!(get_orders_by_asset_pair(order.maker_asset, order.taker_asset).is_empty());
+    }
+
+    #[test]
+    fn test_fill_by_hash_enforces_stored_grace_window() {
+        setup_test();
+        crate::memory::set_test_mode(true);
+        let params = order_params();
+        let soft_expiry = |soft_expiry_ns| CreateOrderOptions {
+            soft_expiry_ns: Some(soft_expiry_ns),
+            ..Default::default()
+        };
+
+        // Soft expiries must fall before the hard expiry
+        assert!(matches!(
+            run_ready(create_order(params.clone(), soft_expiry(params.expiration), test_maker())),
+            Err(OrderError::InvalidExpiration)
+        ));
+        let grace_start = params.expiration - 600_000_000_000;
+        let order_id =
+            run_ready(create_order(params, soft_expiry(grace_start), test_maker())).unwrap();
+
+        // The taker's copy of the order drops the soft expiry to skip the grace window
+        let mut tampered = get_order(order_id).unwrap();
+        tampered.soft_expiry_ns = None;
+        crate::memory::set_test_time(grace_start);
+        assert!(matches!(
+            run_ready(fill_order(
+                &compute_order_hash(&tampered),
+                tampered.taking_amount,
+                test_taker()
+            )),
+            Err(OrderError::OrderInGracePeriod)
+        ));
+    }
+
+    #[test]
+    fn test_nobody_can_fill_after_hard_expiry() {
+        setup_test();
+        let order = store_grace_order(1);
+        register_order_intent(1, test_taker()).unwrap();
+
+        crate::memory::set_test_time(order.expiration);
+        for taker in [test_taker(), Principal::from_slice(&[8; 10])] {
+            assert!(matches!(validate_fill_window(&order, taker), Err(OrderError::OrderExpired)));
+        }
+        assert!(!is_order_active(1));
+    }
+
+    fn pause(token: Principal) {
+        crate::memory::pause_asset(token, "ledger redeployed".to_string(), current_time());
+    }
+
+    #[test]
+    fn test_paused_asset_blocks_creation_and_fill() {
+        setup_test();
+        let (token_a, token_b, token_c) = (
+            Principal::from_slice(&[1; 10]),
+            Principal::from_slice(&[2; 10]),
+            Principal::from_slice(&[3; 10]),
+        );
+        let expiration = current_time() + 3600 * 1_000_000_000;
+        let create = |maker_asset, taker_asset| {
+            validate_create_order(
+                test_taker(),
+                test_taker(),
+                maker_asset,
+                taker_asset,
+                1_000,
+                2_000,
+                expiration,
+            )
+        };
+        assert!(create(token_a, token_b).is_ok());
+
+        pause(token_b);
+        for result in [
+            create(token_a, token_b),
+            create(token_b, token_a),
+            validate_assets_not_paused(token_a, token_b),
+        ] {
+            assert!(
+                matches!(result, Err(OrderError::AssetPaused(ref reason)) if reason == "ledger redeployed")
+            );
+        }
+
+        // Pairs not involving the paused token keep trading
+        assert!(create(token_a, token_c).is_ok());
+        assert_eq!(crate::memory::get_paused_assets().len(), 1);
+    }
+
+    #[test]
+    fn test_paused_asset_allows_cancel() {
+        setup_test();
+        let order = store_fixture_order(1);
+        pause(order.maker_asset);
+
+        assert!(cancel_order(1, order.maker).is_ok());
+        assert!(with_cancelled_orders_read(|cancelled| cancelled.contains(&1)));
+    }
+
+    #[test]
+    fn test_unpause_restores_trading() {
+        setup_test();
+        let order = store_fixture_order(1);
+        pause(order.maker_asset);
+
+        // Pauses survive upgrade
+        let extended = crate::memory::serialize_extended_state();
+        clear_limit_order_data();
+        crate::memory::deserialize_extended_state(extended);
+        assert!(validate_assets_not_paused(order.maker_asset, order.taker_asset).is_err());
+
+        assert!(crate::memory::unpause_asset(order.maker_asset));
+        assert!(!crate::memory::unpause_asset(order.maker_asset));
+        assert!(validate_assets_not_paused(order.maker_asset, order.taker_asset).is_ok());
+        assert!(crate::memory::get_paused_assets().is_empty());
+    }
+
+    /// Put the fixture maker and the test taker into self-trade prevention groups
+    fn join_stp_groups(maker_group: &str, taker_group: &str) {
+        let (maker, _) = crate::test_utils::OrderTestFixtures::test_principals();
+        for group in [maker_group, taker_group] {
+            crate::memory::create_stp_group(group.to_string(), current_time());
+        }
+        assert!(crate::memory::join_stp_group(maker, maker_group));
+        assert!(crate::memory::join_stp_group(test_taker(), taker_group));
+    }
+
+    #[test]
+    fn test_same_stp_group_fill_rejected() {
+        setup_test();
+        store_fixture_order(1);
+        join_stp_groups("desk", "desk");
+
+        assert!(matches!(
+            validate_fill(1, test_taker()),
+            Err(OrderError::SelfTradeBlocked(ref group)) if group == "desk"
+        ));
+        assert!(validate_fill(1, Principal::from_slice(&[8; 10])).is_ok());
+
+        // Membership persists across upgrades
+        let extended = crate::memory::serialize_extended_state();
+        clear_limit_order_data();
+        store_fixture_order(1);
+        crate::memory::deserialize_extended_state(extended);
+        assert_eq!(crate::memory::get_stp_group("desk").unwrap().members.len(), 2);
+        assert!(matches!(validate_fill(1, test_taker()), Err(OrderError::SelfTradeBlocked(_))));
+
+        assert!(crate::memory::leave_stp_group(test_taker()));
+        assert!(validate_fill(1, test_taker()).is_ok());
+    }
+
+    #[test]
+    fn test_different_stp_groups_can_fill() {
+        setup_test();
+        store_fixture_order(1);
+        join_stp_groups("desk-a", "desk-b");
+
+        assert!(validate_fill(1, test_taker()).is_ok());
+        assert!(!crate::memory::join_stp_group(test_taker(), "missing"));
+        assert_eq!(crate::memory::get_stp_group_of(test_taker()), Some("desk-b".to_string()));
+    }
+
+    #[test]
+    fn test_self_trade_opt_out_honored() {
+        setup_test();
+        let mut order = store_fixture_order(1);
+        order.allow_self_trade = Some(true);
+        with_orders(|orders| {
+            orders.insert(1, order.clone());
+        });
+        join_stp_groups("desk", "desk");
+
+        assert!(validate_fill(1, test_taker()).is_ok());
+        order.allow_self_trade = Some(false);
+        assert!(matches!(
+            validate_self_trade(&order, test_taker()),
+            Err(OrderError::SelfTradeBlocked(_))
+        ));
+    }
+
+    #[test]
+    fn test_fill_by_hash_ignores_tampered_self_trade_opt_out() {
+        setup_test();
+        crate::memory::set_test_mode(true);
+        crate::memory::create_stp_group("desk".to_string(), current_time());
+        assert!(crate::memory::join_stp_group(test_maker(), "desk"));
+        assert!(crate::memory::join_stp_group(test_taker(), "desk"));
+        let create = |allow_self_trade| {
+            let options = CreateOrderOptions { allow_self_trade, ..Default::default() };
+            let order_id = run_ready(create_order(order_params(), options, test_maker())).unwrap();
+            get_order(order_id).unwrap()
+        };
+
+        // The taker's copy of the order claims an opt-out the maker never gave
+        let mut tampered = create(None);
+        tampered.allow_self_trade = Some(true);
+        assert!(matches!(
+            run_ready(fill_order(
+                &compute_order_hash(&tampered),
+                tampered.taking_amount,
+                test_taker()
+            )),
+            Err(OrderError::SelfTradeBlocked(_))
+        ));
+
+        // Makers opt out when creating the order
+        let opted_out = create(Some(true));
+        assert_eq!(opted_out.allow_self_trade, Some(true));
+        run_ready(fill_order(
+            &compute_order_hash(&opted_out),
+            opted_out.taking_amount,
+            test_taker(),
+        ))
+        .unwrap();
+    }
+
+    /// Authorize the test taker as an agent of the fixture maker for an hour
+    fn authorize_test_agent(can_create: bool, max_order_size: u64) -> Principal {
+        let (maker, _) = crate::test_utils::OrderTestFixtures::test_principals();
+        let scope = crate::types::AgentScope {
+            can_create,
+            can_cancel: true,
+            max_order_size,
+            expires_at: current_time() + 3_600_000_000_000,
+        };
+        crate::memory::authorize_agent(
+            maker,
+            crate::types::AgentAuthorization { agent: test_taker(), scope, authorized_at: 0 },
+        );
+        maker
+    }
+
+    #[test]
+    fn test_agent_acts_within_scope() {
+        setup_test();
+        let order = store_fixture_order(1);
+        let maker = authorize_test_agent(true, 1_000_000);
+
+        let create = AgentAction::Create { making_amount: 1_000_000 };
+        assert_eq!(
+            resolve_acting_maker(test_taker(), Some(maker), create).unwrap(),
+            (maker, Some(test_taker()))
+        );
+        assert_eq!(resolve_acting_maker(maker, None, AgentAction::Cancel).unwrap(), (maker, None));
+
+        // Cancellation runs as the beneficial maker
+        let (acting, _) =
+            resolve_acting_maker(test_taker(), Some(maker), AgentAction::Cancel).unwrap();
+        cancel_order_by_hash(&compute_order_hash(&order), acting, CancellationMethod::Hash)
+            .unwrap();
+        assert_eq!(get_cancellation_record(&compute_order_hash(&order)).unwrap().maker, maker);
+
+        // Authorizations persist across upgrades
+        let extended = crate::memory::serialize_extended_state();
+        clear_limit_order_data();
+        crate::memory::deserialize_extended_state(extended);
+        assert_eq!(crate::memory::get_agents(maker).len(), 1);
+    }
+
+    #[test]
+    fn test_agent_scope_limits_enforced() {
+        setup_test();
+        let maker = authorize_test_agent(true, 1_000_000);
+        let oversized = AgentAction::Create { making_amount: 1_000_001 };
+        assert!(matches!(
+            resolve_acting_maker(test_taker(), Some(maker), oversized),
+            Err(OrderError::AgentScopeExceeded(ref limit)) if limit == "max_order_size"
+        ));
+
+        authorize_test_agent(false, u64::MAX);
+        assert!(matches!(
+            resolve_acting_maker(test_taker(), Some(maker), AgentAction::Create { making_amount: 1 }),
+            Err(OrderError::AgentScopeExceeded(ref limit)) if limit == "can_create"
+        ));
+
+        // Principals the maker never authorized cannot act for it
+        let stranger = Principal::from_slice(&[8; 10]);
+        assert!(matches!(
+            resolve_acting_maker(stranger, Some(maker), AgentAction::Cancel),
+            Err(OrderError::AgentNotAuthorized)
+        ));
+    }
+
+    #[test]
+    fn test_expired_and_revoked_agents_rejected() {
+        setup_test();
+        let maker = authorize_test_agent(true, 1_000_000);
+        let expires_at =
+            crate::memory::get_agent_authorization(maker, test_taker()).unwrap().scope.expires_at;
+
+        crate::memory::set_test_time(expires_at);
+        assert!(matches!(
+            resolve_acting_maker(test_taker(), Some(maker), AgentAction::Cancel),
+            Err(OrderError::AgentNotAuthorized)
+        ));
+
+        crate::memory::set_test_time(1_000_000_000_000);
+        assert!(resolve_acting_maker(test_taker(), Some(maker), AgentAction::Cancel).is_ok());
+        assert!(crate::memory::revoke_agent(maker, test_taker()));
+        assert!(matches!(
+            resolve_acting_maker(test_taker(), Some(maker), AgentAction::Cancel),
+            Err(OrderError::AgentNotAuthorized)
+        ));
+        assert!(crate::memory::get_agents(maker).is_empty());
+    }
+
+    #[test]
+    fn test_soft_expiry_must_precede_expiration() {
+        setup_test();
+        let expiration = current_time() + 3600 * 1_000_000_000;
+
+        assert!(validate_soft_expiry(None, expiration).is_ok());
+        assert!(validate_soft_expiry(Some(expiration - 1), expiration).is_ok());
+        assert!(validate_soft_expiry(Some(expiration), expiration).is_err());
+        assert!(validate_soft_expiry(Some(current_time()), expiration).is_err());
+    }
+
+    #[test]
+    fn test_management_canister_asset_rejected() {
+        setup_test();
+        let token = Principal::from_slice(&[0xb; 10]);
+        let management = Principal::management_canister();
+
+        for (maker_asset, taker_asset) in [(management, token), (token, management)] {
+            let result = validate_asset_pair(maker_asset, taker_asset);
+            assert!(matches!(result, Err(OrderError::TokenNotSupported(_))));
+        }
+    }
+
+    #[test]
+    fn test_test_mode_skips_transfers() {
+        use std::future::Future;
+        use std::task::{Context, Poll, Waker};
+
+        setup_test();
+        crate::memory::set_test_mode(true);
+        let order = store_fixture_order(1);
+        let taker = Principal::from_slice(&[0xc; 10]);
+
+        // No ledger is called, so the transfers complete on the first poll
+        let mut transfers = std::pin::pin!(execute_order_transfers(
+            &order,
+            taker,
+            order.making_amount,
+            order.taking_amount
+        ));
+        let poll = transfers.as_mut().poll(&mut Context::from_waker(Waker::noop()));
+        assert!(matches!(poll, Poll::Ready(Ok(((0, 0), None)))));
+    }
+
+    #[test]
+    fn test_test_mode_requires_controller() {
+        setup_test();
+
+        assert!(matches!(configure_test_mode(true, false), Err(OrderError::Unauthorized)));
+        assert!(!is_test_mode());
+
+        let enabled = configure_test_mode(true, true);
+        if cfg!(feature = "mainnet") {
+            assert!(matches!(enabled, Err(OrderError::SystemError(_))));
+            return;
+        }
+        assert!(enabled.is_ok());
+        assert!(matches!(configure_test_mode(false, false), Err(OrderError::Unauthorized)));
+        assert!(is_test_mode());
+
+        // The flag survives upgrade
+        let extended = crate::memory::serialize_extended_state();
+        set_test_mode(false);
+        crate::memory::deserialize_extended_state(extended);
+        assert!(is_test_mode());
+    }
+
+    /// Store a fixture order with its own salt, so its hash differs from other fixtures
+    fn store_salted_order(order_id: OrderId, salt: u64) -> Order {
+        let mut order = store_fixture_order(order_id);
+        order.salt = salt;
+        with_orders(|orders| {
+            orders.insert(order_id, order.clone());
+        });
+        order
+    }
+
+    #[test]
+    fn test_cancellation_records_by_hash_and_id() {
+        setup_test();
+        let by_hash = store_salted_order(1, 1);
+        let by_id = store_salted_order(2, 2);
+        let hash = compute_order_hash(&by_hash);
+
+        let stranger = Principal::from_slice(&[0xd; 10]);
+        assert!(matches!(
+            cancel_order_by_hash(&hash, stranger, CancellationMethod::Hash),
+            Err(OrderError::Unauthorized)
+        ));
+        assert!(get_cancellation_proof(&hash).is_none());
+
+        cancel_order_by_hash(&hash, by_hash.maker, CancellationMethod::Hash).unwrap();
+        let record = get_cancellation_proof(&hash).unwrap();
+        assert_eq!(
+            record,
+            CancellationRecord {
+                order_hash: hash.clone(),
+                maker: by_hash.maker,
+                cancelled_at: current_time(),
+                method: CancellationMethod::Hash,
+            }
+        );
+        assert_eq!(is_order_dead(&hash, current_time()), DeadReason::CancelledByHash);
+
+        cancel_order(2, by_id.maker).unwrap();
+        let id_hash = compute_order_hash(&by_id);
+        assert_eq!(get_cancellation_proof(&id_hash).unwrap().method, CancellationMethod::Id);
+        assert_eq!(is_order_dead(&id_hash, current_time()), DeadReason::CancelledByHash);
+    }
+
+    #[test]
+    fn test_bit_invalidation_reports_slot() {
+        setup_test();
+        // Salt 130 is bit 2 of slot 2
+        let order = store_salted_order(1, 130);
+        let mut sibling = store_salted_order(2, 130);
+        sibling.making_amount += 1;
+        with_orders(|orders| {
+            orders.insert(2, sibling.clone());
+        });
+        let hash = compute_order_hash(&order);
+
+        cancel_order_by_hash(&hash, order.maker, CancellationMethod::Bit).unwrap();
+        assert_eq!(crate::memory::get_invalidation_bits(order.maker, 2), 0b100);
+        assert_eq!(is_order_dead(&hash, current_time()), DeadReason::CancelledByBitSlot(2, 0b100));
+
+        // The bit also kills other orders of the maker sharing the salt, without a receipt
+        let sibling_hash = compute_order_hash(&sibling);
+        assert!(get_cancellation_proof(&sibling_hash).is_none());
+        assert_eq!(
+            is_order_dead(&sibling_hash, current_time()),
+            DeadReason::CancelledByBitSlot(2, 0b100)
+        );
+    }
+
+    #[test]
+    fn test_dead_reason_filled_expired_alive() {
+        setup_test();
+        let filled = store_salted_order(1, 1);
+        let expiring = store_salted_order(2, 2);
+        let filled_hash = compute_order_hash(&filled);
+        let expiring_hash = compute_order_hash(&expiring);
+
+        assert_eq!(is_order_dead(&filled_hash, current_time()), DeadReason::Alive);
+        mark_order_filled(1);
+        assert_eq!(is_order_dead(&filled_hash, current_time()), DeadReason::Filled);
+
+        assert_eq!(is_order_dead(&expiring_hash, expiring.expiration), DeadReason::Expired);
+        assert_eq!(is_order_dead(&[0u8; 32], current_time()), DeadReason::Alive);
+    }
+
+    /// Drive a future that makes no ledger calls (test mode) to completion
+    fn run_ready<F: std::future::Future>(future: F) -> F::Output {
+        use std::task::{Context, Poll, Waker};
+
+        let mut future = std::pin::pin!(future);
+        match future.as_mut().poll(&mut Context::from_waker(Waker::noop())) {
+            Poll::Ready(output) => output,
+            Poll::Pending => panic!("future awaited a ledger call"),
+        }
+    }
+
+    /// Fill an order for its whole taking amount, as every fill must
+    fn fill_whole(order_id: OrderId, taker: Principal) -> OrderResult<(u64, u64)> {
+        let amount = get_order(order_id).map_or(0, |order| order.taking_amount);
+        run_ready(execute_fill(order_id, amount, taker))
+    }
+
+    #[test]
+    fn test_simulation_matches_successful_fill() {
+        setup_test();
+        crate::memory::set_test_mode(true);
+        let order = store_fixture_order(1);
+
+        let by_hash = OrderReference::Hash(compute_order_hash(&order));
+        for reference in [OrderReference::Id(1), by_hash] {
+            let simulation = run_ready(simulate_fill(reference, order.taking_amount, test_taker()));
+            assert!(simulation.would_succeed);
+            assert!(simulation.failure_reason.is_none());
+            assert_eq!(simulation.making_amount, order.making_amount);
+            assert_eq!(simulation.taking_amount, order.taking_amount);
+            assert_eq!(simulation.protocol_fee, 0);
+            assert!(simulation.ledger_fees.is_empty());
+        }
+        assert!(get_fills_for_order(1).is_empty());
+
+        fill_whole(1, test_taker()).unwrap();
+        let fills = get_fills_for_order(1);
+        assert_eq!(fills.len(), 1);
+        assert_eq!(fills[0].making_amount, order.making_amount);
+        assert_eq!(fills[0].taking_amount, order.taking_amount);
+    }
+
+    /// Failing fill: its name, how far the amount falls short of the whole order, and the
+    /// setup returning the taker
+    type Scenario = (&'static str, u64, fn(&Order) -> Principal);
+
+    #[test]
+    fn test_simulation_predicts_each_fill_failure() {
+        let scenarios: [Scenario; 8] = [
+            ("not found", 0, |_| {
+                clear_limit_order_data();
+                test_taker()
+            }),
+            ("own order", 0, |order| order.maker),
+            ("paused", 0, |order| {
+                pause(order.taker_asset);
+                test_taker()
+            }),
+            ("expired", 0, |order| {
+                crate::memory::set_test_time(order.expiration);
+                test_taker()
+            }),
+            ("cancelled", 0, |order| {
+                cancel_order(order.id, order.maker).unwrap();
+                test_taker()
+            }),
+            ("filled", 0, |order| {
+                mark_order_filled(order.id);
+                test_taker()
+            }),
+            ("grace period", 0, |order| {
+                crate::memory::set_test_time(order.soft_expiry_ns.unwrap());
+                test_taker()
+            }),
+            ("partial amount", 1, |_| test_taker()),
+        ];
+
+        for (name, shortfall, arrange) in scenarios {
+            setup_test();
+            crate::memory::set_test_mode(true);
+            crate::memory::set_test_time(1_000_000_000_000);
+            let order = store_grace_order(1);
+            let taker = arrange(&order);
+            let amount = order.taking_amount - shortfall;
+
+            let simulation = run_ready(simulate_fill(OrderReference::Id(1), amount, taker));
+            let executed = run_ready(execute_fill(1, amount, taker));
+
+            assert!(!simulation.would_succeed, "{} simulated as success", name);
+            let (predicted, actual) = (simulation.failure_reason.unwrap(), executed.unwrap_err());
+            assert_eq!(
+                std::mem::discriminant(&predicted),
+                std::mem::discriminant(&actual),
+                "{}: predicted {:?}, got {:?}",
+                name,
+                predicted,
+                actual
+            );
+            assert!(get_fills_for_order(1).is_empty());
+        }
+    }
+
+    #[test]
+    fn test_simulated_amounts_are_proportional() {
+        setup_test();
+        crate::memory::set_test_mode(true);
+        let order = store_fixture_order(1);
+
+        // A quarter of the taker amount buys a quarter of the maker amount
+        assert_eq!(compute_fill_amounts(&order, 500_000).unwrap(), (250_000, 500_000));
+
+        // Rounding favours the maker
+        assert_eq!(compute_fill_amounts(&order, 3).unwrap(), (1, 3));
+
+        // Fills are whole, so simulation rejects any other amount just as execution does
+        for amount in [0, 500_000, order.taking_amount + 1] {
+            let simulation = run_ready(simulate_fill(OrderReference::Id(1), amount, test_taker()));
+            assert!(matches!(simulation.failure_reason, Some(OrderError::InvalidAmount)));
+            assert!(matches!(whole_fill_amounts(&order, amount), Err(OrderError::InvalidAmount)));
+        }
+    }
+
+    #[test]
+    fn test_price_same_decimals() {
+        // 0.01 TTA for 0.02 TTB, both with 8 decimals
+        let price = compute_price(1_000_000, 2_000_000, Some(8), Some(8));
+        assert_eq!(
+            price,
+            PriceInfo {
+                numerator: 2,
+                denominator: 1,
+                display: "2.00000000".to_string(),
+                decimals_adjusted: true
+            }
+        );
+    }
+
+    #[test]
+    fn test_price_mixed_decimals() {
+        // 1 TTA (8 decimals) for 0.05 of an 18-decimal token
+        let price = compute_price(100_000_000, 50_000_000_000_000_000, Some(8), Some(18));
+        assert_eq!((price.numerator, price.denominator), (1, 20));
+        assert_eq!(price.display, "0.05000000");
+        assert!(price.decimals_adjusted);
+
+        // The other direction: 0.05 of the 18-decimal token buys 1 TTA
+        let inverse = compute_price(50_000_000_000_000_000, 100_000_000, Some(18), Some(8));
+        assert_eq!(inverse.display, "20.00000000");
+
+        // Fractions are truncated to the display precision
+        assert_eq!(compute_price(3, 1, Some(8), Some(8)).display, "0.33333333");
+    }
+
+    #[test]
+    fn test_price_unknown_decimals_uses_raw_ratio() {
+        setup_test();
+        let order = store_fixture_order(1);
+        crate::memory::set_asset_decimals(order.maker_asset, 8);
+
+        let price = get_normalized_price(1).unwrap();
+        assert_eq!(price.display, "2.00000000");
+        assert!(!price.decimals_adjusted);
+
+        // Once both ledgers are known the price is normalized
+        crate::memory::set_asset_decimals(order.taker_asset, 18);
+        let price = get_normalized_price(1).unwrap();
+        assert_eq!(price.display, "0.00000000");
+        assert_eq!((price.numerator, price.denominator), (1, 5_000_000_000));
+        assert!(price.decimals_adjusted);
+        assert!(get_normalized_price(2).is_none());
+
+        // Decimals too large to scale fall back as well
+        assert!(!compute_price(1, 2, Some(40), Some(8)).decimals_adjusted);
+    }
+
+    fn escrow_manager() -> Principal {
+        Principal::from_slice(&[7; 10])
+    }
+
+    /// Store an escrow-coordinated order and configure the escrow_manager settling it
+    fn store_fusion_order(order_id: OrderId) -> Order {
+        let mut order = store_fixture_order(order_id);
+        order.order_type = OrderType::Fusion;
+        order.processing_strategy = ProcessingStrategy::EscrowCoordination;
+        order.metadata = Some(crate::types::OrderMetadata {
+            hashlock: Some(vec![0xab; 32]),
+            timelock: Some(3600),
+            target_chain: Some("ethereum".to_string()),
+            chain_id: Some(1),
+            safety_deposit: None,
+            escrow_address: None,
+            resolver_address: None,
+            preimage: None,
+        });
+        with_orders(|orders| {
+            orders.insert(order_id, order.clone());
+        });
+        crate::memory::set_test_mode(true);
+        crate::memory::set_escrow_manager(Some(escrow_manager()));
+        order
+    }
+
+    #[test]
+    fn test_fusion_fill_creates_escrow_instead_of_transferring() {
+        setup_test();
+        let order = store_fusion_order(1);
+
+        fill_whole(1, test_taker()).unwrap();
+
+        let fill = get_fusion_fill(1).unwrap();
+        assert_eq!(fill.state, OrderState::EscrowCreated);
+        assert_eq!(fill.taker, test_taker());
+        let order_hash: String =
+            compute_order_hash(&order).iter().map(|b| format!("{:02x}", b)).collect();
+        assert_eq!(fill.escrow_id, Some(order_hash));
+
+        // Nothing settled yet, and the order cannot be filled twice meanwhile
+        assert!(get_fills_for_order(1).is_empty());
+        assert!(!is_order_active(1));
+        assert!(matches!(fill_whole(1, test_taker()), Err(OrderError::OrderInactive)));
+
+        // Without a configured escrow_manager Fusion orders cannot be filled at all
+        store_fusion_order(2);
+        crate::memory::set_escrow_manager(None);
+        assert!(matches!(fill_whole(2, test_taker()), Err(OrderError::EscrowManagerUnavailable)));
+        assert!(get_fusion_fill(2).is_none());
+    }
+
+    /// Id of the escrow settling an order's Fusion fill
+    fn escrow_id_of(order_id: OrderId) -> String {
+        get_fusion_fill(order_id).and_then(|fill| fill.escrow_id).unwrap_or_default()
+    }
+
+    #[test]
+    fn test_fusion_completion_finishes_fill() {
+        setup_test();
+        let order = store_fusion_order(1);
+        fill_whole(1, test_taker()).unwrap();
+        let escrow_id = escrow_id_of(1);
+
+        // Escrows unknown to this canister, e.g. other legs claimed on the escrow_manager, are ignored
+        assert!(matches!(
+            complete_fusion_fill("unknown", escrow_manager()),
+            Err(OrderError::EscrowNotFound)
+        ));
+        complete_fusion_fill(&escrow_id, escrow_manager()).unwrap();
+
+        assert!(get_fusion_fill(1).is_none());
+        assert!(with_filled_orders_read(|filled| filled.contains(&1)));
+        let fills = get_fills_for_order(1);
+        assert_eq!(fills.len(), 1);
+        assert_eq!(fills[0].taker, test_taker());
+        assert_eq!(fills[0].making_amount, order.making_amount);
+
+        // A settled fill cannot be completed again
+        assert!(matches!(
+            complete_fusion_fill(&escrow_id, escrow_manager()),
+            Err(OrderError::EscrowNotFound)
+        ));
+    }
+
+    #[test]
+    fn test_fill_revalidation_rejects_order_taken_meanwhile() {
+        setup_test();
+        let order = store_fusion_order(1);
+        validate_fill(1, test_taker()).unwrap();
+
+        // A concurrent fill stored its Pending escrow fill during the balance check
+        store_fusion_fill(FusionFill {
+            order_id: 1,
+            taker: Principal::from_slice(&[9; 10]),
+            making_amount: order.making_amount,
+            taking_amount: order.taking_amount,
+            escrow_id: None,
+            state: OrderState::Pending,
+            started_at: current_time(),
+        });
+        assert!(matches!(validate_fill(1, test_taker()), Err(OrderError::OrderInactive)));
+    }
+
+    #[test]
+    fn test_fusion_completion_rejects_other_callers() {
+        setup_test();
+        let order = store_fusion_order(1);
+        fill_whole(1, test_taker()).unwrap();
+
+        for caller in [test_taker(), order.maker] {
+            assert!(matches!(
+                complete_fusion_fill(&escrow_id_of(1), caller),
+                Err(OrderError::Unauthorized)
+            ));
+        }
+        assert_eq!(get_fusion_fill(1).unwrap().state, OrderState::EscrowCreated);
+        assert!(get_fills_for_order(1).is_empty());
+    }
+
+    fn test_maker() -> Principal {
+        Principal::from_slice(&[3; 10])
+    }
+
+    /// Parameters of a valid order created by `test_maker`
+    fn order_params() -> CreateOrderParams {
+        CreateOrderParams {
+            receiver: test_maker(),
+            maker_asset: Principal::from_slice(&[1; 10]),
+            taker_asset: Principal::from_slice(&[2; 10]),
+            making_amount: 1_000_000,
+            taking_amount: 2_000_000,
+            expiration: current_time() + 3_600_000_000_000,
+        }
+    }
+
+    /// Escrow terms of a Fusion order whose timelock ends `timelock_in` from now
+    fn escrow_terms(hashlock: Vec<u8>, timelock_in: u64) -> OrderMetadata {
+        OrderMetadata {
+            hashlock: Some(hashlock),
+            timelock: Some(current_time() + timelock_in),
+            target_chain: None,
+            chain_id: None,
+            safety_deposit: Some(1_000),
+            escrow_address: None,
+            resolver_address: None,
+            preimage: None,
+        }
+    }
+
+    #[test]
+    fn test_created_fusion_order_fills_by_hash_through_escrow() {
+        setup_test();
+        crate::memory::set_test_mode(true);
+        crate::memory::set_escrow_manager(Some(escrow_manager()));
+        let options = CreateOrderOptions {
+            escrow_terms: Some(escrow_terms(vec![0xab; 32], 1_800_000_000_000)),
+            ..Default::default()
+        };
+        let order_id = run_ready(create_order(order_params(), options, test_maker())).unwrap();
+        let order = get_order(order_id).unwrap();
+        assert!(matches!(order.processing_strategy, ProcessingStrategy::EscrowCoordination));
+        let order_hash = compute_order_hash(&order);
+
+        // Unknown hashes and partial amounts are rejected
+        assert!(matches!(
+            run_ready(fill_order(&[0; 32], order.taking_amount, test_taker())),
+            Err(OrderError::OrderNotFound)
+        ));
+        assert!(matches!(
+            run_ready(fill_order(&order_hash, order.taking_amount - 1, test_taker())),
+            Err(OrderError::InvalidAmount)
+        ));
+
+        let amounts =
+            run_ready(fill_order(&order_hash, order.taking_amount, test_taker())).unwrap();
+        assert_eq!(amounts, (order.making_amount, order.taking_amount));
+        assert_eq!(get_fusion_fill(order_id).unwrap().state, OrderState::EscrowCreated);
+
+        complete_fusion_fill(&escrow_id_of(order_id), escrow_manager()).unwrap();
+        assert_eq!(get_fills_for_order(order_id).len(), 1);
+    }
+
+    #[test]
+    fn test_create_order_rejects_invalid_escrow_terms() {
+        setup_test();
+        crate::memory::set_test_mode(true);
+        let params = order_params();
+        let past_expiration = params.expiration - current_time() + 1;
+
+        let without_deposit = |safety_deposit| OrderMetadata {
+            safety_deposit,
+            ..escrow_terms(vec![0xab; 32], 1_800_000_000_000)
+        };
+
+        for (terms, short_hashlock) in [
+            (escrow_terms(vec![0xab; 16], 1_800_000_000_000), true),
+            (escrow_terms(vec![0xab; 32], 0), false),
+            (escrow_terms(vec![0xab; 32], ESCROW_MIN_TIMELOCK_NS), false),
+            (escrow_terms(vec![0xab; 32], past_expiration), false),
+            (without_deposit(None), false),
+            (without_deposit(Some(0)), false),
+        ] {
+            let options = CreateOrderOptions { escrow_terms: Some(terms), ..Default::default() };
+            let result = run_ready(create_order(params.clone(), options, test_maker()));
+            if short_hashlock {
+                assert!(matches!(result, Err(OrderError::InvalidHashlock)));
+            } else {
+                assert!(matches!(result, Err(OrderError::InvalidConfiguration(_))));
+            }
+        }
+        assert!(get_active_orders_list().is_empty());
+    }
+
+    /// Simulate a fill whose maker transfer and taker refund both failed
+    fn store_stuck_fill(order_id: OrderId) -> OrderError {
+        let order = store_fixture_order(order_id);
+        let fill = IncompleteFill {
+            order_id,
+            taker: test_taker(),
+            completed_leg: FillLeg::TakerToReceiver,
+            pending_leg: FillLeg::MakerToTaker,
+            block_index: 42,
+            making_amount: order.making_amount,
+            taking_amount: order.taking_amount,
+            timestamp: current_time(),
+        };
+        record_incomplete_fill(
+            fill,
+            OrderError::TokenCallFailed("maker transfer timed out".to_string()),
+            OrderError::TokenCallFailed("refund timed out".to_string()),
+        )
+    }
+
+    #[test]
+    fn test_double_transfer_failure_records_incomplete_fill() {
+        setup_test();
+        let error = store_stuck_fill(1);
+
+        assert!(
+            matches!(error, OrderError::SystemError(message) if message.contains("incomplete fill 1"))
+        );
+        let recorded = crate::memory::list_incomplete_fills();
+        assert_eq!(recorded.len(), 1);
+        assert_eq!(recorded[0].block_index, 42);
+        assert_eq!(recorded[0].pending_leg, FillLeg::MakerToTaker);
+
+        // The order cannot be filled again until an operator resolves it
+        assert!(!is_order_active(1));
+        assert!(get_active_orders().is_empty());
+        assert!(matches!(fill_whole(1, test_taker()), Err(OrderError::OrderInactive)));
+
+        // The record survives upgrades
+        let state = crate::memory::serialize_extended_state();
+        remove_incomplete_fill(1);
+        crate::memory::deserialize_extended_state(state);
+        assert_eq!(crate::memory::list_incomplete_fills(), recorded);
+    }
+
+    #[test]
+    fn test_fill_by_hash_records_failed_rollback() {
+        setup_test();
+        crate::memory::set_test_mode(true);
+        let order_id =
+            run_ready(create_order(order_params(), CreateOrderOptions::default(), test_maker()))
+                .unwrap();
+        let order = get_order(order_id).unwrap();
+
+        // The maker leg fails and the taker's payment cannot be refunded
+        fail_transfer(FillLeg::MakerToTaker, false);
+        fail_transfer(FillLeg::TakerToReceiver, true);
+        let order_hash = compute_order_hash(&order);
+        assert!(matches!(
+            run_ready(fill_order(&order_hash, order.taking_amount, test_taker())),
+            Err(OrderError::SystemError(_))
+        ));
+
+        let fill = get_incomplete_fill(order_id).unwrap();
+        assert_eq!(fill.taker, test_taker());
+        assert_eq!(fill.completed_leg, FillLeg::TakerToReceiver);
+        assert_eq!(fill.pending_leg, FillLeg::MakerToTaker);
+        assert!(get_fills_for_order(order_id).is_empty());
+        assert!(matches!(
+            run_ready(fill_order(&order_hash, order.taking_amount, test_taker())),
+            Err(OrderError::OrderInactive)
+        ));
+    }
+
+    #[test]
+    fn test_incomplete_fill_resolutions_restore_consistency() {
+        setup_test();
+        crate::memory::set_test_mode(true);
+        for order_id in 1..=3 {
+            store_stuck_fill(order_id);
+        }
+
+        // Retrying or manually settling the second leg finishes the fill
+        for (order_id, resolution) in [
+            (1, IncompleteFillResolution::RetrySecondLeg),
+            (2, IncompleteFillResolution::ManualSettled),
+        ] {
+            run_ready(resolve_incomplete_fill(order_id, resolution)).unwrap();
+            assert!(get_incomplete_fill(order_id).is_none());
+            assert!(with_filled_orders_read(|filled| filled.contains(&order_id)));
+            let fills = get_fills_for_order(order_id);
+            assert_eq!(fills.len(), 1);
+            assert_eq!(fills[0].taker, test_taker());
+            assert_eq!(fills[0].block_indices, (42, 0));
+        }
+
+        // Refunding the first leg reopens the order without recording a fill
+        run_ready(resolve_incomplete_fill(3, IncompleteFillResolution::RefundFirstLeg)).unwrap();
+        assert!(get_incomplete_fill(3).is_none());
+        assert!(is_order_active(3));
+        assert!(get_fills_for_order(3).is_empty());
+
+        // Resolved fills cannot be resolved twice
+        assert!(matches!(
+            run_ready(resolve_incomplete_fill(1, IncompleteFillResolution::RetrySecondLeg)),
+            Err(OrderError::OrderNotFound)
+        ));
+    }
+
+    #[test]
+    fn test_incomplete_fill_resolution_is_exclusive() {
+        setup_test();
+        crate::memory::set_test_mode(true);
+        store_stuck_fill(1);
+
+        assert!(begin_incomplete_fill_resolution(1));
+        assert!(matches!(
+            run_ready(resolve_incomplete_fill(1, IncompleteFillResolution::RetrySecondLeg)),
+            Err(OrderError::ConcurrencyError(_))
+        ));
+        assert!(get_incomplete_fill(1).is_some());
+        assert!(get_fills_for_order(1).is_empty());
+
+        end_incomplete_fill_resolution(1);
+        run_ready(resolve_incomplete_fill(1, IncompleteFillResolution::RetrySecondLeg)).unwrap();
+        assert!(get_incomplete_fill(1).is_none());
+    }
+
+    fn integrator() -> Principal {
+        Principal::from_slice(&[0x1f; 10])
+    }
+
+    /// Store a fixture order paying `bps` of each fill to the integrator
+    fn store_order_with_fee(order_id: OrderId, bps: u16) -> Order {
+        let mut order = store_fixture_order(order_id);
+        order.integrator_fee = Some(IntegratorFee { recipient: integrator(), bps });
+        with_orders(|orders| {
+            orders.insert(order_id, order.clone());
+        });
+        order
+    }
+
+    #[test]
+    fn test_integrator_fee_math_and_cap() {
+        setup_test();
+        let fee = |bps| IntegratorFee { recipient: integrator(), bps };
+
+        assert!(validate_integrator_fee(None).is_ok());
+        assert!(validate_integrator_fee(Some(&fee(MAX_INTEGRATOR_FEE_BPS))).is_ok());
+        assert!(matches!(
+            validate_integrator_fee(Some(&fee(MAX_INTEGRATOR_FEE_BPS + 1))),
+            Err(OrderError::IntegratorFeeTooHigh(MAX_INTEGRATOR_FEE_BPS))
+        ));
+        let anonymous = IntegratorFee { recipient: Principal::anonymous(), bps: 10 };
+        assert!(matches!(
+            validate_integrator_fee(Some(&anonymous)),
+            Err(OrderError::AnonymousCaller)
+        ));
+
+        // The fee comes out of the taking amount, rounded down in the maker's favour
+        let order = store_order_with_fee(1, 30);
+        assert_eq!(compute_integrator_fee(&order, 2_000_000), 6_000);
+        assert_eq!(compute_integrator_fee(&store_order_with_fee(2, 100), 333), 3);
+        assert_eq!(compute_integrator_fee(&store_fixture_order(3), 2_000_000), 0);
+
+        // A fee rounding to zero needs no transfer
+        let transfer =
+            FillTransfer { order: &order, taker: test_taker(), making_amount: 1, taking_amount: 2 };
+        assert_eq!(transfer.legs(), vec![FillLeg::TakerToReceiver, FillLeg::MakerToTaker]);
+        let transfer = FillTransfer { taking_amount: 2_000_000, ..transfer };
+        assert_eq!(
+            transfer.legs(),
+            vec![FillLeg::TakerToReceiver, FillLeg::TakerToIntegrator, FillLeg::MakerToTaker]
+        );
+    }
+
+    #[test]
+    fn test_integrator_fee_leg_recorded_and_simulated() {
+        setup_test();
+        crate::memory::set_test_mode(true);
+        let order = store_order_with_fee(1, 30);
+
+        let simulation =
+            run_ready(simulate_fill(OrderReference::Id(1), order.taking_amount, test_taker()));
+        fill_whole(1, test_taker()).unwrap();
+
+        let fills = get_fills_for_order(1);
+        assert_eq!(fills.len(), 1);
+        let payment =
+            IntegratorFeePayment { recipient: integrator(), amount: 6_000, block_index: 0 };
+        assert_eq!(fills[0].integrator_fee, Some(payment));
+        assert!(simulation.would_succeed);
+        assert_eq!(simulation.integrator_fee, 6_000);
+        assert_eq!(simulation.taking_amount, fills[0].taking_amount);
+        assert_eq!(simulation.making_amount, fills[0].making_amount);
+
+        // Orders without a fee keep a two-leg fill
+        store_fixture_order(2);
+        fill_whole(2, test_taker()).unwrap();
+        assert_eq!(get_fills_for_order(2)[0].integrator_fee, None);
+    }
+
+    #[test]
+    fn test_created_order_fee_capped_and_paid_on_fill() {
+        setup_test();
+        crate::memory::set_test_mode(true);
+        let with_fee = |bps| CreateOrderOptions {
+            integrator_fee: Some(IntegratorFee { recipient: integrator(), bps }),
+            ..Default::default()
+        };
+
+        assert!(matches!(
+            run_ready(create_order(
+                order_params(),
+                with_fee(MAX_INTEGRATOR_FEE_BPS + 1),
+                test_maker()
+            )),
+            Err(OrderError::IntegratorFeeTooHigh(MAX_INTEGRATOR_FEE_BPS))
+        ));
+        let order_id = run_ready(create_order(order_params(), with_fee(30), test_maker())).unwrap();
+        let order = get_order(order_id).unwrap();
+
+        run_ready(fill_order(&compute_order_hash(&order), order.taking_amount, test_taker()))
+            .unwrap();
+        let payment =
+            IntegratorFeePayment { recipient: integrator(), amount: 6_000, block_index: 0 };
+        assert_eq!(get_fills_for_order(order_id)[0].integrator_fee, Some(payment));
+    }
+
+    #[test]
+    fn test_failed_fill_rolls_back_fee_leg() {
+        setup_test();
+        crate::memory::set_test_mode(true);
+        store_order_with_fee(1, 30);
+
+        // Both taker-side legs are reversed when the maker leg fails
+        fail_transfer(FillLeg::MakerToTaker, false);
+        assert!(matches!(fill_whole(1, test_taker()), Err(OrderError::TransferFailed(_))));
+        assert!(crate::memory::list_incomplete_fills().is_empty());
+        assert!(get_fills_for_order(1).is_empty());
+        assert!(is_order_active(1));
+
+        // A fee leg that cannot be reversed leaves it and the receiver leg settled
+        fail_transfer(FillLeg::TakerToIntegrator, true);
+        assert!(matches!(fill_whole(1, test_taker()), Err(OrderError::SystemError(_))));
+        let fill = get_incomplete_fill(1).unwrap();
+        assert_eq!(fill.completed_leg, FillLeg::TakerToIntegrator);
+        assert_eq!(fill.pending_leg, FillLeg::MakerToTaker);
+
+        // Refunding reverses every settled leg; a failing reversal keeps the record updated
+        run_ready(resolve_incomplete_fill(1, IncompleteFillResolution::RefundFirstLeg))
+            .unwrap_err();
+        assert_eq!(get_incomplete_fill(1).unwrap().completed_leg, FillLeg::TakerToIntegrator);
+        FAILING_TRANSFERS.with(|failing| failing.borrow_mut().clear());
+        run_ready(resolve_incomplete_fill(1, IncompleteFillResolution::RefundFirstLeg)).unwrap();
+        assert!(get_incomplete_fill(1).is_none());
+        assert!(is_order_active(1));
+    }
+
+    #[test]
+    fn test_retry_after_fee_leg_failure_settles_remaining_legs() {
+        setup_test();
+        crate::memory::set_test_mode(true);
+        store_order_with_fee(1, 30);
+
+        fail_transfer(FillLeg::TakerToIntegrator, false);
+        fail_transfer(FillLeg::TakerToReceiver, true);
+        assert!(fill_whole(1, test_taker()).is_err());
+        let fill = get_incomplete_fill(1).unwrap();
+        assert_eq!(fill.completed_leg, FillLeg::TakerToReceiver);
+        assert_eq!(fill.pending_leg, FillLeg::TakerToIntegrator);
+
+        // Retrying pays the fee and the maker leg, then records the fill with its fee leg
+        FAILING_TRANSFERS.with(|failing| failing.borrow_mut().clear());
+        run_ready(resolve_incomplete_fill(1, IncompleteFillResolution::RetrySecondLeg)).unwrap();
+        let fills = get_fills_for_order(1);
+        assert_eq!(fills.len(), 1);
+        assert_eq!(fills[0].integrator_fee.as_ref().map(|fee| fee.amount), Some(6_000));
+        assert!(with_filled_orders_read(|filled| filled.contains(&1)));
+    }
+
+    const FIVE_MINUTES: u64 = 5 * 60 * 1_000_000_000;
+
+    /// Fill an order of `making_amount` for `taking_amount` at the given time
+    fn fill_at(order_id: OrderId, making_amount: u64, taking_amount: u64, now: u64) -> Order {
+        let mut order = store_fixture_order(order_id);
+        order.making_amount = making_amount;
+        order.taking_amount = taking_amount;
+        crate::memory::set_test_time(now);
+        update_order_filled_state(order_id, &order);
+        order
+    }
+
+    fn candles(order: &Order, interval: CandleInterval) -> Vec<Candle> {
+        get_candles(order.maker_asset, order.taker_asset, interval, 0, u64::MAX)
+    }
+
+    #[test]
+    fn test_fills_land_in_interval_buckets() {
+        setup_test();
+        let start = 10 * FIVE_MINUTES;
+        fill_at(1, 100, 200, start);
+        fill_at(2, 100, 300, start + FIVE_MINUTES - 1);
+        let order = fill_at(3, 100, 100, start + FIVE_MINUTES);
+
+        let five_minutes = candles(&order, CandleInterval::FiveMinutes);
+        assert_eq!(five_minutes.len(), 2);
+        assert_eq!(five_minutes[0].start_ns, start);
+        assert_eq!((five_minutes[0].fill_count, five_minutes[0].taking_volume), (2, 500));
+        assert_eq!(five_minutes[1].start_ns, start + FIVE_MINUTES);
+
+        let hourly = candles(&order, CandleInterval::OneHour);
+        assert_eq!(hourly.len(), 1);
+        assert_eq!((hourly[0].fill_count, hourly[0].making_volume), (3, 300));
+
+        // Only candles starting within the requested range are returned
+        let ranged = get_candles(
+            order.maker_asset,
+            order.taker_asset,
+            CandleInterval::FiveMinutes,
+            start + 1,
+            u64::MAX,
+        );
+        assert_eq!(ranged.len(), 1);
+    }
+
+    #[test]
+    fn test_fill_by_hash_charts_candles() {
+        setup_test();
+        crate::memory::set_test_mode(true);
+        crate::memory::set_test_time(10 * FIVE_MINUTES);
+        let order_id =
+            run_ready(create_order(order_params(), CreateOrderOptions::default(), test_maker()))
+                .unwrap();
+        let order = get_order(order_id).unwrap();
+
+        run_ready(fill_order(&compute_order_hash(&order), order.taking_amount, test_taker()))
+            .unwrap();
+
+        for interval in [CandleInterval::FiveMinutes, CandleInterval::OneHour] {
+            let charted = candles(&order, interval);
+            assert_eq!(charted.len(), 1);
+            assert_eq!(charted[0].fill_count, 1);
+            assert_eq!(
+                (charted[0].making_volume, charted[0].taking_volume),
+                (order.making_amount, order.taking_amount)
+            );
+        }
+        assert_eq!(candles(&order, CandleInterval::FiveMinutes)[0].start_ns, 10 * FIVE_MINUTES);
+    }
+
+    #[test]
+    fn test_candle_tracks_open_high_low_close() {
+        setup_test();
+        for (i, taking_amount) in [200, 500, 100, 300].into_iter().enumerate() {
+            fill_at(i as OrderId + 1, 100, taking_amount, FIVE_MINUTES + i as u64);
+        }
+
+        let candle = candles(&store_fixture_order(1), CandleInterval::FiveMinutes).remove(0);
+        assert_eq!(candle.open.display, "2.00000000");
+        assert_eq!(candle.high.display, "5.00000000");
+        assert_eq!(candle.low.display, "1.00000000");
+        assert_eq!(candle.close.display, "3.00000000");
+    }
+
+    #[test]
+    fn test_candles_roll_over_past_retention() {
+        setup_test();
+        fill_at(1, 100, 200, FIVE_MINUTES);
+        let retention = CandleInterval::FiveMinutes.retention_ns();
+        let order = fill_at(2, 100, 300, FIVE_MINUTES + retention);
+
+        let five_minutes = candles(&order, CandleInterval::FiveMinutes);
+        assert_eq!(five_minutes.len(), 1);
+        assert_eq!(five_minutes[0].start_ns, FIVE_MINUTES + retention);
+
+        // Hourly candles are kept longer, with the empty hours in between charted flat
+        let hourly = candles(&order, CandleInterval::OneHour);
+        assert_eq!(hourly.iter().filter(|candle| candle.fill_count > 0).count(), 2);
+    }
+
+    #[test]
+    fn test_empty_intervals_are_flat_at_previous_close() {
+        setup_test();
+        fill_at(1, 100, 200, FIVE_MINUTES);
+        let order = fill_at(2, 100, 400, 4 * FIVE_MINUTES);
+
+        let five_minutes = candles(&order, CandleInterval::FiveMinutes);
+        assert_eq!(five_minutes.len(), 4);
+        for gap in &five_minutes[1..3] {
+            assert_eq!(gap.fill_count, 0);
+            assert_eq!((gap.making_volume, gap.taking_volume), (0, 0));
+            assert_eq!(gap.open, five_minutes[0].close);
+            assert_eq!(gap.close, five_minutes[0].close);
+        }
+        assert_eq!(five_minutes[3].close.display, "4.00000000");
+    }
+
+    /// Chart two fills of the fixture pair at a price of 2 and cap deviations at 50%
+    fn fills_at_price_two(now: u64) -> Order {
+        fill_at(1, 100, 200, now);
+        let order = fill_at(2, 300, 600, now + 1);
+        crate::memory::set_runtime_limits(RuntimeLimits {
+            max_price_deviation_bps: Some(5_000),
+            ..RuntimeLimits::default()
+        });
+        order
+    }
+
+    #[test]
+    fn test_price_far_from_recent_fills_rejected() {
+        setup_test();
+        let order = fills_at_price_two(FIVE_MINUTES);
+        let (maker_asset, taker_asset) = (order.maker_asset, order.taker_asset);
+
+        assert!(validate_price_sanity(maker_asset, taker_asset, 100, 250, false).is_ok());
+        match validate_price_sanity(maker_asset, taker_asset, 100, 200_000, false) {
+            Err(OrderError::PriceDeviationWarning(order_price, reference_price)) => {
+                assert_eq!(order_price, "2000.00000000");
+                assert_eq!(reference_price, "2.00000000");
+            }
+            other => panic!("expected a price deviation warning, got {:?}", other),
+        }
+        // Orders far below the reference are caught as well
+        assert!(validate_price_sanity(maker_asset, taker_asset, 100_000, 200, false).is_err());
+    }
+
+    #[test]
+    fn test_price_warning_accepted_by_maker() {
+        setup_test();
+        let order = fills_at_price_two(FIVE_MINUTES);
+        let (maker_asset, taker_asset) = (order.maker_asset, order.taker_asset);
+
+        assert!(validate_price_sanity(maker_asset, taker_asset, 100, 200_000, true).is_ok());
+        let sanity = get_price_sanity(maker_asset, taker_asset, 100, 200_000);
+        assert_eq!(sanity.deviation_bps, Some(9_990_000));
+        assert_eq!(sanity.max_deviation_bps, Some(5_000));
+        assert!(sanity.exceeds_limit);
+    }
+
+    #[test]
+    fn test_create_order_requires_accepting_price_warning() {
+        setup_test();
+        crate::memory::set_test_mode(true);
+        let create = |params, options| run_ready(create_order(params, options, test_maker()));
+
+        // A filled order charts the pair at a price of 2
+        let order_id = create(order_params(), CreateOrderOptions::default()).unwrap();
+        let order = get_order(order_id).unwrap();
+        run_ready(fill_order(&compute_order_hash(&order), order.taking_amount, test_taker()))
+            .unwrap();
+        crate::memory::set_runtime_limits(RuntimeLimits {
+            max_price_deviation_bps: Some(5_000),
+            ..RuntimeLimits::default()
+        });
+
+        let params = CreateOrderParams { making_amount: 1_000, ..order_params() };
+        assert!(matches!(
+            create(params.clone(), CreateOrderOptions::default()),
+            Err(OrderError::PriceDeviationWarning(..))
+        ));
+        let accepted =
+            CreateOrderOptions { accept_price_warning: Some(true), ..Default::default() };
+        let order_id = create(params, accepted).unwrap();
+        assert!(get_order(order_id).is_some());
+    }
+
+    #[test]
+    fn test_price_check_skipped_without_recent_fills() {
+        setup_test();
+        let order = store_fixture_order(1);
+        crate::memory::set_runtime_limits(RuntimeLimits {
+            max_price_deviation_bps: Some(5_000),
+            ..RuntimeLimits::default()
+        });
+        let (maker_asset, taker_asset) = (order.maker_asset, order.taker_asset);
+        assert!(validate_price_sanity(maker_asset, taker_asset, 100, 200_000, false).is_ok());
+
+        // Fills older than the reference window no longer count
+        fills_at_price_two(FIVE_MINUTES);
+        crate::memory::set_test_time(2 * FIVE_MINUTES + REFERENCE_PRICE_WINDOW_NS);
+        let sanity = get_price_sanity(maker_asset, taker_asset, 100, 200_000);
+        assert_eq!(sanity.reference_price, None);
+        assert!(!sanity.exceeds_limit);
+        assert!(validate_price_sanity(maker_asset, taker_asset, 100, 200_000, false).is_ok());
     }
 }
@@ -1,6 +1,12 @@
-use crate::types::{Order, OrderId, SystemStats};
+use crate::types::{
+    AgentAuthorization, CancellationRecord, Candle, CandleInterval, FillRecord, FusionFill,
+    IncompleteFill, Order, OrderId, OrderState, OrderStateCounts, PausedAsset, RuntimeLimits,
+    StpGroup, SystemStats,
+};
+use candid::Principal;
+use candid::{CandidType, Deserialize};
 use std::cell::RefCell;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 
 // Global state using thread_local! for safety
 thread_local! {
@@ -10,6 +16,69 @@ thread_local! {
     static CANCELLED_ORDERS: RefCell<HashSet<OrderId>> = RefCell::new(HashSet::new());
     static ORDER_COUNTER: RefCell<u64> = RefCell::new(0);
     static SYSTEM_STATS: RefCell<SystemStats> = RefCell::new(SystemStats::default());
+    static RUNTIME_LIMITS: RefCell<RuntimeLimits> = RefCell::new(RuntimeLimits::default());
+
+    // Fill history: records per order plus an index of (order, position) per maker
+    static FILL_RECORDS: RefCell<HashMap<OrderId, Vec<FillRecord>>> = RefCell::new(HashMap::new());
+    static MAKER_FILLS: RefCell<HashMap<Principal, Vec<(OrderId, usize)>>> = RefCell::new(HashMap::new());
+
+    // Takers negotiating an order, who may still fill it during its grace window
+    static ORDER_INTENTS: RefCell<HashMap<OrderId, HashSet<Principal>>> = RefCell::new(HashMap::new());
+
+    // Tokens the controller halted trading for
+    static PAUSED_ASSETS: RefCell<HashMap<Principal, PausedAsset>> = RefCell::new(HashMap::new());
+
+    // Cancellation receipts by order hash, and 1inch bit invalidators by (maker, slot)
+    static CANCELLATION_RECORDS: RefCell<HashMap<Vec<u8>, CancellationRecord>> = RefCell::new(HashMap::new());
+    static BIT_INVALIDATORS: RefCell<HashMap<(Principal, u64), u64>> = RefCell::new(HashMap::new());
+
+    // Self-trade prevention groups by name (creation time), and the group of each member
+    static STP_GROUPS: RefCell<HashMap<String, u64>> = RefCell::new(HashMap::new());
+    static STP_MEMBERSHIP: RefCell<HashMap<Principal, String>> = RefCell::new(HashMap::new());
+
+    // Session keys each maker authorized, by (maker, agent)
+    static AGENT_AUTHORIZATIONS: RefCell<HashMap<(Principal, Principal), AgentAuthorization>> = RefCell::new(HashMap::new());
+
+    // Escrow-settled fills of Fusion orders, and the escrow_manager that settles them
+    static FUSION_FILLS: RefCell<HashMap<OrderId, FusionFill>> = RefCell::new(HashMap::new());
+    static ESCROW_MANAGER: RefCell<Option<Principal>> = const { RefCell::new(None) };
+
+    // Direct fills stuck after one transfer, and those an operator is resolving right now
+    static INCOMPLETE_FILLS: RefCell<BTreeMap<OrderId, IncompleteFill>> = const { RefCell::new(BTreeMap::new()) };
+    static RESOLVING_INCOMPLETE_FILLS: RefCell<HashSet<OrderId>> = RefCell::new(HashSet::new());
+
+    // Price candles per (maker asset, taker asset, interval), oldest first
+    static CANDLES: RefCell<HashMap<(Principal, Principal, CandleInterval), VecDeque<Candle>>> = RefCell::new(HashMap::new());
+
+    // icrc1_decimals of each asset, fetched from its ledger
+    static ASSET_DECIMALS: RefCell<HashMap<Principal, u8>> = RefCell::new(HashMap::new());
+
+    // Skip ledger balance checks and transfers, for local deployments with mock tokens
+    static TEST_MODE: RefCell<bool> = const { RefCell::new(false) };
+}
+
+// Mock clock so unit tests can run outside a canister and simulate time passing
+#[cfg(test)]
+thread_local! {
+    static TEST_TIME: RefCell<u64> = const { RefCell::new(1_000_000_000_000) };
+}
+
+/// Current canister time in nanoseconds
+#[cfg(not(test))]
+pub fn current_time() -> u64 {
+    ic_cdk::api::time()
+}
+
+/// Current mock time in nanoseconds (for testing)
+#[cfg(test)]
+pub fn current_time() -> u64 {
+    TEST_TIME.with(|time| *time.borrow())
+}
+
+/// Set the mock time (for testing)
+#[cfg(test)]
+pub fn set_test_time(time: u64) {
+    TEST_TIME.with(|current| *current.borrow_mut() = time);
 }
 
 // ============================================================================
@@ -66,6 +135,16 @@ pub fn with_system_stats_read<T>(f: impl FnOnce(&SystemStats) -> T) -> T {
     SYSTEM_STATS.with(|stats| f(&stats.borrow()))
 }
 
+/// Get the system limits currently in force
+pub fn get_runtime_limits() -> RuntimeLimits {
+    RUNTIME_LIMITS.with(|limits| limits.borrow().clone())
+}
+
+/// Replace the system limits; applies to new operations only
+pub fn set_runtime_limits(limits: RuntimeLimits) {
+    RUNTIME_LIMITS.with(|current| *current.borrow_mut() = limits);
+}
+
 /// Generate next unique order ID
 pub fn generate_order_id() -> OrderId {
     with_order_counter(|counter| {
@@ -77,27 +156,32 @@ pub fn generate_order_id() -> OrderId {
 /// Get all active orders (not filled, cancelled, or expired)
 pub fn get_active_orders() -> Vec<Order> {
     with_orders_read(|orders| {
-        let current_time = ic_cdk::api::time();
+        let current_time = current_time();
         orders
             .values()
             .filter(|order| {
                 order.expiration > current_time
                     && !with_filled_orders_read(|filled| filled.contains(&order.id))
                     && !with_cancelled_orders_read(|cancelled| cancelled.contains(&order.id))
+                    && !has_fusion_fill_in_progress(order.id)
+                    && !has_incomplete_fill(order.id)
             })
             .cloned()
             .collect()
     })
 }
 
-/// Check if an order is active (not filled, cancelled, or expired)
+/// Check if an order is active (not filled, cancelled, expired, settling through an escrow or
+/// stuck on an incomplete fill)
 pub fn is_order_active(order_id: OrderId) -> bool {
     with_orders_read(|orders| {
         if let Some(order) = orders.get(&order_id) {
-            let current_time = ic_cdk::api::time();
+            let current_time = current_time();
             order.expiration > current_time
                 && !with_filled_orders_read(|filled| filled.contains(&order_id))
                 && !with_cancelled_orders_read(|cancelled| cancelled.contains(&order_id))
+                && !has_fusion_fill_in_progress(order_id)
+                && !has_incomplete_fill(order_id)
         } else {
             false
         }
@@ -149,6 +233,391 @@ pub fn track_error(error_type: &str) {
     with_system_stats(|stats| {
         stats.track_error(error_type);
     });
+    crate::diagnostics::record_error_event(error_type, current_time());
+}
+
+// ============================================================================
+// FILL HISTORY
+// ============================================================================
+
+/// Record a successful fill in the order's history and the maker's index
+pub fn record_fill(maker: Principal, record: FillRecord) {
+    let order_id = record.order_id;
+    let position = FILL_RECORDS.with(|records| {
+        let mut records = records.borrow_mut();
+        let fills = records.entry(order_id).or_default();
+        fills.push(record);
+        fills.len() - 1
+    });
+    MAKER_FILLS.with(|index| {
+        index.borrow_mut().entry(maker).or_default().push((order_id, position));
+    });
+}
+
+/// Get all fills of an order, oldest first; orders are filled whole, so there is at most one
+pub fn get_fills_for_order(order_id: OrderId) -> Vec<FillRecord> {
+    FILL_RECORDS.with(|records| records.borrow().get(&order_id).cloned().unwrap_or_default())
+}
+
+/// Get a page of fills across all orders of a maker, oldest first
+pub fn get_fills_for_maker(maker: Principal, offset: usize, limit: usize) -> Vec<FillRecord> {
+    let entries: Vec<(OrderId, usize)> = MAKER_FILLS.with(|index| {
+        index
+            .borrow()
+            .get(&maker)
+            .map(|entries| entries.iter().skip(offset).take(limit).cloned().collect())
+            .unwrap_or_default()
+    });
+    FILL_RECORDS.with(|records| {
+        let records = records.borrow();
+        entries
+            .into_iter()
+            .filter_map(|(order_id, position)| records.get(&order_id)?.get(position).cloned())
+            .collect()
+    })
+}
+
+/// Restore fill history after canister upgrade, rebuilding the maker index from stored orders
+fn restore_fill_records(fills: Vec<(OrderId, Vec<FillRecord>)>) {
+    let mut maker_entries: Vec<(Principal, u64, OrderId, usize)> = Vec::new();
+    for (order_id, records) in &fills {
+        if let Some(order) = get_order(*order_id) {
+            for (position, record) in records.iter().enumerate() {
+                maker_entries.push((order.maker, record.timestamp, *order_id, position));
+            }
+        }
+    }
+    maker_entries
+        .sort_by_key(|(_, timestamp, order_id, position)| (*timestamp, *order_id, *position));
+
+    FILL_RECORDS.with(|records| {
+        let mut records = records.borrow_mut();
+        records.clear();
+        records.extend(fills);
+    });
+    MAKER_FILLS.with(|index| {
+        let mut index = index.borrow_mut();
+        index.clear();
+        for (maker, _, order_id, position) in maker_entries {
+            index.entry(maker).or_default().push((order_id, position));
+        }
+    });
+}
+
+// ============================================================================
+// TAKER INTENTS
+// ============================================================================
+
+/// Register a taker as negotiating an order
+pub fn record_order_intent(order_id: OrderId, taker: Principal) {
+    ORDER_INTENTS.with(|intents| {
+        intents.borrow_mut().entry(order_id).or_default().insert(taker);
+    });
+}
+
+/// Check whether a taker registered intent for an order
+pub fn has_order_intent(order_id: OrderId, taker: Principal) -> bool {
+    ORDER_INTENTS.with(|intents| {
+        intents.borrow().get(&order_id).is_some_and(|takers| takers.contains(&taker))
+    })
+}
+
+// ============================================================================
+// ASSET PAUSES
+// ============================================================================
+
+/// Pause trading of a token, replacing the reason if it is already paused
+pub fn pause_asset(token: Principal, reason: String, paused_at: u64) {
+    PAUSED_ASSETS.with(|paused| {
+        paused.borrow_mut().insert(token, PausedAsset { token, reason, paused_at });
+    });
+}
+
+/// Resume trading of a token; returns whether it was paused
+pub fn unpause_asset(token: Principal) -> bool {
+    PAUSED_ASSETS.with(|paused| paused.borrow_mut().remove(&token).is_some())
+}
+
+/// Get the pause of a token, if any
+pub fn get_paused_asset(token: Principal) -> Option<PausedAsset> {
+    PAUSED_ASSETS.with(|paused| paused.borrow().get(&token).cloned())
+}
+
+/// Get all paused tokens, oldest pause first
+pub fn get_paused_assets() -> Vec<PausedAsset> {
+    let mut assets: Vec<PausedAsset> =
+        PAUSED_ASSETS.with(|paused| paused.borrow().values().cloned().collect());
+    assets.sort_by_key(|asset| (asset.paused_at, asset.token));
+    assets
+}
+
+// ============================================================================
+// SELF-TRADE PREVENTION GROUPS
+// ============================================================================
+
+/// Create a self-trade prevention group; returns false if it already exists
+pub fn create_stp_group(name: String, created_at: u64) -> bool {
+    STP_GROUPS.with(|groups| {
+        let mut groups = groups.borrow_mut();
+        if groups.contains_key(&name) {
+            return false;
+        }
+        groups.insert(name, created_at);
+        true
+    })
+}
+
+/// Move a principal into an existing group; returns false if the group does not exist
+pub fn join_stp_group(member: Principal, name: &str) -> bool {
+    if !STP_GROUPS.with(|groups| groups.borrow().contains_key(name)) {
+        return false;
+    }
+    STP_MEMBERSHIP.with(|membership| membership.borrow_mut().insert(member, name.to_string()));
+    true
+}
+
+/// Remove a principal from its group; returns whether it was a member
+pub fn leave_stp_group(member: Principal) -> bool {
+    STP_MEMBERSHIP.with(|membership| membership.borrow_mut().remove(&member).is_some())
+}
+
+/// Get the name of the group a principal belongs to, if any
+pub fn get_stp_group_of(member: Principal) -> Option<String> {
+    STP_MEMBERSHIP.with(|membership| membership.borrow().get(&member).cloned())
+}
+
+/// Get a group with its members sorted
+pub fn get_stp_group(name: &str) -> Option<StpGroup> {
+    let created_at = STP_GROUPS.with(|groups| groups.borrow().get(name).copied())?;
+    let mut members: Vec<Principal> = STP_MEMBERSHIP.with(|membership| {
+        membership.borrow().iter().filter(|(_, group)| *group == name).map(|(m, _)| *m).collect()
+    });
+    members.sort();
+    Some(StpGroup { name: name.to_string(), members, created_at })
+}
+
+// ============================================================================
+// AGENT AUTHORIZATIONS
+// ============================================================================
+
+/// Authorize an agent for a maker, replacing any earlier scope
+pub fn authorize_agent(maker: Principal, authorization: AgentAuthorization) {
+    AGENT_AUTHORIZATIONS.with(|agents| {
+        agents.borrow_mut().insert((maker, authorization.agent), authorization);
+    });
+}
+
+/// Revoke an agent of a maker; returns whether it was authorized
+pub fn revoke_agent(maker: Principal, agent: Principal) -> bool {
+    AGENT_AUTHORIZATIONS.with(|agents| agents.borrow_mut().remove(&(maker, agent)).is_some())
+}
+
+/// Get the authorization a maker gave an agent, expired or not
+pub fn get_agent_authorization(maker: Principal, agent: Principal) -> Option<AgentAuthorization> {
+    AGENT_AUTHORIZATIONS.with(|agents| agents.borrow().get(&(maker, agent)).cloned())
+}
+
+/// Get the agents of a maker, sorted by agent
+pub fn get_agents(maker: Principal) -> Vec<AgentAuthorization> {
+    let mut authorizations: Vec<AgentAuthorization> = AGENT_AUTHORIZATIONS.with(|agents| {
+        agents.borrow().iter().filter(|((m, _), _)| *m == maker).map(|(_, a)| a.clone()).collect()
+    });
+    authorizations.sort_by_key(|authorization| authorization.agent);
+    authorizations
+}
+
+// ============================================================================
+// FUSION FILLS
+// ============================================================================
+
+/// Set the escrow_manager canister that settles Fusion fills
+pub fn set_escrow_manager(canister_id: Option<Principal>) {
+    ESCROW_MANAGER.with(|manager| *manager.borrow_mut() = canister_id);
+}
+
+/// Get the escrow_manager canister that settles Fusion fills, if configured
+pub fn get_escrow_manager() -> Option<Principal> {
+    ESCROW_MANAGER.with(|manager| *manager.borrow())
+}
+
+/// Store the fill of a Fusion order, replacing an earlier failed attempt
+pub fn store_fusion_fill(fill: FusionFill) {
+    FUSION_FILLS.with(|fills| {
+        fills.borrow_mut().insert(fill.order_id, fill);
+    });
+}
+
+/// Get the escrow-settled fill of an order
+pub fn get_fusion_fill(order_id: OrderId) -> Option<FusionFill> {
+    FUSION_FILLS.with(|fills| fills.borrow().get(&order_id).cloned())
+}
+
+/// Find the Fusion fill settled by the escrow with the given id
+pub fn find_fusion_fill_by_escrow(escrow_id: &str) -> Option<FusionFill> {
+    FUSION_FILLS.with(|fills| {
+        fills.borrow().values().find(|fill| fill.escrow_id.as_deref() == Some(escrow_id)).cloned()
+    })
+}
+
+/// Remove the escrow-settled fill of an order once it is settled
+pub fn remove_fusion_fill(order_id: OrderId) -> Option<FusionFill> {
+    FUSION_FILLS.with(|fills| fills.borrow_mut().remove(&order_id))
+}
+
+/// Whether an escrow is being created or awaited for the order
+pub fn has_fusion_fill_in_progress(order_id: OrderId) -> bool {
+    FUSION_FILLS.with(|fills| {
+        fills.borrow().get(&order_id).is_some_and(|fill| {
+            matches!(fill.state, OrderState::Pending | OrderState::EscrowCreated)
+        })
+    })
+}
+
+/// Record a direct fill left with only one transfer settled
+pub fn store_incomplete_fill(fill: IncompleteFill) {
+    INCOMPLETE_FILLS.with(|fills| {
+        fills.borrow_mut().insert(fill.order_id, fill);
+    });
+}
+
+/// Get the incomplete fill of an order
+pub fn get_incomplete_fill(order_id: OrderId) -> Option<IncompleteFill> {
+    INCOMPLETE_FILLS.with(|fills| fills.borrow().get(&order_id).cloned())
+}
+
+/// Remove the incomplete fill of an order once it is resolved
+pub fn remove_incomplete_fill(order_id: OrderId) -> Option<IncompleteFill> {
+    INCOMPLETE_FILLS.with(|fills| fills.borrow_mut().remove(&order_id))
+}
+
+/// Whether the order is stuck on an incomplete fill
+pub fn has_incomplete_fill(order_id: OrderId) -> bool {
+    INCOMPLETE_FILLS.with(|fills| fills.borrow().contains_key(&order_id))
+}
+
+/// All incomplete fills, oldest order first
+pub fn list_incomplete_fills() -> Vec<IncompleteFill> {
+    INCOMPLETE_FILLS.with(|fills| fills.borrow().values().cloned().collect())
+}
+
+/// Mark an incomplete fill as being resolved, false if a resolution is already running
+pub fn begin_incomplete_fill_resolution(order_id: OrderId) -> bool {
+    RESOLVING_INCOMPLETE_FILLS.with(|resolving| resolving.borrow_mut().insert(order_id))
+}
+
+/// Clear the in-progress mark of an incomplete fill resolution
+pub fn end_incomplete_fill_resolution(order_id: OrderId) {
+    RESOLVING_INCOMPLETE_FILLS.with(|resolving| {
+        resolving.borrow_mut().remove(&order_id);
+    });
+}
+
+// ============================================================================
+// PRICE CANDLES
+// ============================================================================
+
+/// Update the candles of an asset pair at one interval
+pub fn update_candles(
+    maker_asset: Principal,
+    taker_asset: Principal,
+    interval: CandleInterval,
+    update: impl FnOnce(&mut VecDeque<Candle>),
+) {
+    CANDLES.with(|candles| {
+        update(candles.borrow_mut().entry((maker_asset, taker_asset, interval)).or_default())
+    });
+}
+
+/// Get the stored candles of an asset pair at one interval, oldest first
+pub fn get_stored_candles(
+    maker_asset: Principal,
+    taker_asset: Principal,
+    interval: CandleInterval,
+) -> Vec<Candle> {
+    CANDLES.with(|candles| {
+        candles
+            .borrow()
+            .get(&(maker_asset, taker_asset, interval))
+            .map(|candles| candles.iter().cloned().collect())
+            .unwrap_or_default()
+    })
+}
+
+// ============================================================================
+// CANCELLATION RECORDS
+// ============================================================================
+
+/// Store the receipt of a cancelled order hash
+pub fn record_cancellation(record: CancellationRecord) {
+    CANCELLATION_RECORDS.with(|records| {
+        records.borrow_mut().insert(record.order_hash.clone(), record);
+    });
+}
+
+/// Get the cancellation receipt of an order hash, if any
+pub fn get_cancellation_record(order_hash: &[u8]) -> Option<CancellationRecord> {
+    CANCELLATION_RECORDS.with(|records| records.borrow().get(order_hash).cloned())
+}
+
+/// Set bits in a maker's invalidator slot, returning the slot's bits afterwards
+pub fn invalidate_bits(maker: Principal, slot: u64, bits: u64) -> u64 {
+    BIT_INVALIDATORS.with(|invalidators| {
+        let mut invalidators = invalidators.borrow_mut();
+        let slot_bits = invalidators.entry((maker, slot)).or_default();
+        *slot_bits |= bits;
+        *slot_bits
+    })
+}
+
+/// Get the invalidated bits of a maker's slot
+pub fn get_invalidation_bits(maker: Principal, slot: u64) -> u64 {
+    BIT_INVALIDATORS
+        .with(|invalidators| invalidators.borrow().get(&(maker, slot)).copied().unwrap_or(0))
+}
+
+/// Cache the decimals reported by an asset's ledger
+pub fn set_asset_decimals(token: Principal, decimals: u8) {
+    ASSET_DECIMALS.with(|cache| {
+        cache.borrow_mut().insert(token, decimals);
+    });
+}
+
+/// Get the cached decimals of an asset, if its ledger was queried
+pub fn get_asset_decimals(token: Principal) -> Option<u8> {
+    ASSET_DECIMALS.with(|cache| cache.borrow().get(&token).copied())
+}
+
+// ============================================================================
+// TEST MODE
+// ============================================================================
+
+/// Whether ledger balance checks and transfers are skipped
+pub fn is_test_mode() -> bool {
+    TEST_MODE.with(|mode| *mode.borrow())
+}
+
+/// Enable or disable skipping of ledger balance checks and transfers
+pub fn set_test_mode(enabled: bool) {
+    TEST_MODE.with(|mode| *mode.borrow_mut() = enabled);
+}
+
+/// Count stored orders per state at the given time
+pub fn count_orders_by_state(current_time: u64) -> OrderStateCounts {
+    with_orders_read(|orders| {
+        let mut counts = OrderStateCounts { total: orders.len() as u64, ..Default::default() };
+        for order_id in orders.keys() {
+            if with_filled_orders_read(|filled| filled.contains(order_id)) {
+                counts.filled += 1;
+            } else if with_cancelled_orders_read(|cancelled| cancelled.contains(order_id)) {
+                counts.cancelled += 1;
+            } else if orders[order_id].expiration <= current_time {
+                counts.expired += 1;
+            } else {
+                counts.active += 1;
+            }
+        }
+        counts
+    })
 }
 
 // ============================================================================
@@ -167,6 +636,169 @@ pub fn serialize_limit_order_state(
     (orders.into_iter().collect(), filled, cancelled, counter, stats)
 }
 
+/// Candles of one (maker asset, taker asset, interval), as persisted across upgrades
+pub type CandleSeries = (Principal, Principal, CandleInterval, Vec<Candle>);
+
+/// State added after the original upgrade tuple, kept optional so older snapshots still decode
+#[derive(CandidType, Deserialize, Clone, Debug, Default)]
+pub struct ExtendedState {
+    pub alarm_thresholds: Option<Vec<(String, u64)>>,
+    pub fill_records: Option<Vec<(OrderId, Vec<FillRecord>)>>,
+    pub runtime_limits: Option<RuntimeLimits>,
+    pub order_intents: Option<Vec<(OrderId, Vec<Principal>)>>,
+    pub paused_assets: Option<Vec<PausedAsset>>,
+    pub test_mode: Option<bool>,
+    pub cancellation_records: Option<Vec<CancellationRecord>>,
+    pub bit_invalidators: Option<Vec<(Principal, u64, u64)>>,
+    pub asset_decimals: Option<Vec<(Principal, u8)>>,
+    pub stp_groups: Option<Vec<(String, u64)>>,
+    pub stp_membership: Option<Vec<(Principal, String)>>,
+    pub agent_authorizations: Option<Vec<(Principal, AgentAuthorization)>>,
+    pub fusion_fills: Option<Vec<FusionFill>>,
+    pub escrow_manager: Option<Principal>,
+    pub candles: Option<Vec<CandleSeries>>,
+    pub incomplete_fills: Option<Vec<IncompleteFill>>,
+    pub export_tokens: Option<Vec<(String, Principal)>>,
+}
+
+/// Serialize state that is not part of the original upgrade tuple
+pub fn serialize_extended_state() -> ExtendedState {
+    ExtendedState {
+        alarm_thresholds: Some(crate::diagnostics::get_alarm_thresholds()),
+        fill_records: Some(FILL_RECORDS.with(|records| {
+            records.borrow().iter().map(|(id, fills)| (*id, fills.clone())).collect()
+        })),
+        runtime_limits: Some(get_runtime_limits()),
+        order_intents: Some(ORDER_INTENTS.with(|intents| {
+            intents
+                .borrow()
+                .iter()
+                .map(|(id, takers)| (*id, takers.iter().cloned().collect()))
+                .collect()
+        })),
+        paused_assets: Some(get_paused_assets()),
+        test_mode: Some(is_test_mode()),
+        cancellation_records: Some(
+            CANCELLATION_RECORDS.with(|records| records.borrow().values().cloned().collect()),
+        ),
+        bit_invalidators: Some(BIT_INVALIDATORS.with(|invalidators| {
+            invalidators
+                .borrow()
+                .iter()
+                .map(|((maker, slot), bits)| (*maker, *slot, *bits))
+                .collect()
+        })),
+        asset_decimals: Some(ASSET_DECIMALS.with(|cache| {
+            cache.borrow().iter().map(|(token, decimals)| (*token, *decimals)).collect()
+        })),
+        stp_groups: Some(STP_GROUPS.with(|groups| {
+            groups.borrow().iter().map(|(name, created_at)| (name.clone(), *created_at)).collect()
+        })),
+        stp_membership: Some(STP_MEMBERSHIP.with(|membership| {
+            membership.borrow().iter().map(|(member, group)| (*member, group.clone())).collect()
+        })),
+        agent_authorizations: Some(AGENT_AUTHORIZATIONS.with(|agents| {
+            agents.borrow().iter().map(|((maker, _), a)| (*maker, a.clone())).collect()
+        })),
+        fusion_fills: Some(FUSION_FILLS.with(|fills| fills.borrow().values().cloned().collect())),
+        escrow_manager: get_escrow_manager(),
+        candles: Some(CANDLES.with(|candles| {
+            candles
+                .borrow()
+                .iter()
+                .map(|((maker_asset, taker_asset, interval), candles)| {
+                    (*maker_asset, *taker_asset, *interval, candles.iter().cloned().collect())
+                })
+                .collect()
+        })),
+        incomplete_fills: Some(list_incomplete_fills()),
+        export_tokens: Some(crate::export::get_export_tokens()),
+    }
+}
+
+/// Restore state that is not part of the original upgrade tuple
+///
+/// Must run after `deserialize_limit_order_state` since fill history is indexed by order maker.
+pub fn deserialize_extended_state(state: ExtendedState) {
+    crate::diagnostics::restore_alarm_thresholds(state.alarm_thresholds.unwrap_or_default());
+    restore_fill_records(state.fill_records.unwrap_or_default());
+    set_runtime_limits(state.runtime_limits.unwrap_or_default());
+    ORDER_INTENTS.with(|intents| {
+        let mut intents = intents.borrow_mut();
+        intents.clear();
+        for (order_id, takers) in state.order_intents.unwrap_or_default() {
+            intents.insert(order_id, takers.into_iter().collect());
+        }
+    });
+    PAUSED_ASSETS.with(|paused| {
+        let mut paused = paused.borrow_mut();
+        paused.clear();
+        for asset in state.paused_assets.unwrap_or_default() {
+            paused.insert(asset.token, asset);
+        }
+    });
+    set_test_mode(state.test_mode.unwrap_or_default());
+    CANCELLATION_RECORDS.with(|records| {
+        let mut records = records.borrow_mut();
+        records.clear();
+        for record in state.cancellation_records.unwrap_or_default() {
+            records.insert(record.order_hash.clone(), record);
+        }
+    });
+    BIT_INVALIDATORS.with(|invalidators| {
+        let mut invalidators = invalidators.borrow_mut();
+        invalidators.clear();
+        for (maker, slot, bits) in state.bit_invalidators.unwrap_or_default() {
+            invalidators.insert((maker, slot), bits);
+        }
+    });
+    ASSET_DECIMALS.with(|cache| {
+        *cache.borrow_mut() = state.asset_decimals.unwrap_or_default().into_iter().collect();
+    });
+    STP_GROUPS.with(|groups| {
+        *groups.borrow_mut() = state.stp_groups.unwrap_or_default().into_iter().collect();
+    });
+    STP_MEMBERSHIP.with(|membership| {
+        *membership.borrow_mut() = state.stp_membership.unwrap_or_default().into_iter().collect();
+    });
+    AGENT_AUTHORIZATIONS.with(|agents| {
+        *agents.borrow_mut() = state
+            .agent_authorizations
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(maker, authorization)| ((maker, authorization.agent), authorization))
+            .collect();
+    });
+    FUSION_FILLS.with(|fills| {
+        *fills.borrow_mut() = state
+            .fusion_fills
+            .unwrap_or_default()
+            .into_iter()
+            .map(|fill| (fill.order_id, fill))
+            .collect();
+    });
+    set_escrow_manager(state.escrow_manager);
+    CANDLES.with(|candles| {
+        *candles.borrow_mut() = state
+            .candles
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(maker_asset, taker_asset, interval, candles)| {
+                ((maker_asset, taker_asset, interval), candles.into())
+            })
+            .collect();
+    });
+    INCOMPLETE_FILLS.with(|fills| {
+        *fills.borrow_mut() = state
+            .incomplete_fills
+            .unwrap_or_default()
+            .into_iter()
+            .map(|fill| (fill.order_id, fill))
+            .collect();
+    });
+    crate::export::restore_export_tokens(state.export_tokens.unwrap_or_default());
+}
+
 /// Deserialize limit order state after canister upgrade
 pub fn deserialize_limit_order_state(
     orders: Vec<(OrderId, Order)>,
@@ -211,10 +843,29 @@ pub fn deserialize_limit_order_state(
 }
 
 /// Clear all limit order data (for testing)
+#[cfg(test)]
 pub fn clear_limit_order_data() {
     with_orders(|orders| orders.clear());
     with_filled_orders(|filled| filled.clear());
     with_cancelled_orders(|cancelled| cancelled.clear());
     with_order_counter(|counter| *counter = 0);
     with_system_stats(|stats| *stats = SystemStats::default());
+    FILL_RECORDS.with(|records| records.borrow_mut().clear());
+    MAKER_FILLS.with(|index| index.borrow_mut().clear());
+    ORDER_INTENTS.with(|intents| intents.borrow_mut().clear());
+    PAUSED_ASSETS.with(|paused| paused.borrow_mut().clear());
+    CANCELLATION_RECORDS.with(|records| records.borrow_mut().clear());
+    BIT_INVALIDATORS.with(|invalidators| invalidators.borrow_mut().clear());
+    ASSET_DECIMALS.with(|cache| cache.borrow_mut().clear());
+    STP_GROUPS.with(|groups| groups.borrow_mut().clear());
+    STP_MEMBERSHIP.with(|membership| membership.borrow_mut().clear());
+    AGENT_AUTHORIZATIONS.with(|agents| agents.borrow_mut().clear());
+    FUSION_FILLS.with(|fills| fills.borrow_mut().clear());
+    set_escrow_manager(None);
+    CANDLES.with(|candles| candles.borrow_mut().clear());
+    INCOMPLETE_FILLS.with(|fills| fills.borrow_mut().clear());
+    RESOLVING_INCOMPLETE_FILLS.with(|resolving| resolving.borrow_mut().clear());
+    set_runtime_limits(RuntimeLimits::default());
+    crate::diagnostics::clear_diagnostics_data();
+    crate::export::restore_export_tokens(vec![]);
 }
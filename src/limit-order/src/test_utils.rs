@@ -1,6 +1,9 @@
 use crate::memory::{clear_limit_order_data, generate_order_id};
 use crate::mock_icrc1_token::{cleanup_test_tokens, setup_test_tokens, Account, TransferArgs};
-use crate::types::{CreateOrderParams, Order, OrderError, OrderId, SystemStats};
+use crate::types::{
+    CreateOrderParams, MakerTraits, Order, OrderError, OrderId, OrderType, ProcessingStrategy,
+    SystemStats, TakerTraits,
+};
 use candid::Principal;
 use std::str::FromStr;
 
@@ -22,7 +25,7 @@ impl OrderTestFixtures {
 
     /// Generate test token canisters (using valid principals that are different)
     pub fn test_token_canisters() -> (Principal, Principal) {
-        let token_a = Principal::management_canister(); // Management canister as token A
+        let token_a = Principal::from_slice(&[0xa; 10]); // Mock token A
         let token_b = Principal::anonymous(); // Anonymous principal as token B
         (token_a, token_b)
     }
@@ -42,7 +45,16 @@ impl OrderTestFixtures {
             making_amount: 1_000_000,                    // 1 TTA
             taking_amount: 2_000_000,                    // 2 TTB
             expiration: current_time + 3600_000_000_000, // 1 hour from now
+            soft_expiry_ns: None,
+            allow_self_trade: None,
+            agent: None,
+            integrator_fee: None,
             created_at: current_time,
+            order_type: OrderType::Normal,
+            processing_strategy: ProcessingStrategy::DirectTransfer,
+            salt: 1,
+            maker_traits: MakerTraits::None,
+            taker_traits: TakerTraits::None,
             metadata: None,
         }
     }
@@ -137,6 +149,9 @@ impl TestEnvironment {
 
         let (maker, taker) = OrderTestFixtures::test_principals();
 
+        // Mock tokens are not real ledgers, so skip balance checks and transfers
+        crate::memory::set_test_mode(true);
+
         // Setup ICRC-1 mock tokens with initial balances for MVP testing
         setup_test_tokens(
             maker,
@@ -153,6 +168,7 @@ impl TestEnvironment {
         cleanup_test_tokens();
         #[cfg(test)]
         clear_limit_order_data();
+        crate::memory::set_test_mode(false);
     }
 
     /// Create test scenario with multiple orders
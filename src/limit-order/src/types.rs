@@ -8,9 +8,18 @@ use std::collections::HashMap;
 
 pub type OrderId = u64;
 
-// System limits
+// System limits (defaults for the runtime-tunable RuntimeLimits)
 pub const MAX_ACTIVE_ORDERS: usize = 10_000;
+pub const MAX_ORDERS_PER_MAKER: usize = 100;
 pub const MAX_EXPIRATION_DAYS: u64 = 30;
+pub const MIN_EXPIRATION_SECS: u64 = 60;
+pub const MIN_ORDER_AMOUNT: u64 = 1;
+pub const MAX_TOKEN_AMOUNT: u64 = u64::MAX / 1000; // Leave room for calculations
+
+// Sanity bounds enforced when limits are changed at runtime
+pub const RUNTIME_MIN_ACTIVE_ORDERS: u64 = 100;
+pub const RUNTIME_MAX_ACTIVE_ORDERS: u64 = 1_000_000;
+pub const RUNTIME_MAX_EXPIRATION_DAYS: u64 = 365;
 
 // ============================================================================
 // HASHLOCK & TIMELOCK TYPES - Cross-Chain Functionality
@@ -119,6 +128,10 @@ pub struct Order {
     pub making_amount: u64,
     pub taking_amount: u64,
     pub expiration: u64, // Nanoseconds since epoch
+    pub soft_expiry_ns: Option<u64>, // Start of the grace window before hard expiration
+    pub allow_self_trade: Option<bool>, // Maker opt-out of self-trade prevention groups
+    pub agent: Option<Principal>,       // Session key that created the order for the maker
+    pub integrator_fee: Option<IntegratorFee>, // Frontend share of the taker asset of each fill
     pub created_at: u64,
 
     // Order Type Classification
@@ -134,6 +147,16 @@ pub struct Order {
     pub metadata: Option<OrderMetadata>,
 }
 
+/// Share of each fill's taker asset paid to the frontend that placed the order
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct IntegratorFee {
+    pub recipient: Principal,
+    pub bps: u16, // At most MAX_INTEGRATOR_FEE_BPS
+}
+
+/// Largest integrator fee an order may carry, in basis points of the taking amount
+pub const MAX_INTEGRATOR_FEE_BPS: u16 = 100;
+
 #[derive(CandidType, Deserialize, Clone, Debug)]
 pub struct OrderMetadata {
     // Cross-chain fields for fusion orders
@@ -158,6 +181,95 @@ pub enum OrderState {
     Failed,        // Fusion order: escrow creation or completion failed
 }
 
+/// Fill of an escrow-coordinated order waiting for the escrow_manager to settle it
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct FusionFill {
+    pub order_id: OrderId,
+    pub taker: Principal,
+    pub making_amount: u64,
+    pub taking_amount: u64,
+    pub escrow_id: Option<String>, // Returned by the escrow_manager once the escrow exists
+    pub state: OrderState,          // Pending, EscrowCreated or Failed
+    pub started_at: u64,
+}
+
+/// Error returned by the escrow_manager, mirrored so its rejections keep their reason
+///
+/// Must list every variant of the escrow_manager's `EscrowError`, or replies using a missing one
+/// fail to decode.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub enum EscrowManagerError {
+    EscrowNotFound,
+    InsufficientBalance,
+    Unauthorized,
+    InvalidState,
+    TimelockNotExpired,
+    TimelockExpired,
+    InvalidReceipt,
+    TransferFailed,
+    OrderNotFound,
+    SystemError,
+    ChainFusionRequestFailed,
+    ThresholdECDSAUnavailable,
+    EVMAddressDerivationFailed,
+    EVMEscrowCreationFailed,
+    NetworkPartitionDetected,
+    ChainHealthDegraded,
+    InsufficientConfirmations,
+    InvalidHashlock,
+    InvalidOrderHash,
+    InvalidAddress,
+    InvalidToken,
+    InvalidAmount,
+    TimelockTooShort,
+    EscrowAlreadyExists,
+    InvalidTimelockCoordination,
+    SecretVerificationFailed,
+    CrossChainCoordinationFailed,
+    StateTransitionInvalid,
+    EventLoggingFailed,
+    SlippageProtectionViolation,
+    ExecutionAmountMismatch,
+    InvalidPartialFill,
+    PartialFillValidationFailed,
+    ChainFusion { method: String, detail: String },
+    Ecdsa { stage: String, detail: String },
+    ProviderConsensusFailure { method: String, detail: String },
+    OperationInProgress,
+}
+
+/// Token transfer of a direct fill, in execution order
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum FillLeg {
+    TakerToReceiver,   // Taker asset paid to the order receiver, less the integrator fee
+    TakerToIntegrator, // Integrator fee paid to its recipient, only for orders carrying one
+    MakerToTaker,      // Maker asset paid to the taker
+}
+
+/// Fill where a transfer and the rollback of an earlier leg both failed
+///
+/// `completed_leg` and every leg before it stay settled. Identified by its order id, since the
+/// order cannot be filled again until it is resolved.
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct IncompleteFill {
+    pub order_id: OrderId,
+    pub taker: Principal,
+    pub completed_leg: FillLeg,
+    pub pending_leg: FillLeg,
+    pub block_index: u64, // Ledger block of the completed leg
+    pub making_amount: u64,
+    pub taking_amount: u64,
+    pub timestamp: u64,
+}
+
+/// How an operator settles an incomplete fill
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum IncompleteFillResolution {
+    RetrySecondLeg, // Execute the legs after the completed one and finish the fill
+    ManualSettled,  // The pending legs were settled outside the canister; finish the fill
+    RefundFirstLeg, // Reverse the settled legs and reopen the order
+}
+
 #[derive(CandidType, Deserialize, Clone, Debug)]
 pub enum OrderError {
     // Validation Errors
@@ -167,6 +279,7 @@ pub enum OrderError {
     InvalidReceiver,
     InvalidOrderId,
     InvalidPrincipal,
+    PriceDeviationWarning(String, String), // Order price, reference price from recent fills
 
     // State Errors
     OrderNotFound,
@@ -174,6 +287,7 @@ pub enum OrderError {
     OrderCancelled,
     OrderExpired,
     OrderInactive,
+    OrderInGracePeriod,
 
     // Authorization Errors
     Unauthorized,
@@ -181,6 +295,11 @@ pub enum OrderError {
     InsufficientAmount,
     AnonymousCaller,
     NotOrderMaker,
+    SelfTradeBlocked(String), // Self-trade prevention group shared by maker and taker
+    StpGroupNotFound(String),
+    AgentNotAuthorized,          // No unexpired authorization from the maker
+    AgentScopeExceeded(String), // Scope limit the action would break
+    IntegratorFeeTooHigh(u16),  // Largest fee allowed, in basis points
     
     // 1inch LOP Compliance Errors
     MismatchArraysLengths,
@@ -190,6 +309,7 @@ pub enum OrderError {
     TransferFailed(String),
     BalanceCheckFailed(String),
     TokenNotSupported(String),
+    AssetPaused(String), // Reason given by the controller
 
     // Cross-Chain Errors
     InvalidHashlock,
@@ -218,6 +338,7 @@ pub enum OrderError {
     SystemError(String),
     MemoryError(String),
     ConcurrencyError(String),
+    InvalidConfiguration(String),
 
     // Rate Limiting & DoS Protection
     TooManyOrders,
@@ -234,6 +355,346 @@ pub struct SystemStats {
     pub error_counts: HashMap<String, u64>,    // Error frequency tracking
 }
 
+// ============================================================================
+// RUNTIME LIMITS - Controller-Tunable System Limits
+// ============================================================================
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct RuntimeLimits {
+    pub max_active_orders: u64,
+    pub max_orders_per_maker: u64,
+    pub max_expiration_days: u64,
+    pub min_expiration_secs: u64,
+    pub min_order_amount: u64, // Dust threshold for making and taking amounts
+    pub max_token_amount: u64,
+    pub max_price_deviation_bps: Option<u64>, // Largest deviation from recent fills; None disables the check
+}
+
+impl Default for RuntimeLimits {
+    fn default() -> Self {
+        Self {
+            max_active_orders: MAX_ACTIVE_ORDERS as u64,
+            max_orders_per_maker: MAX_ORDERS_PER_MAKER as u64,
+            max_expiration_days: MAX_EXPIRATION_DAYS,
+            min_expiration_secs: MIN_EXPIRATION_SECS,
+            min_order_amount: MIN_ORDER_AMOUNT,
+            max_token_amount: MAX_TOKEN_AMOUNT,
+            max_price_deviation_bps: None,
+        }
+    }
+}
+
+impl RuntimeLimits {
+    /// Sanity-check limits before they replace the current ones
+    pub fn validate(&self) -> OrderResult<()> {
+        let invalid = |msg: &str| Err(OrderError::InvalidConfiguration(msg.to_string()));
+
+        if !(RUNTIME_MIN_ACTIVE_ORDERS..=RUNTIME_MAX_ACTIVE_ORDERS)
+            .contains(&self.max_active_orders)
+        {
+            return invalid("max_active_orders must be between 100 and 1000000");
+        }
+        if self.max_orders_per_maker == 0 || self.max_orders_per_maker > self.max_active_orders {
+            return invalid("max_orders_per_maker must be between 1 and max_active_orders");
+        }
+        if self.max_expiration_days == 0 || self.max_expiration_days > RUNTIME_MAX_EXPIRATION_DAYS {
+            return invalid("max_expiration_days must be between 1 and 365");
+        }
+        if self.min_expiration_secs >= self.max_expiration_days * 24 * 3600 {
+            return invalid("min_expiration_secs must be shorter than max_expiration_days");
+        }
+        if self.min_order_amount == 0 || self.min_order_amount > self.max_token_amount {
+            return invalid("min_order_amount must be between 1 and max_token_amount");
+        }
+        if self.max_token_amount > MAX_TOKEN_AMOUNT {
+            return invalid("max_token_amount exceeds the overflow-safe maximum");
+        }
+        if self.max_price_deviation_bps == Some(0) {
+            return invalid("max_price_deviation_bps must be positive");
+        }
+
+        Ok(())
+    }
+}
+
+// ============================================================================
+// ASSET PAUSES - Controller Trading Halts
+// ============================================================================
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct PausedAsset {
+    pub token: Principal,
+    pub reason: String,
+    pub paused_at: u64,
+}
+
+/// Principals that may not fill each other's orders, e.g. sub-identities of one market maker
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct StpGroup {
+    pub name: String,
+    pub members: Vec<Principal>,
+    pub created_at: u64,
+}
+
+// ============================================================================
+// AGENT AUTHORIZATIONS - Session Keys Acting for a Maker
+// ============================================================================
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct AgentScope {
+    pub can_create: bool,
+    pub can_cancel: bool,
+    pub max_order_size: u64, // Largest making amount of an order the agent may create
+    pub expires_at: u64,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct AgentAuthorization {
+    pub agent: Principal,
+    pub scope: AgentScope,
+    pub authorized_at: u64,
+}
+
+/// Maker action an agent performs, checked against its scope
+#[derive(Clone, Debug, PartialEq)]
+pub enum AgentAction {
+    Create { making_amount: u64 },
+    Cancel,
+}
+
+// ============================================================================
+// CANISTER CONFIGURATION - Install Arguments
+// ============================================================================
+
+#[derive(CandidType, Deserialize, Clone, Debug, Default)]
+pub struct InitArgs {
+    pub test_mode: bool, // Skip ledger balance checks and transfers (local mock tokens only)
+}
+
+// ============================================================================
+// CANCELLATION TYPES - Proofs That an Order Hash Is Dead
+// ============================================================================
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub enum CancellationMethod {
+    Hash, // 1inch cancel_order without extension
+    Bit,  // 1inch bit invalidator, kills every maker order sharing the salt's slot bit
+    Id,   // Canister-native cancellation by order ID
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct CancellationRecord {
+    pub order_hash: Vec<u8>,
+    pub maker: Principal,
+    pub cancelled_at: u64,
+    pub method: CancellationMethod,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub enum DeadReason {
+    Alive, // Also returned for hashes that match no stored order
+    Filled,
+    CancelledByHash,
+    CancelledByBitSlot(u64, u64), // (slot, invalidated bits of the maker's slot)
+    Expired,
+}
+
+// ============================================================================
+// FILL HISTORY TYPES - Maker Trade History
+// ============================================================================
+
+/// Settled fill of an order; orders are filled whole, so each order has at most one
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct FillRecord {
+    pub order_id: OrderId,
+    pub taker: Principal,
+    pub making_amount: u64,
+    pub taking_amount: u64,
+    pub block_indices: (u64, u64), // (taker asset transfer, maker asset transfer)
+    pub timestamp: u64,
+    pub integrator_fee: Option<IntegratorFeePayment>, // None for fills without a fee leg
+}
+
+/// Integrator fee leg of a settled fill
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct IntegratorFeePayment {
+    pub recipient: Principal,
+    pub amount: u64,
+    pub block_index: u64, // Zero when settled outside the canister
+}
+
+/// Implied price of an order, in taker asset per whole maker asset
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct PriceInfo {
+    pub numerator: u128, // Reduced fraction numerator / denominator
+    pub denominator: u128,
+    pub display: String,
+    pub decimals_adjusted: bool, // False when a ledger's decimals are unknown and the raw ratio is used
+}
+
+/// How an order price compares with the pair's recent fills
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct PriceSanity {
+    pub order_price: PriceInfo,
+    pub reference_price: Option<PriceInfo>, // Volume-weighted over recent fills; None without any
+    pub deviation_bps: Option<u64>,
+    pub max_deviation_bps: Option<u64>,
+    pub exceeds_limit: bool,
+}
+
+/// Width of the candles fills are charted in
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CandleInterval {
+    FiveMinutes, // Kept for 7 days
+    OneHour,     // Kept for 90 days
+}
+
+impl CandleInterval {
+    /// Width of one candle in nanoseconds
+    pub fn duration_ns(self) -> u64 {
+        match self {
+            CandleInterval::FiveMinutes => 5 * 60 * 1_000_000_000,
+            CandleInterval::OneHour => 60 * 60 * 1_000_000_000,
+        }
+    }
+
+    /// How long candles are kept, in nanoseconds
+    pub fn retention_ns(self) -> u64 {
+        let day = 24 * 60 * 60 * 1_000_000_000;
+        match self {
+            CandleInterval::FiveMinutes => 7 * day,
+            CandleInterval::OneHour => 90 * day,
+        }
+    }
+}
+
+/// Implied prices and volume of an asset pair's fills during one interval
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct Candle {
+    pub start_ns: u64,
+    pub open: PriceInfo,
+    pub high: PriceInfo,
+    pub low: PriceInfo,
+    pub close: PriceInfo,
+    pub making_volume: u64, // Maker asset filled during the interval
+    pub taking_volume: u64, // Taker asset filled during the interval
+    pub fill_count: u64,    // Zero for intervals without fills between two charted ones
+}
+
+/// Page of active orders with a witness against the canister's certified data
+///
+/// The certificate is only present for query calls; the witness is a CBOR-encoded hash tree.
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct CertifiedOrders {
+    pub orders: Vec<Order>,
+    pub page: u64,
+    pub page_count: u64,
+    pub certificate: Vec<u8>,
+    pub witness: Vec<u8>,
+}
+
+/// HTTP gateway request
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct HttpRequest {
+    pub method: String,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+/// HTTP gateway response
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct HttpResponse {
+    pub status_code: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+/// Order lookup by canister order ID or by order hash
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub enum OrderReference {
+    Id(OrderId),
+    Hash(Vec<u8>),
+}
+
+/// Predicted transfer legs of a fill, computed without executing it
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct FillSimulation {
+    pub making_amount: u64, // Maker asset the taker would receive
+    pub taking_amount: u64, // Taker asset the taker would send
+    pub protocol_fee: u64,
+    pub integrator_fee: u64, // Part of the taking amount paid to the integrator, not the maker
+    pub ledger_fees: Vec<(Principal, u64)>, // Fee charged by each ledger for its transfer
+    pub would_succeed: bool,
+    pub failure_reason: Option<OrderError>,
+}
+
+// ============================================================================
+// DIAGNOSTICS TYPES - Error Alarms and Incident Triage
+// ============================================================================
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct ErrorEvent {
+    pub error_type: String,
+    pub timestamp: u64,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub struct ErrorAlarm {
+    pub error_type: String,
+    pub max_per_hour: u64,
+    pub count_in_window: u64,
+    pub fired_at: u64,
+    pub cleared_at: Option<u64>,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct OrderStateCounts {
+    pub total: u64,
+    pub active: u64,
+    pub filled: u64,
+    pub cancelled: u64,
+    pub expired: u64,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, Default)]
+pub struct MemoryStatistics {
+    pub orders_stored: u64,
+    pub filled_tracked: u64,
+    pub cancelled_tracked: u64,
+    pub error_events_buffered: u64,
+    pub stable_memory_pages: u64,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq)]
+pub enum HealthStatus {
+    Healthy,
+    Degraded,  // Recent critical errors, alarm timer missing or stable memory near its limit
+    Unhealthy, // Storage invariants violated
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct HealthReport {
+    pub status: HealthStatus,
+    pub order_count: u64,
+    pub active_order_count: u64,
+    pub last_error: Option<String>,
+    pub stable_memory_ok: bool,
+    pub timer_registered: bool,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct DiagnosticsDump {
+    pub generated_at: u64,
+    pub recent_errors: Vec<ErrorEvent>,
+    pub error_counts: HashMap<String, u64>,
+    pub alarm_thresholds: Vec<(String, u64)>,
+    pub active_alarms: Vec<ErrorAlarm>,
+    pub alarm_history: Vec<ErrorAlarm>,
+    pub order_counts: OrderStateCounts,
+    pub memory: MemoryStatistics,
+}
+
 #[derive(CandidType, Deserialize, Clone, Debug)]
 pub struct CreateOrderParams {
     pub receiver: Principal,
@@ -244,6 +705,17 @@ pub struct CreateOrderParams {
     pub expiration: u64,
 }
 
+/// Optional settings of a new order
+#[derive(CandidType, Deserialize, Clone, Debug, Default)]
+pub struct CreateOrderOptions {
+    pub soft_expiry_ns: Option<u64>,
+    pub allow_self_trade: Option<bool>, // Maker opt-out of self-trade prevention groups
+    pub on_behalf_of: Option<Principal>, // Maker an authorized agent creates the order for
+    pub integrator_fee: Option<IntegratorFee>,
+    pub accept_price_warning: Option<bool>, // Create even if priced far from recent fills
+    pub escrow_terms: Option<OrderMetadata>, // Hashlock, timelock and safety deposit; fills settle through the escrow_manager
+}
+
 pub type OrderResult<T> = std::result::Result<T, OrderError>;
 
 impl std::fmt::Display for OrderError {
@@ -255,21 +727,41 @@ impl std::fmt::Display for OrderError {
             OrderError::InvalidReceiver => write!(f, "Invalid receiver"),
             OrderError::InvalidOrderId => write!(f, "Invalid order ID"),
             OrderError::InvalidPrincipal => write!(f, "Invalid principal"),
+            OrderError::PriceDeviationWarning(order_price, reference_price) => write!(
+                f,
+                "Order price {} deviates from the recent fill price {}",
+                order_price, reference_price
+            ),
             OrderError::OrderNotFound => write!(f, "Order not found"),
             OrderError::OrderAlreadyFilled => write!(f, "Order already filled"),
             OrderError::OrderCancelled => write!(f, "Order cancelled"),
             OrderError::OrderExpired => write!(f, "Order expired"),
             OrderError::OrderInactive => write!(f, "Order inactive"),
+            OrderError::OrderInGracePeriod => {
+                write!(f, "Order in expiry grace period, only negotiating takers may fill")
+            }
             OrderError::Unauthorized => write!(f, "Unauthorized"),
             OrderError::InsufficientBalance => write!(f, "Insufficient balance"),
             OrderError::InsufficientAmount => write!(f, "Insufficient amount"),
             OrderError::AnonymousCaller => write!(f, "Anonymous caller not allowed"),
             OrderError::NotOrderMaker => write!(f, "Not the order maker"),
+            OrderError::SelfTradeBlocked(group) => {
+                write!(f, "Maker and taker share self-trade prevention group {}", group)
+            }
+            OrderError::StpGroupNotFound(group) => {
+                write!(f, "Self-trade prevention group not found: {}", group)
+            }
+            OrderError::AgentNotAuthorized => write!(f, "Agent not authorized by the maker"),
+            OrderError::AgentScopeExceeded(limit) => write!(f, "Agent scope exceeded: {}", limit),
+            OrderError::IntegratorFeeTooHigh(max_bps) => {
+                write!(f, "Integrator fee above the {} bps maximum", max_bps)
+            }
             OrderError::MismatchArraysLengths => write!(f, "Mismatched array lengths"),
             OrderError::TokenCallFailed(msg) => write!(f, "Token call failed: {}", msg),
             OrderError::TransferFailed(msg) => write!(f, "Transfer failed: {}", msg),
             OrderError::BalanceCheckFailed(msg) => write!(f, "Balance check failed: {}", msg),
             OrderError::TokenNotSupported(msg) => write!(f, "Token not supported: {}", msg),
+            OrderError::AssetPaused(reason) => write!(f, "Asset paused: {}", reason),
 
             // Cross-Chain Errors
             OrderError::InvalidHashlock => write!(f, "Invalid hashlock"),
@@ -303,6 +795,7 @@ impl std::fmt::Display for OrderError {
             OrderError::SystemError(msg) => write!(f, "System error: {}", msg),
             OrderError::MemoryError(msg) => write!(f, "Memory error: {}", msg),
             OrderError::ConcurrencyError(msg) => write!(f, "Concurrency error: {}", msg),
+            OrderError::InvalidConfiguration(msg) => write!(f, "Invalid configuration: {}", msg),
             OrderError::TooManyOrders => write!(f, "Too many orders"),
             OrderError::OrderCreationRateLimited => write!(f, "Order creation rate limited"),
             OrderError::SystemOverloaded => write!(f, "System overloaded"),
@@ -434,4 +927,27 @@ impl TokenInterface {
             }
         }
     }
+
+    pub async fn decimals(&self) -> OrderResult<u8> {
+        let result: std::result::Result<(u8,), _> =
+            ic_cdk::api::call::call(self.canister_id, "icrc1_decimals", ()).await;
+
+        match result {
+            Ok((decimals,)) => Ok(decimals),
+            Err(e) => Err(OrderError::TokenCallFailed(format!("Decimals query failed: {:?}", e))),
+        }
+    }
+
+    pub async fn fee(&self) -> OrderResult<u64> {
+        let result: std::result::Result<(candid::Nat,), _> =
+            ic_cdk::api::call::call(self.canister_id, "icrc1_fee", ()).await;
+
+        match result {
+            Ok((fee,)) => fee
+                .0
+                .try_into()
+                .map_err(|_| OrderError::TokenCallFailed("Fee too large for u64".to_string())),
+            Err(e) => Err(OrderError::TokenCallFailed(format!("Fee query failed: {:?}", e))),
+        }
+    }
 }
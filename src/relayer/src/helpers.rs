@@ -1,11 +1,20 @@
-use crate::types::{CrossChainOrderDto, FusionError};
+use crate::memory;
+use crate::types::{AmountCaps, CrossChainOrderDto, EscrowContracts, FusionError, SupportedChain};
+use candid::{Nat, Principal};
+use ic_cdk::api::call::{CallResult, RejectionCode};
+use icrc_ledger_types::icrc1::account::Account;
+use icrc_ledger_types::icrc1::transfer::{TransferArg, TransferError};
+use icrc_ledger_types::icrc2::transfer_from::{TransferFromArgs, TransferFromError};
 
 // ============================================================================
 // VALIDATION HELPERS
 // ============================================================================
 
 /// Helper function to validate 1inch order parameters
-pub fn validate_order_parameters(order: &CrossChainOrderDto) -> Result<(), FusionError> {
+pub fn validate_order_parameters(
+    order: &CrossChainOrderDto,
+    caps: &AmountCaps,
+) -> Result<(), FusionError> {
     // Validate salt
     if order.salt.is_empty() {
         return Err(FusionError::InvalidSalt);
@@ -28,21 +37,79 @@ pub fn validate_order_parameters(order: &CrossChainOrderDto) -> Result<(), Fusio
         return Err(FusionError::TokenAddressInvalid);
     }
 
-    // Validate amounts (must be parseable as numbers)
-    if order.making_amount.parse::<u64>().is_err() {
-        return Err(FusionError::InvalidAmount);
+    // Validate amounts (must be non-zero decimal integers within the chain's caps)
+    let making_amount = parse_amount(&order.making_amount)?;
+    let taking_amount = parse_amount(&order.taking_amount)?;
+
+    if making_amount > caps.max_making_amount {
+        return Err(FusionError::AmountExceedsCap(caps.max_making_amount.clone()));
+    }
+
+    if taking_amount > caps.max_taking_amount {
+        return Err(FusionError::AmountExceedsCap(caps.max_taking_amount.clone()));
     }
 
-    if order.taking_amount.parse::<u64>().is_err() {
+    Ok(())
+}
+
+/// Parse a non-zero decimal amount of arbitrary size
+pub fn parse_amount(amount: &str) -> Result<Nat, FusionError> {
+    if amount.is_empty() || !amount.bytes().all(|b| b.is_ascii_digit()) {
         return Err(FusionError::InvalidAmount);
     }
 
-    // Check that amounts are not zero
-    if order.making_amount == "0" || order.taking_amount == "0" {
+    let amount = Nat::parse(amount.as_bytes()).map_err(|_| FusionError::InvalidAmount)?;
+    if amount == 0u64 {
         return Err(FusionError::InvalidAmount);
     }
+    Ok(amount)
+}
 
-    Ok(())
+/// Validate that every registered contract is a well-formed Ethereum address
+pub fn validate_escrow_contracts(contracts: &EscrowContracts) -> Result<(), FusionError> {
+    let addresses = [&contracts.factory, &contracts.src_impl, &contracts.dst_impl, &contracts.lop];
+    if addresses.iter().all(|address| is_valid_eth_address(address)) {
+        Ok(())
+    } else {
+        Err(FusionError::TokenAddressInvalid)
+    }
+}
+
+/// Validate that a chain's escrow factory, if it has one, is a well-formed Ethereum address
+pub fn validate_supported_chain(chain: &SupportedChain) -> Result<(), FusionError> {
+    match &chain.escrow_factory {
+        Some(factory) if !is_valid_eth_address(factory) => Err(FusionError::TokenAddressInvalid),
+        _ => Ok(()),
+    }
+}
+
+/// Reject callers that are not controllers of this canister
+pub fn require_controller() -> Result<(), FusionError> {
+    if ic_cdk::api::is_controller(&ic_cdk::caller()) {
+        Ok(())
+    } else {
+        Err(FusionError::Unauthorized)
+    }
+}
+
+/// Reject callers that are neither registered resolvers nor controllers
+pub fn require_resolver() -> Result<(), FusionError> {
+    let caller = ic_cdk::caller();
+    if memory::is_resolver(&caller) || ic_cdk::api::is_controller(&caller) {
+        Ok(())
+    } else {
+        Err(FusionError::Unauthorized)
+    }
+}
+
+/// Reject callers other than the resolver that recorded an order's escrow
+///
+/// An order without a recorded escrow has no owner, so every caller is rejected.
+pub fn require_escrow_owner(order_id: &str, caller: Principal) -> Result<(), FusionError> {
+    match memory::get_escrow_owner(order_id) {
+        Some(owner) if owner == caller => Ok(()),
+        _ => Err(FusionError::Unauthorized),
+    }
 }
 
 /// Validate Ethereum address format
@@ -52,6 +119,156 @@ pub fn is_valid_eth_address(address: &str) -> bool {
         && address[2..].chars().all(|c| c.is_ascii_hexdigit())
 }
 
+// ============================================================================
+// SIGNATURE HELPERS
+// ============================================================================
+
+/// Half of the secp256k1 group order; larger s values are malleable duplicates
+const SECP256K1_HALF_ORDER: [u8; 32] = [
+    0x7f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0x5d, 0x57, 0x6e, 0x73, 0x57, 0xa4, 0x50, 0x1d, 0xdf, 0xe9, 0x2f, 0x46, 0x68, 0x1b, 0x20, 0xa0,
+];
+
+/// Validate an ECDSA signature and normalize it to 0x-prefixed 65-byte r ‖ s ‖ v hex
+///
+/// Accepts EIP-2098 64-byte compact signatures and v values of 0/1, which are expanded to
+/// the 65-byte form with v of 27/28. Signatures with s above the half order are rejected.
+pub fn normalize_signature(signature: &str) -> Result<String, FusionError> {
+    let invalid = |reason: String| Err(FusionError::InvalidEIP712Signature(reason));
+
+    let Some(hex_part) = signature.strip_prefix("0x") else {
+        return invalid("missing 0x prefix".to_string());
+    };
+    let Ok(mut bytes) = hex::decode(hex_part) else {
+        return invalid("not valid hex".to_string());
+    };
+
+    match bytes.len() {
+        65 => {}
+        64 => {
+            // EIP-2098: the top bit of s carries the y parity
+            let y_parity = bytes[32] >> 7;
+            bytes[32] &= 0x7f;
+            bytes.push(27 + y_parity);
+        }
+        length => {
+            return invalid(format!("expected 65 bytes or 64-byte compact form, got {}", length))
+        }
+    }
+
+    bytes[64] = match bytes[64] {
+        0 | 1 => bytes[64] + 27,
+        27 | 28 => bytes[64],
+        v => return invalid(format!("invalid recovery id v={}", v)),
+    };
+
+    let (r, s) = (&bytes[..32], &bytes[32..64]);
+    if r.iter().all(|b| *b == 0) || s.iter().all(|b| *b == 0) {
+        return invalid("r and s must be non-zero".to_string());
+    }
+    if s > &SECP256K1_HALF_ORDER[..] {
+        return invalid("malleable signature: s above half curve order".to_string());
+    }
+
+    Ok(format!("0x{}", hex::encode(bytes)))
+}
+
+// ============================================================================
+// SECRET SCHEDULE HELPERS
+// ============================================================================
+
+/// Fill progress of a completely filled order, in basis points
+pub const FULL_FILL_BPS: u32 = 10_000;
+
+/// Whether secret `idx` of `secret_count` may be revealed at the given fill progress
+///
+/// Secret i (0-based) covers the fill up to (i + 1) / N of the order, so the last secret only
+/// unlocks once the order is completely filled.
+pub fn is_secret_unlocked(idx: usize, secret_count: usize, fill_progress_bps: u32) -> bool {
+    fill_progress_bps as u64 * secret_count as u64 >= (idx as u64 + 1) * FULL_FILL_BPS as u64
+}
+
+/// Find the secret hash a secret opens, returning its index and the 0x-prefixed lowercase secret
+pub fn match_secret(
+    secret: &str,
+    secret_hashes: &[String],
+) -> Result<(usize, String), FusionError> {
+    let bytes = hex::decode(secret.strip_prefix("0x").unwrap_or(secret))
+        .map_err(|_| FusionError::InvalidSecretHash)?;
+    let hash = hex::encode(fusion_crypto::keccak256(&bytes));
+
+    secret_hashes
+        .iter()
+        .position(|secret_hash| secret_hash.eq_ignore_ascii_case(&hash))
+        .map(|idx| (idx, format!("0x{}", hex::encode(&bytes))))
+        .ok_or(FusionError::InvalidSecretHash)
+}
+
+// ============================================================================
+// DEPOSIT LEDGER HELPERS
+// ============================================================================
+
+/// Pull deposit funds the submitter approved for the relayer (ICRC-2)
+pub async fn collect_deposit(
+    ledger: Principal,
+    from: Principal,
+    amount: u64,
+) -> Result<(), FusionError> {
+    let args = TransferFromArgs {
+        spender_subaccount: None,
+        from: Account { owner: from, subaccount: None },
+        to: Account { owner: ic_cdk::id(), subaccount: None },
+        amount: Nat::from(amount),
+        fee: None,
+        memo: None,
+        created_at_time: None,
+    };
+
+    let result: CallResult<(Result<Nat, TransferFromError>,)> =
+        ic_cdk::call(ledger, "icrc2_transfer_from", (args,)).await;
+    match result {
+        Ok((Ok(_),)) => Ok(()),
+        Ok((Err(e),)) => Err(FusionError::DepositTransferFailed(format!("{:?}", e))),
+        Err(e) => Err(ledger_call_failed(e)),
+    }
+}
+
+/// Pay deposit funds back to a submitter, net of the ledger fee, returning the amount sent
+pub async fn pay_out_deposit(
+    ledger: Principal,
+    to: Principal,
+    amount: u64,
+) -> Result<u64, FusionError> {
+    let fee: CallResult<(Nat,)> = ic_cdk::call(ledger, "icrc1_fee", ()).await;
+    let fee = match fee {
+        Ok((fee,)) => u64::try_from(fee.0).map_err(|_| FusionError::InvalidAmount)?,
+        Err(e) => return Err(ledger_call_failed(e)),
+    };
+    let payout =
+        amount.checked_sub(fee).filter(|payout| *payout > 0).ok_or(FusionError::InvalidAmount)?;
+
+    let args = TransferArg {
+        from_subaccount: None,
+        to: Account { owner: to, subaccount: None },
+        amount: Nat::from(payout),
+        fee: Some(Nat::from(fee)),
+        memo: None,
+        created_at_time: None,
+    };
+    let result: CallResult<(Result<Nat, TransferError>,)> =
+        ic_cdk::call(ledger, "icrc1_transfer", (args,)).await;
+    match result {
+        Ok((Ok(_),)) => Ok(payout),
+        Ok((Err(e),)) => Err(FusionError::DepositTransferFailed(format!("{:?}", e))),
+        Err(e) => Err(ledger_call_failed(e)),
+    }
+}
+
+/// Error for a ledger call the ledger rejected or never answered
+fn ledger_call_failed((code, message): (RejectionCode, String)) -> FusionError {
+    FusionError::DepositTransferFailed(format!("{:?}: {}", code, message))
+}
+
 // ============================================================================
 // HASH GENERATION HELPERS
 // ============================================================================
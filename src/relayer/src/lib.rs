@@ -1,8 +1,15 @@
 mod helpers;
 mod memory;
+mod metrics;
 mod types;
 
-use types::{CrossChainOrderDto, FusionError, Order, OrderStatus};
+use candid::Principal;
+use types::{
+    AmountCaps, AuditAction, AuditEntry, CrossChainOrderDto, DepositBalance, DepositPolicy,
+    EscrowContracts, FusionError, HttpRequest, HttpResponse, Order, OrderEscrowInfo, OrderStatus,
+    OrderStatusSummary, RelayerMetrics, RevealedSecret, SecretSubmission, SubmissionQuota,
+    SupportedChain,
+};
 
 // ============================================================================
 // 1INCH FUSION+ API ENDPOINTS (Our Bible)
@@ -13,37 +20,82 @@ use types::{CrossChainOrderDto, FusionError, Order, OrderStatus};
 async fn fusion_plus_relayer_submit(
     order: CrossChainOrderDto,
     src_chain_id: u64,
+    dst_chain_id: u64,
     signature: String,
     extension: String,
     quote_id: String,
     secret_hashes: Vec<String>,
 ) -> Result<String, FusionError> {
-    let caller = ic_cdk::caller();
+    let result = submit_order(
+        ic_cdk::caller(),
+        order,
+        (src_chain_id, dst_chain_id),
+        signature,
+        extension,
+        quote_id,
+        secret_hashes,
+    );
+    metrics::record_submission_result(&result, memory::current_time());
+    result
+}
+
+/// Validate and store a submitted order
+fn submit_order(
+    caller: Principal,
+    order: CrossChainOrderDto,
+    (src_chain_id, dst_chain_id): (u64, u64),
+    signature: String,
+    extension: String,
+    quote_id: String,
+    secret_hashes: Vec<String>,
+) -> Result<String, FusionError> {
+    // Shed load before doing any work: global pending cap, then per caller and maker limits
+    let now = memory::current_time();
+    memory::settle_expired_deposits(now);
+    let (pending, next_expiry) = memory::count_pending_orders(now);
+    if pending >= memory::get_submission_quota().max_pending_orders {
+        return Err(FusionError::QuotaExceeded(next_expiry.unwrap_or_default()));
+    }
+    memory::take_submission_tokens(caller, &order.maker, now)?;
+
+    // Both ends of the swap must be chains resolvers can settle on
+    memory::get_supported_chain(src_chain_id)?;
+    memory::get_supported_chain(dst_chain_id)?;
 
     // Validate order parameters
-    helpers::validate_order_parameters(&order)?;
+    helpers::validate_order_parameters(&order, &memory::get_amount_caps(src_chain_id))?;
 
-    // Validate signature format
-    if signature.is_empty() {
-        return Err(FusionError::InvalidEIP712Signature);
-    }
+    // Validate signature format and store it in canonical 65-byte form
+    let signature = helpers::normalize_signature(&signature)?;
 
     // Validate secret hashes
     if secret_hashes.is_empty() {
         return Err(FusionError::InvalidSecretHash);
     }
 
+    // Each fill threshold needs its own secret, so hashes may not repeat within the order
+    let mut seen = std::collections::HashSet::new();
     for hash in &secret_hashes {
         if hash.len() != 64 || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
             return Err(FusionError::InvalidSecretHash);
         }
+        if !seen.insert(hash.to_lowercase()) {
+            return Err(FusionError::InvalidSecretHash);
+        }
+    }
+
+    // A secret shared with another active order would let a resolver drain both
+    for hash in &secret_hashes {
+        if let Some(owner) = memory::find_secret_hash_owner(hash) {
+            return Err(FusionError::DuplicateSecretHash(owner));
+        }
     }
 
     // Generate unique order ID (hash)
     let order_id = helpers::generate_order_hash(&order, src_chain_id, &signature);
 
     // Create internal order structure
-    let internal_order = Order::new(
+    let mut internal_order = Order::new(
         order_id.clone(),
         order.maker.clone(),
         caller, // ICP principal of the maker
@@ -57,11 +109,24 @@ async fn fusion_plus_relayer_submit(
         quote_id,
         extension,
         src_chain_id,
-        1, // dst_chain_id (ICP = 1 for now)
+        dst_chain_id,
+    );
+    internal_order.secret_hashes = secret_hashes; // One per fill threshold for partial fills
+    let details = format!(
+        "src_chain_id={} dst_chain_id={} secret_hashes={}",
+        src_chain_id,
+        dst_chain_id,
+        internal_order.secret_hashes.len()
     );
 
+    // Lock the caller's deposit for as long as the order stays active
+    if let Some(policy) = memory::get_deposit_policy().filter(|policy| policy.amount > 0) {
+        memory::lock_deposit(caller, &order_id, policy.amount)?;
+    }
+
     // Store the order
     memory::store_order(internal_order)?;
+    audit(&order_id, caller, AuditAction::Submitted, details);
 
     // Log order creation
     ic_cdk::println!("📋 Order submitted: {}", order_id);
@@ -81,9 +146,50 @@ fn fusion_plus_order_status(order_hash: String) -> Result<Order, FusionError> {
     memory::get_order(&order_hash)
 }
 
+/// Most order hashes accepted by one bulk status or existence query
+const MAX_BULK_ORDER_HASHES: usize = 200;
+
+/// Get compact statuses of many orders in input order, None for unknown hashes - Used by: Resolvers
+#[ic_cdk::query]
+fn fusion_plus_orders_status_bulk(
+    order_hashes: Vec<String>,
+) -> Result<Vec<(String, Option<OrderStatusSummary>)>, FusionError> {
+    check_bulk_size(&order_hashes)?;
+    Ok(order_hashes
+        .into_iter()
+        .map(|order_hash| {
+            let summary = memory::get_order(&order_hash).ok().map(|order| OrderStatusSummary {
+                updated_at: memory::get_last_audit_time(&order_hash).unwrap_or(order.created_at),
+                status: order.status,
+                fill_progress_bps: order.fill_progress_bps,
+            });
+            (order_hash, summary)
+        })
+        .collect())
+}
+
+/// Check which of many order hashes the relayer knows, in input order - Used by: Resolvers
+#[ic_cdk::query]
+fn fusion_plus_orders_exist(order_hashes: Vec<String>) -> Result<Vec<bool>, FusionError> {
+    check_bulk_size(&order_hashes)?;
+    Ok(order_hashes.iter().map(|order_hash| memory::get_order(order_hash).is_ok()).collect())
+}
+
+/// Reject bulk queries over more order hashes than one call may cover
+fn check_bulk_size(order_hashes: &[String]) -> Result<(), FusionError> {
+    if order_hashes.len() > MAX_BULK_ORDER_HASHES {
+        return Err(FusionError::TooManyOrderHashes(MAX_BULK_ORDER_HASHES as u64));
+    }
+    Ok(())
+}
+
 /// Get order escrow - matches 1inch /fusion-plus/orders/v1.0/order/escrow
 #[ic_cdk::query]
-fn fusion_plus_order_escrow(order_hash: String, chain_id: u64) -> Result<Order, FusionError> {
+fn fusion_plus_order_escrow(
+    order_hash: String,
+    chain_id: u64,
+) -> Result<OrderEscrowInfo, FusionError> {
+    let contracts = memory::get_chain_contracts(chain_id)?;
     let order = memory::get_order(&order_hash)?;
 
     // Filter by chain ID if needed
@@ -91,7 +197,9 @@ fn fusion_plus_order_escrow(order_hash: String, chain_id: u64) -> Result<Order,
         return Err(FusionError::OrderNotFound);
     }
 
-    Ok(order)
+    let escrow_address = memory::get_escrow_address(&order_hash, chain_id);
+
+    Ok(OrderEscrowInfo { order, contracts, escrow_address })
 }
 
 /// Get order secrets - matches 1inch /fusion-plus/orders/v1.0/order/secrets/{orderHash}
@@ -101,6 +209,102 @@ fn fusion_plus_order_secrets(order_hash: String) -> Result<Vec<String>, FusionEr
     Ok(order.secret_hashes)
 }
 
+/// Submit a secret revealed by the maker - matches 1inch /fusion-plus/relayer/v1.0/submit/secret
+///
+/// The nonce must exceed the maker's previous submission nonce, so an observed submission
+/// cannot be replayed.
+#[ic_cdk::update]
+fn fusion_plus_relayer_submit_secret(
+    order_hash: String,
+    secret: String,
+    nonce: u64,
+) -> Result<(), FusionError> {
+    submit_secret(ic_cdk::caller(), &order_hash, &secret, nonce)
+}
+
+/// Accept a maker's secret once the fill progress covers its threshold
+fn submit_secret(
+    caller: Principal,
+    order_hash: &str,
+    secret: &str,
+    nonce: u64,
+) -> Result<(), FusionError> {
+    let order = memory::get_order(order_hash)?;
+    if caller != order.maker_icp_principal {
+        return Err(FusionError::Unauthorized);
+    }
+
+    let next_nonce = memory::get_secret_nonce(caller) + 1;
+    if nonce < next_nonce {
+        return Err(FusionError::InvalidNonce(next_nonce));
+    }
+
+    let (idx, secret) = helpers::match_secret(secret, &order.secret_hashes)?;
+    let progress = order.fill_progress_bps.unwrap_or(0);
+    if !helpers::is_secret_unlocked(idx, order.secret_hashes.len(), progress) {
+        return Err(FusionError::SecretNotYetUnlockable);
+    }
+
+    memory::store_revealed_secret(order_hash, idx as u32, secret);
+    memory::record_secret_submission(SecretSubmission {
+        maker: caller,
+        nonce,
+        order_hash: order_hash.to_string(),
+        idx: idx as u32,
+        submitted_at: memory::current_time(),
+    });
+    audit(order_hash, caller, AuditAction::SecretRevealed, format!("idx={} nonce={}", idx, nonce));
+    ic_cdk::println!("🔑 Secret {} revealed for order {}", idx, order_hash);
+    Ok(())
+}
+
+/// Get the secrets revealed so far - matches 1inch /fusion-plus/orders/v1.0/order/secrets/{orderHash}
+#[ic_cdk::query]
+fn fusion_plus_order_revealed_secrets(
+    order_hash: String,
+) -> Result<Vec<RevealedSecret>, FusionError> {
+    let order = memory::get_order(&order_hash)?;
+    let progress = order.fill_progress_bps.unwrap_or(0);
+
+    Ok(memory::get_revealed_secrets(&order_hash)
+        .into_iter()
+        .filter(|revealed| {
+            helpers::is_secret_unlocked(revealed.idx as usize, order.secret_hashes.len(), progress)
+        })
+        .collect())
+}
+
+/// Get a page of an order's audit trail, oldest first - Used by: Auditors/Support
+#[ic_cdk::query]
+fn fusion_plus_order_audit(
+    order_hash: String,
+    offset: u64,
+    limit: u64,
+) -> Result<Vec<AuditEntry>, FusionError> {
+    memory::get_order(&order_hash)?;
+    Ok(memory::get_audit_entries(&order_hash, offset as usize, limit as usize))
+}
+
+/// Record a mutation of an order in its audit trail
+fn audit(order_hash: &str, caller: Principal, action: AuditAction, details: String) {
+    memory::record_audit_entry(
+        order_hash,
+        AuditEntry { timestamp: memory::current_time(), caller, action, details },
+    );
+}
+
+/// Get the maker submissions that revealed an order's secrets - Used by: Auditors
+#[ic_cdk::query]
+fn get_secret_submissions(order_hash: String) -> Vec<SecretSubmission> {
+    memory::get_secret_submissions(&order_hash)
+}
+
+/// Get the lowest nonce a maker may use for their next secret submission - Used by: Makers
+#[ic_cdk::query]
+fn get_next_secret_nonce(maker: Principal) -> u64 {
+    memory::get_secret_nonce(maker) + 1
+}
+
 /// Get ready-to-accept secret fills - matches 1inch /fusion-plus/orders/v1.0/order/ready-to-accept-secret-fills/{orderHash}
 #[ic_cdk::query]
 fn fusion_plus_order_ready_to_accept_secret_fills(order_hash: String) -> Result<bool, FusionError> {
@@ -112,6 +316,326 @@ fn fusion_plus_order_ready_to_accept_secret_fills(order_hash: String) -> Result<
     Ok(ready)
 }
 
+// ============================================================================
+// ESCROW LIFECYCLE & CHAIN REGISTRY
+// ============================================================================
+
+/// Record the escrow deployed for an order on a chain - Used by: Resolvers
+///
+/// Only EVM legs are recorded: the ICP leg's escrow lives in the escrow manager canister, which
+/// has no factory contracts to register, so the ICP chain is rejected as unsupported here.
+#[ic_cdk::update]
+fn fusion_plus_relayer_escrow_created(
+    order_hash: String,
+    chain_id: u64,
+    escrow_address: String,
+) -> Result<(), FusionError> {
+    helpers::require_resolver()?;
+    record_escrow_created(ic_cdk::caller(), &order_hash, chain_id, escrow_address)
+}
+
+/// Record an escrow address for an order on one of its chains; a recorded escrow is never replaced
+fn record_escrow_created(
+    caller: Principal,
+    order_hash: &str,
+    chain_id: u64,
+    escrow_address: String,
+) -> Result<(), FusionError> {
+    memory::get_chain_contracts(chain_id)?;
+    let order = memory::get_order(order_hash)?;
+
+    if order.src_chain_id != chain_id && order.dst_chain_id != chain_id {
+        return Err(FusionError::OrderNotFound);
+    }
+
+    if !helpers::is_valid_eth_address(&escrow_address) {
+        return Err(FusionError::TokenAddressInvalid);
+    }
+    if let Some(recorded) = memory::get_escrow_address(order_hash, chain_id) {
+        return Err(FusionError::EscrowAlreadyRecorded(recorded));
+    }
+    // The first escrow claims the order; escrows on its other chain belong to the same resolver
+    if memory::get_escrow_owner(order_hash).is_some() {
+        helpers::require_escrow_owner(order_hash, caller)?;
+    }
+
+    memory::claim_escrow_owner(order_hash, caller);
+    memory::set_escrow_address(order_hash, chain_id, escrow_address.clone());
+    audit(
+        order_hash,
+        caller,
+        AuditAction::EscrowRecorded,
+        format!("chain_id={} address={}", chain_id, escrow_address),
+    );
+
+    ic_cdk::println!(
+        "🔐 Escrow {} recorded for order {} on chain {}",
+        escrow_address,
+        order_hash,
+        chain_id
+    );
+
+    Ok(())
+}
+
+/// Report how much of an order has been filled, in basis points - Used by: Resolvers
+#[ic_cdk::update]
+fn fusion_plus_relayer_fill_progress(
+    order_hash: String,
+    fill_progress_bps: u32,
+) -> Result<(), FusionError> {
+    helpers::require_resolver()?;
+    record_fill_progress(ic_cdk::caller(), &order_hash, fill_progress_bps)
+}
+
+/// Advance the fill progress of an active order; progress never moves backwards
+///
+/// Only the resolver that recorded the order's escrow may report, so an escrow must exist first.
+fn record_fill_progress(
+    caller: Principal,
+    order_hash: &str,
+    fill_progress_bps: u32,
+) -> Result<(), FusionError> {
+    let mut order = memory::get_order(order_hash)?;
+    helpers::require_escrow_owner(order_hash, caller)?;
+
+    if !matches!(order.status, OrderStatus::Pending | OrderStatus::Accepted) {
+        return Err(FusionError::OrderNotPending);
+    }
+    if fill_progress_bps > helpers::FULL_FILL_BPS
+        || fill_progress_bps < order.fill_progress_bps.unwrap_or(0)
+    {
+        return Err(FusionError::InvalidAmount);
+    }
+
+    order.fill_progress_bps = Some(fill_progress_bps);
+    memory::store_order(order)?;
+    audit(
+        order_hash,
+        caller,
+        AuditAction::FillProgress,
+        format!("fill_progress_bps={}", fill_progress_bps),
+    );
+    Ok(())
+}
+
+/// Register or update a chain orders may be submitted from or to - Used by: Controllers
+#[ic_cdk::update]
+fn set_supported_chain(chain_id: u64, chain: SupportedChain) -> Result<(), FusionError> {
+    helpers::require_controller()?;
+    helpers::validate_supported_chain(&chain)?;
+    memory::set_supported_chain(chain_id, chain);
+    Ok(())
+}
+
+/// Stop accepting new orders from or to a chain - Used by: Controllers
+#[ic_cdk::update]
+fn remove_supported_chain(chain_id: u64) -> Result<(), FusionError> {
+    helpers::require_controller()?;
+    memory::remove_supported_chain(chain_id)
+}
+
+/// List the chains orders may be submitted from or to - Used by: Makers/Resolvers/Frontend
+#[ic_cdk::query]
+fn list_supported_chains() -> Vec<(u64, SupportedChain)> {
+    memory::list_supported_chains()
+}
+
+/// Register escrow contracts for a chain - Used by: Controllers
+#[ic_cdk::update]
+fn set_chain_contracts(chain_id: u64, contracts: EscrowContracts) -> Result<(), FusionError> {
+    helpers::require_controller()?;
+    helpers::validate_escrow_contracts(&contracts)?;
+    memory::set_chain_contracts(chain_id, contracts);
+    Ok(())
+}
+
+/// Remove escrow contracts for a chain - Used by: Controllers
+#[ic_cdk::update]
+fn remove_chain_contracts(chain_id: u64) -> Result<(), FusionError> {
+    helpers::require_controller()?;
+    memory::remove_chain_contracts(chain_id)
+}
+
+/// Allow a principal to report escrows and fill progress - Used by: Controllers
+#[ic_cdk::update]
+fn add_resolver(resolver: Principal) -> Result<(), FusionError> {
+    helpers::require_controller()?;
+    memory::add_resolver(resolver);
+    Ok(())
+}
+
+/// Stop accepting escrow and fill reports from a principal - Used by: Controllers
+#[ic_cdk::update]
+fn remove_resolver(resolver: Principal) -> Result<(), FusionError> {
+    helpers::require_controller()?;
+    memory::remove_resolver(resolver);
+    Ok(())
+}
+
+/// List the principals allowed to report escrows and fill progress - Used by: Frontend
+#[ic_cdk::query]
+fn list_resolvers() -> Vec<Principal> {
+    memory::list_resolvers()
+}
+
+/// Get escrow contracts registered for a chain - Used by: Resolvers/Frontend
+#[ic_cdk::query]
+fn get_chain_contracts(chain_id: u64) -> Result<EscrowContracts, FusionError> {
+    memory::get_chain_contracts(chain_id)
+}
+
+/// List all chains with registered escrow contracts - Used by: Resolvers/Frontend
+#[ic_cdk::query]
+fn list_chain_contracts() -> Vec<(u64, EscrowContracts)> {
+    memory::list_chain_contracts()
+}
+
+/// Set the largest order amounts accepted on a chain - Used by: Controllers
+#[ic_cdk::update]
+fn set_amount_caps(chain_id: u64, caps: AmountCaps) -> Result<(), FusionError> {
+    helpers::require_controller()?;
+    memory::set_amount_caps(chain_id, caps);
+    Ok(())
+}
+
+/// Remove a chain's amount caps so the defaults apply - Used by: Controllers
+#[ic_cdk::update]
+fn remove_amount_caps(chain_id: u64) -> Result<(), FusionError> {
+    helpers::require_controller()?;
+    memory::remove_amount_caps(chain_id);
+    Ok(())
+}
+
+/// Set the amount caps for chains without their own caps - Used by: Controllers
+#[ic_cdk::update]
+fn set_default_amount_caps(caps: AmountCaps) -> Result<(), FusionError> {
+    helpers::require_controller()?;
+    memory::set_default_amount_caps(caps);
+    Ok(())
+}
+
+/// Get the order amount caps that apply on a chain - Used by: Frontend
+#[ic_cdk::query]
+fn get_amount_caps(chain_id: u64) -> AmountCaps {
+    memory::get_amount_caps(chain_id)
+}
+
+/// Set the submission rate limits and global pending order cap - Used by: Controllers
+#[ic_cdk::update]
+fn set_submission_quota(quota: SubmissionQuota) -> Result<(), FusionError> {
+    helpers::require_controller()?;
+    if quota.bucket_capacity == 0 || quota.refill_interval_ns == 0 || quota.max_pending_orders == 0
+    {
+        return Err(FusionError::InvalidAmount);
+    }
+    memory::set_submission_quota(quota);
+    Ok(())
+}
+
+/// Get the submission rate limits and global pending order cap - Used by: Frontend, Resolvers
+#[ic_cdk::query]
+fn get_submission_quota() -> SubmissionQuota {
+    memory::get_submission_quota()
+}
+
+/// Set the deposit each submission locks - Used by: Controllers
+///
+/// The ledger cannot change while submitters hold deposits paid in on the current one.
+#[ic_cdk::update]
+fn set_deposit_policy(policy: DepositPolicy) -> Result<(), FusionError> {
+    helpers::require_controller()?;
+    let ledger_changed =
+        memory::get_deposit_policy().is_some_and(|current| current.ledger != policy.ledger);
+    if ledger_changed && memory::deposits_held() {
+        return Err(FusionError::InvalidAmount);
+    }
+    memory::set_deposit_policy(policy);
+    Ok(())
+}
+
+/// Get the deposit each submission locks - Used by: Makers/Frontend
+#[ic_cdk::query]
+fn get_deposit_policy() -> Option<DepositPolicy> {
+    memory::get_deposit_policy()
+}
+
+/// Get the deposit funds a submitter holds with the relayer - Used by: Makers/Frontend
+#[ic_cdk::query]
+fn get_deposit_balance(owner: Principal) -> DepositBalance {
+    memory::get_deposit_balance(owner)
+}
+
+/// Pay in deposit funds approved for the relayer on the deposit ledger - Used by: Makers
+#[ic_cdk::update]
+async fn top_up_deposit(amount: u64) -> Result<DepositBalance, FusionError> {
+    let caller = ic_cdk::caller();
+    if caller == Principal::anonymous() {
+        return Err(FusionError::Unauthorized);
+    }
+    if amount == 0 {
+        return Err(FusionError::InvalidAmount);
+    }
+    let policy = memory::get_deposit_policy().ok_or(FusionError::InsufficientDeposit(0))?;
+
+    helpers::collect_deposit(policy.ledger, caller, amount).await?;
+    memory::credit_deposit(caller, amount);
+    Ok(memory::get_deposit_balance(caller))
+}
+
+/// Withdraw the deposit funds no active order holds, net of the ledger fee - Used by: Makers
+#[ic_cdk::update]
+async fn withdraw_deposit() -> Result<u64, FusionError> {
+    let caller = ic_cdk::caller();
+    let policy = memory::get_deposit_policy().ok_or(FusionError::InsufficientDeposit(0))?;
+    memory::settle_expired_deposits(memory::current_time());
+
+    // Taken before the ledger call so concurrent withdrawals cannot pay out the same funds
+    let amount = memory::take_available_deposit(caller);
+    if amount == 0 {
+        return Err(FusionError::InsufficientDeposit(0));
+    }
+    let result = helpers::pay_out_deposit(policy.ledger, caller, amount).await;
+    if result.is_err() {
+        memory::credit_deposit(caller, amount);
+    }
+    result
+}
+
+// ============================================================================
+// MONITORING
+// ============================================================================
+
+/// Get order count and volume metrics - Used by: Operators/Monitoring
+#[ic_cdk::query]
+fn get_relayer_metrics() -> RelayerMetrics {
+    metrics::get_metrics(memory::current_time())
+}
+
+/// Serve metrics in Prometheus text format at /metrics - Used by: Monitoring scrapers
+#[ic_cdk::query]
+fn http_request(request: HttpRequest) -> HttpResponse {
+    let path = request.url.split('?').next().unwrap_or_default();
+
+    if path != "/metrics" {
+        return HttpResponse {
+            status_code: 404,
+            headers: vec![("Content-Type".to_string(), "text/plain".to_string())],
+            body: b"Not found".to_vec(),
+        };
+    }
+
+    let body = metrics::render_prometheus(&metrics::get_metrics(memory::current_time()));
+    HttpResponse {
+        status_code: 200,
+        headers: vec![(
+            "Content-Type".to_string(),
+            "text/plain; version=0.0.4; charset=utf-8".to_string(),
+        )],
+        body: body.into_bytes(),
+    }
+}
+
 // ============================================================================
 // CANISTER LIFECYCLE
 // ============================================================================
@@ -119,14 +643,20 @@ fn fusion_plus_order_ready_to_accept_secret_fills(order_hash: String) -> Result<
 #[ic_cdk::pre_upgrade]
 fn pre_upgrade() {
     let (orders, identities) = memory::serialize_relayer_state();
-    ic_cdk::storage::stable_save((orders, identities)).expect("Failed to save state");
+    let extended_state = memory::serialize_extended_state();
+    ic_cdk::storage::stable_save((orders, identities, extended_state))
+        .expect("Failed to save state");
 }
 
 #[ic_cdk::post_upgrade]
 fn post_upgrade() {
-    let (orders, identities): (Vec<(String, Order)>, Vec<(String, String)>) =
-        ic_cdk::storage::stable_restore().expect("Failed to restore state");
+    let (orders, identities, extended_state): (
+        Vec<(String, Order)>,
+        Vec<(String, String)>,
+        Option<memory::RelayerExtendedState>,
+    ) = ic_cdk::storage::stable_restore().expect("Failed to restore state");
     memory::deserialize_relayer_state(orders, identities);
+    memory::deserialize_extended_state(extended_state.unwrap_or_default());
 }
 
 // Candid export for DID generation
@@ -138,8 +668,26 @@ ic_cdk::export_candid!();
 
 #[cfg(test)]
 mod tests {
-    use crate::helpers::{generate_order_hash, is_valid_eth_address, validate_order_parameters};
-    use crate::types::{CrossChainOrderDto, FusionError};
+    use crate::helpers::{
+        generate_order_hash, is_valid_eth_address, normalize_signature, parse_amount,
+        validate_order_parameters,
+    };
+    use crate::memory;
+    use crate::metrics;
+    use crate::types::{
+        AmountCaps, AuditAction, CrossChainOrderDto, DepositBalance, DepositPolicy,
+        EscrowContracts, FusionError, Order, OrderStatus, OrderStatusSummary, SecretSubmission,
+        SubmissionQuota, SupportedChain, ICP_CHAIN_ID,
+    };
+    use candid::Principal;
+
+    // EIP-2098 test vector ("Hello World"), full 65-byte form
+    const SIG_R: &str = "68a020a209d3d56c46f38cc50a33f704f4a9a10a59377f8dd762ac66910e9b90";
+    const SIG_S: &str = "7e865ad05c4035ab5792787d4a0297a43617ae897930a6fe4d822b8faea52064";
+    const VALID_SIGNATURE: &str = "0x68a020a209d3d56c46f38cc50a33f704f4a9a10a59377f8dd762ac66910e9b907e865ad05c4035ab5792787d4a0297a43617ae897930a6fe4d822b8faea520641b";
+
+    /// Resolver reporting fills and escrows in lifecycle tests
+    const RESOLVER: Principal = Principal::from_slice(&[7; 10]);
 
     fn create_test_order() -> CrossChainOrderDto {
         CrossChainOrderDto {
@@ -154,6 +702,67 @@ mod tests {
         }
     }
 
+    fn create_stored_order(id: &str, src_chain_id: u64) -> Order {
+        let dto = create_test_order();
+        let order = Order::new(
+            id.to_string(),
+            dto.maker,
+            Principal::anonymous(),
+            dto.salt,
+            dto.maker_asset,
+            dto.taker_asset,
+            dto.making_amount,
+            dto.taking_amount,
+            "a".repeat(64),
+            "0xsignature".to_string(),
+            "quote".to_string(),
+            "0x".to_string(),
+            src_chain_id,
+            1,
+        );
+        memory::store_order(order.clone()).unwrap();
+        order
+    }
+
+    fn test_contracts(suffix: char) -> EscrowContracts {
+        let address = format!("0x{}", suffix.to_string().repeat(40));
+        EscrowContracts {
+            factory: address.clone(),
+            src_impl: address.clone(),
+            dst_impl: address.clone(),
+            lop: address,
+        }
+    }
+
+    fn test_chain() -> SupportedChain {
+        SupportedChain {
+            name: "Base Sepolia".to_string(),
+            finality_delay_secs: 12,
+            escrow_factory: Some(format!("0x{}", "f".repeat(40))),
+        }
+    }
+
+    fn submit(
+        salt: &str,
+        signature: &str,
+        secret_hashes: Vec<String>,
+    ) -> Result<String, FusionError> {
+        let mut order = create_test_order();
+        order.salt = salt.to_string();
+        memory::set_supported_chain(84532, test_chain());
+        let result = crate::submit_order(
+            Principal::anonymous(),
+            order,
+            (84532, ICP_CHAIN_ID),
+            signature.to_string(),
+            "0x".to_string(),
+            "quote".to_string(),
+            secret_hashes,
+        );
+        metrics::record_submission_result(&result, memory::current_time());
+        result
+    }
+
     #[test]
     fn test_is_valid_eth_address() {
         // Valid addresses (42 chars: 0x + 40 hex chars)
@@ -172,7 +781,7 @@ mod tests {
     #[test]
     fn test_validate_order_parameters_valid() {
         let order = create_test_order();
-        match validate_order_parameters(&order) {
+        match validate_order_parameters(&order, &AmountCaps::default()) {
             Ok(()) => (),
             Err(e) => panic!("Expected validation to succeed but got error: {:?}", e),
         }
@@ -183,7 +792,7 @@ mod tests {
         let mut order = create_test_order();
         order.salt = "".to_string();
 
-        match validate_order_parameters(&order) {
+        match validate_order_parameters(&order, &AmountCaps::default()) {
             Err(FusionError::InvalidSalt) => (),
             _ => panic!("Expected InvalidSalt error"),
         }
@@ -194,7 +803,7 @@ mod tests {
         let mut order = create_test_order();
         order.maker = "invalid_address".to_string();
 
-        match validate_order_parameters(&order) {
+        match validate_order_parameters(&order, &AmountCaps::default()) {
             Err(FusionError::TokenAddressInvalid) => (),
             _ => panic!("Expected TokenAddressInvalid error"),
         }
@@ -205,7 +814,7 @@ mod tests {
         let mut order = create_test_order();
         order.making_amount = "0".to_string();
 
-        match validate_order_parameters(&order) {
+        match validate_order_parameters(&order, &AmountCaps::default()) {
             Err(FusionError::InvalidAmount) => (),
             _ => panic!("Expected InvalidAmount error"),
         }
@@ -216,12 +825,205 @@ mod tests {
         let mut order = create_test_order();
         order.making_amount = "not_a_number".to_string();
 
-        match validate_order_parameters(&order) {
+        match validate_order_parameters(&order, &AmountCaps::default()) {
             Err(FusionError::InvalidAmount) => (),
             _ => panic!("Expected InvalidAmount error"),
         }
     }
 
+    fn caps(max_making_amount: u64, max_taking_amount: u64) -> AmountCaps {
+        AmountCaps {
+            max_making_amount: max_making_amount.into(),
+            max_taking_amount: max_taking_amount.into(),
+        }
+    }
+
+    #[test]
+    fn test_amount_cap_boundary() {
+        let mut order = create_test_order();
+        order.making_amount = "1000".to_string();
+        order.taking_amount = "2000".to_string();
+
+        assert!(validate_order_parameters(&order, &caps(1000, 2000)).is_ok());
+        match validate_order_parameters(&order, &caps(999, 2000)) {
+            Err(FusionError::AmountExceedsCap(cap)) => assert_eq!(cap, 999u64),
+            other => panic!("Expected AmountExceedsCap error, got {:?}", other),
+        }
+        match validate_order_parameters(&order, &caps(1000, 1999)) {
+            Err(FusionError::AmountExceedsCap(cap)) => assert_eq!(cap, 1999u64),
+            other => panic!("Expected AmountExceedsCap error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_amount_caps_per_chain_on_submit() {
+        memory::clear_relayer_state();
+        memory::set_amount_caps(84532, caps(1, 1));
+
+        let result = submit("42", VALID_SIGNATURE, vec!["a".repeat(64)]);
+        assert!(matches!(result, Err(FusionError::AmountExceedsCap(_))));
+
+        // Other chains fall back to the defaults
+        assert_eq!(memory::get_amount_caps(1), AmountCaps::default());
+        memory::remove_amount_caps(84532);
+        assert!(submit("42", VALID_SIGNATURE, vec!["a".repeat(64)]).is_ok());
+
+        // Removing caps a chain does not have is a no-op
+        memory::remove_amount_caps(84532);
+        assert_eq!(memory::get_amount_caps(84532), AmountCaps::default());
+    }
+
+    #[test]
+    fn test_duplicate_secret_hash_rejected_while_active() {
+        memory::clear_relayer_state();
+        let first = submit("1", VALID_SIGNATURE, vec!["a".repeat(64)]).unwrap();
+
+        // Hashes are compared case-insensitively
+        match submit("2", VALID_SIGNATURE, vec!["A".repeat(64)]) {
+            Err(FusionError::DuplicateSecretHash(owner)) => assert_eq!(owner, first),
+            other => panic!("Expected DuplicateSecretHash error, got {:?}", other),
+        }
+
+        // Accepted orders still hold their hashes
+        let mut order = memory::get_order(&first).unwrap();
+        order.status = OrderStatus::Accepted;
+        memory::store_order(order).unwrap();
+        assert!(matches!(
+            submit("2", VALID_SIGNATURE, vec!["a".repeat(64)]),
+            Err(FusionError::DuplicateSecretHash(_))
+        ));
+    }
+
+    #[test]
+    fn test_secret_hash_released_when_order_completes() {
+        memory::clear_relayer_state();
+        let first = submit("1", VALID_SIGNATURE, vec!["a".repeat(64)]).unwrap();
+
+        let mut order = memory::get_order(&first).unwrap();
+        order.status = OrderStatus::Completed;
+        memory::store_order(order).unwrap();
+
+        assert!(submit("2", VALID_SIGNATURE, vec!["a".repeat(64)]).is_ok());
+    }
+
+    #[test]
+    fn test_duplicate_secret_hash_detected_in_any_position() {
+        memory::clear_relayer_state();
+        let first =
+            submit("1", VALID_SIGNATURE, vec!["a".repeat(64), "b".repeat(64), "c".repeat(64)])
+                .unwrap();
+
+        // Only the last hash of the new order overlaps, with the middle hash of the first
+        match submit("2", VALID_SIGNATURE, vec!["d".repeat(64), "b".repeat(64)]) {
+            Err(FusionError::DuplicateSecretHash(owner)) => assert_eq!(owner, first),
+            other => panic!("Expected DuplicateSecretHash error, got {:?}", other),
+        }
+        assert!(memory::find_secret_hash_owner(&"d".repeat(64)).is_none());
+
+        // Hashes may not repeat within one order either, whatever their case
+        assert!(matches!(
+            submit("3", VALID_SIGNATURE, vec!["e".repeat(64), "f".repeat(64), "E".repeat(64)]),
+            Err(FusionError::InvalidSecretHash)
+        ));
+        assert!(memory::find_secret_hash_owner(&"e".repeat(64)).is_none());
+
+        // The index is rebuilt from the orders after an upgrade
+        let (orders, identities) = memory::serialize_relayer_state();
+        memory::clear_relayer_state();
+        memory::deserialize_relayer_state(orders, identities);
+        assert_eq!(memory::find_secret_hash_owner(&"c".repeat(64)), Some(first));
+    }
+
+    #[test]
+    fn test_large_amounts_parse_without_overflow() {
+        let huge = format!("1{}", "0".repeat(30)); // 10^30 wei
+        assert!(parse_amount(&huge).is_ok());
+        assert!(matches!(parse_amount("000"), Err(FusionError::InvalidAmount)));
+        assert!(matches!(parse_amount("-5"), Err(FusionError::InvalidAmount)));
+        assert!(matches!(parse_amount(""), Err(FusionError::InvalidAmount)));
+
+        let mut order = create_test_order();
+        order.making_amount = huge.clone();
+        let result = validate_order_parameters(&order, &AmountCaps::default());
+        assert!(matches!(result, Err(FusionError::AmountExceedsCap(_))));
+
+        let raised =
+            AmountCaps { max_making_amount: parse_amount(&huge).unwrap(), ..caps(0, u64::MAX) };
+        assert!(validate_order_parameters(&order, &raised).is_ok());
+    }
+
+    fn signature_reason(signature: &str) -> String {
+        match normalize_signature(signature) {
+            Err(FusionError::InvalidEIP712Signature(reason)) => reason,
+            other => panic!("accepted {}: {:?}", signature, other),
+        }
+    }
+
+    #[test]
+    fn test_normalize_signature_valid_forms() {
+        assert_eq!(normalize_signature(VALID_SIGNATURE).unwrap(), VALID_SIGNATURE);
+        assert_eq!(
+            normalize_signature(&VALID_SIGNATURE.to_uppercase().replace("0X", "0x")).unwrap(),
+            VALID_SIGNATURE
+        );
+
+        // v of 0/1 is normalized to 27/28
+        let v_zero = format!("0x{}{}00", SIG_R, SIG_S);
+        assert_eq!(normalize_signature(&v_zero).unwrap(), VALID_SIGNATURE);
+        let v_one = format!("0x{}{}01", SIG_R, SIG_S);
+        assert!(normalize_signature(&v_one).unwrap().ends_with("1c"));
+    }
+
+    #[test]
+    fn test_compact_signature_expansion() {
+        // EIP-2098 vectors: even y parity, then odd y parity stored in the top bit of s
+        let compact = format!("0x{}{}", SIG_R, SIG_S);
+        assert_eq!(normalize_signature(&compact).unwrap(), VALID_SIGNATURE);
+
+        // Orders are stored with the normalized signature
+        let order_hash = submit("compact", &compact, vec!["a".repeat(64)]).unwrap();
+        assert_eq!(memory::get_order(&order_hash).unwrap().signature, VALID_SIGNATURE);
+
+        let compact = "0x9328da16089fcba9bececa81663203989f2df5fe1faa6291a45381c81bd17f76939c6d6b623b42da56557e5e734a43dc83345ddfadec52cbe24d0cc64f550793";
+        let expected = "0x9328da16089fcba9bececa81663203989f2df5fe1faa6291a45381c81bd17f76139c6d6b623b42da56557e5e734a43dc83345ddfadec52cbe24d0cc64f5507931c";
+        assert_eq!(normalize_signature(compact).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_malformed_signatures_rejected() {
+        assert_eq!(signature_reason(""), "missing 0x prefix");
+        assert_eq!(signature_reason(&VALID_SIGNATURE[2..]), "missing 0x prefix");
+        assert_eq!(signature_reason("0xsig"), "not valid hex");
+        assert_eq!(
+            signature_reason(&VALID_SIGNATURE[..VALID_SIGNATURE.len() - 1]),
+            "not valid hex"
+        );
+        assert_eq!(
+            signature_reason(&VALID_SIGNATURE[..VALID_SIGNATURE.len() - 4]),
+            "expected 65 bytes or 64-byte compact form, got 63"
+        );
+        assert_eq!(
+            signature_reason(&format!("0x{}{}1d", SIG_R, SIG_S)),
+            "invalid recovery id v=29"
+        );
+        assert_eq!(
+            signature_reason(&format!("0x{}{}1b", "0".repeat(64), SIG_S)),
+            "r and s must be non-zero"
+        );
+
+        // s = n - s_valid is the malleable twin of the valid signature
+        let high_s = "8179a52fa3bfca54a86d8782b5fd685a84972e5d3617f93d725032fd219120dd";
+        assert_eq!(
+            signature_reason(&format!("0x{}{}1c", SIG_R, high_s)),
+            "malleable signature: s above half curve order"
+        );
+
+        assert!(matches!(
+            submit("1", "0xsig", vec!["a".repeat(64)]),
+            Err(FusionError::InvalidEIP712Signature(_))
+        ));
+    }
+
     #[test]
     fn test_generate_order_hash() {
         let order = create_test_order();
@@ -255,4 +1057,798 @@ mod tests {
         // Different order data should produce different hashes
         assert_ne!(hash1, hash2);
     }
+
+    #[test]
+    fn test_chain_contracts_registry_crud() {
+        memory::clear_relayer_state();
+
+        memory::set_chain_contracts(84532, test_contracts('a'));
+        memory::set_chain_contracts(1, test_contracts('b'));
+        assert_eq!(memory::get_chain_contracts(84532).unwrap(), test_contracts('a'));
+
+        // Replacing keeps a single entry per chain
+        memory::set_chain_contracts(84532, test_contracts('c'));
+        let chains: Vec<u64> = memory::list_chain_contracts().iter().map(|(id, _)| *id).collect();
+        assert_eq!(chains, vec![1, 84532]);
+        assert_eq!(memory::get_chain_contracts(84532).unwrap(), test_contracts('c'));
+
+        memory::remove_chain_contracts(84532).unwrap();
+        assert!(matches!(
+            memory::get_chain_contracts(84532),
+            Err(FusionError::UnsupportedChain(_))
+        ));
+        assert!(matches!(
+            memory::remove_chain_contracts(84532),
+            Err(FusionError::UnsupportedChain(_))
+        ));
+    }
+
+    #[test]
+    fn test_validate_escrow_contracts() {
+        assert!(crate::helpers::validate_escrow_contracts(&test_contracts('a')).is_ok());
+
+        let mut contracts = test_contracts('a');
+        contracts.lop = "0x123".to_string();
+        assert!(matches!(
+            crate::helpers::validate_escrow_contracts(&contracts),
+            Err(FusionError::TokenAddressInvalid)
+        ));
+    }
+
+    #[test]
+    fn test_order_escrow_address_populated_and_missing() {
+        memory::clear_relayer_state();
+        memory::set_chain_contracts(84532, test_contracts('a'));
+        create_stored_order("0xorder", 84532);
+
+        let info = crate::fusion_plus_order_escrow("0xorder".to_string(), 84532).unwrap();
+        assert_eq!(info.order.id, "0xorder");
+        assert_eq!(info.contracts, test_contracts('a'));
+        assert_eq!(info.escrow_address, None);
+
+        let escrow = format!("0x{}", "d".repeat(40));
+        memory::set_escrow_address("0xorder", 84532, escrow.clone());
+        let info = crate::fusion_plus_order_escrow("0xorder".to_string(), 84532).unwrap();
+        assert_eq!(info.escrow_address, Some(escrow));
+    }
+
+    #[test]
+    fn test_order_escrow_unknown_chain() {
+        memory::clear_relayer_state();
+        create_stored_order("0xorder", 84532);
+
+        match crate::fusion_plus_order_escrow("0xorder".to_string(), 84532) {
+            Err(FusionError::UnsupportedChain(_)) => (),
+            other => panic!("Expected UnsupportedChain error, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn test_escrow_recorded_once_per_chain() {
+        memory::clear_relayer_state();
+        memory::set_chain_contracts(84532, test_contracts('a'));
+        create_stored_order("0xorder", 84532);
+
+        let escrow = format!("0x{}", "d".repeat(40));
+        crate::record_escrow_created(RESOLVER, "0xorder", 84532, escrow.clone()).unwrap();
+        match crate::record_escrow_created(
+            RESOLVER,
+            "0xorder",
+            84532,
+            format!("0x{}", "e".repeat(40)),
+        ) {
+            Err(FusionError::EscrowAlreadyRecorded(recorded)) => assert_eq!(recorded, escrow),
+            other => panic!("Expected EscrowAlreadyRecorded error, got {:?}", other),
+        }
+        assert_eq!(memory::get_escrow_address("0xorder", 84532), Some(escrow));
+
+        // The ICP leg is held by the escrow manager, not recorded by resolvers
+        assert!(matches!(
+            crate::record_escrow_created(
+                RESOLVER,
+                "0xorder",
+                ICP_CHAIN_ID,
+                format!("0x{}", "d".repeat(40))
+            ),
+            Err(FusionError::UnsupportedChain(_))
+        ));
+    }
+
+    #[test]
+    fn test_only_escrow_owner_reports_progress() {
+        memory::clear_relayer_state();
+        memory::set_chain_contracts(84532, test_contracts('a'));
+        memory::set_chain_contracts(1, test_contracts('b'));
+        create_stored_order("0xorder", 84532);
+        let other = Principal::from_slice(&[8; 10]);
+
+        // Nobody may report before an escrow exists
+        for resolver in [RESOLVER, other] {
+            assert!(matches!(
+                crate::record_fill_progress(resolver, "0xorder", 1_000),
+                Err(FusionError::Unauthorized)
+            ));
+        }
+        assert_eq!(memory::get_order("0xorder").unwrap().fill_progress_bps, Some(0));
+        crate::record_escrow_created(RESOLVER, "0xorder", 84532, format!("0x{}", "d".repeat(40)))
+            .unwrap();
+        assert_eq!(memory::get_escrow_owner("0xorder"), Some(RESOLVER));
+
+        assert!(matches!(
+            crate::record_fill_progress(other, "0xorder", 5_000),
+            Err(FusionError::Unauthorized)
+        ));
+        assert!(matches!(
+            crate::record_escrow_created(other, "0xorder", 1, format!("0x{}", "e".repeat(40))),
+            Err(FusionError::Unauthorized)
+        ));
+        crate::record_fill_progress(RESOLVER, "0xorder", 5_000).unwrap();
+        assert_eq!(memory::get_order("0xorder").unwrap().fill_progress_bps, Some(5_000));
+
+        // Ownership is kept across upgrades
+        let extended = memory::serialize_extended_state();
+        memory::deserialize_extended_state(extended);
+        assert_eq!(memory::get_escrow_owner("0xorder"), Some(RESOLVER));
+    }
+
+    #[test]
+    fn test_resolver_registry_survives_upgrade() {
+        memory::clear_relayer_state();
+        memory::add_resolver(RESOLVER);
+        assert!(memory::is_resolver(&RESOLVER));
+        assert!(!memory::is_resolver(&Principal::anonymous()));
+
+        let extended = memory::serialize_extended_state();
+        memory::clear_relayer_state();
+        assert!(!memory::is_resolver(&RESOLVER));
+        memory::deserialize_extended_state(extended);
+        assert_eq!(memory::list_resolvers(), vec![RESOLVER]);
+
+        memory::remove_resolver(RESOLVER);
+        assert!(!memory::is_resolver(&RESOLVER));
+    }
+
+    #[test]
+    fn test_metrics_after_mixed_workload() {
+        memory::clear_relayer_state();
+        let hour_ns = 3600 * 1_000_000_000;
+        memory::set_test_time(10 * hour_ns);
+        let secret = vec!["a".repeat(64)];
+
+        let first = submit("1", VALID_SIGNATURE, secret.clone()).unwrap();
+        submit("2", VALID_SIGNATURE, vec!["b".repeat(64)]).unwrap();
+        assert!(submit("3", "", secret.clone()).is_err());
+        assert!(submit("4", VALID_SIGNATURE, vec!["xyz".to_string()]).is_err());
+        assert!(submit("5", VALID_SIGNATURE, vec![]).is_err());
+
+        // Status updates move the order between counts
+        let mut order = memory::get_order(&first).unwrap();
+        order.status = OrderStatus::Cancelled;
+        memory::store_order(order).unwrap();
+
+        // A submission two hours later is the only one in the last hour
+        memory::set_test_time(12 * hour_ns);
+        submit("6", VALID_SIGNATURE, secret).unwrap();
+
+        let metrics = metrics::get_metrics(memory::current_time());
+        assert_eq!(metrics.total_submissions, 3);
+        assert_eq!(metrics.active_orders, 2);
+        assert_eq!(metrics.submissions_last_hour, 1);
+        assert_eq!(
+            metrics.orders_by_status,
+            vec![
+                ("pending".to_string(), 2),
+                ("accepted".to_string(), 0),
+                ("completed".to_string(), 0),
+                ("failed".to_string(), 0),
+                ("cancelled".to_string(), 1),
+            ]
+        );
+        assert_eq!(
+            metrics.rejected_submissions,
+            vec![("InvalidEIP712Signature".to_string(), 1), ("InvalidSecretHash".to_string(), 2),]
+        );
+    }
+
+    #[test]
+    fn test_metrics_prometheus_exposition() {
+        memory::clear_relayer_state();
+        memory::set_test_time(1_000_000_000_000);
+        submit("1", VALID_SIGNATURE, vec!["a".repeat(64)]).unwrap();
+        submit("2", "", vec!["a".repeat(64)]).unwrap_err();
+
+        let expected = "\
+# HELP relayer_submissions_total Orders accepted by the relayer.
+# TYPE relayer_submissions_total counter
+relayer_submissions_total 1
+# HELP relayer_active_orders Orders pending or accepted.
+# TYPE relayer_active_orders gauge
+relayer_active_orders 1
+# HELP relayer_orders Orders by status.
+# TYPE relayer_orders gauge
+relayer_orders{status=\"pending\"} 1
+relayer_orders{status=\"accepted\"} 0
+relayer_orders{status=\"completed\"} 0
+relayer_orders{status=\"failed\"} 0
+relayer_orders{status=\"cancelled\"} 0
+# HELP relayer_submissions_last_hour Orders accepted in the last hour.
+# TYPE relayer_submissions_last_hour gauge
+relayer_submissions_last_hour 1
+# HELP relayer_rejected_submissions_total Rejected submissions by error type.
+# TYPE relayer_rejected_submissions_total counter
+relayer_rejected_submissions_total{error=\"InvalidEIP712Signature\"} 1
+";
+        let metrics = metrics::get_metrics(memory::current_time());
+        assert_eq!(metrics::render_prometheus(&metrics), expected);
+    }
+
+    #[test]
+    fn test_metrics_survive_upgrade() {
+        memory::clear_relayer_state();
+        submit("1", VALID_SIGNATURE, vec!["a".repeat(64)]).unwrap();
+        submit("2", "", vec!["a".repeat(64)]).unwrap_err();
+        let before = metrics::get_metrics(memory::current_time());
+
+        let (orders, identities) = memory::serialize_relayer_state();
+        let extended = memory::serialize_extended_state();
+        memory::clear_relayer_state();
+        memory::deserialize_relayer_state(orders.clone(), identities.clone());
+        memory::deserialize_extended_state(extended);
+
+        let after = metrics::get_metrics(memory::current_time());
+        assert_eq!(after.total_submissions, before.total_submissions);
+        assert_eq!(after.orders_by_status, before.orders_by_status);
+        assert_eq!(after.rejected_submissions, before.rejected_submissions);
+        assert_eq!(after.submissions_last_hour, 1);
+
+        // Snapshots from before metrics existed rebuild status counts from the orders
+        memory::clear_relayer_state();
+        memory::deserialize_relayer_state(orders, identities);
+        memory::deserialize_extended_state(memory::RelayerExtendedState::default());
+        let legacy = metrics::get_metrics(memory::current_time());
+        assert_eq!(legacy.active_orders, 1);
+        assert_eq!(legacy.total_submissions, 0);
+    }
+
+    fn secret(idx: u32) -> String {
+        format!("0x{:064x}", idx + 1)
+    }
+
+    /// Submit an order with one secret per quarter of the fill
+    fn submit_partial_fill_order() -> String {
+        memory::clear_relayer_state();
+        let hashes = (0..4)
+            .map(|idx| {
+                hex::encode(fusion_crypto::keccak256(&hex::decode(&secret(idx)[2..]).unwrap()))
+            })
+            .collect();
+        submit("partial", VALID_SIGNATURE, hashes).unwrap()
+    }
+
+    /// Record an escrow for the order as RESOLVER, which may then report its fill progress
+    fn record_test_escrow(order_hash: &str) {
+        memory::set_chain_contracts(84532, test_contracts('a'));
+        crate::record_escrow_created(RESOLVER, order_hash, 84532, format!("0x{}", "d".repeat(40)))
+            .unwrap();
+    }
+
+    fn revealed_indices(order_hash: &str) -> Vec<u32> {
+        crate::fusion_plus_order_revealed_secrets(order_hash.to_string())
+            .unwrap()
+            .iter()
+            .map(|revealed| revealed.idx)
+            .collect()
+    }
+
+    #[test]
+    fn test_secrets_unlock_stepwise_with_fill_progress() {
+        let order_hash = submit_partial_fill_order();
+        record_test_escrow(&order_hash);
+        let maker = Principal::anonymous();
+        assert_eq!(memory::get_order(&order_hash).unwrap().secret_hashes.len(), 4);
+
+        // Nothing is filled yet, so even the first secret stays locked
+        assert!(matches!(
+            crate::submit_secret(maker, &order_hash, &secret(0), 1),
+            Err(FusionError::SecretNotYetUnlockable)
+        ));
+
+        crate::record_fill_progress(RESOLVER, &order_hash, 2_500).unwrap();
+        crate::submit_secret(maker, &order_hash, &secret(0), 1).unwrap();
+        assert!(matches!(
+            crate::submit_secret(maker, &order_hash, &secret(1), 2),
+            Err(FusionError::SecretNotYetUnlockable)
+        ));
+
+        crate::record_fill_progress(RESOLVER, &order_hash, 6_000).unwrap();
+        crate::submit_secret(maker, &order_hash, &secret(1).to_uppercase().replace("0X", "0x"), 2)
+            .unwrap();
+        assert_eq!(revealed_indices(&order_hash), vec![0, 1]);
+        assert_eq!(memory::get_revealed_secrets(&order_hash)[1].secret, secret(1));
+    }
+
+    #[test]
+    fn test_final_secret_unlocks_at_full_fill() {
+        let order_hash = submit_partial_fill_order();
+        record_test_escrow(&order_hash);
+        let maker = Principal::anonymous();
+
+        crate::record_fill_progress(RESOLVER, &order_hash, 9_999).unwrap();
+        assert!(matches!(
+            crate::submit_secret(maker, &order_hash, &secret(3), 1),
+            Err(FusionError::SecretNotYetUnlockable)
+        ));
+
+        crate::record_fill_progress(RESOLVER, &order_hash, 10_000).unwrap();
+        crate::submit_secret(maker, &order_hash, &secret(3), 1).unwrap();
+        assert_eq!(revealed_indices(&order_hash), vec![3]);
+
+        // Revealed secrets survive upgrade
+        let extended = memory::serialize_extended_state();
+        memory::clear_relayer_state();
+        memory::deserialize_extended_state(extended);
+        assert_eq!(memory::get_revealed_secrets(&order_hash).len(), 1);
+    }
+
+    #[test]
+    fn test_secret_submission_rejections() {
+        let order_hash = submit_partial_fill_order();
+        record_test_escrow(&order_hash);
+        crate::record_fill_progress(RESOLVER, &order_hash, 5_000).unwrap();
+
+        assert!(matches!(
+            crate::submit_secret(Principal::management_canister(), &order_hash, &secret(0), 1),
+            Err(FusionError::Unauthorized)
+        ));
+        for wrong in [secret(9), "0xnothex".to_string()] {
+            assert!(matches!(
+                crate::submit_secret(Principal::anonymous(), &order_hash, &wrong, 1),
+                Err(FusionError::InvalidSecretHash)
+            ));
+        }
+
+        // Progress cannot move backwards or beyond a complete fill
+        for progress in [4_999, 10_001] {
+            assert!(matches!(
+                crate::record_fill_progress(RESOLVER, &order_hash, progress),
+                Err(FusionError::InvalidAmount)
+            ));
+        }
+        assert_eq!(memory::get_order(&order_hash).unwrap().fill_progress_bps, Some(5_000));
+    }
+
+    #[test]
+    fn test_secret_nonce_reuse_rejected() {
+        let order_hash = submit_partial_fill_order();
+        record_test_escrow(&order_hash);
+        let maker = Principal::anonymous();
+        crate::record_fill_progress(RESOLVER, &order_hash, 10_000).unwrap();
+
+        // Nonces start at 1
+        assert!(matches!(
+            crate::submit_secret(maker, &order_hash, &secret(0), 0),
+            Err(FusionError::InvalidNonce(1))
+        ));
+        crate::submit_secret(maker, &order_hash, &secret(0), 1).unwrap();
+
+        // Replaying the same submission, or reusing its nonce for another secret, fails
+        for idx in [0, 1] {
+            assert!(matches!(
+                crate::submit_secret(maker, &order_hash, &secret(idx), 1),
+                Err(FusionError::InvalidNonce(2))
+            ));
+        }
+        assert_eq!(crate::get_next_secret_nonce(maker), 2);
+    }
+
+    #[test]
+    fn test_secret_nonce_out_of_order_rejected() {
+        let order_hash = submit_partial_fill_order();
+        record_test_escrow(&order_hash);
+        let maker = Principal::anonymous();
+        crate::record_fill_progress(RESOLVER, &order_hash, 10_000).unwrap();
+
+        // Gaps are allowed, but a lower nonce after a higher one is not
+        crate::submit_secret(maker, &order_hash, &secret(0), 5).unwrap();
+        assert!(matches!(
+            crate::submit_secret(maker, &order_hash, &secret(1), 3),
+            Err(FusionError::InvalidNonce(6))
+        ));
+        crate::submit_secret(maker, &order_hash, &secret(1), 6).unwrap();
+
+        // Rejected submissions do not consume a nonce
+        assert!(crate::submit_secret(maker, &order_hash, "0xnothex", 7).is_err());
+        assert_eq!(crate::get_next_secret_nonce(maker), 7);
+    }
+
+    #[test]
+    fn test_secret_submission_audit_records() {
+        let order_hash = submit_partial_fill_order();
+        record_test_escrow(&order_hash);
+        let maker = Principal::anonymous();
+        crate::record_fill_progress(RESOLVER, &order_hash, 10_000).unwrap();
+
+        memory::set_test_time(2_000_000_000_000);
+        crate::submit_secret(maker, &order_hash, &secret(2), 1).unwrap();
+        memory::set_test_time(3_000_000_000_000);
+        crate::submit_secret(maker, &order_hash, &secret(0), 2).unwrap();
+
+        let expected = vec![
+            SecretSubmission {
+                maker,
+                nonce: 1,
+                order_hash: order_hash.clone(),
+                idx: 2,
+                submitted_at: 2_000_000_000_000,
+            },
+            SecretSubmission {
+                maker,
+                nonce: 2,
+                order_hash: order_hash.clone(),
+                idx: 0,
+                submitted_at: 3_000_000_000_000,
+            },
+        ];
+        assert_eq!(crate::get_secret_submissions(order_hash.clone()), expected);
+
+        // Audit records and nonces survive upgrade
+        let extended = memory::serialize_extended_state();
+        memory::clear_relayer_state();
+        memory::deserialize_extended_state(extended);
+        assert_eq!(memory::get_secret_submissions(&order_hash), expected);
+        assert_eq!(memory::get_secret_nonce(maker), 2);
+    }
+
+    #[test]
+    fn test_audit_trail_records_full_lifecycle() {
+        let order_hash = submit_partial_fill_order();
+        let maker = Principal::anonymous();
+        let escrow = format!("0x{}", "ab".repeat(20));
+        memory::set_chain_contracts(84532, test_contracts('a'));
+
+        memory::set_test_time(2_000_000_000_000);
+        crate::record_escrow_created(RESOLVER, &order_hash, 84532, escrow.clone()).unwrap();
+        crate::record_fill_progress(RESOLVER, &order_hash, 2_500).unwrap();
+        memory::set_test_time(3_000_000_000_000);
+        crate::submit_secret(maker, &order_hash, &secret(0), 1).unwrap();
+
+        // Rejected mutations leave no trace
+        assert!(crate::submit_secret(maker, &order_hash, &secret(0), 1).is_err());
+        assert!(crate::record_fill_progress(RESOLVER, &order_hash, 1_000).is_err());
+
+        let entries = crate::fusion_plus_order_audit(order_hash.clone(), 0, 10).unwrap();
+        let trail: Vec<_> =
+            entries.iter().map(|e| (e.action.clone(), e.caller, e.timestamp)).collect();
+        assert_eq!(
+            trail,
+            vec![
+                (AuditAction::Submitted, maker, 1_000_000_000_000),
+                (AuditAction::EscrowRecorded, RESOLVER, 2_000_000_000_000),
+                (AuditAction::FillProgress, RESOLVER, 2_000_000_000_000),
+                (AuditAction::SecretRevealed, maker, 3_000_000_000_000),
+            ]
+        );
+        assert_eq!(entries[1].details, format!("chain_id=84532 address={}", escrow));
+        assert_eq!(entries[3].details, "idx=0 nonce=1");
+
+        // Paging, unknown orders and upgrades
+        assert_eq!(
+            crate::fusion_plus_order_audit(order_hash.clone(), 1, 2).unwrap(),
+            entries[1..3]
+        );
+        assert!(matches!(
+            crate::fusion_plus_order_audit("0xmissing".to_string(), 0, 10),
+            Err(FusionError::OrderNotFound)
+        ));
+
+        let (orders, identities) = memory::serialize_relayer_state();
+        let extended = memory::serialize_extended_state();
+        memory::clear_relayer_state();
+        memory::deserialize_relayer_state(orders, identities);
+        memory::deserialize_extended_state(extended);
+        assert_eq!(crate::fusion_plus_order_audit(order_hash, 0, 10).unwrap(), entries);
+    }
+
+    #[test]
+    fn test_audit_trail_capped_per_order() {
+        let order_hash = submit_partial_fill_order();
+        record_test_escrow(&order_hash);
+        for _ in 0..memory::MAX_AUDIT_ENTRIES_PER_ORDER + 5 {
+            crate::record_fill_progress(RESOLVER, &order_hash, 5_000).unwrap();
+        }
+
+        let entries = crate::fusion_plus_order_audit(order_hash, 0, u64::MAX).unwrap();
+        assert_eq!(entries.len(), memory::MAX_AUDIT_ENTRIES_PER_ORDER);
+        assert!(entries.iter().all(|entry| entry.action == AuditAction::FillProgress));
+    }
+
+    fn submit_as(caller: Principal, maker: &str, n: u64) -> Result<String, FusionError> {
+        let mut order = create_test_order();
+        order.maker = maker.to_string();
+        order.salt = n.to_string();
+        memory::set_supported_chain(84532, test_chain());
+        crate::submit_order(
+            caller,
+            order,
+            (84532, ICP_CHAIN_ID),
+            VALID_SIGNATURE.to_string(),
+            "0x".to_string(),
+            "quote".to_string(),
+            vec![format!("{:064x}", n)],
+        )
+    }
+
+    const MINUTE: u64 = 60_000_000_000;
+
+    #[test]
+    fn test_submission_bucket_exhaustion_and_refill() {
+        memory::clear_relayer_state();
+        memory::set_test_time(1_000_000_000_000);
+        memory::set_submission_quota(SubmissionQuota {
+            bucket_capacity: 2,
+            refill_interval_ns: MINUTE,
+            max_pending_orders: 100,
+        });
+        let maker = "0x1234567890123456789012345678901234567890";
+        let caller = Principal::from_slice(&[1; 10]);
+
+        assert!(submit_as(caller, maker, 1).is_ok());
+        assert!(submit_as(caller, maker, 2).is_ok());
+        assert!(matches!(submit_as(caller, maker, 3), Err(FusionError::RateLimited(MINUTE))));
+
+        // Another principal signing for the same maker address shares its bucket
+        let other = Principal::from_slice(&[2; 10]);
+        assert!(matches!(
+            submit_as(other, &maker.to_uppercase(), 3),
+            Err(FusionError::RateLimited(_))
+        ));
+
+        // Another maker through the same caller is limited by the caller's bucket
+        let other_maker = "0x9999999999999999999999999999999999999999";
+        assert!(matches!(submit_as(caller, other_maker, 3), Err(FusionError::RateLimited(_))));
+        assert!(submit_as(other, other_maker, 3).is_ok());
+
+        // One token back after an interval, with the hint counting down until then
+        memory::set_test_time(1_000_000_000_000 + MINUTE / 2);
+        assert!(matches!(
+            submit_as(caller, maker, 4),
+            Err(FusionError::RateLimited(wait)) if wait == MINUTE / 2
+        ));
+        memory::set_test_time(1_000_000_000_000 + MINUTE);
+        assert!(submit_as(caller, maker, 4).is_ok());
+        assert!(matches!(submit_as(caller, maker, 5), Err(FusionError::RateLimited(_))));
+
+        // Refills never exceed the capacity
+        memory::set_test_time(1_000_000_000_000 + 10 * MINUTE);
+        assert!(submit_as(caller, maker, 6).is_ok());
+        assert!(submit_as(caller, maker, 7).is_ok());
+        let result = submit_as(caller, maker, 8);
+        assert!(matches!(result, Err(FusionError::RateLimited(_))));
+
+        metrics::record_submission_result(&result, memory::current_time());
+        let metrics = metrics::get_metrics(memory::current_time());
+        assert_eq!(metrics.rejected_submissions, vec![("RateLimited".to_string(), 1)]);
+    }
+
+    #[test]
+    fn test_global_pending_order_cap() {
+        memory::clear_relayer_state();
+        memory::set_test_time(1_000_000_000_000);
+        memory::set_submission_quota(SubmissionQuota {
+            bucket_capacity: 10,
+            refill_interval_ns: MINUTE,
+            max_pending_orders: 2,
+        });
+        let maker = "0x1234567890123456789012345678901234567890";
+
+        assert!(submit_as(Principal::from_slice(&[1; 10]), maker, 1).is_ok());
+        memory::set_test_time(1_000_000_000_000 + MINUTE);
+        assert!(submit_as(Principal::from_slice(&[2; 10]), maker, 2).is_ok());
+
+        // The hint points at the first order to expire
+        let hour = 3_600_000_000_000;
+        assert!(matches!(
+            submit_as(Principal::from_slice(&[3; 10]), maker, 3),
+            Err(FusionError::QuotaExceeded(wait)) if wait == hour - MINUTE
+        ));
+
+        // Rejections by the cap do not spend the caller's tokens, and expiry frees a slot
+        memory::set_test_time(1_000_000_000_000 + hour);
+        assert!(submit_as(Principal::from_slice(&[3; 10]), maker, 3).is_ok());
+    }
+
+    /// Require a deposit of 100 per submission, refunding one untouched expiry in a row
+    fn require_deposits() {
+        memory::set_deposit_policy(DepositPolicy {
+            ledger: Principal::from_slice(&[9; 10]),
+            amount: 100,
+            free_untouched_expiries: 1,
+        });
+    }
+
+    #[test]
+    fn test_submission_locks_deposit_until_order_completes() {
+        memory::clear_relayer_state();
+        require_deposits();
+        let maker = "0x1234567890123456789012345678901234567890";
+        let caller = Principal::from_slice(&[1; 10]);
+
+        assert!(matches!(submit_as(caller, maker, 1), Err(FusionError::InsufficientDeposit(100))));
+        memory::credit_deposit(caller, 150);
+        let order_hash = submit_as(caller, maker, 1).unwrap();
+        let balance = DepositBalance { available: 50, locked: 100, forfeited: 0 };
+        assert_eq!(memory::get_deposit_balance(caller), balance);
+        assert!(matches!(submit_as(caller, maker, 2), Err(FusionError::InsufficientDeposit(100))));
+
+        // Locked deposits survive upgrades
+        let (orders, identities) = memory::serialize_relayer_state();
+        let extended = memory::serialize_extended_state();
+        memory::clear_relayer_state();
+        memory::deserialize_relayer_state(orders, identities);
+        memory::deserialize_extended_state(extended);
+        assert_eq!(memory::get_deposit_balance(caller), balance);
+
+        // The deposit returns once the order leaves the active states
+        let mut order = memory::get_order(&order_hash).unwrap();
+        order.status = OrderStatus::Completed;
+        memory::store_order(order).unwrap();
+        let refunded = DepositBalance { available: 150, locked: 0, forfeited: 0 };
+        assert_eq!(memory::get_deposit_balance(caller), refunded);
+    }
+
+    #[test]
+    fn test_repeated_untouched_expiries_forfeit_deposits() {
+        memory::clear_relayer_state();
+        memory::set_test_time(1_000_000_000_000);
+        require_deposits();
+        let makers = ["1", "2", "3"].map(|digit| format!("0x{}", digit.repeat(40)));
+        let caller = Principal::from_slice(&[1; 10]);
+        let other = Principal::from_slice(&[2; 10]);
+        memory::credit_deposit(caller, 1_000);
+        memory::credit_deposit(other, 1_000);
+
+        // Switching maker addresses does not restart the caller's streak
+        let mut orders = vec![];
+        for (n, maker) in (1..=3).zip(&makers) {
+            memory::set_test_time(1_000_000_000_000 + n * MINUTE);
+            orders.push(submit_as(caller, maker, n).unwrap());
+        }
+        record_test_escrow(&orders[2]);
+        crate::record_fill_progress(RESOLVER, &orders[2], 2_500).unwrap();
+
+        // Another depositor reusing a maker address keeps a streak of its own
+        submit_as(other, &makers[0], 4).unwrap();
+
+        // The first untouched expiry is refunded, the second in a row forfeited
+        let hour = 3_600_000_000_000;
+        memory::set_test_time(1_000_000_000_000 + hour + 3 * MINUTE);
+        assert_eq!(memory::settle_expired_deposits(memory::current_time()), 1);
+        let balance = DepositBalance { available: 900, locked: 0, forfeited: 100 };
+        assert_eq!(memory::get_deposit_balance(caller), balance);
+        let untouched = DepositBalance { available: 1_000, locked: 0, forfeited: 0 };
+        assert_eq!(memory::get_deposit_balance(other), untouched);
+
+        // The touched order reset the streak, so the next untouched expiry is refunded again
+        submit_as(caller, &makers[0], 5).unwrap();
+        memory::set_test_time(1_000_000_000_000 + 2 * hour + 3 * MINUTE);
+        assert_eq!(memory::settle_expired_deposits(memory::current_time()), 0);
+        assert_eq!(memory::get_deposit_balance(caller), balance);
+    }
+
+    fn submit_between(src_chain_id: u64, dst_chain_id: u64) -> Result<String, FusionError> {
+        crate::submit_order(
+            Principal::anonymous(),
+            create_test_order(),
+            (src_chain_id, dst_chain_id),
+            VALID_SIGNATURE.to_string(),
+            "0x".to_string(),
+            "quote".to_string(),
+            vec!["a".repeat(64)],
+        )
+    }
+
+    #[test]
+    fn test_supported_chain_pair_stored_on_order() {
+        memory::clear_relayer_state();
+        memory::set_supported_chain(84532, test_chain());
+
+        let order_id = submit_between(84532, ICP_CHAIN_ID).unwrap();
+        let order = memory::get_order(&order_id).unwrap();
+        assert_eq!(order.src_chain_id, 84532);
+        assert_eq!(order.dst_chain_id, ICP_CHAIN_ID);
+    }
+
+    #[test]
+    fn test_unknown_chains_rejected_with_supported_ids() {
+        memory::clear_relayer_state();
+
+        // A fresh registry only knows ICP
+        match submit_between(84532, ICP_CHAIN_ID) {
+            Err(FusionError::UnsupportedChain(ids)) => assert_eq!(ids, vec![ICP_CHAIN_ID]),
+            other => panic!("Expected UnsupportedChain error, got {:?}", other),
+        }
+
+        memory::set_supported_chain(84532, test_chain());
+        match submit_between(84532, 1) {
+            Err(FusionError::UnsupportedChain(ids)) => assert_eq!(ids, vec![ICP_CHAIN_ID, 84532]),
+            other => panic!("Expected UnsupportedChain error, got {:?}", other),
+        }
+        assert!(memory::get_active_orders().is_empty());
+
+        // Removed chains stop accepting orders, and the registry survives upgrades
+        let state = memory::serialize_extended_state();
+        memory::remove_supported_chain(84532).unwrap();
+        assert!(matches!(
+            submit_between(84532, ICP_CHAIN_ID),
+            Err(FusionError::UnsupportedChain(_))
+        ));
+        memory::deserialize_extended_state(state);
+        assert!(submit_between(84532, ICP_CHAIN_ID).is_ok());
+    }
+
+    #[test]
+    fn test_supported_chain_factory_validation() {
+        let mut chain = test_chain();
+        assert!(crate::helpers::validate_supported_chain(&chain).is_ok());
+        assert!(crate::helpers::validate_supported_chain(&SupportedChain::icp()).is_ok());
+
+        chain.escrow_factory = Some("0x123".to_string());
+        assert!(matches!(
+            crate::helpers::validate_supported_chain(&chain),
+            Err(FusionError::TokenAddressInvalid)
+        ));
+    }
+
+    #[test]
+    fn test_bulk_status_preserves_input_order() {
+        let order_hash = submit_partial_fill_order();
+        record_test_escrow(&order_hash);
+        memory::set_test_time(2_000_000_000_000);
+        crate::record_fill_progress(RESOLVER, &order_hash, 2_500).unwrap();
+        let other = submit("other", VALID_SIGNATURE, vec!["e".repeat(64)]).unwrap();
+
+        let hashes = vec![other.clone(), "0xunknown".to_string(), order_hash.clone()];
+        let statuses = crate::fusion_plus_orders_status_bulk(hashes.clone()).unwrap();
+        let returned: Vec<_> = statuses.iter().map(|(hash, _)| hash.clone()).collect();
+        assert_eq!(returned, hashes);
+
+        // Untouched orders report their creation time, unknown hashes no summary
+        let other_created = memory::get_order(&other).unwrap().created_at;
+        assert_eq!(
+            statuses[0].1,
+            Some(OrderStatusSummary {
+                status: OrderStatus::Pending,
+                fill_progress_bps: Some(0),
+                updated_at: other_created,
+            })
+        );
+        assert_eq!(statuses[1].1, None);
+        assert_eq!(
+            statuses[2].1,
+            Some(OrderStatusSummary {
+                status: OrderStatus::Pending,
+                fill_progress_bps: Some(2_500),
+                updated_at: 2_000_000_000_000,
+            })
+        );
+
+        assert_eq!(crate::fusion_plus_orders_exist(hashes).unwrap(), vec![true, false, true]);
+    }
+
+    #[test]
+    fn test_bulk_queries_reject_oversized_batches() {
+        memory::clear_relayer_state();
+        let at_cap = vec!["0xunknown".to_string(); crate::MAX_BULK_ORDER_HASHES];
+        assert_eq!(crate::fusion_plus_orders_exist(at_cap.clone()).unwrap().len(), at_cap.len());
+
+        let over_cap = vec!["0xunknown".to_string(); crate::MAX_BULK_ORDER_HASHES + 1];
+        assert!(matches!(
+            crate::fusion_plus_orders_status_bulk(over_cap.clone()),
+            Err(FusionError::TooManyOrderHashes(200))
+        ));
+        assert!(matches!(
+            crate::fusion_plus_orders_exist(over_cap),
+            Err(FusionError::TooManyOrderHashes(200))
+        ));
+    }
 }
@@ -1,20 +1,101 @@
-use crate::types::{FusionError, Order, OrderStatus};
+use crate::types::{
+    AmountCaps, AuditEntry, DepositBalance, DepositPolicy, EscrowContracts, FusionError, Order,
+    OrderStatus, RevealedSecret, SecretSubmission, SubmissionQuota, SupportedChain, TokenBucket,
+    ICP_CHAIN_ID,
+};
+use candid::Principal;
+use candid::{CandidType, Deserialize};
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 
 // Global state using thread_local! for safety
 thread_local! {
     static ORDERS: RefCell<HashMap<String, Order>> = RefCell::new(HashMap::new());
+    static SUPPORTED_CHAINS: RefCell<BTreeMap<u64, SupportedChain>> = RefCell::new(default_supported_chains());
+    static CHAIN_CONTRACTS: RefCell<HashMap<u64, EscrowContracts>> = RefCell::new(HashMap::new());
+    static ESCROW_ADDRESSES: RefCell<HashMap<(String, u64), String>> = RefCell::new(HashMap::new());
+    // Order id -> resolver that recorded the order's first escrow
+    static ESCROW_OWNERS: RefCell<HashMap<String, Principal>> = RefCell::new(HashMap::new());
+    static RESOLVERS: RefCell<BTreeSet<Principal>> = const { RefCell::new(BTreeSet::new()) };
+    static REVEALED_SECRETS: RefCell<HashMap<String, BTreeMap<u32, String>>> = RefCell::new(HashMap::new());
+    static AMOUNT_CAPS: RefCell<HashMap<u64, AmountCaps>> = RefCell::new(HashMap::new());
+    static DEFAULT_AMOUNT_CAPS: RefCell<AmountCaps> = RefCell::new(AmountCaps::default());
+    static SECRET_NONCES: RefCell<HashMap<Principal, u64>> = RefCell::new(HashMap::new());
+    static SECRET_SUBMISSIONS: RefCell<HashMap<String, Vec<SecretSubmission>>> = RefCell::new(HashMap::new());
+    static ORDER_AUDIT: RefCell<HashMap<String, Vec<AuditEntry>>> = RefCell::new(HashMap::new());
+    static SUBMISSION_QUOTA: RefCell<SubmissionQuota> = RefCell::new(SubmissionQuota::default());
+    static CALLER_BUCKETS: RefCell<HashMap<Principal, TokenBucket>> = RefCell::new(HashMap::new());
+    // Keyed by lowercase maker address
+    static MAKER_BUCKETS: RefCell<HashMap<String, TokenBucket>> = RefCell::new(HashMap::new());
+    // Lowercase secret hash -> id of the active order using it (derived from ORDERS)
+    static SECRET_HASH_OWNERS: RefCell<HashMap<String, String>> = RefCell::new(HashMap::new());
+    static DEPOSIT_POLICY: RefCell<Option<DepositPolicy>> = const { RefCell::new(None) };
+    static DEPOSIT_BALANCES: RefCell<HashMap<Principal, DepositBalance>> = RefCell::new(HashMap::new());
+    // Order id -> (submitter, amount) of the deposit the order holds
+    static ORDER_DEPOSITS: RefCell<HashMap<String, (Principal, u64)>> = RefCell::new(HashMap::new());
+    // Depositor -> orders in a row that expired untouched
+    static UNTOUCHED_EXPIRIES: RefCell<HashMap<Principal, u32>> = RefCell::new(HashMap::new());
+}
+
+// Mock clock so unit tests can run outside a canister
+#[cfg(test)]
+thread_local! {
+    static TEST_TIME: RefCell<u64> = const { RefCell::new(1_000_000_000_000) };
+}
+
+/// Current canister time in nanoseconds
+#[cfg(not(test))]
+pub fn current_time() -> u64 {
+    ic_cdk::api::time()
+}
+
+/// Current mock time in nanoseconds (for testing)
+#[cfg(test)]
+pub fn current_time() -> u64 {
+    TEST_TIME.with(|time| *time.borrow())
+}
+
+/// Set the mock time (for testing)
+#[cfg(test)]
+pub fn set_test_time(time: u64) {
+    TEST_TIME.with(|current| *current.borrow_mut() = time);
 }
 
 /// Store an order (create or update)
 pub fn store_order(order: Order) -> Result<(), FusionError> {
     ORDERS.with(|orders| {
-        orders.borrow_mut().insert(order.id.clone(), order);
+        let status = order.status.clone();
+        index_secret_hashes(&order);
+        if !matches!(status, OrderStatus::Pending | OrderStatus::Accepted) {
+            settle_order_deposit(&order, false);
+        }
+        let previous = orders.borrow_mut().insert(order.id.clone(), order);
+        crate::metrics::record_status_change(previous.map(|p| p.status).as_ref(), &status);
         Ok(())
     })
 }
 
+/// Claim the secret hashes of an active order, or release them once it is terminal
+fn index_secret_hashes(order: &Order) {
+    let active = matches!(order.status, OrderStatus::Pending | OrderStatus::Accepted);
+    SECRET_HASH_OWNERS.with(|owners| {
+        let mut owners = owners.borrow_mut();
+        for hash in &order.secret_hashes {
+            let key = hash.to_lowercase();
+            if active {
+                owners.insert(key, order.id.clone());
+            } else if owners.get(&key) == Some(&order.id) {
+                owners.remove(&key);
+            }
+        }
+    });
+}
+
+/// Find the active order that already uses a secret hash
+pub fn find_secret_hash_owner(secret_hash: &str) -> Option<String> {
+    SECRET_HASH_OWNERS.with(|owners| owners.borrow().get(&secret_hash.to_lowercase()).cloned())
+}
+
 /// Get an order by ID
 pub fn get_order(order_id: &str) -> Result<Order, FusionError> {
     ORDERS.with(|orders| orders.borrow().get(order_id).cloned().ok_or(FusionError::OrderNotFound))
@@ -32,6 +113,636 @@ pub fn get_active_orders() -> Vec<Order> {
     })
 }
 
+/// Count unexpired pending orders and the time left until the first of them expires
+pub fn count_pending_orders(now: u64) -> (u64, Option<u64>) {
+    ORDERS.with(|orders| {
+        orders
+            .borrow()
+            .values()
+            .filter(|order| order.status == OrderStatus::Pending && order.expires_at > now)
+            .fold((0, None), |(count, next), order| {
+                let left = order.expires_at - now;
+                (count + 1, Some(next.map_or(left, |next: u64| next.min(left))))
+            })
+    })
+}
+
+/// Registry a fresh canister starts with: only the Internet Computer
+fn default_supported_chains() -> BTreeMap<u64, SupportedChain> {
+    BTreeMap::from([(ICP_CHAIN_ID, SupportedChain::icp())])
+}
+
+/// Register or replace a chain orders may be submitted from or to
+pub fn set_supported_chain(chain_id: u64, chain: SupportedChain) {
+    SUPPORTED_CHAINS.with(|registry| {
+        registry.borrow_mut().insert(chain_id, chain);
+    });
+}
+
+/// Stop accepting orders from or to a chain
+pub fn remove_supported_chain(chain_id: u64) -> Result<(), FusionError> {
+    SUPPORTED_CHAINS
+        .with(|registry| registry.borrow_mut().remove(&chain_id).map(|_| ()))
+        .ok_or_else(unsupported_chain)
+}
+
+/// Get a supported chain's metadata
+pub fn get_supported_chain(chain_id: u64) -> Result<SupportedChain, FusionError> {
+    SUPPORTED_CHAINS
+        .with(|registry| registry.borrow().get(&chain_id).cloned())
+        .ok_or_else(unsupported_chain)
+}
+
+/// List all supported chains, by id
+pub fn list_supported_chains() -> Vec<(u64, SupportedChain)> {
+    SUPPORTED_CHAINS.with(|registry| {
+        registry.borrow().iter().map(|(chain_id, chain)| (*chain_id, chain.clone())).collect()
+    })
+}
+
+/// Error for a chain missing from a registry, naming the chains that are supported
+pub fn unsupported_chain() -> FusionError {
+    FusionError::UnsupportedChain(
+        SUPPORTED_CHAINS.with(|registry| registry.borrow().keys().copied().collect()),
+    )
+}
+
+/// Register or replace the escrow contracts for a chain
+pub fn set_chain_contracts(chain_id: u64, contracts: EscrowContracts) {
+    CHAIN_CONTRACTS.with(|registry| {
+        registry.borrow_mut().insert(chain_id, contracts);
+    });
+}
+
+/// Get the escrow contracts registered for a chain
+pub fn get_chain_contracts(chain_id: u64) -> Result<EscrowContracts, FusionError> {
+    CHAIN_CONTRACTS
+        .with(|registry| registry.borrow().get(&chain_id).cloned().ok_or_else(unsupported_chain))
+}
+
+/// Remove the escrow contracts registered for a chain
+pub fn remove_chain_contracts(chain_id: u64) -> Result<(), FusionError> {
+    CHAIN_CONTRACTS.with(|registry| {
+        registry.borrow_mut().remove(&chain_id).map(|_| ()).ok_or_else(unsupported_chain)
+    })
+}
+
+/// List all registered chains and their escrow contracts
+pub fn list_chain_contracts() -> Vec<(u64, EscrowContracts)> {
+    CHAIN_CONTRACTS.with(|registry| {
+        let mut entries: Vec<(u64, EscrowContracts)> =
+            registry.borrow().iter().map(|(k, v)| (*k, v.clone())).collect();
+        entries.sort_by_key(|(chain_id, _)| *chain_id);
+        entries
+    })
+}
+
+/// Set the amount caps for a chain, overriding the defaults
+pub fn set_amount_caps(chain_id: u64, caps: AmountCaps) {
+    AMOUNT_CAPS.with(|registry| {
+        registry.borrow_mut().insert(chain_id, caps);
+    });
+}
+
+/// Remove the amount caps for a chain so the defaults apply again
+///
+/// Chains without caps of their own already use the defaults, so there is nothing to remove.
+pub fn remove_amount_caps(chain_id: u64) {
+    AMOUNT_CAPS.with(|registry| {
+        registry.borrow_mut().remove(&chain_id);
+    });
+}
+
+/// Set the amount caps for chains without their own caps
+pub fn set_default_amount_caps(caps: AmountCaps) {
+    DEFAULT_AMOUNT_CAPS.with(|defaults| *defaults.borrow_mut() = caps);
+}
+
+/// Get the amount caps that apply on a chain
+pub fn get_amount_caps(chain_id: u64) -> AmountCaps {
+    AMOUNT_CAPS
+        .with(|registry| registry.borrow().get(&chain_id).cloned())
+        .unwrap_or_else(|| DEFAULT_AMOUNT_CAPS.with(|defaults| defaults.borrow().clone()))
+}
+
+/// Set the submission limits
+pub fn set_submission_quota(quota: SubmissionQuota) {
+    SUBMISSION_QUOTA.with(|current| *current.borrow_mut() = quota);
+}
+
+/// Get the submission limits
+pub fn get_submission_quota() -> SubmissionQuota {
+    SUBMISSION_QUOTA.with(|quota| quota.borrow().clone())
+}
+
+/// Take one submission token from both the caller's and the maker address's bucket
+///
+/// Nothing is taken unless both have a token; the error carries the wait until they do.
+pub fn take_submission_tokens(caller: Principal, maker: &str, now: u64) -> Result<(), FusionError> {
+    let quota = get_submission_quota();
+    let maker = maker.to_lowercase();
+
+    let mut caller_bucket = CALLER_BUCKETS
+        .with(|buckets| buckets.borrow().get(&caller).cloned())
+        .unwrap_or_else(|| TokenBucket::full(&quota, now));
+    let mut maker_bucket = MAKER_BUCKETS
+        .with(|buckets| buckets.borrow().get(&maker).cloned())
+        .unwrap_or_else(|| TokenBucket::full(&quota, now));
+    caller_bucket.refill(&quota, now);
+    maker_bucket.refill(&quota, now);
+
+    let wait = caller_bucket.wait_ns(&quota, now).max(maker_bucket.wait_ns(&quota, now));
+    if wait > 0 {
+        return Err(FusionError::RateLimited(wait));
+    }
+
+    caller_bucket.tokens -= 1;
+    maker_bucket.tokens -= 1;
+    CALLER_BUCKETS.with(|buckets| {
+        store_bucket(&mut buckets.borrow_mut(), caller, caller_bucket, &quota, now)
+    });
+    MAKER_BUCKETS
+        .with(|buckets| store_bucket(&mut buckets.borrow_mut(), maker, maker_bucket, &quota, now));
+    Ok(())
+}
+
+/// Tracked buckets above which refilled ones are dropped
+const MAX_IDLE_BUCKETS: usize = 10_000;
+
+/// Store a bucket, dropping refilled ones once enough are tracked to matter
+fn store_bucket<K: std::hash::Hash + Eq>(
+    buckets: &mut HashMap<K, TokenBucket>,
+    key: K,
+    bucket: TokenBucket,
+    quota: &SubmissionQuota,
+    now: u64,
+) {
+    buckets.insert(key, bucket);
+    if buckets.len() > MAX_IDLE_BUCKETS {
+        buckets.retain(|_, bucket| {
+            let mut bucket = bucket.clone();
+            bucket.refill(quota, now);
+            !bucket.is_full(quota)
+        });
+    }
+}
+
+/// Set the deposit each submission locks
+pub fn set_deposit_policy(policy: DepositPolicy) {
+    DEPOSIT_POLICY.with(|current| *current.borrow_mut() = Some(policy));
+}
+
+/// Get the deposit policy, None until a controller first sets one
+pub fn get_deposit_policy() -> Option<DepositPolicy> {
+    DEPOSIT_POLICY.with(|policy| policy.borrow().clone())
+}
+
+/// Get the deposit funds a submitter holds with the relayer
+pub fn get_deposit_balance(owner: Principal) -> DepositBalance {
+    DEPOSIT_BALANCES.with(|balances| balances.borrow().get(&owner).cloned().unwrap_or_default())
+}
+
+/// Whether any submitter holds available or locked deposit funds
+pub fn deposits_held() -> bool {
+    DEPOSIT_BALANCES.with(|balances| {
+        balances.borrow().values().any(|balance| balance.available > 0 || balance.locked > 0)
+    })
+}
+
+/// Apply a change to a submitter's deposit balance
+fn update_deposit_balance<R>(owner: Principal, f: impl FnOnce(&mut DepositBalance) -> R) -> R {
+    DEPOSIT_BALANCES.with(|balances| f(balances.borrow_mut().entry(owner).or_default()))
+}
+
+/// Add funds paid in by a submitter to their available deposit
+pub fn credit_deposit(owner: Principal, amount: u64) {
+    update_deposit_balance(owner, |balance| balance.available += amount);
+}
+
+/// Take a submitter's whole available deposit for withdrawal
+pub fn take_available_deposit(owner: Principal) -> u64 {
+    update_deposit_balance(owner, |balance| std::mem::take(&mut balance.available))
+}
+
+/// Lock part of a submitter's available deposit for the lifetime of an order
+pub fn lock_deposit(owner: Principal, order_id: &str, amount: u64) -> Result<(), FusionError> {
+    update_deposit_balance(owner, |balance| {
+        if balance.available < amount {
+            return Err(FusionError::InsufficientDeposit(amount));
+        }
+        balance.available -= amount;
+        balance.locked += amount;
+        Ok(())
+    })?;
+    ORDER_DEPOSITS.with(|deposits| {
+        deposits.borrow_mut().insert(order_id.to_string(), (owner, amount));
+    });
+    Ok(())
+}
+
+/// Release the deposit an order holds, back to its submitter or forfeited
+fn settle_order_deposit(order: &Order, forfeit: bool) {
+    let Some((owner, amount)) =
+        ORDER_DEPOSITS.with(|deposits| deposits.borrow_mut().remove(&order.id))
+    else {
+        return;
+    };
+
+    update_deposit_balance(owner, |balance| {
+        balance.locked -= amount;
+        if forfeit {
+            balance.forfeited += amount;
+        } else {
+            balance.available += amount;
+        }
+    });
+}
+
+/// Settle the deposits of active orders that expired, returning how many were forfeited
+///
+/// An order is untouched when it expired still Pending, without fill progress or a recorded
+/// escrow. Each depositor may let `free_untouched_expiries` such orders lapse in a row before
+/// their deposits are forfeited; any touched order resets the count. The streak follows the
+/// principal that paid the deposit, since the maker address is chosen by the submitter.
+pub fn settle_expired_deposits(now: u64) -> u64 {
+    let free_expiries =
+        get_deposit_policy().map_or(u32::MAX, |policy| policy.free_untouched_expiries);
+    let mut expired: Vec<(Order, Principal)> = ORDER_DEPOSITS.with(|deposits| {
+        deposits
+            .borrow()
+            .iter()
+            .filter_map(|(order_id, (owner, _))| Some((get_order(order_id).ok()?, *owner)))
+            .filter(|(order, _)| order.expires_at <= now)
+            .collect()
+    });
+    expired.sort_by_key(|(order, _)| (order.expires_at, order.id.clone()));
+
+    let mut forfeited = 0;
+    for (order, depositor) in expired {
+        let touched = order.status != OrderStatus::Pending
+            || order.fill_progress_bps.unwrap_or(0) > 0
+            || get_escrow_address(&order.id, order.src_chain_id).is_some()
+            || get_escrow_address(&order.id, order.dst_chain_id).is_some();
+        let streak = UNTOUCHED_EXPIRIES.with(|expiries| {
+            let mut expiries = expiries.borrow_mut();
+            if touched {
+                expiries.remove(&depositor);
+                0
+            } else {
+                let streak = expiries.entry(depositor).or_default();
+                *streak += 1;
+                *streak
+            }
+        });
+
+        let forfeit = streak > free_expiries;
+        settle_order_deposit(&order, forfeit);
+        forfeited += u64::from(forfeit);
+    }
+    forfeited
+}
+
+/// Record the escrow deployed for an order on a chain
+pub fn set_escrow_address(order_id: &str, chain_id: u64, escrow_address: String) {
+    ESCROW_ADDRESSES.with(|addresses| {
+        addresses.borrow_mut().insert((order_id.to_string(), chain_id), escrow_address);
+    });
+}
+
+/// Record the resolver owning an order's escrows, unless one already does
+pub fn claim_escrow_owner(order_id: &str, resolver: Principal) {
+    ESCROW_OWNERS.with(|owners| {
+        owners.borrow_mut().entry(order_id.to_string()).or_insert(resolver);
+    });
+}
+
+/// Get the resolver that recorded an order's first escrow, if any
+pub fn get_escrow_owner(order_id: &str) -> Option<Principal> {
+    ESCROW_OWNERS.with(|owners| owners.borrow().get(order_id).copied())
+}
+
+/// Allow a principal to report escrows and fill progress
+pub fn add_resolver(resolver: Principal) {
+    RESOLVERS.with(|resolvers| {
+        resolvers.borrow_mut().insert(resolver);
+    });
+}
+
+/// Stop accepting escrow and fill reports from a principal
+pub fn remove_resolver(resolver: Principal) {
+    RESOLVERS.with(|resolvers| {
+        resolvers.borrow_mut().remove(&resolver);
+    });
+}
+
+/// Check whether a principal is a registered resolver
+pub fn is_resolver(principal: &Principal) -> bool {
+    RESOLVERS.with(|resolvers| resolvers.borrow().contains(principal))
+}
+
+/// List all registered resolvers
+pub fn list_resolvers() -> Vec<Principal> {
+    RESOLVERS.with(|resolvers| resolvers.borrow().iter().copied().collect())
+}
+
+/// Get the escrow deployed for an order on a chain, if any
+pub fn get_escrow_address(order_id: &str, chain_id: u64) -> Option<String> {
+    ESCROW_ADDRESSES
+        .with(|addresses| addresses.borrow().get(&(order_id.to_string(), chain_id)).cloned())
+}
+
+/// Record a secret revealed by the maker of an order
+pub fn store_revealed_secret(order_id: &str, idx: u32, secret: String) {
+    REVEALED_SECRETS.with(|secrets| {
+        secrets.borrow_mut().entry(order_id.to_string()).or_default().insert(idx, secret);
+    });
+}
+
+/// Get the secrets revealed for an order, by index
+pub fn get_revealed_secrets(order_id: &str) -> Vec<RevealedSecret> {
+    REVEALED_SECRETS.with(|secrets| {
+        secrets
+            .borrow()
+            .get(order_id)
+            .map(|revealed| {
+                revealed
+                    .iter()
+                    .map(|(idx, secret)| RevealedSecret { idx: *idx, secret: secret.clone() })
+                    .collect()
+            })
+            .unwrap_or_default()
+    })
+}
+
+/// Get the last secret submission nonce a maker used, 0 if none
+pub fn get_secret_nonce(maker: Principal) -> u64 {
+    SECRET_NONCES.with(|nonces| nonces.borrow().get(&maker).copied().unwrap_or(0))
+}
+
+/// Record a secret submission, consuming its nonce for the maker
+pub fn record_secret_submission(submission: SecretSubmission) {
+    SECRET_NONCES.with(|nonces| {
+        nonces.borrow_mut().insert(submission.maker, submission.nonce);
+    });
+    SECRET_SUBMISSIONS.with(|submissions| {
+        submissions.borrow_mut().entry(submission.order_hash.clone()).or_default().push(submission);
+    });
+}
+
+/// Get the submissions that revealed secrets of an order, oldest first
+pub fn get_secret_submissions(order_id: &str) -> Vec<SecretSubmission> {
+    SECRET_SUBMISSIONS
+        .with(|submissions| submissions.borrow().get(order_id).cloned().unwrap_or_default())
+}
+
+/// Most audit entries kept per order; the oldest are dropped beyond this
+pub const MAX_AUDIT_ENTRIES_PER_ORDER: usize = 200;
+
+/// Append an entry to an order's audit trail
+pub fn record_audit_entry(order_id: &str, entry: AuditEntry) {
+    ORDER_AUDIT.with(|audit| {
+        let mut audit = audit.borrow_mut();
+        let entries = audit.entry(order_id.to_string()).or_default();
+        entries.push(entry);
+        if entries.len() > MAX_AUDIT_ENTRIES_PER_ORDER {
+            entries.drain(..entries.len() - MAX_AUDIT_ENTRIES_PER_ORDER);
+        }
+    });
+}
+
+/// Get a page of an order's audit trail, oldest first
+pub fn get_audit_entries(order_id: &str, offset: usize, limit: usize) -> Vec<AuditEntry> {
+    ORDER_AUDIT.with(|audit| {
+        audit
+            .borrow()
+            .get(order_id)
+            .map(|entries| entries.iter().skip(offset).take(limit).cloned().collect())
+            .unwrap_or_default()
+    })
+}
+
+/// Time of the latest entry in an order's audit trail
+pub fn get_last_audit_time(order_id: &str) -> Option<u64> {
+    ORDER_AUDIT.with(|audit| {
+        audit.borrow().get(order_id).and_then(|entries| entries.last()).map(|entry| entry.timestamp)
+    })
+}
+
+/// State added after the original upgrade tuple, kept optional so older snapshots still decode
+#[derive(Clone, Debug, Default, CandidType, Deserialize)]
+pub struct RelayerExtendedState {
+    pub chain_contracts: Option<Vec<(u64, EscrowContracts)>>,
+    pub escrow_addresses: Option<Vec<(String, u64, String)>>,
+    pub metrics: Option<crate::metrics::MetricsState>,
+    pub revealed_secrets: Option<Vec<(String, Vec<RevealedSecret>)>>,
+    pub amount_caps: Option<Vec<(u64, AmountCaps)>>,
+    pub default_amount_caps: Option<AmountCaps>,
+    pub secret_nonces: Option<Vec<(Principal, u64)>>,
+    pub secret_submissions: Option<Vec<SecretSubmission>>,
+    pub order_audit: Option<Vec<(String, Vec<AuditEntry>)>>,
+    pub submission_quota: Option<SubmissionQuota>,
+    pub supported_chains: Option<Vec<(u64, SupportedChain)>>,
+    pub deposit_policy: Option<DepositPolicy>,
+    pub deposit_balances: Option<Vec<(Principal, DepositBalance)>>,
+    pub order_deposits: Option<Vec<(String, Principal, u64)>>,
+    pub untouched_expiries: Option<Vec<(Principal, u32)>>,
+    pub resolvers: Option<Vec<Principal>>,
+    pub escrow_owners: Option<Vec<(String, Principal)>>,
+}
+
+/// Serialize state that is not part of the original upgrade tuple
+pub fn serialize_extended_state() -> RelayerExtendedState {
+    let escrow_addresses = ESCROW_ADDRESSES.with(|addresses| {
+        addresses
+            .borrow()
+            .iter()
+            .map(|((order_id, chain_id), address)| (order_id.clone(), *chain_id, address.clone()))
+            .collect()
+    });
+
+    let revealed_secrets = REVEALED_SECRETS.with(|secrets| {
+        secrets
+            .borrow()
+            .keys()
+            .map(|order_id| (order_id.clone(), get_revealed_secrets(order_id)))
+            .collect()
+    });
+
+    RelayerExtendedState {
+        chain_contracts: Some(list_chain_contracts()),
+        escrow_addresses: Some(escrow_addresses),
+        metrics: Some(crate::metrics::serialize_metrics_state()),
+        revealed_secrets: Some(revealed_secrets),
+        amount_caps: Some(
+            AMOUNT_CAPS
+                .with(|registry| registry.borrow().iter().map(|(k, v)| (*k, v.clone())).collect()),
+        ),
+        default_amount_caps: Some(DEFAULT_AMOUNT_CAPS.with(|defaults| defaults.borrow().clone())),
+        secret_nonces: Some(SECRET_NONCES.with(|nonces| {
+            nonces.borrow().iter().map(|(maker, nonce)| (*maker, *nonce)).collect()
+        })),
+        secret_submissions: Some(
+            SECRET_SUBMISSIONS
+                .with(|submissions| submissions.borrow().values().flatten().cloned().collect()),
+        ),
+        order_audit: Some(ORDER_AUDIT.with(|audit| {
+            audit
+                .borrow()
+                .iter()
+                .map(|(order_id, entries)| (order_id.clone(), entries.clone()))
+                .collect()
+        })),
+        submission_quota: Some(get_submission_quota()),
+        supported_chains: Some(list_supported_chains()),
+        deposit_policy: get_deposit_policy(),
+        deposit_balances: Some(DEPOSIT_BALANCES.with(|balances| {
+            balances.borrow().iter().map(|(owner, balance)| (*owner, balance.clone())).collect()
+        })),
+        order_deposits: Some(ORDER_DEPOSITS.with(|deposits| {
+            deposits
+                .borrow()
+                .iter()
+                .map(|(order_id, (owner, amount))| (order_id.clone(), *owner, *amount))
+                .collect()
+        })),
+        untouched_expiries: Some(UNTOUCHED_EXPIRIES.with(|expiries| {
+            expiries.borrow().iter().map(|(depositor, streak)| (*depositor, *streak)).collect()
+        })),
+        resolvers: Some(list_resolvers()),
+        escrow_owners: Some(ESCROW_OWNERS.with(|owners| {
+            owners.borrow().iter().map(|(order_id, owner)| (order_id.clone(), *owner)).collect()
+        })),
+    }
+}
+
+/// Restore state that is not part of the original upgrade tuple
+pub fn deserialize_extended_state(state: RelayerExtendedState) {
+    CHAIN_CONTRACTS.with(|registry| {
+        let mut registry = registry.borrow_mut();
+        registry.clear();
+        registry.extend(state.chain_contracts.unwrap_or_default());
+    });
+
+    ESCROW_ADDRESSES.with(|addresses| {
+        let mut addresses = addresses.borrow_mut();
+        addresses.clear();
+        for (order_id, chain_id, address) in state.escrow_addresses.unwrap_or_default() {
+            addresses.insert((order_id, chain_id), address);
+        }
+    });
+
+    REVEALED_SECRETS.with(|secrets| {
+        let mut secrets = secrets.borrow_mut();
+        secrets.clear();
+        for (order_id, revealed) in state.revealed_secrets.unwrap_or_default() {
+            secrets.insert(
+                order_id,
+                revealed.into_iter().map(|revealed| (revealed.idx, revealed.secret)).collect(),
+            );
+        }
+    });
+
+    AMOUNT_CAPS.with(|registry| {
+        let mut registry = registry.borrow_mut();
+        registry.clear();
+        registry.extend(state.amount_caps.unwrap_or_default());
+    });
+    set_default_amount_caps(state.default_amount_caps.unwrap_or_default());
+
+    SECRET_NONCES.with(|nonces| {
+        *nonces.borrow_mut() = state.secret_nonces.unwrap_or_default().into_iter().collect();
+    });
+    SECRET_SUBMISSIONS.with(|submissions| {
+        let mut submissions = submissions.borrow_mut();
+        submissions.clear();
+        for submission in state.secret_submissions.unwrap_or_default() {
+            submissions.entry(submission.order_hash.clone()).or_default().push(submission);
+        }
+    });
+
+    ORDER_AUDIT.with(|audit| {
+        *audit.borrow_mut() = state.order_audit.unwrap_or_default().into_iter().collect();
+    });
+
+    // Snapshots from before the registry start with ICP only, so EVM chains must be registered
+    SUPPORTED_CHAINS.with(|registry| {
+        *registry.borrow_mut() = state
+            .supported_chains
+            .map(|chains| chains.into_iter().collect())
+            .unwrap_or_else(default_supported_chains);
+    });
+
+    // Buckets are not persisted; an upgrade gives every submitter a fresh allowance
+    set_submission_quota(state.submission_quota.unwrap_or_default());
+
+    DEPOSIT_POLICY.with(|policy| *policy.borrow_mut() = state.deposit_policy);
+    DEPOSIT_BALANCES.with(|balances| {
+        *balances.borrow_mut() = state.deposit_balances.unwrap_or_default().into_iter().collect();
+    });
+    ORDER_DEPOSITS.with(|deposits| {
+        *deposits.borrow_mut() = state
+            .order_deposits
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(order_id, owner, amount)| (order_id, (owner, amount)))
+            .collect();
+    });
+    UNTOUCHED_EXPIRIES.with(|expiries| {
+        *expiries.borrow_mut() = state.untouched_expiries.unwrap_or_default().into_iter().collect();
+    });
+    RESOLVERS.with(|resolvers| {
+        *resolvers.borrow_mut() = state.resolvers.unwrap_or_default().into_iter().collect();
+    });
+    ESCROW_OWNERS.with(|owners| {
+        *owners.borrow_mut() = state.escrow_owners.unwrap_or_default().into_iter().collect();
+    });
+
+    // Snapshots from before metrics existed only lack counters derivable from the orders
+    let metrics = state.metrics.unwrap_or_else(|| crate::metrics::MetricsState {
+        status_counts: count_orders_by_status(),
+        ..Default::default()
+    });
+    crate::metrics::deserialize_metrics_state(metrics);
+}
+
+/// Count stored orders per status label
+fn count_orders_by_status() -> BTreeMap<String, u64> {
+    ORDERS.with(|orders| {
+        let mut counts = BTreeMap::new();
+        for order in orders.borrow().values() {
+            *counts.entry(crate::metrics::status_label(&order.status).to_string()).or_insert(0) +=
+                1;
+        }
+        counts
+    })
+}
+
+/// Clear all relayer state (for testing)
+#[cfg(test)]
+pub fn clear_relayer_state() {
+    ORDERS.with(|orders| orders.borrow_mut().clear());
+    SUPPORTED_CHAINS.with(|registry| *registry.borrow_mut() = default_supported_chains());
+    CHAIN_CONTRACTS.with(|registry| registry.borrow_mut().clear());
+    ESCROW_ADDRESSES.with(|addresses| addresses.borrow_mut().clear());
+    RESOLVERS.with(|resolvers| resolvers.borrow_mut().clear());
+    ESCROW_OWNERS.with(|owners| owners.borrow_mut().clear());
+    REVEALED_SECRETS.with(|secrets| secrets.borrow_mut().clear());
+    AMOUNT_CAPS.with(|registry| registry.borrow_mut().clear());
+    SECRET_HASH_OWNERS.with(|owners| owners.borrow_mut().clear());
+    SECRET_NONCES.with(|nonces| nonces.borrow_mut().clear());
+    SECRET_SUBMISSIONS.with(|submissions| submissions.borrow_mut().clear());
+    ORDER_AUDIT.with(|audit| audit.borrow_mut().clear());
+    set_default_amount_caps(AmountCaps::default());
+    set_submission_quota(SubmissionQuota::default());
+    CALLER_BUCKETS.with(|buckets| buckets.borrow_mut().clear());
+    MAKER_BUCKETS.with(|buckets| buckets.borrow_mut().clear());
+    DEPOSIT_POLICY.with(|policy| *policy.borrow_mut() = None);
+    DEPOSIT_BALANCES.with(|balances| balances.borrow_mut().clear());
+    ORDER_DEPOSITS.with(|deposits| deposits.borrow_mut().clear());
+    UNTOUCHED_EXPIRIES.with(|expiries| expiries.borrow_mut().clear());
+    crate::metrics::deserialize_metrics_state(Default::default());
+}
+
 /// Serialize the entire relayer state for upgrade
 pub fn serialize_relayer_state() -> (Vec<(String, Order)>, Vec<(String, String)>) {
     let orders =
@@ -55,4 +766,8 @@ pub fn deserialize_relayer_state(
             map.insert(id, order);
         }
     });
+
+    // The secret hash index is derived from the orders rather than persisted
+    SECRET_HASH_OWNERS.with(|owners| owners.borrow_mut().clear());
+    ORDERS.with(|orders| orders.borrow().values().for_each(index_secret_hashes));
 }
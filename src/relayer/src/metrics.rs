@@ -0,0 +1,184 @@
+use crate::types::{FusionError, OrderStatus, RelayerMetrics};
+use candid::{CandidType, Deserialize};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt::Write;
+
+// ============================================================================
+// RELAYER METRICS - Incrementally Maintained Counters
+// ============================================================================
+
+/// Width of the buckets used to count recent submissions
+const BUCKET_NS: u64 = 60 * 1_000_000_000;
+
+/// Number of buckets covering the last hour
+const BUCKETS_PER_HOUR: u64 = 60;
+
+/// All order statuses, in the order they are reported
+const ALL_STATUSES: [OrderStatus; 5] = [
+    OrderStatus::Pending,
+    OrderStatus::Accepted,
+    OrderStatus::Completed,
+    OrderStatus::Failed,
+    OrderStatus::Cancelled,
+];
+
+/// Persisted counters, restored verbatim after upgrade
+#[derive(Clone, Debug, Default, CandidType, Deserialize)]
+pub struct MetricsState {
+    pub total_submissions: u64,
+    pub status_counts: BTreeMap<String, u64>,
+    pub rejections: BTreeMap<String, u64>,
+    pub submission_buckets: Vec<(u64, u64)>, // (bucket start minute, submissions)
+}
+
+thread_local! {
+    static TOTAL_SUBMISSIONS: RefCell<u64> = const { RefCell::new(0) };
+    static STATUS_COUNTS: RefCell<BTreeMap<String, u64>> = const { RefCell::new(BTreeMap::new()) };
+    static REJECTIONS: RefCell<BTreeMap<String, u64>> = const { RefCell::new(BTreeMap::new()) };
+    static SUBMISSION_BUCKETS: RefCell<VecDeque<(u64, u64)>> = const { RefCell::new(VecDeque::new()) };
+}
+
+/// Metric label for an order status
+pub fn status_label(status: &OrderStatus) -> &'static str {
+    match status {
+        OrderStatus::Pending => "pending",
+        OrderStatus::Accepted => "accepted",
+        OrderStatus::Completed => "completed",
+        OrderStatus::Failed => "failed",
+        OrderStatus::Cancelled => "cancelled",
+    }
+}
+
+/// Record the outcome of an order submission
+pub fn record_submission_result(result: &Result<String, FusionError>, current_time: u64) {
+    match result {
+        Ok(_) => record_submission(current_time),
+        Err(error) => record_rejection(error),
+    }
+}
+
+/// Record an accepted submission in the total and the recent-submissions window
+fn record_submission(current_time: u64) {
+    TOTAL_SUBMISSIONS.with(|total| *total.borrow_mut() += 1);
+
+    let minute = current_time / BUCKET_NS;
+    SUBMISSION_BUCKETS.with(|buckets| {
+        let mut buckets = buckets.borrow_mut();
+        match buckets.back_mut() {
+            Some((start, count)) if *start == minute => *count += 1,
+            _ => buckets.push_back((minute, 1)),
+        }
+        while buckets.front().is_some_and(|(start, _)| *start + BUCKETS_PER_HOUR <= minute) {
+            buckets.pop_front();
+        }
+    });
+}
+
+/// Record a rejected submission by error type
+fn record_rejection(error: &FusionError) {
+    REJECTIONS.with(|rejections| {
+        *rejections.borrow_mut().entry(error.name().to_string()).or_insert(0) += 1;
+    });
+}
+
+/// Move an order between status counts when it is stored
+pub fn record_status_change(previous: Option<&OrderStatus>, current: &OrderStatus) {
+    if previous == Some(current) {
+        return;
+    }
+
+    STATUS_COUNTS.with(|counts| {
+        let mut counts = counts.borrow_mut();
+        if let Some(previous) = previous {
+            if let Some(count) = counts.get_mut(status_label(previous)) {
+                *count = count.saturating_sub(1);
+            }
+        }
+        *counts.entry(status_label(current).to_string()).or_insert(0) += 1;
+    });
+}
+
+/// Build a snapshot of all relayer metrics
+pub fn get_metrics(current_time: u64) -> RelayerMetrics {
+    let status_counts = STATUS_COUNTS.with(|counts| counts.borrow().clone());
+    let count_for = |status: &OrderStatus| status_counts.get(status_label(status)).copied();
+
+    let minute = current_time / BUCKET_NS;
+    let submissions_last_hour = SUBMISSION_BUCKETS.with(|buckets| {
+        buckets
+            .borrow()
+            .iter()
+            .filter(|(start, _)| *start + BUCKETS_PER_HOUR > minute)
+            .map(|(_, count)| count)
+            .sum()
+    });
+
+    RelayerMetrics {
+        total_submissions: TOTAL_SUBMISSIONS.with(|total| *total.borrow()),
+        active_orders: count_for(&OrderStatus::Pending).unwrap_or(0)
+            + count_for(&OrderStatus::Accepted).unwrap_or(0),
+        orders_by_status: ALL_STATUSES
+            .iter()
+            .map(|status| (status_label(status).to_string(), count_for(status).unwrap_or(0)))
+            .collect(),
+        submissions_last_hour,
+        rejected_submissions: REJECTIONS
+            .with(|rejections| rejections.borrow().iter().map(|(k, v)| (k.clone(), *v)).collect()),
+        timestamp: current_time,
+    }
+}
+
+/// Render metrics in the Prometheus text exposition format
+pub fn render_prometheus(metrics: &RelayerMetrics) -> String {
+    let mut out = String::new();
+
+    let _ = writeln!(out, "# HELP relayer_submissions_total Orders accepted by the relayer.");
+    let _ = writeln!(out, "# TYPE relayer_submissions_total counter");
+    let _ = writeln!(out, "relayer_submissions_total {}", metrics.total_submissions);
+
+    let _ = writeln!(out, "# HELP relayer_active_orders Orders pending or accepted.");
+    let _ = writeln!(out, "# TYPE relayer_active_orders gauge");
+    let _ = writeln!(out, "relayer_active_orders {}", metrics.active_orders);
+
+    let _ = writeln!(out, "# HELP relayer_orders Orders by status.");
+    let _ = writeln!(out, "# TYPE relayer_orders gauge");
+    for (status, count) in &metrics.orders_by_status {
+        let _ = writeln!(out, "relayer_orders{{status=\"{}\"}} {}", status, count);
+    }
+
+    let _ = writeln!(out, "# HELP relayer_submissions_last_hour Orders accepted in the last hour.");
+    let _ = writeln!(out, "# TYPE relayer_submissions_last_hour gauge");
+    let _ = writeln!(out, "relayer_submissions_last_hour {}", metrics.submissions_last_hour);
+
+    let _ = writeln!(
+        out,
+        "# HELP relayer_rejected_submissions_total Rejected submissions by error type."
+    );
+    let _ = writeln!(out, "# TYPE relayer_rejected_submissions_total counter");
+    for (error, count) in &metrics.rejected_submissions {
+        let _ =
+            writeln!(out, "relayer_rejected_submissions_total{{error=\"{}\"}} {}", error, count);
+    }
+
+    out
+}
+
+/// Serialize counters for canister upgrade
+pub fn serialize_metrics_state() -> MetricsState {
+    MetricsState {
+        total_submissions: TOTAL_SUBMISSIONS.with(|total| *total.borrow()),
+        status_counts: STATUS_COUNTS.with(|counts| counts.borrow().clone()),
+        rejections: REJECTIONS.with(|rejections| rejections.borrow().clone()),
+        submission_buckets: SUBMISSION_BUCKETS
+            .with(|buckets| buckets.borrow().iter().cloned().collect()),
+    }
+}
+
+/// Restore counters after canister upgrade
+pub fn deserialize_metrics_state(state: MetricsState) {
+    TOTAL_SUBMISSIONS.with(|total| *total.borrow_mut() = state.total_submissions);
+    STATUS_COUNTS.with(|counts| *counts.borrow_mut() = state.status_counts);
+    REJECTIONS.with(|rejections| *rejections.borrow_mut() = state.rejections);
+    SUBMISSION_BUCKETS.with(|buckets| *buckets.borrow_mut() = state.submission_buckets.into());
+}
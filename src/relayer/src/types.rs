@@ -1,4 +1,4 @@
-use candid::{CandidType, Principal};
+use candid::{CandidType, Nat, Principal};
 use serde::{Deserialize, Serialize};
 
 // ============================================================================
@@ -62,6 +62,148 @@ pub struct Order {
     pub dst_chain_id: u64,
     pub secret_hashes: Vec<String>,
     pub fills: Vec<String>,
+    pub fill_progress_bps: Option<u32>, // Reported by resolvers, None for orders stored before tracking
+}
+
+/// Escrow-related contract addresses deployed on a chain
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize, PartialEq)]
+pub struct EscrowContracts {
+    pub factory: String,
+    pub src_impl: String,
+    pub dst_impl: String,
+    pub lop: String,
+}
+
+/// Order escrow response - order plus the contracts that apply on the requested chain
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize)]
+pub struct OrderEscrowInfo {
+    pub order: Order,
+    pub contracts: EscrowContracts,
+    pub escrow_address: Option<String>,
+}
+
+/// Largest order amounts accepted on a chain, in the token's smallest unit
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize, PartialEq)]
+pub struct AmountCaps {
+    pub max_making_amount: Nat,
+    pub max_taking_amount: Nat,
+}
+
+/// Registry id reserved for the Internet Computer, which has no EVM chain id (its SLIP-44 coin type)
+pub const ICP_CHAIN_ID: u64 = 223;
+
+/// A chain orders may be submitted from or to, with the metadata resolvers need to settle on it
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize, PartialEq)]
+pub struct SupportedChain {
+    pub name: String,
+    pub finality_delay_secs: u64, // Wait before treating an escrow deployment as final
+    pub escrow_factory: Option<String>, // None where escrows are not deployed by a factory (ICP)
+}
+
+/// Submission limits: token buckets per caller and per maker address, plus a global cap
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize, PartialEq)]
+pub struct SubmissionQuota {
+    pub bucket_capacity: u32,    // Submissions allowed in a burst
+    pub refill_interval_ns: u64, // Time to earn back one submission
+    pub max_pending_orders: u64, // Unexpired pending orders across all makers
+}
+
+/// Refundable deposit each submission locks, making junk orders costly to submit in bulk
+///
+/// Deposits return once an order leaves the active states. Orders expiring untouched are
+/// refunded too, until their depositor lets more than `free_untouched_expiries` lapse in a row;
+/// the deposits of further untouched expiries are forfeited.
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize, PartialEq)]
+pub struct DepositPolicy {
+    pub ledger: Principal, // ICRC-2 ledger deposits are paid in (ICP)
+    pub amount: u64,       // Locked per submission in the ledger's smallest unit, 0 disables
+    pub free_untouched_expiries: u32,
+}
+
+/// Deposit funds a submitter holds with the relayer
+#[derive(Clone, Debug, Default, CandidType, Deserialize, Serialize, PartialEq)]
+pub struct DepositBalance {
+    pub available: u64, // Free to lock for submissions or withdraw
+    pub locked: u64,    // Held by orders that are still active
+    pub forfeited: u64, // Lost to orders that expired untouched
+}
+
+/// Submissions left to one caller or maker address as of `updated_at`
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize, PartialEq)]
+pub struct TokenBucket {
+    pub tokens: u32,
+    pub updated_at: u64,
+}
+
+/// Compact status of an order for resolvers re-syncing many orders at once
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize, PartialEq)]
+pub struct OrderStatusSummary {
+    pub status: OrderStatus,
+    pub fill_progress_bps: Option<u32>,
+    pub updated_at: u64, // Latest audited mutation, or creation for untouched orders
+}
+
+/// Secret revealed by the maker for one fill threshold of an order
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize, PartialEq)]
+pub struct RevealedSecret {
+    pub idx: u32,
+    pub secret: String,
+}
+
+/// Audit record of the maker submission that revealed a secret
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize, PartialEq)]
+pub struct SecretSubmission {
+    pub maker: Principal,
+    pub nonce: u64,
+    pub order_hash: String,
+    pub idx: u32,
+    pub submitted_at: u64,
+}
+
+/// Kind of order mutation recorded in the audit trail
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize, PartialEq)]
+pub enum AuditAction {
+    Submitted,
+    FillProgress,
+    SecretRevealed,
+    EscrowRecorded,
+}
+
+/// One mutation of an order and who made it
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize, PartialEq)]
+pub struct AuditEntry {
+    pub timestamp: u64,
+    pub caller: Principal,
+    pub action: AuditAction,
+    pub details: String,
+}
+
+/// Relayer metrics snapshot for monitoring
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize)]
+pub struct RelayerMetrics {
+    pub total_submissions: u64,
+    pub active_orders: u64,
+    pub orders_by_status: Vec<(String, u64)>,
+    pub submissions_last_hour: u64,
+    pub rejected_submissions: Vec<(String, u64)>,
+    pub timestamp: u64,
+}
+
+/// HTTP gateway request
+#[derive(Clone, Debug, CandidType, Deserialize)]
+pub struct HttpRequest {
+    pub method: String,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+/// HTTP gateway response
+#[derive(Clone, Debug, CandidType, Deserialize)]
+pub struct HttpResponse {
+    pub status_code: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
 }
 
 /// Order status
@@ -84,10 +226,25 @@ pub enum FusionError {
 
     // Validation Errors
     InvalidAmount,
+    AmountExceedsCap(Nat), // The cap that was exceeded
     InvalidSecretHash,
-    InvalidEIP712Signature,
+    DuplicateSecretHash(String), // Id of the active order already using the hash
+    SecretNotYetUnlockable,
+    InvalidNonce(u64),              // Lowest nonce the maker may submit next
+    InvalidEIP712Signature(String), // Reason the signature was rejected
     InvalidSalt,
     TokenAddressInvalid,
+    UnsupportedChain(Vec<u64>),    // Ids of the supported chains
+    TooManyOrderHashes(u64),       // Most order hashes accepted by one bulk query
+    EscrowAlreadyRecorded(String), // Address already recorded for the order on the chain
+
+    // Quota Errors
+    RateLimited(u64),   // Nanoseconds until the caller or maker may submit again
+    QuotaExceeded(u64), // Nanoseconds until the next pending order expires
+
+    // Deposit Errors
+    InsufficientDeposit(u64),      // Deposit each submission locks
+    DepositTransferFailed(String), // Ledger rejection or failed ledger call
 
     // System Errors
     SystemError,
@@ -98,6 +255,89 @@ pub enum FusionError {
 // IMPLEMENTATIONS
 // ============================================================================
 
+impl FusionError {
+    /// Variant name without payload, used as a stable metric label
+    pub fn name(&self) -> &'static str {
+        match self {
+            FusionError::OrderNotFound => "OrderNotFound",
+            FusionError::OrderNotPending => "OrderNotPending",
+            FusionError::OrderExpired => "OrderExpired",
+            FusionError::InvalidAmount => "InvalidAmount",
+            FusionError::AmountExceedsCap(_) => "AmountExceedsCap",
+            FusionError::InvalidSecretHash => "InvalidSecretHash",
+            FusionError::DuplicateSecretHash(_) => "DuplicateSecretHash",
+            FusionError::SecretNotYetUnlockable => "SecretNotYetUnlockable",
+            FusionError::InvalidNonce(_) => "InvalidNonce",
+            FusionError::InvalidEIP712Signature(_) => "InvalidEIP712Signature",
+            FusionError::InvalidSalt => "InvalidSalt",
+            FusionError::TokenAddressInvalid => "TokenAddressInvalid",
+            FusionError::UnsupportedChain(_) => "UnsupportedChain",
+            FusionError::TooManyOrderHashes(_) => "TooManyOrderHashes",
+            FusionError::EscrowAlreadyRecorded(_) => "EscrowAlreadyRecorded",
+            FusionError::RateLimited(_) => "RateLimited",
+            FusionError::QuotaExceeded(_) => "QuotaExceeded",
+            FusionError::InsufficientDeposit(_) => "InsufficientDeposit",
+            FusionError::DepositTransferFailed(_) => "DepositTransferFailed",
+            FusionError::SystemError => "SystemError",
+            FusionError::Unauthorized => "Unauthorized",
+        }
+    }
+}
+
+impl Default for AmountCaps {
+    /// Amounts that still fit the u64 conversions done by resolvers and escrows
+    fn default() -> Self {
+        Self { max_making_amount: Nat::from(u64::MAX), max_taking_amount: Nat::from(u64::MAX) }
+    }
+}
+
+impl SupportedChain {
+    /// Registry entry for the Internet Computer, present until a controller replaces it
+    pub fn icp() -> Self {
+        Self { name: "Internet Computer".to_string(), finality_delay_secs: 2, escrow_factory: None }
+    }
+}
+
+impl Default for SubmissionQuota {
+    /// Bursts of 10 submissions, one more per minute, 10k pending orders overall
+    fn default() -> Self {
+        Self { bucket_capacity: 10, refill_interval_ns: 60_000_000_000, max_pending_orders: 10_000 }
+    }
+}
+
+impl TokenBucket {
+    /// A bucket holding the full burst allowance
+    pub fn full(quota: &SubmissionQuota, now: u64) -> Self {
+        Self { tokens: quota.bucket_capacity, updated_at: now }
+    }
+
+    /// Add the tokens earned since the last update, up to the bucket capacity
+    pub fn refill(&mut self, quota: &SubmissionQuota, now: u64) {
+        let interval = quota.refill_interval_ns.max(1);
+        let earned = now.saturating_sub(self.updated_at) / interval;
+        if u64::from(self.tokens) + earned >= u64::from(quota.bucket_capacity) {
+            *self = Self::full(quota, now);
+        } else {
+            self.tokens += earned as u32;
+            self.updated_at += earned * interval;
+        }
+    }
+
+    /// Nanoseconds until a token is available, zero if one already is
+    pub fn wait_ns(&self, quota: &SubmissionQuota, now: u64) -> u64 {
+        if self.tokens > 0 {
+            0
+        } else {
+            (self.updated_at + quota.refill_interval_ns.max(1)).saturating_sub(now)
+        }
+    }
+
+    /// Whether the bucket is back at capacity, making it equivalent to an untracked one
+    pub fn is_full(&self, quota: &SubmissionQuota) -> bool {
+        self.tokens >= quota.bucket_capacity
+    }
+}
+
 impl Order {
     /// Create a new Order compatible with 1inch API
     pub fn new(
@@ -116,7 +356,7 @@ impl Order {
         src_chain_id: u64,
         dst_chain_id: u64,
     ) -> Self {
-        let current_time = ic_cdk::api::time();
+        let current_time = crate::memory::current_time();
 
         Self {
             // Core Order Data
@@ -155,6 +395,7 @@ impl Order {
             dst_chain_id,
             secret_hashes: vec![hashlock.clone()],
             fills: vec![],
+            fill_progress_bps: Some(0),
         }
     }
 }